@@ -8,10 +8,13 @@ use ratatui::{
     widgets::{Block, Paragraph},
 };
 use rpc::comms::{ClientLobbyState, ClientMessage, ServerMessage};
-use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::{io::AsyncWriteExt, sync::mpsc::UnboundedReceiver};
 
 use crate::{AppMessage, app_lobby::AppLobby};
 
+/// Kept next to wherever the client is run from, losing it means starting again with fresh stats
+const KEY_FILE: &str = "player.key";
+
 pub struct AppAuth;
 
 impl AppAuth {
@@ -178,7 +181,9 @@ impl AppAuth {
             server
         };
 
-        let mut client = EncryptedClient::<ClientMessage, ServerMessage>::connect(&addr).await?;
+        let key = Self::load_key().await?;
+        let mut client =
+            EncryptedClient::<ClientMessage, ServerMessage>::connect(&addr, &key).await?;
 
         client
             .sender
@@ -195,6 +200,33 @@ impl AppAuth {
         ))
     }
 
+    /// The server knows a player by this key rather than by their name
+    async fn load_key() -> anyhow::Result<Vec<u8>> {
+        match tokio::fs::read(KEY_FILE).await {
+            Ok(key) => Ok(key),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let key = encr::generate_key()?;
+                Self::save_key(&key).await?;
+                Ok(key)
+            }
+            Err(err) => Err(anyhow!("Failed to read {KEY_FILE} {err:?}")),
+        }
+    }
+
+    /// Anyone who can read the key can play as this account, so only the owner gets to
+    async fn save_key(key: &[u8]) -> anyhow::Result<()> {
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options.open(KEY_FILE).await?;
+        file.write_all(key).await?;
+        file.sync_all().await?;
+
+        Ok(())
+    }
+
     async fn wait_for_auth(receiver: &mut EncryptedReceiver<ServerMessage>) -> anyhow::Result<u32> {
         loop {
             let msg = receiver.recv().await?;
@@ -1,4 +1,5 @@
 use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
 use ratatui::{
     DefaultTerminal, Frame,
     layout::{Constraint, Direction, Layout},
@@ -7,10 +8,14 @@ use ratatui::{
     text::{Line, Span, Text},
     widgets::{Block, Borders, Cell, Row, Table},
 };
-use rpc::{comms::ClientLobbyState, game_state::GameType};
+use rpc::{
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientLobbyState, ClientMessage, RoomVisibility},
+    game_state::{GameStartState, GameType},
+};
 use tokio::sync::mpsc::UnboundedReceiver;
 
-use crate::AppMessage;
+use crate::{AppMessage, chat_pane::ChatPane};
 
 #[derive(Debug, Clone)]
 pub struct AppLobby {
@@ -18,25 +23,48 @@ pub struct AppLobby {
     pub id: u32,
     state: ClientLobbyState,
     view: LobbyView,
+    /// Shown under the commands, e.g. why the last join didn't work
+    notice: Option<String>,
+    chat: ChatPane,
 }
 
 #[derive(Debug, Clone)]
 enum LobbyView {
     Main(usize),
     Create(GameCreate),
+    /// Typing in an invite code
+    Code(Vec<char>),
+    Password(PasswordEntry),
 }
 
 #[derive(Debug, Clone)]
 pub struct GameCreate {
     pub name: Vec<char>,
     pub game_type: GameType,
+    /// The password is typed straight into `RoomVisibility::Private`
+    pub visibility: RoomVisibility,
+    editing_password: bool,
+}
+
+/// Private rooms picked from the list ask for the password before going in
+#[derive(Debug, Clone)]
+struct PasswordEntry {
+    game_id: u32,
+    spectate: bool,
+    password: Vec<char>,
 }
 
 #[derive(Debug)]
 pub enum LobbyResult {
     Exit,
     Create(GameCreate),
-    Join(u32),
+    /// With the password for private rooms
+    Join(u32, Option<String>),
+    Spectate(u32, Option<String>),
+    JoinByCode(String),
+    Replays,
+    Stats,
+    QuickPlay,
 }
 
 /// After a user authenticates, they get sent here.
@@ -44,7 +72,13 @@ pub enum LobbyResult {
 ///
 ///  1. Create a new game
 ///  2. Join an existing game
-///  3. Quit
+///  3. Spectate a running game
+///  4. Watch replays of old games
+///  5. Check their stats and the leaderboard
+///  6. Queue for quick play
+///  7. Get into a private room with an invite code
+///  8. Chat with everyone else in the lobby
+///  9. Quit
 ///
 impl AppLobby {
     pub fn new(name: String, id: u32, state: ClientLobbyState) -> AppLobby {
@@ -53,14 +87,48 @@ impl AppLobby {
             id,
             state,
             view: LobbyView::Main(0),
+            notice: None,
+            chat: ChatPane::new(ChatChannel::Lobby),
+        }
+    }
+
+    pub fn with_notice(mut self, notice: String) -> AppLobby {
+        self.notice = Some(notice);
+        self
+    }
+
+    /// Private rooms ask for the password first, there's nothing to return until it's typed in
+    fn enter_game(&mut self, game_id: u32, private: bool, spectate: bool) -> Option<LobbyResult> {
+        if private {
+            self.view = LobbyView::Password(PasswordEntry {
+                game_id,
+                spectate,
+                password: vec![],
+            });
+            return None;
         }
+
+        Some(if spectate {
+            LobbyResult::Spectate(game_id, None)
+        } else {
+            LobbyResult::Join(game_id, None)
+        })
     }
 
     pub async fn start(
         mut self,
         terminal: &mut DefaultTerminal,
         app_receiver: &mut UnboundedReceiver<AppMessage>,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
     ) -> anyhow::Result<LobbyResult> {
+        // Anything said while we were away is picked up again
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                self.id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Lobby),
+            ))
+            .await?;
+
         terminal.draw(|frame| self.render(frame))?;
         while let Some(message) = app_receiver.recv().await {
             match message {
@@ -73,75 +141,205 @@ impl AppLobby {
                         self.state.player_count = player_count
                     }
                     rpc::comms::ServerMessage::GameState(_) => {}
-                    rpc::comms::ServerMessage::JoinedGame(_, _) => {}
+                    rpc::comms::ServerMessage::JoinedGame(_, _, _) => {}
+                    rpc::comms::ServerMessage::RemovedFromGame(_)
+                    | rpc::comms::ServerMessage::ReplayList(_)
+                    | rpc::comms::ServerMessage::Replay(_, _)
+                    | rpc::comms::ServerMessage::ReplayError(_)
+                    | rpc::comms::ServerMessage::Profile(_, _)
+                    | rpc::comms::ServerMessage::Leaderboard(_, _)
+                    | rpc::comms::ServerMessage::QueueStatus(_)
+                    | rpc::comms::ServerMessage::QueueError(_)
+                    | rpc::comms::ServerMessage::JoinFailed(_) => {}
+                    rpc::comms::ServerMessage::Chat(message) => self.chat.push(message),
+                    rpc::comms::ServerMessage::ChatHistory(channel, history) => {
+                        self.chat.set_history(channel, history)
+                    }
+                    rpc::comms::ServerMessage::ChatRejected(reason) => self.chat.rejected(reason),
                 },
                 AppMessage::TerminalEvent(event) => {
                     if let Event::Key(key_event) = event
                         && key_event.kind == KeyEventKind::Release
                     {
                         match key_event.code {
-                            KeyCode::Up => match self.view {
+                            code if self.chat.is_typing() => {
+                                if let Some(text) = self.chat.key(code) {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            self.id,
+                                            ClientAuthedCommand::Chat(ChatChannel::Lobby, text),
+                                        ))
+                                        .await?;
+                                }
+                            }
+                            KeyCode::Char('/') if matches!(self.view, LobbyView::Main(_)) => {
+                                self.chat.start_typing()
+                            }
+                            KeyCode::Up => match &mut self.view {
                                 LobbyView::Main(idx) => {
                                     if self.state.games.is_empty() {
                                         continue;
                                     }
-                                    let new_idx = if idx == 0 {
+                                    *idx = if *idx == 0 {
                                         self.state.games.len() - 1
                                     } else {
-                                        idx - 1
+                                        *idx - 1
                                     };
-                                    self.view = LobbyView::Main(new_idx);
                                 }
-                                LobbyView::Create(_) => {}
+                                LobbyView::Create(create) => create.editing_password = false,
+                                LobbyView::Code(_) | LobbyView::Password(_) => {}
                             },
-                            KeyCode::Down => match self.view {
+                            KeyCode::Down => match &mut self.view {
                                 LobbyView::Main(idx) => {
                                     if self.state.games.is_empty() {
                                         continue;
                                     }
 
-                                    let new_idx = if idx >= self.state.games.len() - 1 {
+                                    *idx = if *idx >= self.state.games.len() - 1 {
                                         0
                                     } else {
-                                        idx + 1
+                                        *idx + 1
                                     };
-                                    self.view = LobbyView::Main(new_idx);
                                 }
-                                LobbyView::Create(_) => {}
+                                LobbyView::Create(create) => {
+                                    create.editing_password =
+                                        matches!(create.visibility, RoomVisibility::Private(_));
+                                }
+                                LobbyView::Code(_) | LobbyView::Password(_) => {}
                             },
+                            KeyCode::Left | KeyCode::Right
+                                if let LobbyView::Create(create) = &mut self.view =>
+                            {
+                                let choices = game_choices(&self.state);
+                                let idx = choices
+                                    .iter()
+                                    .position(|game| *game == create.game_type)
+                                    .unwrap_or(0);
+
+                                create.game_type = if key_event.code == KeyCode::Right {
+                                    choices[(idx + 1) % choices.len()]
+                                } else {
+                                    choices[(idx + choices.len() - 1) % choices.len()]
+                                };
+                            }
+                            KeyCode::Tab => {
+                                if let LobbyView::Create(create) = &mut self.view {
+                                    create.visibility = match create.visibility {
+                                        RoomVisibility::Public => {
+                                            RoomVisibility::Private(String::new())
+                                        }
+                                        RoomVisibility::Private(_) => RoomVisibility::Hidden,
+                                        RoomVisibility::Hidden => RoomVisibility::Public,
+                                    };
+                                    create.editing_password =
+                                        matches!(create.visibility, RoomVisibility::Private(_));
+                                }
+                            }
                             KeyCode::Char(c) => match &mut self.view {
-                                LobbyView::Main(_) => {
+                                LobbyView::Main(idx) => {
+                                    let idx = *idx;
+
                                     if c == 'c' {
                                         self.view = LobbyView::Create(GameCreate {
                                             name: vec![],
                                             game_type: GameType::Uno,
+                                            visibility: RoomVisibility::Public,
+                                            editing_password: false,
                                         })
+                                    } else if c == 'j' {
+                                        self.view = LobbyView::Code(vec![]);
+                                    } else if c == 'r' {
+                                        return Ok(LobbyResult::Replays);
+                                    } else if c == 'l' {
+                                        return Ok(LobbyResult::Stats);
+                                    } else if c == 'q' {
+                                        return Ok(LobbyResult::QuickPlay);
+                                    } else if c == 's'
+                                        && let Some(game) = self.state.games.get(idx)
+                                        && let Some(result) =
+                                            self.enter_game(game.id, game.private, true)
+                                    {
+                                        return Ok(result);
                                     }
                                 }
                                 LobbyView::Create(create) => {
-                                    create.name.push(c);
+                                    match (&mut create.visibility, create.editing_password) {
+                                        (RoomVisibility::Private(password), true) => {
+                                            password.push(c)
+                                        }
+                                        _ => create.name.push(c),
+                                    }
                                 }
+                                LobbyView::Code(code) => code.push(c),
+                                LobbyView::Password(entry) => entry.password.push(c),
                             },
                             KeyCode::Backspace => match &mut self.view {
                                 LobbyView::Main(_) => {}
                                 LobbyView::Create(create) => {
-                                    create.name.pop();
+                                    match (&mut create.visibility, create.editing_password) {
+                                        (RoomVisibility::Private(password), true) => {
+                                            password.pop();
+                                        }
+                                        _ => {
+                                            create.name.pop();
+                                        }
+                                    }
+                                }
+                                LobbyView::Code(code) => {
+                                    code.pop();
+                                }
+                                LobbyView::Password(entry) => {
+                                    entry.password.pop();
                                 }
                             },
-                            KeyCode::Enter => match self.view {
+                            KeyCode::Enter => match &self.view {
                                 LobbyView::Main(idx) => {
-                                    if let Some(game) = self.state.games.get(idx) {
-                                        return Ok(LobbyResult::Join(game.id));
+                                    // Games that have already started, or locked or full ones, can only be watched
+                                    if let Some(game) = self.state.games.get(*idx) {
+                                        let spectate = match game.start_state {
+                                            GameStartState::Setup => {
+                                                game.locked
+                                                    || game.active_players >= game.max_players
+                                            }
+                                            // Blackjack deals round after round, a free seat can be taken any time
+                                            GameStartState::Active
+                                                if game.game_type == GameType::Blackjack =>
+                                            {
+                                                game.locked
+                                                    || game.active_players >= game.max_players
+                                            }
+                                            GameStartState::Active | GameStartState::Ending => true,
+                                        };
+
+                                        if let Some(result) =
+                                            self.enter_game(game.id, game.private, spectate)
+                                        {
+                                            return Ok(result);
+                                        }
                                     }
                                 }
                                 LobbyView::Create(game_create) => {
                                     return Ok(LobbyResult::Create(game_create.clone()));
                                 }
+                                LobbyView::Code(code) => {
+                                    return Ok(LobbyResult::JoinByCode(String::from_iter(code)));
+                                }
+                                LobbyView::Password(entry) => {
+                                    let password = Some(String::from_iter(&entry.password));
+
+                                    return Ok(if entry.spectate {
+                                        LobbyResult::Spectate(entry.game_id, password)
+                                    } else {
+                                        LobbyResult::Join(entry.game_id, password)
+                                    });
+                                }
                             },
 
                             KeyCode::Esc => match self.view {
                                 LobbyView::Main(_) => break,
-                                LobbyView::Create(_) => self.view = LobbyView::Main(0),
+                                LobbyView::Create(_)
+                                | LobbyView::Code(_)
+                                | LobbyView::Password(_) => self.view = LobbyView::Main(0),
                             },
 
                             // KeyCode::Home => !(),
@@ -198,6 +396,8 @@ impl AppLobby {
         match &self.view {
             LobbyView::Main(idx) => self.main_view(frame, *idx),
             LobbyView::Create(create) => self.create_view(frame, create),
+            LobbyView::Code(code) => self.entry_view(frame, "Invite Code: ", code),
+            LobbyView::Password(entry) => self.entry_view(frame, "Password: ", &entry.password),
         }
     }
 
@@ -217,16 +417,32 @@ impl AppLobby {
             .constraints([Constraint::Length(44), Constraint::Fill(1)])
             .split(inner);
 
-        let main_text = Text::from(
+        let mut main_text = Text::from(
             r#"Welcome to Tempest!
 
 Press "c" to create a new game
 Press Up / Down to chose a game to join
 Press Enter to join a selected game
+Press "s" to spectate a selected game
+Press "r" to watch replays
+Press "l" for stats and the leaderboard
+Press "q" to quick play
+Press "j" to join with an invite code
+Press "/" to chat
 "#,
         );
 
-        frame.render_widget(main_text, chunks[0]);
+        if let Some(notice) = &self.notice {
+            main_text.push_line(Line::from(notice.clone()).light_red());
+        }
+
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(14), Constraint::Fill(1)])
+            .split(chunks[0]);
+
+        frame.render_widget(main_text, left[0]);
+        self.chat.render(frame, left[1]);
         frame.render_widget(self.game_list(idx), chunks[1]);
 
         // println!("Render here");
@@ -244,23 +460,110 @@ Press Enter to join a selected game
                 let (game_cell, user_cell) = match game.game_type {
                     GameType::Uno => (
                         Cell::new("Uno").light_cyan(),
-                        Cell::new(format!("{} / 4", game.active_players)).gray(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::CrazyEights => (
+                        Cell::new("Eights").light_red(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::Holdem => (
+                        Cell::new("Poker").light_green(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::Hearts => (
+                        Cell::new("Hearts").light_magenta(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::ConnectFour => (
+                        Cell::new("Conn4").light_yellow(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::Chess => (
+                        Cell::new("Chess").light_blue(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::Snake => (
+                        Cell::new("Snake").green(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::LiarsDice => (
+                        Cell::new("Dice").yellow(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::Codenames => (
+                        Cell::new("Codes").light_red(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::Blackjack => (
+                        Cell::new("BJack").cyan(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::Battleship => (
+                        Cell::new("Ships").blue(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
+                    ),
+                    GameType::UnoVariant(_) => (
+                        Cell::new("Uno+").light_cyan(),
+                        Cell::new(format!("{} / {}", game.active_players, game.max_players)).gray(),
                     ),
                 };
 
+                let state_cell = match game.start_state {
+                    GameStartState::Setup if game.locked => Cell::new("Locked").light_red(),
+                    GameStartState::Setup if game.active_players >= game.max_players => {
+                        Cell::new("Full").light_red()
+                    }
+                    GameStartState::Setup => Cell::new("Open").light_green(),
+                    GameStartState::Active | GameStartState::Ending => {
+                        Cell::new("Playing").light_yellow()
+                    }
+                };
+
+                let spectator_cell = match game.spectator_count {
+                    0 => Cell::new(""),
+                    count => Cell::new(format!("{count} watching")).gray(),
+                };
+
+                let players: Vec<Span> = game
+                    .players
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, player)| {
+                        let mut spans = vec![];
+                        if i > 0 {
+                            spans.push(Span::from(", "));
+                        }
+                        spans.push(Span::from(player.name.clone()));
+                        if let Some(rating) = player.rating {
+                            spans.push(Span::from(format!(" {rating}")).gray());
+                        }
+                        spans
+                    })
+                    .collect();
+
                 Row::new(vec![
                     Cell::new(if i == idx { ">" } else { " " }).blue(),
-                    Cell::new(game.name.clone()),
+                    if game.private {
+                        Cell::new(format!("{} (Private)", game.name)).light_magenta()
+                    } else {
+                        Cell::new(game.name.clone())
+                    },
                     game_cell,
+                    state_cell,
                     user_cell,
+                    spectator_cell,
+                    Cell::new(Line::from(players)),
                 ])
             })
             .collect();
 
         let widths = vec![
             Constraint::Length(1),
-            Constraint::Length(15),
-            Constraint::Length(4),
+            Constraint::Length(24),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Length(11),
             Constraint::Fill(1),
         ];
 
@@ -296,18 +599,68 @@ Press Enter to join a selected game
             .constraints([Constraint::Fill(1), Constraint::Length(1)])
             .split(inner);
 
-        let mut main_text = Text::from("Create a new Game! ( Currently Only Uno Available )");
+        let mut main_text = Text::from("Create a new Game!");
+        main_text.push_line(Line::from(""));
+        main_text.push_line(Line::from(vec![
+            Span::from("Game: "),
+            Span::from(format!(
+                "< {} >",
+                game_name(create.game_type, &self.state.uno_variants)
+            ))
+            .light_cyan(),
+        ]));
         main_text.push_line(Line::from(""));
         main_text.push_line(Line::from("Lobby Name: "));
         main_text.push_line(Line::from(vec![
-            Span::from("> ").blue(),
+            Span::from(if create.editing_password { "  " } else { "> " }).blue(),
             Span::from(String::from_iter(&create.name)),
         ]));
 
+        main_text.push_line(Line::from(""));
+        main_text.push_line(Line::from(match create.visibility {
+            RoomVisibility::Public => "Public ~ Listed for anyone to join",
+            RoomVisibility::Private(_) => "Private ~ Listed, needs a password or invite code",
+            RoomVisibility::Hidden => "Hidden ~ Not listed, needs the invite code",
+        }));
+
+        if let RoomVisibility::Private(password) = &create.visibility {
+            main_text.push_line(Line::from("Password: "));
+            main_text.push_line(Line::from(vec![
+                Span::from(if create.editing_password { "> " } else { "  " }).blue(),
+                Span::from(password.clone()),
+            ]));
+        }
+
+        main_text.push_line(Line::from(""));
+        main_text.push_line(Line::from(
+            "Press Tab to change who can join, Up / Down to move between fields",
+        ));
+        main_text.push_line(Line::from("Left / Right to pick the game"));
+        main_text.push_line(Line::from(
+            "The invite code is shown to everyone in the room",
+        ));
+
         frame.render_widget(main_text, chunks[0]);
         frame.render_widget(self.get_block(), frame.area())
     }
 
+    /// A single line to type into, for invite codes and passwords
+    fn entry_view(&self, frame: &mut Frame, label: &str, entry: &[char]) {
+        let outer = self.get_block();
+        let inner = outer.inner(frame.area());
+        frame.render_widget(outer, frame.area());
+
+        let mut main_text = Text::from(label.to_string());
+        main_text.push_line(Line::from(vec![
+            Span::from("> ").blue(),
+            Span::from(String::from_iter(entry)),
+        ]));
+        main_text.push_line(Line::from(""));
+        main_text.push_line(Line::from("Press Enter to go in, Esc to go back"));
+
+        frame.render_widget(main_text, inner);
+    }
+
     fn get_block(&self) -> Block<'_> {
         Block::bordered()
             .border_style(Style::new().light_blue())
@@ -322,3 +675,23 @@ Press Enter to join a selected game
             )
     }
 }
+
+/// Every game the server can host, with its Uno variants on the end
+fn game_choices(state: &ClientLobbyState) -> Vec<GameType> {
+    GameType::ALL
+        .into_iter()
+        .filter(|game| !state.unavailable_games.contains(game))
+        .chain((0..state.uno_variants.len()).map(|idx| GameType::UnoVariant(idx as u8)))
+        .collect()
+}
+
+/// Variants go by the name the server gave them
+fn game_name(game_type: GameType, uno_variants: &[String]) -> String {
+    match game_type {
+        GameType::UnoVariant(idx) => uno_variants
+            .get(idx as usize)
+            .cloned()
+            .unwrap_or(game_type.name().to_string()),
+        _ => game_type.name().to_string(),
+    }
+}
@@ -0,0 +1,205 @@
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Margin},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph},
+};
+use rpc::{
+    comms::{ClientAuthedCommand, ClientMessage, QueueStatus, QuickPlayRequest, ServerMessage},
+    game_state::GameType,
+    uno::{UnoBotDifficulty, UnoSettings},
+};
+use tokio::sync::mpsc;
+
+use crate::{AppMessage, GameResult, uno_client::UnoClient};
+
+enum QueueView {
+    /// Picking the rules, along with why the last attempt to queue failed
+    Rules(Option<String>),
+    /// The status only comes in once the server has looked at the queue
+    Queued(Option<QueueStatus>),
+}
+
+/// Quick play, the server puts a table together and we drop straight into the game.
+///
+/// The rules can be changed with the same keys a host uses to set up a room.
+/// Only players asking for exactly the same rules get matched together.
+pub struct AppQueue;
+
+impl AppQueue {
+    pub async fn start(
+        user_id: u32,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        // Strangers walk away more than friends, so quick play games keep things moving
+        let mut settings = UnoSettings {
+            turn_timer_secs: Some(30),
+            kick_after_timeouts: Some(3),
+            replace_leavers: Some(UnoBotDifficulty::Greedy),
+            ..Default::default()
+        };
+        let mut allow_bots = true;
+        let mut view = QueueView::Rules(None);
+
+        terminal.draw(|frame| Self::render(frame, &view, &settings, allow_bots))?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::JoinedGame(lobby, game_type, user_state) => {
+                        return Ok(GameResult::Game(lobby, game_type, user_state));
+                    }
+                    ServerMessage::QueueStatus(status) => {
+                        // A status can arrive just after leaving the queue
+                        if let QueueView::Queued(_) = view {
+                            view = QueueView::Queued(Some(status));
+                        }
+                    }
+                    ServerMessage::QueueError(err) => view = QueueView::Rules(Some(err)),
+                    _ => continue,
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    match (&view, key_event.code) {
+                        (QueueView::Rules(_), KeyCode::Char('b')) => allow_bots = !allow_bots,
+                        (QueueView::Rules(_), KeyCode::Char(c)) => {
+                            let Some(new_settings) = UnoClient::settings_for_key(c, settings)
+                            else {
+                                continue;
+                            };
+                            settings = new_settings;
+                        }
+                        (QueueView::Rules(_), KeyCode::Enter) => {
+                            tcp_sender
+                                .send(&ClientMessage::Authed(
+                                    user_id,
+                                    ClientAuthedCommand::JoinQueue(QuickPlayRequest {
+                                        game_type: GameType::Uno,
+                                        rules: bincode::encode_to_vec(
+                                            settings,
+                                            bincode::config::standard(),
+                                        )?,
+                                        allow_bots,
+                                    }),
+                                ))
+                                .await?;
+
+                            view = QueueView::Queued(None);
+                        }
+                        (QueueView::Rules(_), KeyCode::Esc) => return Ok(GameResult::None),
+                        (QueueView::Queued(_), KeyCode::Esc) => {
+                            tcp_sender
+                                .send(&ClientMessage::Authed(
+                                    user_id,
+                                    ClientAuthedCommand::LeaveQueue,
+                                ))
+                                .await?;
+
+                            view = QueueView::Rules(None);
+                        }
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| Self::render(frame, &view, &settings, allow_bots))?;
+        }
+
+        Ok(GameResult::Exit)
+    }
+
+    fn render(frame: &mut Frame, view: &QueueView, settings: &UnoSettings, allow_bots: bool) {
+        let area = frame.area();
+
+        let bottom = match view {
+            QueueView::Rules(_) => " Esc to go back ",
+            QueueView::Queued(_) => " Esc to leave the queue ",
+        };
+
+        let outer = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(Line::from(" Tempest ~ Quick Play ~ Uno ").bold().white())
+            .title_bottom(Line::from(bottom).bold().white().right_aligned());
+
+        let inner = outer.inner(area);
+        frame.render_widget(outer, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(30), Constraint::Fill(1)])
+            .split(inner);
+
+        let mut rules = Text::from(Line::from("Rules").bold());
+        for line in UnoClient::settings_summary(settings).lines() {
+            rules.push_line(line.to_string());
+        }
+        rules.push_line(if allow_bots {
+            "Bots Can Fill Seats"
+        } else {
+            "No Bots"
+        });
+
+        frame.render_widget(
+            Paragraph::new(rules).block(Block::default().borders(Borders::RIGHT)),
+            chunks[0],
+        );
+
+        let main_text = match view {
+            QueueView::Rules(err) => {
+                let mut text = Text::from(
+                    "Press Enter to queue\n\n\
+                     Press \"d\" to change the deck count\n\
+                     Press \"t\" to toggle teams, \"h\" to share partner hands\n\
+                     Press \"m\" to change the turn timer\n\
+                     Press \"k\" to change when players are kicked\n\
+                     Press \"r\" to change the bots taking over leavers\n\
+                     Press \"b\" to allow bots to fill empty seats",
+                );
+
+                if let Some(err) = err {
+                    text.push_line(Line::from(""));
+                    text.push_line(Line::from(err.clone()).light_red());
+                }
+
+                text
+            }
+            QueueView::Queued(None) => Text::from("Joining the queue"),
+            QueueView::Queued(Some(status)) => {
+                let mut text = Text::from(Line::from("Looking for a game").bold());
+                text.push_line(Line::from(""));
+                text.push_line(format!("Players waiting: {}", status.queued));
+                text.push_line(format!(
+                    "Waited:          {}m {:02}s",
+                    status.waited_secs / 60,
+                    status.waited_secs % 60
+                ));
+                text.push_line(Line::from(vec![
+                    Span::from("Estimated wait:  "),
+                    Span::from(match status.estimated_wait_secs {
+                        0 => "Any moment".to_string(),
+                        secs => format!("{}m {:02}s", secs / 60, secs % 60),
+                    })
+                    .light_green(),
+                ]));
+                text
+            }
+        };
+
+        frame.render_widget(main_text, chunks[1].inner(Margin::new(1, 0)));
+    }
+}
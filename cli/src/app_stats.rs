@@ -0,0 +1,264 @@
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Row, Table},
+};
+use rpc::{
+    comms::{ClientAuthedCommand, ClientMessage, ServerMessage},
+    game_state::GameType,
+    stats::{LeaderboardEntry, PlayerProfile, PlayerStats},
+};
+
+/// Only the last few rating changes fit next to the rest of the stats
+const RATING_HISTORY_SHOWN: usize = 10;
+use tokio::sync::mpsc;
+
+use crate::AppMessage;
+
+/// Reached from the lobby, shows the user's own stats next to the leaderboard.
+/// The leaderboard can be switched between every game and a single game type.
+pub struct AppStats;
+
+impl AppStats {
+    pub async fn start(
+        name: &str,
+        user_id: u32,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<()> {
+        let mut profile: Option<PlayerProfile> = None;
+        let mut leaderboard: Vec<LeaderboardEntry> = vec![];
+        let mut scope: Option<GameType> = None;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchProfile(name.to_string()),
+            ))
+            .await?;
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchLeaderboard(scope),
+            ))
+            .await?;
+
+        terminal.draw(|frame| Self::render(frame, name, &profile, &leaderboard, scope))?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::Profile(_, new_profile) => profile = new_profile,
+                    ServerMessage::Leaderboard(board_scope, entries) => {
+                        // Only the latest scope asked for is wanted
+                        if board_scope == scope {
+                            leaderboard = entries;
+                        }
+                    }
+                    _ => continue,
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    match key_event.code {
+                        KeyCode::Char('g') => {
+                            scope = match scope {
+                                None => Some(GameType::ALL[0]),
+                                Some(game_type) if game_type.next() == GameType::ALL[0] => None,
+                                Some(game_type) => Some(game_type.next()),
+                            };
+                            leaderboard = vec![];
+
+                            tcp_sender
+                                .send(&ClientMessage::Authed(
+                                    user_id,
+                                    ClientAuthedCommand::FetchLeaderboard(scope),
+                                ))
+                                .await?;
+                        }
+                        KeyCode::Esc => return Ok(()),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| Self::render(frame, name, &profile, &leaderboard, scope))?;
+        }
+
+        Ok(())
+    }
+
+    fn render(
+        frame: &mut Frame,
+        name: &str,
+        profile: &Option<PlayerProfile>,
+        leaderboard: &[LeaderboardEntry],
+        scope: Option<GameType>,
+    ) {
+        let area = frame.area();
+
+        let outer = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {name} ~ Stats "))
+                    .bold()
+                    .white(),
+            )
+            .title_bottom(
+                Line::from(" Esc to go back ")
+                    .bold()
+                    .white()
+                    .right_aligned(),
+            );
+
+        let inner = outer.inner(area);
+        frame.render_widget(outer, area);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(40), Constraint::Fill(1)])
+            .split(inner);
+
+        frame.render_widget(Self::profile_text(profile), chunks[0]);
+        frame.render_widget(Self::leaderboard_table(leaderboard, scope), chunks[1]);
+    }
+
+    fn profile_text(profile: &Option<PlayerProfile>) -> Text<'static> {
+        let Some(profile) = profile else {
+            return Text::from(
+                "No games finished yet\n\nFinish a game to start\n building up stats",
+            );
+        };
+
+        let mut text = Text::from(Line::from(format!("Rating: {}", profile.rating)).bold());
+
+        text.push_line(Line::from(""));
+        text.push_line(Line::from("Overall").bold());
+        Self::push_stats(&mut text, &profile.overall);
+
+        for (game_type, stats) in profile.per_game.iter() {
+            text.push_line(Line::from(""));
+            text.push_line(Line::from(Self::game_name(Some(*game_type))).bold());
+            Self::push_stats(&mut text, stats);
+        }
+
+        if !profile.rating_history.is_empty() {
+            text.push_line(Line::from(""));
+            text.push_line(Line::from("Recent Ratings").bold());
+        }
+
+        for change in profile
+            .rating_history
+            .iter()
+            .rev()
+            .take(RATING_HISTORY_SHOWN)
+        {
+            let change_span = if change.change < 0 {
+                Span::from(format!("{:>4}", change.change)).light_red()
+            } else {
+                Span::from(format!("{:>+4}", change.change)).light_green()
+            };
+
+            text.push_line(Line::from(vec![
+                change_span,
+                Span::from(format!(
+                    " {} ~ {}",
+                    change.rating,
+                    Self::game_name(Some(change.game_type))
+                )),
+            ]));
+        }
+
+        text
+    }
+
+    fn push_stats(text: &mut Text<'static>, stats: &PlayerStats) {
+        text.push_line(format!("Games Played: {}", stats.games_played));
+        text.push_line(format!("Wins:         {}", stats.wins));
+        text.push_line(format!("Busts:        {}", stats.busts));
+        text.push_line(format!("Cards Played: {}", stats.cards_played));
+        text.push_line(format!(
+            "Average Game: {}m {:02}s",
+            stats.average_game_secs() / 60,
+            stats.average_game_secs() % 60
+        ));
+
+        let finishes: Vec<String> = stats
+            .finishes
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(idx, count)| format!("#{} x{count}", idx + 1))
+            .collect();
+
+        if !finishes.is_empty() {
+            text.push_line(format!("Finishes:     {}", finishes.join(", ")));
+        }
+    }
+
+    fn leaderboard_table(leaderboard: &[LeaderboardEntry], scope: Option<GameType>) -> Table<'_> {
+        let header = Row::new(vec!["#", "Name", "Rating", "Wins", "Played", "Busts"]).bold();
+
+        let rows: Vec<Row> = leaderboard
+            .iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                Row::new(vec![
+                    Cell::new((idx + 1).to_string()).light_green(),
+                    Cell::new(entry.name.clone()),
+                    Cell::new(entry.rating.to_string()),
+                    Cell::new(entry.stats.wins.to_string()),
+                    Cell::new(entry.stats.games_played.to_string()),
+                    Cell::new(entry.stats.busts.to_string()),
+                ])
+            })
+            .collect();
+
+        let widths = vec![
+            Constraint::Length(3),
+            Constraint::Length(15),
+            Constraint::Length(7),
+            Constraint::Length(6),
+            Constraint::Length(7),
+            Constraint::Fill(1),
+        ];
+
+        let block = Block::new()
+            .title_top(
+                Line::from(format!(
+                    "Leaderboard ~ {} ( \"g\" to switch )",
+                    Self::game_name(scope)
+                ))
+                .bold()
+                .white(),
+            )
+            .borders(Borders::LEFT)
+            .border_style(Style::new().gray());
+
+        Table::new(rows, widths)
+            .header(header)
+            .style(Style::default().white())
+            .block(block)
+    }
+
+    fn game_name(game_type: Option<GameType>) -> &'static str {
+        match game_type {
+            None => "All Games",
+            Some(game_type) => game_type.name(),
+        }
+    }
+}
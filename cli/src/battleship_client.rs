@@ -0,0 +1,671 @@
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    battleship::{
+        BATTLESHIP_SIZE, BattleshipAction, BattleshipClientAction, BattleshipClientGameState,
+        BattleshipPhase, BattleshipPlayer, ServerBattleshipCommand, Ship, ShipKind, ShotResult,
+    },
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    AppMessage, GameResult, board_table::BoardLayout, card_table, chat_pane::ChatPane, room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Both players place their fleets at once,
+ neither sees the other's.
+Arrow keys move, R turns the ship,
+ Enter puts it down, Backspace takes
+ the last one back up.
+
+Then take turns firing with Enter,
+ sink all five ships to win.
+"#;
+
+/// Each square is drawn this many characters wide
+const CELL_WIDTH: u16 = 2;
+/// Between the two boards
+const BOARD_GAP: &str = "   ";
+
+/// Battleship, two players
+pub struct BattleshipClient;
+
+impl BattleshipClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerBattleshipCommand::GameState(state) = Self::decode(data)?;
+                    server_state = Some(state);
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some(server_state) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: BattleshipClientGameState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<BattleshipAction> = server_state.action.drain(..).collect();
+        let mut cursor: (u8, u8) = (0, 0);
+        let mut horizontal = true;
+        // The fleet being put together, only sent once all of it is down
+        let mut fleet: Vec<Ship> = vec![];
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &events,
+                cursor,
+                Self::next_ship(&fleet, cursor, horizontal),
+                &fleet,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerBattleshipCommand::GameState(mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let placing = Self::placing(&server_state, user_id);
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Char('/') => chat.start_typing(),
+                        KeyCode::Left => {
+                            cursor.0 = (cursor.0 + BATTLESHIP_SIZE - 1) % BATTLESHIP_SIZE;
+                        }
+                        KeyCode::Right => {
+                            cursor.0 = (cursor.0 + 1) % BATTLESHIP_SIZE;
+                        }
+                        KeyCode::Up => {
+                            cursor.1 = (cursor.1 + BATTLESHIP_SIZE - 1) % BATTLESHIP_SIZE;
+                        }
+                        KeyCode::Down => {
+                            cursor.1 = (cursor.1 + 1) % BATTLESHIP_SIZE;
+                        }
+                        KeyCode::Char('r') if placing => horizontal = !horizontal,
+                        KeyCode::Backspace if placing => {
+                            fleet.pop();
+                        }
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup {
+                                if server_state.host_user == user_id {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                        ))
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            if placing {
+                                match Self::next_ship(&fleet, cursor, horizontal) {
+                                    Some((ship, true)) => fleet.push(ship),
+                                    Some((_, false)) => {}
+                                    None => {
+                                        tcp_sender
+                                            .send(&Self::encode(
+                                                user_id,
+                                                BattleshipClientAction::PlaceFleet(fleet.clone()),
+                                            )?)
+                                            .await?;
+                                    }
+                                }
+                            } else if Self::my_turn(&server_state, user_id)
+                                && Self::opponent(&server_state, user_id).is_some_and(|player| {
+                                    player.shots.iter().all(|shot| shot.at != cursor)
+                                })
+                            {
+                                tcp_sender
+                                    .send(&Self::encode(
+                                        user_id,
+                                        BattleshipClientAction::Fire(cursor),
+                                    )?)
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &events,
+                    cursor,
+                    Self::next_ship(&fleet, cursor, horizontal),
+                    &fleet,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712364"))
+    }
+
+    /// Still putting ships down, the server hasn't got a fleet from us yet
+    fn placing(server_state: &BattleshipClientGameState, user_id: u32) -> bool {
+        server_state.game_state == GameStartState::Active
+            && server_state.phase == BattleshipPhase::Placing
+            && server_state
+                .players
+                .iter()
+                .any(|player| player.id == user_id && !player.placed)
+    }
+
+    /// The ship that would go down at the cursor and whether it fits,
+    ///  nothing once the whole fleet is down
+    fn next_ship(fleet: &[Ship], cursor: (u8, u8), horizontal: bool) -> Option<(Ship, bool)> {
+        let kind = ShipKind::FLEET.get(fleet.len())?;
+        let ship = Ship {
+            kind: *kind,
+            at: cursor,
+            horizontal,
+        };
+        let fits = ship.in_bounds() && fleet.iter().all(|other| !ship.overlaps(other));
+
+        Some((ship, fits))
+    }
+
+    fn my_turn(server_state: &BattleshipClientGameState, user_id: u32) -> bool {
+        server_state.game_state == GameStartState::Active && server_state.turn == Some(user_id)
+    }
+
+    fn opponent(
+        server_state: &BattleshipClientGameState,
+        user_id: u32,
+    ) -> Option<&BattleshipPlayer> {
+        server_state
+            .players
+            .iter()
+            .find(|player| player.id != user_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &BattleshipClientGameState,
+        events: &[BattleshipAction],
+        cursor: (u8, u8),
+        next_ship: Option<(Ship, bool)>,
+        fleet: &[Ship],
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active if server_state.phase == BattleshipPhase::Placing => {
+                " Placing Fleets ".to_string()
+            }
+            GameStartState::Active if Self::my_turn(server_state, user_id) => {
+                " My Turn ".to_string()
+            }
+            GameStartState::Active => match server_state
+                .players
+                .iter()
+                .find(|player| Some(player.id) == server_state.turn)
+            {
+                Some(player) => format!(" {}'s Turn ", player.name),
+                None => String::new(),
+            },
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Battleship ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let board_width = (3 + CELL_WIDTH * BATTLESHIP_SIZE as u16) * 2 + BOARD_GAP.len() as u16;
+        let layout = BoardLayout::split(inner, board_width + 3, server_state.players.len());
+
+        Self::boards(
+            frame,
+            layout.board,
+            user_id,
+            server_state,
+            cursor,
+            next_ship,
+            fleet,
+            spectating,
+        );
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::BOTTOM)),
+            layout.users,
+        );
+
+        Self::event_list(frame, layout.events, events);
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        let help = match server_state.game_state {
+            GameStartState::Setup if server_state.host_user == user_id => {
+                format!("{HELP_TEXT}\nPress Enter to start once there are two players")
+            }
+            GameStartState::Setup => format!("{HELP_TEXT}\nWaiting for the host to start"),
+            GameStartState::Active if Self::placing(server_state, user_id) => match next_ship {
+                Some((ship, _)) => format!(
+                    "{HELP_TEXT}\nPlacing the {} ({} long)",
+                    ship.kind.name(),
+                    ship.kind.size()
+                ),
+                None => format!("{HELP_TEXT}\nPress Enter to lock in the fleet"),
+            },
+            GameStartState::Active if server_state.phase == BattleshipPhase::Placing => {
+                format!("{HELP_TEXT}\nWaiting for the other fleet")
+            }
+            _ => HELP_TEXT.to_string(),
+        };
+
+        frame.render_widget(
+            Paragraph::new(help).block(Block::default().borders(Borders::TOP)),
+            help_rows[0],
+        );
+        chat.render(frame, help_rows[1]);
+    }
+
+    /// Our own waters on the left and where we're firing on the right,
+    ///  spectators just get the two players in seat order
+    #[allow(clippy::too_many_arguments)]
+    fn boards(
+        frame: &mut Frame,
+        area: Rect,
+        user_id: u32,
+        server_state: &BattleshipClientGameState,
+        cursor: (u8, u8),
+        next_ship: Option<(Ship, bool)>,
+        fleet: &[Ship],
+        spectating: bool,
+    ) {
+        let (left, right) = if spectating {
+            (server_state.players.first(), server_state.players.get(1))
+        } else {
+            (
+                server_state
+                    .players
+                    .iter()
+                    .find(|player| player.id == user_id),
+                Self::opponent(server_state, user_id),
+            )
+        };
+
+        let placing = Self::placing(server_state, user_id);
+        let firing =
+            Self::my_turn(server_state, user_id) && server_state.phase == BattleshipPhase::Firing;
+
+        // Until the fleet is sent the server doesn't know where anything is
+        let left_ships = match left {
+            Some(_) if placing => fleet,
+            Some(player) => player.ships.as_slice(),
+            None => &[],
+        };
+        let preview = if placing { next_ship } else { None };
+
+        let mut lines = vec![Line::from("")];
+
+        let title = |name: &str| {
+            format!(
+                "   {name:<width$}",
+                width = (CELL_WIDTH * BATTLESHIP_SIZE as u16) as usize
+            )
+        };
+        let name = |player: Option<&BattleshipPlayer>| {
+            player
+                .map(|player| player.name.clone())
+                .unwrap_or("Waiting".to_string())
+        };
+        let (left_title, right_title) = if spectating {
+            (title(&name(left)), title(&name(right)))
+        } else {
+            (title("My Waters"), title(&name(right)))
+        };
+        lines.push(Line::from(format!("{left_title}{BOARD_GAP}{right_title}")).bold());
+
+        let header = format!(
+            "   {}",
+            (0..BATTLESHIP_SIZE)
+                .map(|col| format!(" {}", (b'A' + col) as char))
+                .collect::<String>()
+        );
+        lines.push(Line::from(format!("{header}{BOARD_GAP}{header}")).gray());
+
+        for row in 0..BATTLESHIP_SIZE {
+            let mut spans = vec![];
+
+            spans.push(Span::from(format!("{:>2} ", row + 1)).gray());
+            for col in 0..BATTLESHIP_SIZE {
+                let mut span = Self::square(left, left_ships, preview, (col, row));
+                if placing && cursor == (col, row) {
+                    span = span.on_dark_gray();
+                }
+                spans.push(span);
+            }
+
+            spans.push(Span::from(BOARD_GAP));
+
+            spans.push(Span::from(format!("{:>2} ", row + 1)).gray());
+            for col in 0..BATTLESHIP_SIZE {
+                let ships = right.map(|player| player.ships.as_slice()).unwrap_or(&[]);
+                let mut span = Self::square(right, ships, None, (col, row));
+                if firing && cursor == (col, row) {
+                    span = span.on_dark_gray();
+                }
+                spans.push(span);
+            }
+
+            lines.push(Line::from(spans));
+        }
+
+        lines.push(Line::from(""));
+        if let Some(winner) = server_state.winner.and_then(|winner| {
+            server_state
+                .players
+                .iter()
+                .find(|player| player.id == winner)
+        }) {
+            lines.push(
+                Line::from(format!(" {} sank the whole fleet!", winner.name))
+                    .bold()
+                    .light_green(),
+            );
+        }
+
+        frame.render_widget(
+            Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::RIGHT)),
+            area,
+        );
+    }
+
+    /// One square of a player's waters, with whatever of their fleet we can see
+    fn square(
+        player: Option<&BattleshipPlayer>,
+        ships: &[Ship],
+        preview: Option<(Ship, bool)>,
+        at: (u8, u8),
+    ) -> Span<'static> {
+        let shots = player.map(|player| player.shots.as_slice()).unwrap_or(&[]);
+        let ship = ships.iter().find(|ship| ship.cells().contains(&at));
+
+        if let Some((ship, fits)) = preview
+            && ship.cells().contains(&at)
+        {
+            return if fits {
+                Span::from(" ■").light_yellow()
+            } else {
+                Span::from(" ■").light_red()
+            };
+        }
+
+        match shots.iter().find(|shot| shot.at == at) {
+            Some(shot) if shot.result == ShotResult::Miss => Span::from(" •").white(),
+            Some(_) if ship.is_some_and(|ship| ship.is_sunk(shots)) => {
+                Span::from(" X").red().bold()
+            }
+            Some(_) => Span::from(" X").light_red().bold(),
+            None if ship.is_some() => Span::from(" ■").gray(),
+            None => Span::from(" ~").blue(),
+        }
+    }
+
+    fn user_list(server_state: &BattleshipClientGameState) -> Table<'_> {
+        let rows: Vec<Row> = server_state
+            .players
+            .iter()
+            .map(|player| {
+                let is_turn = server_state.game_state == GameStartState::Active
+                    && server_state.turn == Some(player.id);
+
+                let mut name = vec![Span::from(player.name.clone())];
+                if let Some(rating) = player.rating {
+                    name.push(Span::from(format!(" {rating}")).gray());
+                }
+                if player.id == server_state.host_user {
+                    name.push(Span::from(" H").light_yellow());
+                }
+
+                let sunk = player
+                    .shots
+                    .iter()
+                    .filter(|shot| matches!(shot.result, ShotResult::Sunk(_)))
+                    .count();
+
+                let status = match (server_state.game_state, server_state.phase) {
+                    (GameStartState::Setup, _) => Span::from(""),
+                    (_, BattleshipPhase::Placing) if player.placed => {
+                        Span::from("Ready").light_green()
+                    }
+                    (_, BattleshipPhase::Placing) => Span::from("Placing").gray(),
+                    (_, BattleshipPhase::Firing) => {
+                        Span::from(format!("{} afloat", ShipKind::FLEET.len() - sunk))
+                    }
+                };
+
+                Row::new(vec![
+                    Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                    Cell::new(Line::from(name)),
+                    Cell::new(status),
+                ])
+            })
+            .collect();
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(8),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "Player", "Ships"]).bold())
+            .style(Style::default().white())
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[BattleshipAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                BattleshipAction::Init => Line::from("Server Started"),
+                BattleshipAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                BattleshipAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                BattleshipAction::GameStarted => {
+                    Line::from("~ Game started, place your fleets ~").light_cyan()
+                }
+                BattleshipAction::UserPlacedFleet(user) => {
+                    Line::from(format!("{user} placed their fleet"))
+                }
+                BattleshipAction::FiringStarted(user) => {
+                    Line::from(format!("~ {user} fires first ~")).light_cyan()
+                }
+                BattleshipAction::UserFired(user, (col, row), result) => {
+                    let at = format!("{}{}", (b'A' + col) as char, row + 1);
+                    match result {
+                        ShotResult::Miss => Line::from(format!("{user} fired at {at}, miss")),
+                        ShotResult::Hit => {
+                            Line::from(format!("{user} fired at {at}, hit!")).light_red()
+                        }
+                        ShotResult::Sunk(kind) => {
+                            Line::from(format!("{user} sank the {} at {at}!", kind.name()))
+                                .red()
+                                .bold()
+                        }
+                    }
+                }
+                BattleshipAction::UserWon(user) => {
+                    Line::from(format!("{user} Won! ")).light_green()
+                }
+                BattleshipAction::GameEnded => Line::from("Game Over"),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerBattleshipCommand> {
+        bincode::decode_from_slice::<ServerBattleshipCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: BattleshipClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
@@ -0,0 +1,698 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    blackjack::{
+        BLACKJACK_MAX_BET, BLACKJACK_MIN_BET, BLACKJACK_SHOE_SIZES, BlackjackAction,
+        BlackjackClientAction, BlackjackClientGameState, BlackjackHand, BlackjackPhase,
+        BlackjackPlayer, HandOutcome, ServerBlackjackCommand, hand_total,
+    },
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    AppMessage, GameResult, board_table::BoardLayout, card_table, chat_pane::ChatPane, room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Up and Down change the bet by 10,
+ Left and Right by 100, Enter bets.
+H hits, S stands, D doubles, P splits.
+Y or N when the dealer offers insurance.
+
+Dealer stands on soft 17, blackjack pays 3 to 2.
+"#;
+
+/// The table is drawn this many characters wide
+const TABLE_WIDTH: u16 = 58;
+
+/// Blackjack, everyone against the dealer at once
+pub struct BlackjackClient;
+
+impl BlackjackClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerBlackjackCommand::GameState(state) = Self::decode(data)?;
+                    server_state = Some(state);
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some(server_state) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: BlackjackClientGameState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<BlackjackAction> = server_state.action.drain(..).collect();
+        let mut bet = BLACKJACK_MIN_BET * 5;
+        let mut timer_from = Instant::now();
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        // The phase clock needs redrawing even when nobody is doing anything
+        let mut redraw = tokio::time::interval(Duration::from_millis(500));
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &events,
+                bet,
+                timer_from,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        loop {
+            let msg = tokio::select! {
+                msg = app_receiver.recv() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    msg
+                }
+                _ = redraw.tick() => {
+                    if server_state.time_left_ms.is_some() {
+                        terminal.draw(|frame| {
+                            Self::render(
+                                frame,
+                                user_id,
+                                &lobby,
+                                &server_state,
+                                &events,
+                                bet,
+                                timer_from,
+                                spectating,
+                                &chat,
+                            )
+                        })?;
+                    }
+                    continue;
+                }
+            };
+
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerBlackjackCommand::GameState(mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+                        timer_from = Instant::now();
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let me = server_state
+                        .players
+                        .iter()
+                        .find(|player| player.id == user_id);
+                    let chips = me.map(|player| player.chips).unwrap_or(0);
+                    let setup = server_state.game_state == GameStartState::Setup;
+                    let phase = (!setup).then_some(server_state.phase);
+
+                    let action = match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                            None
+                        }
+                        KeyCode::Char('/') => {
+                            chat.start_typing();
+                            None
+                        }
+                        KeyCode::Left | KeyCode::Right
+                            if setup && server_state.host_user == user_id =>
+                        {
+                            let idx = BLACKJACK_SHOE_SIZES
+                                .iter()
+                                .position(|decks| *decks == server_state.decks)
+                                .unwrap_or(0);
+                            let count = BLACKJACK_SHOE_SIZES.len();
+
+                            let idx = match key_event.code {
+                                KeyCode::Left => (idx + count - 1) % count,
+                                _ => (idx + 1) % count,
+                            };
+
+                            Some(BlackjackClientAction::SetDecks(BLACKJACK_SHOE_SIZES[idx]))
+                        }
+                        KeyCode::Up => {
+                            bet += 10;
+                            None
+                        }
+                        KeyCode::Down => {
+                            bet = bet.saturating_sub(10);
+                            None
+                        }
+                        KeyCode::Right => {
+                            bet += 100;
+                            None
+                        }
+                        KeyCode::Left => {
+                            bet = bet.saturating_sub(100);
+                            None
+                        }
+                        KeyCode::Enter if setup => {
+                            if server_state.host_user == user_id {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                    ))
+                                    .await?;
+                            }
+                            None
+                        }
+                        KeyCode::Enter
+                            if phase == Some(BlackjackPhase::Betting)
+                                && me.is_some_and(|player| player.bet.is_none()) =>
+                        {
+                            Some(BlackjackClientAction::Bet(Self::clamp_bet(bet, chips)))
+                        }
+                        KeyCode::Char('y') | KeyCode::Char('Y')
+                            if phase == Some(BlackjackPhase::Insurance) =>
+                        {
+                            Some(BlackjackClientAction::Insurance(true))
+                        }
+                        KeyCode::Char('n') | KeyCode::Char('N')
+                            if phase == Some(BlackjackPhase::Insurance) =>
+                        {
+                            Some(BlackjackClientAction::Insurance(false))
+                        }
+                        KeyCode::Char('h') | KeyCode::Char('H')
+                            if phase == Some(BlackjackPhase::Playing) =>
+                        {
+                            Some(BlackjackClientAction::Hit)
+                        }
+                        KeyCode::Char('s') | KeyCode::Char('S')
+                            if phase == Some(BlackjackPhase::Playing) =>
+                        {
+                            Some(BlackjackClientAction::Stand)
+                        }
+                        KeyCode::Char('d') | KeyCode::Char('D')
+                            if phase == Some(BlackjackPhase::Playing) =>
+                        {
+                            Some(BlackjackClientAction::Double)
+                        }
+                        KeyCode::Char('p') | KeyCode::Char('P')
+                            if phase == Some(BlackjackPhase::Playing) =>
+                        {
+                            Some(BlackjackClientAction::Split)
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    };
+
+                    bet = Self::clamp_bet(bet, chips);
+
+                    if let Some(action) = action {
+                        tcp_sender.send(&Self::encode(user_id, action)?).await?;
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &events,
+                    bet,
+                    timer_from,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712363"))
+    }
+
+    /// Kept inside the table limits, and what's actually in front of the player
+    fn clamp_bet(bet: u64, chips: u64) -> u64 {
+        bet.clamp(
+            BLACKJACK_MIN_BET,
+            BLACKJACK_MAX_BET.min(chips.max(BLACKJACK_MIN_BET)),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &BlackjackClientGameState,
+        events: &[BlackjackAction],
+        bet: u64,
+        timer_from: Instant,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let phase_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active => match server_state.phase {
+                BlackjackPhase::Betting => " Place Your Bets ".to_string(),
+                BlackjackPhase::Insurance => " Insurance? ".to_string(),
+                BlackjackPhase::Playing => format!(" Round {} ", server_state.round),
+            },
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Blackjack ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(phase_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let layout = BoardLayout::split(inner, TABLE_WIDTH, server_state.players.len());
+
+        Self::table(frame, layout.board, user_id, server_state, bet, timer_from);
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::BOTTOM)),
+            layout.users,
+        );
+
+        Self::event_list(frame, layout.events, events);
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        let help = match server_state.game_state {
+            GameStartState::Setup if server_state.host_user == user_id => {
+                format!("{HELP_TEXT}\nLeft and Right change the shoe, Enter opens the table")
+            }
+            GameStartState::Setup => format!("{HELP_TEXT}\nWaiting for the host to start"),
+            _ => HELP_TEXT.to_string(),
+        };
+
+        frame.render_widget(
+            Paragraph::new(help).block(Block::default().borders(Borders::TOP)),
+            help_rows[0],
+        );
+        chat.render(frame, help_rows[1]);
+    }
+
+    /// The dealer across the top, then every seat with all of its hands
+    fn table(
+        frame: &mut Frame,
+        area: Rect,
+        user_id: u32,
+        server_state: &BlackjackClientGameState,
+        bet: u64,
+        timer_from: Instant,
+    ) {
+        let mut lines = vec![];
+
+        let mut dealer = vec![Span::from(" Dealer  ").bold()];
+        for card in server_state.dealer.iter() {
+            dealer.push(card_table::playing_card_span(card));
+            dealer.push(Span::from(" "));
+        }
+        if server_state.dealer_hidden {
+            dealer.push(Span::from("??").dark_gray());
+        } else if !server_state.dealer.is_empty() {
+            let (total, _) = hand_total(&server_state.dealer);
+            dealer.push(Span::from(format!("  {total}")).gray());
+        }
+        lines.push(Line::from(dealer));
+
+        lines.push(
+            Line::from(format!(
+                " {} deck shoe, {} cards left",
+                server_state.decks, server_state.shoe_left
+            ))
+            .dark_gray(),
+        );
+
+        for player in server_state.players.iter() {
+            lines.push(Line::from(""));
+            lines.extend(Self::seat_lines(player, player.id == user_id));
+        }
+
+        frame.render_widget(
+            Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::RIGHT)),
+            area,
+        );
+
+        let prompt_area = Rect {
+            x: area.x + 1,
+            y: area.bottom().saturating_sub(3),
+            width: area.width.saturating_sub(2),
+            height: 3.min(area.height),
+        };
+
+        let me = server_state
+            .players
+            .iter()
+            .find(|player| player.id == user_id);
+
+        let mut prompt = vec![];
+
+        if let Some(ms) = server_state.time_left_ms {
+            let left = ms.saturating_sub(timer_from.elapsed().as_millis() as u32);
+            prompt.push(Line::from(format!("{}s left", left.div_ceil(1000))).light_yellow());
+        }
+
+        if let Some(me) = me
+            && server_state.game_state == GameStartState::Active
+        {
+            match server_state.phase {
+                BlackjackPhase::Betting if me.bet.is_none() => {
+                    prompt.push(Line::from(vec![
+                        Span::from("Bet "),
+                        Span::from(Self::clamp_bet(bet, me.chips).to_string())
+                            .bold()
+                            .light_yellow(),
+                        Span::from(", Enter to place it"),
+                    ]));
+                }
+                BlackjackPhase::Betting => {
+                    prompt.push(Line::from("Waiting for the others to bet"));
+                }
+                BlackjackPhase::Insurance if !me.hands.is_empty() && me.insurance.is_none() => {
+                    prompt.push(Line::from("Dealer shows an ace, insurance? Y / N"));
+                }
+                BlackjackPhase::Playing if me.active_hand.is_some() => {
+                    prompt.push(Line::from("H hit, S stand, D double, P split"));
+                }
+                _ => prompt.push(Line::from("Waiting on the rest of the table")),
+            }
+        }
+
+        frame.render_widget(Paragraph::new(Text::from(prompt)), prompt_area);
+    }
+
+    fn seat_lines(player: &BlackjackPlayer, is_me: bool) -> Vec<Line<'static>> {
+        let name = if is_me {
+            Span::from(format!(" {}", player.name)).bold().light_green()
+        } else {
+            Span::from(format!(" {}", player.name)).bold()
+        };
+
+        let mut header = vec![name, Span::from(format!("  {} chips", player.chips)).gray()];
+
+        if let Some(bet) = player.bet {
+            header.push(Span::from(format!("  bet {bet}")).light_yellow());
+        }
+
+        if let Some(insurance) = player.insurance.filter(|insurance| *insurance > 0) {
+            header.push(Span::from(format!("  insured {insurance}")).light_cyan());
+        }
+
+        match player.last_net {
+            Some(net) if net > 0 => header.push(Span::from(format!("  +{net}")).light_green()),
+            Some(net) if net < 0 => header.push(Span::from(format!("  {net}")).light_red()),
+            Some(_) => header.push(Span::from("  even").gray()),
+            None => {}
+        }
+
+        let mut lines = vec![Line::from(header)];
+
+        for (idx, hand) in player.hands.iter().enumerate() {
+            lines.push(Self::hand_line(hand, player.active_hand == Some(idx as u8)));
+        }
+
+        lines
+    }
+
+    fn hand_line(hand: &BlackjackHand, active: bool) -> Line<'static> {
+        let mut spans = vec![if active {
+            Span::from("  > ").bold().light_green()
+        } else {
+            Span::from("    ")
+        }];
+
+        for card in hand.cards.iter() {
+            spans.push(card_table::playing_card_span(card));
+            spans.push(Span::from(" "));
+        }
+
+        let total = if hand.is_blackjack() {
+            Span::from(" BJ").bold().light_yellow()
+        } else {
+            let (total, soft) = hand_total(&hand.cards);
+            match (total, soft) {
+                (total, _) if total > 21 => Span::from(format!(" {total}")).light_red(),
+                (total, true) => Span::from(format!(" soft {total}")),
+                (total, false) => Span::from(format!(" {total}")),
+            }
+        };
+        spans.push(total);
+
+        let doubled = if hand.doubled { " doubled" } else { "" };
+        spans.push(Span::from(format!("  {}{doubled}", hand.bet)).gray());
+
+        if let Some(outcome) = hand.outcome {
+            let outcome_span = Span::from(format!("  {}", outcome.name())).bold();
+            spans.push(match outcome {
+                HandOutcome::Blackjack | HandOutcome::Won => outcome_span.light_green(),
+                HandOutcome::Push => outcome_span.gray(),
+                HandOutcome::Lost | HandOutcome::Bust => outcome_span.light_red(),
+            });
+        }
+
+        Line::from(spans)
+    }
+
+    fn user_list(server_state: &BlackjackClientGameState) -> Table<'_> {
+        let rows: Vec<Row> = server_state
+            .players
+            .iter()
+            .map(|player| {
+                let mut name = vec![Span::from(player.name.clone())];
+                if let Some(rating) = player.rating {
+                    name.push(Span::from(format!(" {rating}")).gray());
+                }
+                if player.id == server_state.host_user {
+                    name.push(Span::from(" H").light_yellow());
+                }
+
+                let waiting = match server_state.phase {
+                    BlackjackPhase::Betting => player.bet.is_none(),
+                    BlackjackPhase::Insurance => {
+                        !player.hands.is_empty() && player.insurance.is_none()
+                    }
+                    BlackjackPhase::Playing => player.active_hand.is_some(),
+                } && server_state.game_state == GameStartState::Active;
+
+                Row::new(vec![
+                    Cell::new(if waiting { ">" } else { " " }).light_green(),
+                    Cell::new(Line::from(name)),
+                    Cell::new(player.chips.to_string()).light_yellow(),
+                ])
+            })
+            .collect();
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(8),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "Player", "Chips"]).bold())
+            .style(Style::default().white())
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[BlackjackAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                BlackjackAction::Init => Line::from("Server Started"),
+                BlackjackAction::UserJoined(user) => Line::from(format!("{user} Sat Down ")),
+                BlackjackAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                BlackjackAction::DecksChanged(decks) => {
+                    Line::from(format!("Shoe changed to {decks} decks"))
+                }
+                BlackjackAction::GameStarted => Line::from("~ The table is open ~").light_cyan(),
+                BlackjackAction::ShoeShuffled => Line::from("The shoe was shuffled").gray(),
+                BlackjackAction::RoundDealt(round) => {
+                    Line::from(format!("~ Round {round} dealt ~")).light_cyan()
+                }
+                BlackjackAction::UserInsured(user, amount) => {
+                    Line::from(format!("{user} insured for {amount}"))
+                }
+                BlackjackAction::DealerPeeked(true) => {
+                    Line::from("Dealer has blackjack").light_red()
+                }
+                BlackjackAction::DealerPeeked(false) => Line::from("Dealer doesn't have blackjack"),
+                BlackjackAction::UserHit(user, card) => Line::from(vec![
+                    Span::from(format!("{user} hit, ")),
+                    card_table::playing_card_span(card),
+                ]),
+                BlackjackAction::UserStood(user) => Line::from(format!("{user} stood")),
+                BlackjackAction::UserDoubled(user, card) => Line::from(vec![
+                    Span::from(format!("{user} doubled, ")),
+                    card_table::playing_card_span(card),
+                ]),
+                BlackjackAction::UserSplit(user) => Line::from(format!("{user} split")),
+                BlackjackAction::UserBusted(user) => {
+                    Line::from(format!("{user} busted")).light_red()
+                }
+                BlackjackAction::DealerStood(total) => {
+                    Line::from(format!("Dealer stands on {total}"))
+                }
+                BlackjackAction::DealerBusted => Line::from("Dealer busted").light_green(),
+                BlackjackAction::UserSettled(user, net) if *net > 0 => {
+                    Line::from(format!("{user} won {net}")).light_green()
+                }
+                BlackjackAction::UserSettled(user, net) if *net < 0 => {
+                    Line::from(format!("{user} lost {}", -net)).light_red()
+                }
+                BlackjackAction::UserSettled(user, _) => Line::from(format!("{user} broke even")),
+                BlackjackAction::UserBroke(user) => {
+                    Line::from(format!("{user} can't cover the minimum bet")).light_yellow()
+                }
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerBlackjackCommand> {
+        bincode::decode_from_slice::<ServerBlackjackCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: BlackjackClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
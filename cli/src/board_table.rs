@@ -0,0 +1,45 @@
+use std::cmp::max;
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+/// The screen layout shared by the board games, the grid takes the left side:
+///
+/// +-------+--------~
+/// |       | User List
+/// | Board +--------~
+/// |       | Events
+/// |       +--------~
+/// |       | Help
+/// +-------+--------~
+pub struct BoardLayout {
+    pub board: Rect,
+    pub users: Rect,
+    pub events: Rect,
+    pub help: Rect,
+}
+
+impl BoardLayout {
+    /// The board is given exactly the width it needs, everything else shares what's left
+    pub fn split(area: Rect, board_width: u16, user_rows: usize) -> BoardLayout {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(board_width), Constraint::Fill(1)])
+            .split(area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(max(4, user_rows as u16 + 1)),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+            ])
+            .split(columns[1]);
+
+        BoardLayout {
+            board: columns[0],
+            users: rows[0],
+            events: rows[1],
+            help: rows[2],
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::cmp::max;
+
+use ratatui::{
+    Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Paragraph},
+};
+use rpc::cards::PlayingCard;
+
+/// The screen layout shared by the card games, first used for Uno:
+///
+/// +------+------+------~
+/// | User | Last | Events
+/// | List | Card |
+/// +------+------+------~
+/// |             |
+/// | Local Cards | Help
+/// |             |
+/// +-------------+------~
+/// | Turn Timer         ~
+/// +--------------------~
+pub struct TableLayout {
+    pub users: Rect,
+    pub last_card: Rect,
+    pub events: Rect,
+    pub hand: Rect,
+    pub help: Rect,
+    pub timer: Rect,
+}
+
+impl TableLayout {
+    /// Bigger tables need more room for the user list
+    pub fn split(area: Rect, user_rows: usize, has_timer: bool) -> TableLayout {
+        let row_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(max(6, user_rows as u16)),
+                Constraint::Fill(1),
+                Constraint::Length(if has_timer { 1 } else { 0 }),
+            ])
+            .split(area);
+
+        let top_columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(35),
+                Constraint::Length(25),
+                Constraint::Fill(1),
+            ])
+            .split(row_chunks[0]);
+
+        let bottom_columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(60), Constraint::Fill(40)])
+            .split(row_chunks[1]);
+
+        TableLayout {
+            users: top_columns[0],
+            last_card: top_columns[1],
+            events: top_columns[2],
+            hand: bottom_columns[0],
+            help: bottom_columns[1],
+            timer: row_chunks[2],
+        }
+    }
+}
+
+/// Cards are laid out 10 to a row, with a marker above the selected one
+pub fn hand<'a, T>(
+    frame: &mut Frame,
+    mut area: Rect,
+    cards: &'a [T],
+    card_idx: usize,
+    card_text: impl Fn(&'a T) -> Paragraph<'a>,
+) {
+    for (i, card) in cards.iter().enumerate() {
+        let rect = Rect {
+            x: area.x + ((i % 10) * 6) as u16,
+            y: area.y + ((i / 10) * 5) as u16 + 2,
+            width: 5,
+            height: 4,
+        };
+
+        frame.render_widget(card_text(card), rect);
+    }
+
+    area.x += ((card_idx % 10) * 6) as u16 + 2;
+    area.y += ((card_idx / 10) * 5) as u16 + 1;
+    area.height = 1;
+    area.width = 1;
+
+    frame.render_widget(Paragraph::new("v").bold().white(), area);
+}
+
+/// A single card from a normal deck, sized to fit the hand layout.
+/// Cards that can't be played are dimmed.
+pub fn playing_card_text(card: &PlayingCard, playable: bool) -> Paragraph<'static> {
+    let mut block = Block::bordered();
+
+    block = match (playable, card.suit.is_red()) {
+        (false, _) => block.dark_gray(),
+        (true, true) => block.light_red(),
+        (true, false) => block.white(),
+    };
+
+    Paragraph::new(Text::from(format!(
+        "{:>2}\n {}",
+        card.rank.short(),
+        card.suit.symbol()
+    )))
+    .block(block)
+}
+
+pub fn playing_card_span(card: &PlayingCard) -> Span<'static> {
+    let span = Span::from(card.to_string());
+
+    if card.suit.is_red() {
+        span.light_red()
+    } else {
+        span.white()
+    }
+}
+
+pub fn render_loading(frame: &mut Frame, user_name: &str, lobby: &str) {
+    frame.render_widget(
+        Paragraph::new(Text::from(Line::from("Loading Into Server").bold())).block(
+            Block::bordered()
+                .border_style(Style::new().light_blue())
+                .title_top(
+                    Line::from(format!(" Tempest ~ {user_name} ~ {lobby} "))
+                        .bold()
+                        .white(),
+                )
+                .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned()),
+        ),
+        frame.area(),
+    )
+}
@@ -0,0 +1,124 @@
+use crossterm::event::KeyCode;
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Paragraph},
+};
+use rpc::chat::{CHAT_HISTORY, CHAT_MAX_LEN, ChatChannel, ChatMessage};
+
+/// The chat box shared by the lobby and the game screens.
+///
+/// Press "/" to start typing, Enter sends and Esc gives up on the message.
+/// While typing every key goes to the chat, so nothing else gets triggered by accident.
+#[derive(Debug, Clone)]
+pub struct ChatPane {
+    pub channel: ChatChannel,
+    messages: Vec<ChatMessage>,
+    input: Option<Vec<char>>,
+    /// Why the last message didn't go through
+    rejected: Option<String>,
+}
+
+impl ChatPane {
+    pub fn new(channel: ChatChannel) -> ChatPane {
+        ChatPane {
+            channel,
+            messages: vec![],
+            input: None,
+            rejected: None,
+        }
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.input.is_some()
+    }
+
+    pub fn start_typing(&mut self) {
+        self.input = Some(vec![]);
+        self.rejected = None;
+    }
+
+    /// Messages for other channels are left alone
+    pub fn push(&mut self, message: ChatMessage) {
+        if message.channel != self.channel {
+            return;
+        }
+
+        self.messages.push(message);
+        if self.messages.len() > CHAT_HISTORY {
+            self.messages.remove(0);
+        }
+    }
+
+    pub fn set_history(&mut self, channel: ChatChannel, history: Vec<ChatMessage>) {
+        if channel == self.channel {
+            self.messages = history;
+        }
+    }
+
+    pub fn rejected(&mut self, reason: String) {
+        self.rejected = Some(reason);
+    }
+
+    /// Gives back the message once Enter is pressed
+    pub fn key(&mut self, code: KeyCode) -> Option<String> {
+        let input = self.input.as_mut()?;
+
+        match code {
+            KeyCode::Char(c) if input.len() < CHAT_MAX_LEN => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                let text = String::from_iter(self.input.take()?);
+                if !text.trim().is_empty() {
+                    return Some(text);
+                }
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    /// The newest messages that fit, with the input line at the bottom
+    pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::default()
+            .borders(Borders::TOP)
+            .border_style(Style::new().gray())
+            .title_top(Line::from(" Chat ( \"/\" to talk ) ").bold().white());
+
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let shown = (inner.height as usize).saturating_sub(1);
+        let skip = self.messages.len().saturating_sub(shown);
+
+        let mut text = Text::default();
+        for _ in self.messages.len().min(shown)..shown {
+            text.push_line(Line::from(""));
+        }
+
+        for message in self.messages.iter().skip(skip) {
+            text.push_line(Line::from(vec![
+                Span::from(format!("{}: ", message.from)).light_cyan(),
+                Span::from(message.text.clone()),
+            ]));
+        }
+
+        text.push_line(match (&self.input, &self.rejected) {
+            (Some(input), _) => Line::from(vec![
+                Span::from("> ").blue(),
+                Span::from(String::from_iter(input)),
+                Span::from("_").gray(),
+            ]),
+            (None, Some(reason)) => Line::from(reason.clone()).light_red(),
+            (None, None) => Line::from(""),
+        });
+
+        frame.render_widget(Paragraph::new(text), inner);
+    }
+}
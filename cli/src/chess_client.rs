@@ -0,0 +1,801 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    chat::ChatChannel,
+    chess::{
+        self, ChessAction, ChessClientAction, ChessClientGameState, ChessMove, PieceKind,
+        ServerChessCommand, square_name,
+    },
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    AppMessage, GameResult, board_table::BoardLayout, card_table, chat_pane::ChatPane, room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Move the cursor with the arrow keys,
+ Enter picks up a piece, Enter again puts it down.
+Esc drops the piece you're holding.
+Promotions ask which piece you want.
+
+Press R twice to resign.
+"#;
+
+const LIGHT_SQUARE: Color = Color::Rgb(200, 170, 120);
+const DARK_SQUARE: Color = Color::Rgb(140, 100, 60);
+const LAST_MOVE_SQUARE: Color = Color::Rgb(190, 190, 80);
+const TARGET_SQUARE: Color = Color::Rgb(100, 160, 100);
+
+/// Chess for two, the board is turned round for black
+pub struct ChessClient;
+
+/// Everything about where the user is in making a move
+#[derive(Default)]
+struct MoveInput {
+    /// File then rank, from white's side
+    cursor: (u8, u8),
+    picked_up: Option<u8>,
+    /// Waiting on which piece to promote to, the index into `PieceKind::PROMOTIONS`
+    promoting: Option<(ChessMove, usize)>,
+    resign_armed: bool,
+}
+
+impl ChessClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerChessCommand::GameState(state) = Self::decode(data)?;
+                    server_state = Some(state);
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some(server_state) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: ChessClientGameState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<ChessAction> = server_state.action.drain(..).collect();
+        let mut input = MoveInput {
+            cursor: (4, 1),
+            ..Default::default()
+        };
+        let mut clocks_from = Instant::now();
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        // The clocks need redrawing even when nobody is moving
+        let mut redraw = tokio::time::interval(Duration::from_millis(250));
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &events,
+                &input,
+                clocks_from,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        loop {
+            let msg = tokio::select! {
+                msg = app_receiver.recv() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    msg
+                }
+                _ = redraw.tick() => {
+                    if server_state.game_state == GameStartState::Active
+                        && server_state.clocks_ms.is_some()
+                    {
+                        terminal.draw(|frame| {
+                            Self::render(
+                                frame,
+                                user_id,
+                                &lobby,
+                                &server_state,
+                                &events,
+                                &input,
+                                clocks_from,
+                                spectating,
+                                &chat,
+                            )
+                        })?;
+                    }
+                    continue;
+                }
+            };
+
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerChessCommand::GameState(mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+
+                        // Black starts looking at their own pieces
+                        if server_state.game_state == GameStartState::Setup
+                            && state.game_state == GameStartState::Active
+                            && Self::my_color(&state, user_id) == Some(chess::Color::Black)
+                        {
+                            input.cursor = (4, 6);
+                        }
+
+                        // Whatever was picked up may not be ours to move any more
+                        if state.position != server_state.position {
+                            input.picked_up = None;
+                            input.promoting = None;
+                        }
+
+                        server_state = state;
+                        clocks_from = Instant::now();
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let resign_armed = std::mem::take(&mut input.resign_armed);
+                    let my_color = Self::my_color(&server_state, user_id);
+                    // Up the screen is towards the other player
+                    let forward: i8 = match my_color {
+                        Some(chess::Color::Black) => -1,
+                        _ => 1,
+                    };
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Char('/') => chat.start_typing(),
+                        KeyCode::Left | KeyCode::Right if input.promoting.is_some() => {
+                            if let Some((_, idx)) = input.promoting.as_mut() {
+                                let count = PieceKind::PROMOTIONS.len();
+                                *idx = if key_event.code == KeyCode::Left {
+                                    (*idx + count - 1) % count
+                                } else {
+                                    (*idx + 1) % count
+                                };
+                            }
+                        }
+                        KeyCode::Char(letter) if input.promoting.is_some() => {
+                            let Some(idx) = PieceKind::PROMOTIONS.iter().position(|kind| {
+                                kind.letter().eq_ignore_ascii_case(&letter.to_string())
+                            }) else {
+                                continue;
+                            };
+
+                            if let Some((mut mv, _)) = input.promoting.take() {
+                                mv.promotion = Some(PieceKind::PROMOTIONS[idx]);
+                                input.picked_up = None;
+                                tcp_sender
+                                    .send(&Self::encode(user_id, ChessClientAction::Move(mv))?)
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Left => input.cursor.0 = Self::step(input.cursor.0, -forward),
+                        KeyCode::Right => input.cursor.0 = Self::step(input.cursor.0, forward),
+                        KeyCode::Up => input.cursor.1 = Self::step(input.cursor.1, forward),
+                        KeyCode::Down => input.cursor.1 = Self::step(input.cursor.1, -forward),
+                        KeyCode::Char('t') | KeyCode::Char('T')
+                            if server_state.game_state == GameStartState::Setup
+                                && server_state.host_user == user_id =>
+                        {
+                            tcp_sender
+                                .send(&Self::encode(user_id, ChessClientAction::CycleTimeControl)?)
+                                .await?;
+                        }
+                        KeyCode::Char('r') | KeyCode::Char('R')
+                            if server_state.game_state == GameStartState::Active
+                                && my_color.is_some() =>
+                        {
+                            if resign_armed {
+                                tcp_sender
+                                    .send(&Self::encode(user_id, ChessClientAction::Resign)?)
+                                    .await?;
+                            } else {
+                                input.resign_armed = true;
+                            }
+                        }
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup {
+                                if server_state.host_user == user_id {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                        ))
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            if let Some((mut mv, idx)) = input.promoting.take() {
+                                mv.promotion = Some(PieceKind::PROMOTIONS[idx]);
+                                input.picked_up = None;
+                                tcp_sender
+                                    .send(&Self::encode(user_id, ChessClientAction::Move(mv))?)
+                                    .await?;
+                                continue;
+                            }
+
+                            if let Some(mv) = Self::enter(&server_state, my_color, &mut input) {
+                                tcp_sender
+                                    .send(&Self::encode(user_id, ChessClientAction::Move(mv))?)
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Esc if input.promoting.is_some() || input.picked_up.is_some() => {
+                            input.promoting = None;
+                            input.picked_up = None;
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &events,
+                    &input,
+                    clocks_from,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712359"))
+    }
+
+    fn step(value: u8, by: i8) -> u8 {
+        (value as i8 + by).clamp(0, 7) as u8
+    }
+
+    fn my_color(server_state: &ChessClientGameState, user_id: u32) -> Option<chess::Color> {
+        server_state
+            .players
+            .iter()
+            .find(|player| player.id == user_id)
+            .and_then(|player| player.color)
+    }
+
+    /// Picks up the piece under the cursor, or puts the one held down there.
+    /// Hands back the move once it's ready to send.
+    fn enter(
+        server_state: &ChessClientGameState,
+        my_color: Option<chess::Color>,
+        input: &mut MoveInput,
+    ) -> Option<ChessMove> {
+        if server_state.game_state != GameStartState::Active
+            || my_color != Some(server_state.position.to_move)
+        {
+            return None;
+        }
+
+        let position = &server_state.position;
+        let square = input.cursor.1 * 8 + input.cursor.0;
+        let legal = position.legal_moves();
+
+        if let Some(from) = input.picked_up {
+            // Promotions come four to a square, the piece is picked after
+            let found = legal
+                .iter()
+                .find(|mv| mv.from == from && mv.to == square)
+                .copied();
+
+            match found {
+                Some(mv) if mv.promotion.is_some() => {
+                    input.promoting = Some((mv, 0));
+                    return None;
+                }
+                Some(mv) => {
+                    input.picked_up = None;
+                    return Some(mv);
+                }
+                None => {}
+            }
+        }
+
+        // Anything else just swaps which piece is held, or puts it back
+        let own_piece = position.board[square as usize]
+            .is_some_and(|piece| Some(piece.color) == my_color)
+            && legal.iter().any(|mv| mv.from == square);
+
+        input.picked_up = (own_piece && input.picked_up != Some(square)).then_some(square);
+
+        None
+    }
+
+    /// What's left on each clock, the side to move's counted down since the state arrived
+    fn clocks_left(server_state: &ChessClientGameState, clocks_from: Instant) -> Option<[u32; 2]> {
+        let mut clocks = server_state.clocks_ms?;
+
+        if server_state.game_state == GameStartState::Active {
+            let left = &mut clocks[server_state.position.to_move.idx()];
+            *left = left.saturating_sub(clocks_from.elapsed().as_millis() as u32);
+        }
+
+        Some(clocks)
+    }
+
+    fn clock_text(ms: u32) -> String {
+        let secs = ms.div_ceil(1000);
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+
+    fn time_control_name(time_control: Option<(u32, u32)>) -> String {
+        match time_control {
+            Some((minutes, 0)) => format!("{minutes} min"),
+            Some((minutes, increment)) => format!("{minutes} | {increment}"),
+            None => "Untimed".to_string(),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &ChessClientGameState,
+        events: &[ChessAction],
+        input: &MoveInput,
+        clocks_from: Instant,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let my_color = Self::my_color(server_state, user_id);
+
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active if my_color == Some(server_state.position.to_move) => {
+                " My Turn ".to_string()
+            }
+            GameStartState::Active => {
+                format!(" {} To Move ", server_state.position.to_move.name())
+            }
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Chess ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let layout = BoardLayout::split(inner, 32, server_state.players.len());
+
+        let board_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(12), Constraint::Fill(1)])
+            .split(layout.board);
+
+        Self::board(
+            frame,
+            board_rows[0],
+            server_state,
+            input,
+            my_color == Some(chess::Color::Black),
+            spectating,
+        );
+        Self::move_list(frame, board_rows[1], server_state);
+
+        frame.render_widget(
+            Self::user_list(server_state, clocks_from)
+                .block(Block::default().borders(Borders::BOTTOM)),
+            layout.users,
+        );
+
+        Self::event_list(frame, layout.events, events);
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        let time_control = Self::time_control_name(server_state.time_control);
+        let help = match server_state.game_state {
+            GameStartState::Setup if server_state.host_user == user_id => format!(
+                "{HELP_TEXT}\nClock: {time_control}, T to change it\nPress Enter to start once there are two players"
+            ),
+            GameStartState::Setup => {
+                format!("{HELP_TEXT}\nClock: {time_control}\nWaiting for the host to start")
+            }
+            _ if input.resign_armed => format!("{HELP_TEXT}\nPress R again to resign"),
+            _ => HELP_TEXT.to_string(),
+        };
+
+        frame.render_widget(
+            Paragraph::new(help).block(Block::default().borders(Borders::TOP)),
+            help_rows[0],
+        );
+        chat.render(frame, help_rows[1]);
+    }
+
+    /// Rank 8 at the top for white, rank 1 for black
+    fn board(
+        frame: &mut Frame,
+        area: Rect,
+        server_state: &ChessClientGameState,
+        input: &MoveInput,
+        flipped: bool,
+        spectating: bool,
+    ) {
+        let position = &server_state.position;
+        let show_cursor = !spectating && server_state.game_state == GameStartState::Active;
+
+        let targets: Vec<u8> = input
+            .picked_up
+            .map(|from| {
+                position
+                    .legal_moves()
+                    .iter()
+                    .filter(|mv| mv.from == from)
+                    .map(|mv| mv.to)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let checked_king = (0..64u8).find(|square| {
+            position.board[*square as usize].is_some_and(|piece| {
+                piece.kind == PieceKind::King
+                    && piece.color == position.to_move
+                    && position.in_check(piece.color)
+            })
+        });
+
+        let files: Vec<u8> = if flipped {
+            (0..8).rev().collect()
+        } else {
+            (0..8).collect()
+        };
+        let ranks: Vec<u8> = if flipped {
+            (0..8).collect()
+        } else {
+            (0..8).rev().collect()
+        };
+
+        let mut lines = vec![Line::from("")];
+
+        for rank in &ranks {
+            let mut spans = vec![Span::from(format!(" {} ", rank + 1)).gray()];
+
+            for file in &files {
+                let square = rank * 8 + file;
+
+                let mut bg = if (rank + file) % 2 == 0 {
+                    DARK_SQUARE
+                } else {
+                    LIGHT_SQUARE
+                };
+
+                if server_state
+                    .last_move
+                    .is_some_and(|mv| mv.from == square || mv.to == square)
+                {
+                    bg = LAST_MOVE_SQUARE;
+                }
+                if targets.contains(&square) {
+                    bg = TARGET_SQUARE;
+                }
+                if checked_king == Some(square) {
+                    bg = Color::LightRed;
+                }
+                if input.picked_up == Some(square) {
+                    bg = Color::LightGreen;
+                }
+                if show_cursor && input.cursor == (*file, *rank) {
+                    bg = Color::LightBlue;
+                }
+
+                let span = match position.board[square as usize] {
+                    Some(piece) => {
+                        let fg = match piece.color {
+                            chess::Color::White => Color::White,
+                            chess::Color::Black => Color::Black,
+                        };
+                        Span::from(format!(" {} ", piece.kind.symbol()))
+                            .fg(fg)
+                            .bold()
+                    }
+                    None if targets.contains(&square) => Span::from(" · ").black(),
+                    None => Span::from("   "),
+                };
+
+                spans.push(span.bg(bg));
+            }
+
+            lines.push(Line::from(spans));
+        }
+
+        lines.push(
+            Line::from(format!(
+                "   {}",
+                files
+                    .iter()
+                    .map(|file| format!(" {} ", (b'a' + file) as char))
+                    .collect::<String>()
+            ))
+            .gray(),
+        );
+
+        if let Some((mv, idx)) = &input.promoting {
+            let mut spans = vec![Span::from(format!(" {} = ", square_name(mv.to)))];
+            for (i, kind) in PieceKind::PROMOTIONS.iter().enumerate() {
+                let span = Span::from(format!(" {} ", kind.letter()));
+                spans.push(if i == *idx {
+                    span.bold().on_light_blue()
+                } else {
+                    span
+                });
+            }
+            lines.push(Line::from(spans));
+        } else if let Some(outcome) = &server_state.outcome {
+            lines.push(
+                Line::from(format!(" {}", outcome.description()))
+                    .bold()
+                    .light_green(),
+            );
+        } else if checked_king.is_some() {
+            lines.push(Line::from(" Check!").bold().light_red());
+        }
+
+        frame.render_widget(
+            Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::RIGHT)),
+            area,
+        );
+    }
+
+    /// Numbered in pairs, only the latest moves if they don't all fit
+    fn move_list(frame: &mut Frame, area: Rect, server_state: &ChessClientGameState) {
+        let lines: Vec<Line> = server_state
+            .moves
+            .chunks(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                Line::from(format!(
+                    " {:>3}. {:<8} {}",
+                    i + 1,
+                    pair[0],
+                    pair.get(1).map(String::as_str).unwrap_or("")
+                ))
+            })
+            .collect();
+
+        let skip = lines
+            .len()
+            .saturating_sub(area.height.saturating_sub(1) as usize);
+
+        frame.render_widget(
+            Paragraph::new(Text::from(lines.into_iter().skip(skip).collect::<Vec<_>>()))
+                .block(Block::default().borders(Borders::TOP | Borders::RIGHT)),
+            area,
+        );
+    }
+
+    fn user_list(server_state: &ChessClientGameState, clocks_from: Instant) -> Table<'_> {
+        let clocks = Self::clocks_left(server_state, clocks_from);
+
+        let rows: Vec<Row> = server_state
+            .players
+            .iter()
+            .map(|player| {
+                let is_turn = server_state.game_state == GameStartState::Active
+                    && player.color == Some(server_state.position.to_move);
+
+                let mut name = vec![Span::from(player.name.clone())];
+                if let Some(rating) = player.rating {
+                    name.push(Span::from(format!(" {rating}")).gray());
+                }
+                if player.id == server_state.host_user {
+                    name.push(Span::from(" H").light_yellow());
+                }
+
+                let side = match player.color {
+                    Some(chess::Color::White) => Span::from("♔").white(),
+                    Some(chess::Color::Black) => Span::from("♚").gray(),
+                    None => Span::from(""),
+                };
+
+                let clock = match (clocks, player.color) {
+                    (Some(clocks), Some(color)) => {
+                        let ms = clocks[color.idx()];
+                        let span = Span::from(Self::clock_text(ms));
+                        if !is_turn {
+                            span.gray()
+                        } else if ms < 20_000 {
+                            span.bold().light_red()
+                        } else {
+                            span.bold().white()
+                        }
+                    }
+                    _ => Span::from(""),
+                };
+
+                Row::new(vec![
+                    Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                    Cell::new(side),
+                    Cell::new(Line::from(name)),
+                    Cell::new(clock),
+                ])
+            })
+            .collect();
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(6),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "", "Player", "Clock"]).bold())
+            .style(Style::default().white())
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[ChessAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                ChessAction::Init => Line::from("Server Started"),
+                ChessAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                ChessAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                ChessAction::GameStarted(white, black) => {
+                    Line::from(format!("~ {white} has white, {black} has black ~")).light_cyan()
+                }
+                ChessAction::Moved(user, text) => Line::from(format!("{user} played {text}")),
+                ChessAction::GameEnded(outcome) => Line::from(outcome.description()).light_green(),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerChessCommand> {
+        bincode::decode_from_slice::<ServerChessCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: ChessClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
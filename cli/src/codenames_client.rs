@@ -0,0 +1,720 @@
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    chat::ChatChannel,
+    codenames::{
+        CODENAMES_GRID_SIZE, CODENAMES_MAX_CLUE_LEN, CardKind, Clue, CodenamesAction,
+        CodenamesClientAction, CodenamesClientGameState, Role, ServerCodenamesCommand, Team,
+    },
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    AppMessage, GameResult, board_table::BoardLayout, card_table, chat_pane::ChatPane, room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Spymasters see who every word belongs to,
+ press C to give a clue, a word and a number.
+Operatives use the arrow keys and Enter to
+ guess, E stops guessing early.
+A miss ends the turn, the assassin loses it all.
+"#;
+
+const SETUP_HELP: &str = r#"Picking teams:
+T swaps team, S swaps between spymaster
+ and operative.
+Each team needs one spymaster and at least
+ one operative.
+"#;
+
+/// Each card is drawn this many characters wide
+const CARD_WIDTH: u16 = 14;
+const CARD_HEIGHT: u16 = 3;
+
+/// Codenames, two teams with a spymaster each
+pub struct CodenamesClient;
+
+impl CodenamesClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerCodenamesCommand::GameState(state) = Self::decode(data)?;
+                    server_state = Some(state);
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some(server_state) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: CodenamesClientGameState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<CodenamesAction> = server_state.action.drain(..).collect();
+        let mut cursor: usize = 0;
+        // Some while the spymaster is typing a clue, it's sent as "word number"
+        let mut clue_input: Option<String> = None;
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &events,
+                cursor,
+                clue_input.as_deref(),
+                spectating,
+                &chat,
+            )
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerCodenamesCommand::GameState(mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+
+                        if !Self::can_give_clue(&server_state, user_id) {
+                            clue_input = None;
+                        }
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let me = server_state
+                        .players
+                        .iter()
+                        .find(|player| player.id == user_id)
+                        .map(|player| (player.team, player.role));
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        code if clue_input.is_some() => {
+                            let Some(input) = clue_input.as_mut() else {
+                                continue;
+                            };
+
+                            match code {
+                                KeyCode::Char(ch) if input.len() < CODENAMES_MAX_CLUE_LEN + 3 => {
+                                    input.push(ch)
+                                }
+                                KeyCode::Backspace => {
+                                    input.pop();
+                                }
+                                KeyCode::Esc => clue_input = None,
+                                KeyCode::Enter => {
+                                    if let Some(clue) = Self::parse_clue(input)
+                                        && clue.is_valid(&server_state.cards)
+                                    {
+                                        tcp_sender
+                                            .send(&Self::encode(
+                                                user_id,
+                                                CodenamesClientAction::GiveClue(clue),
+                                            )?)
+                                            .await?;
+                                        clue_input = None;
+                                    }
+                                }
+                                _ => continue,
+                            }
+                        }
+                        KeyCode::Char('/') => chat.start_typing(),
+                        KeyCode::Char('t') | KeyCode::Char('T')
+                            if server_state.game_state == GameStartState::Setup =>
+                        {
+                            let Some((team, role)) = me else {
+                                continue;
+                            };
+
+                            // The other side's spymaster spot might be taken already
+                            let team = team.other();
+                            let role = if role == Role::Spymaster
+                                && server_state.players.iter().any(|player| {
+                                    player.team == team && player.role == Role::Spymaster
+                                }) {
+                                Role::Operative
+                            } else {
+                                role
+                            };
+
+                            tcp_sender
+                                .send(&Self::encode(
+                                    user_id,
+                                    CodenamesClientAction::Pick(team, role),
+                                )?)
+                                .await?;
+                        }
+                        KeyCode::Char('s') | KeyCode::Char('S')
+                            if server_state.game_state == GameStartState::Setup =>
+                        {
+                            let Some((team, role)) = me else {
+                                continue;
+                            };
+
+                            let role = match role {
+                                Role::Spymaster => Role::Operative,
+                                Role::Operative => Role::Spymaster,
+                            };
+
+                            tcp_sender
+                                .send(&Self::encode(
+                                    user_id,
+                                    CodenamesClientAction::Pick(team, role),
+                                )?)
+                                .await?;
+                        }
+                        KeyCode::Char('c') | KeyCode::Char('C')
+                            if Self::can_give_clue(&server_state, user_id) =>
+                        {
+                            clue_input = Some(String::new());
+                        }
+                        KeyCode::Char('e') | KeyCode::Char('E')
+                            if Self::can_guess(&server_state, user_id) =>
+                        {
+                            tcp_sender
+                                .send(&Self::encode(user_id, CodenamesClientAction::EndTurn)?)
+                                .await?;
+                        }
+                        KeyCode::Left if !cursor.is_multiple_of(CODENAMES_GRID_SIZE) => cursor -= 1,
+                        KeyCode::Right
+                            if cursor % CODENAMES_GRID_SIZE < CODENAMES_GRID_SIZE - 1 =>
+                        {
+                            cursor += 1
+                        }
+                        KeyCode::Up if cursor >= CODENAMES_GRID_SIZE => {
+                            cursor -= CODENAMES_GRID_SIZE
+                        }
+                        KeyCode::Down
+                            if cursor + CODENAMES_GRID_SIZE < server_state.cards.len() =>
+                        {
+                            cursor += CODENAMES_GRID_SIZE
+                        }
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup {
+                                if server_state.host_user == user_id {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                        ))
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            if Self::can_guess(&server_state, user_id)
+                                && server_state
+                                    .cards
+                                    .get(cursor)
+                                    .is_some_and(|card| card.revealed.is_none())
+                            {
+                                tcp_sender
+                                    .send(&Self::encode(
+                                        user_id,
+                                        CodenamesClientAction::Guess(cursor as u8),
+                                    )?)
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &events,
+                    cursor,
+                    clue_input.as_deref(),
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712362"))
+    }
+
+    /// "word number", anything else isn't a clue
+    fn parse_clue(input: &str) -> Option<Clue> {
+        let mut parts = input.split_whitespace();
+        let word = parts.next()?;
+        let count = parts.next()?.parse().ok()?;
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Clue {
+            word: word.to_uppercase(),
+            count,
+        })
+    }
+
+    fn on_turn(server_state: &CodenamesClientGameState, user_id: u32, role: Role) -> bool {
+        server_state.game_state == GameStartState::Active
+            && server_state.players.iter().any(|player| {
+                player.id == user_id && player.team == server_state.turn && player.role == role
+            })
+    }
+
+    fn can_give_clue(server_state: &CodenamesClientGameState, user_id: u32) -> bool {
+        server_state.clue.is_none() && Self::on_turn(server_state, user_id, Role::Spymaster)
+    }
+
+    fn can_guess(server_state: &CodenamesClientGameState, user_id: u32) -> bool {
+        server_state.clue.is_some() && Self::on_turn(server_state, user_id, Role::Operative)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &CodenamesClientGameState,
+        events: &[CodenamesAction],
+        cursor: usize,
+        clue_input: Option<&str>,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active if server_state.clue.is_none() => {
+                format!(" {} Spymaster's Turn ", server_state.turn.name())
+            }
+            GameStartState::Active => format!(" {} Team Guessing ", server_state.turn.name()),
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Codenames ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let layout = BoardLayout::split(
+            inner,
+            CARD_WIDTH * CODENAMES_GRID_SIZE as u16 + 3,
+            server_state.players.len(),
+        );
+
+        Self::board(
+            frame,
+            layout.board,
+            user_id,
+            server_state,
+            cursor,
+            clue_input,
+        );
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::BOTTOM)),
+            layout.users,
+        );
+
+        Self::event_list(frame, layout.events, events);
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        let help = match server_state.game_state {
+            GameStartState::Setup if server_state.host_user == user_id => {
+                format!("{SETUP_HELP}\nPress Enter to start once both teams are ready")
+            }
+            GameStartState::Setup => format!("{SETUP_HELP}\nWaiting for the host to start"),
+            _ => HELP_TEXT.to_string(),
+        };
+
+        frame.render_widget(
+            Paragraph::new(help).block(Block::default().borders(Borders::TOP)),
+            help_rows[0],
+        );
+        chat.render(frame, help_rows[1]);
+    }
+
+    /// The grid of cards, with the clue and what's left to find underneath.
+    /// Spymasters see the key as the colour of the words that haven't been guessed.
+    fn board(
+        frame: &mut Frame,
+        area: Rect,
+        user_id: u32,
+        server_state: &CodenamesClientGameState,
+        cursor: usize,
+        clue_input: Option<&str>,
+    ) {
+        frame.render_widget(Block::default().borders(Borders::RIGHT), area);
+
+        let show_cursor = Self::can_guess(server_state, user_id);
+
+        for (idx, card) in server_state.cards.iter().enumerate() {
+            let rect = Rect {
+                x: area.x + 1 + (idx % CODENAMES_GRID_SIZE) as u16 * CARD_WIDTH,
+                y: area.y + (idx / CODENAMES_GRID_SIZE) as u16 * CARD_HEIGHT,
+                width: CARD_WIDTH - 1,
+                height: CARD_HEIGHT,
+            };
+
+            if rect.bottom() > area.bottom() {
+                continue;
+            }
+
+            let key = server_state
+                .key
+                .as_ref()
+                .and_then(|key| key.get(idx).copied());
+
+            let word = Span::from(card.word.clone());
+            let (word, block) = match card.revealed {
+                Some(CardKind::Agent(Team::Red)) => {
+                    (word.bold().white(), Block::bordered().on_red().light_red())
+                }
+                Some(CardKind::Agent(Team::Blue)) => (
+                    word.bold().white(),
+                    Block::bordered().on_blue().light_blue(),
+                ),
+                Some(CardKind::Bystander) => (
+                    word.black(),
+                    Block::bordered().on_light_yellow().light_yellow(),
+                ),
+                Some(CardKind::Assassin) => (
+                    word.bold().light_magenta(),
+                    Block::bordered().on_black().light_magenta(),
+                ),
+                None => match key {
+                    Some(CardKind::Agent(Team::Red)) => {
+                        (word.light_red(), Block::bordered().dark_gray())
+                    }
+                    Some(CardKind::Agent(Team::Blue)) => {
+                        (word.light_blue(), Block::bordered().dark_gray())
+                    }
+                    Some(CardKind::Bystander) => (word.gray(), Block::bordered().dark_gray()),
+                    Some(CardKind::Assassin) => (
+                        word.light_magenta().underlined(),
+                        Block::bordered().dark_gray(),
+                    ),
+                    None => (word.white(), Block::bordered().gray()),
+                },
+            };
+
+            let block = if show_cursor && idx == cursor {
+                block.light_green().bold()
+            } else {
+                block
+            };
+
+            frame.render_widget(
+                Paragraph::new(Line::from(word)).centered().block(block),
+                rect,
+            );
+        }
+
+        let status_area = Rect {
+            x: area.x + 1,
+            y: area.y + CARD_HEIGHT * CODENAMES_GRID_SIZE as u16 + 1,
+            width: area.width.saturating_sub(2),
+            height: area
+                .height
+                .saturating_sub(CARD_HEIGHT * CODENAMES_GRID_SIZE as u16 + 1),
+        };
+
+        let mut lines = vec![Line::from(vec![
+            Span::from(format!(
+                "Red {} left",
+                server_state.remaining[Team::Red.idx()]
+            ))
+            .light_red()
+            .bold(),
+            Span::from("  ~  "),
+            Span::from(format!(
+                "Blue {} left",
+                server_state.remaining[Team::Blue.idx()]
+            ))
+            .light_blue()
+            .bold(),
+        ])];
+
+        match server_state.game_state {
+            GameStartState::Setup => {}
+            GameStartState::Active => match &server_state.clue {
+                Some(clue) => {
+                    lines.push(Line::from(vec![
+                        Span::from("Clue: "),
+                        Span::from(format!("{} {}", clue.word, clue.count))
+                            .bold()
+                            .light_yellow(),
+                        Span::from(format!(", {} guesses left", server_state.guesses_left)).gray(),
+                    ]));
+
+                    if show_cursor {
+                        lines.push(Line::from("Enter to guess, E to stop guessing"));
+                    }
+                }
+                None if Self::can_give_clue(server_state, user_id) => match clue_input {
+                    Some(input) => {
+                        lines.push(Line::from(vec![
+                            Span::from("Clue: "),
+                            Span::from(format!("{input}_")).light_yellow(),
+                        ]));
+                        lines.push(Line::from("Type a word then a number, Enter to give it"));
+                    }
+                    None => lines.push(Line::from("Press C to give your team a clue")),
+                },
+                None => lines.push(Line::from(format!(
+                    "Waiting for the {} spymaster's clue",
+                    server_state.turn.name()
+                ))),
+            },
+            GameStartState::Ending => {
+                if let Some(winner) = server_state.winner {
+                    lines.push(
+                        Line::from(format!("{} team wins!", winner.name()))
+                            .bold()
+                            .light_green(),
+                    );
+                }
+            }
+        }
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), status_area);
+    }
+
+    /// Red team first, each with their spymaster at the top
+    fn user_list(server_state: &CodenamesClientGameState) -> Table<'_> {
+        let mut players: Vec<_> = server_state.players.iter().collect();
+        players.sort_by_key(|player| (player.team.idx(), player.role != Role::Spymaster));
+
+        let rows: Vec<Row> = players
+            .into_iter()
+            .map(|player| {
+                let is_turn = server_state.game_state == GameStartState::Active
+                    && player.team == server_state.turn
+                    && (player.role == Role::Spymaster) == server_state.clue.is_none();
+
+                let mut name = vec![Span::from(player.name.clone())];
+                if let Some(rating) = player.rating {
+                    name.push(Span::from(format!(" {rating}")).gray());
+                }
+                if player.id == server_state.host_user {
+                    name.push(Span::from(" H").light_yellow());
+                }
+
+                let team = match player.team {
+                    Team::Red => Span::from("Red").light_red(),
+                    Team::Blue => Span::from("Blue").light_blue(),
+                };
+
+                Row::new(vec![
+                    Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                    Cell::new(team),
+                    Cell::new(player.role.name()).gray(),
+                    Cell::new(Line::from(name)),
+                ])
+            })
+            .collect();
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(4),
+            Constraint::Length(9),
+            Constraint::Fill(1),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "Team", "Role", "Player"]).bold())
+            .style(Style::default().white())
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[CodenamesAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                CodenamesAction::Init => Line::from("Server Started"),
+                CodenamesAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                CodenamesAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                CodenamesAction::UserMoved(user, team, role) => {
+                    Line::from(format!("{user} is now {} {}", team.name(), role.name()))
+                }
+                CodenamesAction::GameStarted(team) => {
+                    Line::from(format!("~ Game started, {} goes first ~", team.name())).light_cyan()
+                }
+                CodenamesAction::ClueGiven(user, team, clue) => {
+                    let line = Line::from(format!("{user} gave {} {}", clue.word, clue.count));
+                    match team {
+                        Team::Red => line.light_red(),
+                        Team::Blue => line.light_blue(),
+                    }
+                }
+                CodenamesAction::UserGuessed(user, word, kind) => {
+                    let what = match kind {
+                        CardKind::Agent(team) => format!("{} agent", team.name()),
+                        CardKind::Bystander => "a bystander".to_string(),
+                        CardKind::Assassin => "the assassin!".to_string(),
+                    };
+                    Line::from(format!("{user} guessed {word}, {what}"))
+                }
+                CodenamesAction::UserEndedTurn(user) => {
+                    Line::from(format!("{user} stopped guessing"))
+                }
+                CodenamesAction::TeamWon(team) => {
+                    Line::from(format!("{} team Won! ", team.name())).light_green()
+                }
+                CodenamesAction::GameEnded => Line::from("Game Over"),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerCodenamesCommand> {
+        bincode::decode_from_slice::<ServerCodenamesCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: CodenamesClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
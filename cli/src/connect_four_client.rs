@@ -0,0 +1,491 @@
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    connect_four::{
+        CONNECT_FOUR_COLUMNS, CONNECT_FOUR_ROWS, ConnectFourAction, ConnectFourClientAction,
+        ConnectFourClientGameState, ConnectFourResult, Disc, ServerConnectFourCommand, drop_row,
+    },
+    game_state::{GameStartState, GameUserState},
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    AppMessage, GameResult, board_table::BoardLayout, card_table, chat_pane::ChatPane, room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Use the arrow keys to pick a column,
+ Enter to drop a disc in it.
+Red goes first.
+
+Get four in a row across, down,
+ or diagonally to win.
+"#;
+
+/// Each slot is drawn this many characters wide
+const CELL_WIDTH: u16 = 4;
+
+/// Connect Four, two players
+pub struct ConnectFourClient;
+
+impl ConnectFourClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerConnectFourCommand::GameState(state) = Self::decode(data)?;
+                    server_state = Some(state);
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some(server_state) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: ConnectFourClientGameState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<ConnectFourAction> = server_state.action.drain(..).collect();
+        let mut column: usize = CONNECT_FOUR_COLUMNS / 2;
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &events,
+                column,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerConnectFourCommand::GameState(mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Char('/') => chat.start_typing(),
+                        KeyCode::Left => {
+                            column = (column + CONNECT_FOUR_COLUMNS - 1) % CONNECT_FOUR_COLUMNS;
+                        }
+                        KeyCode::Right => {
+                            column = (column + 1) % CONNECT_FOUR_COLUMNS;
+                        }
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup {
+                                if server_state.host_user == user_id {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                        ))
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            if Self::my_turn(&server_state, user_id)
+                                && drop_row(&server_state.board, column).is_some()
+                            {
+                                tcp_sender
+                                    .send(&Self::encode(
+                                        user_id,
+                                        ConnectFourClientAction::Drop(column as u8),
+                                    )?)
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &events,
+                    column,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712358"))
+    }
+
+    fn my_turn(server_state: &ConnectFourClientGameState, user_id: u32) -> bool {
+        server_state.game_state == GameStartState::Active
+            && server_state
+                .players
+                .iter()
+                .any(|player| player.id == user_id && player.disc == Some(server_state.turn))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &ConnectFourClientGameState,
+        events: &[ConnectFourAction],
+        column: usize,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active if Self::my_turn(server_state, user_id) => {
+                " My Turn ".to_string()
+            }
+            GameStartState::Active => match server_state
+                .players
+                .iter()
+                .find(|player| player.disc == Some(server_state.turn))
+            {
+                Some(player) => format!(" {}'s Turn ", player.name),
+                None => String::new(),
+            },
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Connect Four ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let layout = BoardLayout::split(
+            inner,
+            CELL_WIDTH * CONNECT_FOUR_COLUMNS as u16 + 4,
+            server_state.players.len(),
+        );
+
+        Self::board(frame, layout.board, server_state, column, spectating);
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::BOTTOM)),
+            layout.users,
+        );
+
+        Self::event_list(frame, layout.events, events);
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        let help = match server_state.game_state {
+            GameStartState::Setup if server_state.host_user == user_id => {
+                format!("{HELP_TEXT}\nPress Enter to start once there are two players")
+            }
+            GameStartState::Setup => format!("{HELP_TEXT}\nWaiting for the host to start"),
+            _ => HELP_TEXT.to_string(),
+        };
+
+        frame.render_widget(
+            Paragraph::new(help).block(Block::default().borders(Borders::TOP)),
+            help_rows[0],
+        );
+        chat.render(frame, help_rows[1]);
+    }
+
+    /// Top row drawn first, with the column marker above it
+    fn board(
+        frame: &mut Frame,
+        area: Rect,
+        server_state: &ConnectFourClientGameState,
+        column: usize,
+        spectating: bool,
+    ) {
+        let mut lines = vec![];
+
+        let marker = if spectating || server_state.game_state != GameStartState::Active {
+            Line::from("")
+        } else {
+            let mut marker = " ".repeat(2 + column * CELL_WIDTH as usize + 1);
+            marker.push('v');
+            Line::from(marker).bold().white()
+        };
+        lines.push(marker);
+
+        for row in (0..CONNECT_FOUR_ROWS).rev() {
+            let mut spans = vec![Span::from(" |").light_blue()];
+
+            for col in 0..CONNECT_FOUR_COLUMNS {
+                let at = (row as u8, col as u8);
+                let mut span = match server_state.board[row][col] {
+                    Some(Disc::Red) => Span::from(" ●  ").light_red(),
+                    Some(Disc::Yellow) => Span::from(" ●  ").light_yellow(),
+                    None => Span::from(" ·  ").dark_gray(),
+                };
+
+                if server_state.winning_line.contains(&at) {
+                    span = span.bold().on_dark_gray();
+                } else if server_state.last_drop == Some(at) {
+                    span = span.bold();
+                }
+
+                spans.push(span);
+            }
+
+            spans.push(Span::from("|").light_blue());
+            lines.push(Line::from(spans));
+        }
+
+        lines.push(
+            Line::from(format!(
+                " +{}+",
+                "-".repeat(CELL_WIDTH as usize * CONNECT_FOUR_COLUMNS)
+            ))
+            .light_blue(),
+        );
+        lines.push(Line::from(format!(
+            "  {}",
+            (1..=CONNECT_FOUR_COLUMNS)
+                .map(|col| format!(" {col}  "))
+                .collect::<String>()
+        )));
+
+        if let Some(result) = &server_state.result {
+            lines.push(Line::from(""));
+            lines.push(match result {
+                ConnectFourResult::Won(name) => Line::from(format!(" {name} connected four!"))
+                    .bold()
+                    .light_green(),
+                ConnectFourResult::Draw => Line::from(" The board is full, it's a draw").bold(),
+                ConnectFourResult::Forfeit(name) => {
+                    Line::from(format!(" {name} wins, the other player left"))
+                        .bold()
+                        .light_green()
+                }
+            });
+        }
+
+        frame.render_widget(
+            Paragraph::new(Text::from(lines)).block(Block::default().borders(Borders::RIGHT)),
+            area,
+        );
+    }
+
+    fn user_list(server_state: &ConnectFourClientGameState) -> Table<'_> {
+        let rows: Vec<Row> = server_state
+            .players
+            .iter()
+            .map(|player| {
+                let is_turn = server_state.game_state == GameStartState::Active
+                    && player.disc == Some(server_state.turn);
+
+                let mut name = vec![Span::from(player.name.clone())];
+                if let Some(rating) = player.rating {
+                    name.push(Span::from(format!(" {rating}")).gray());
+                }
+                if player.id == server_state.host_user {
+                    name.push(Span::from(" H").light_yellow());
+                }
+
+                let disc = match player.disc {
+                    Some(Disc::Red) => Span::from("●").light_red(),
+                    Some(Disc::Yellow) => Span::from("●").light_yellow(),
+                    None => Span::from(""),
+                };
+
+                Row::new(vec![
+                    Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                    Cell::new(disc),
+                    Cell::new(Line::from(name)),
+                ])
+            })
+            .collect();
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "", "Player"]).bold())
+            .style(Style::default().white())
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[ConnectFourAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                ConnectFourAction::Init => Line::from("Server Started"),
+                ConnectFourAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                ConnectFourAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                ConnectFourAction::GameStarted(user) => {
+                    Line::from(format!("~ Game started, {user} is red ~")).light_cyan()
+                }
+                ConnectFourAction::Dropped(user, column) => {
+                    Line::from(format!("{user} dropped in column {}", column + 1))
+                }
+                ConnectFourAction::GameEnded(ConnectFourResult::Won(user))
+                | ConnectFourAction::GameEnded(ConnectFourResult::Forfeit(user)) => {
+                    Line::from(format!("{user} Won! ")).light_green()
+                }
+                ConnectFourAction::GameEnded(ConnectFourResult::Draw) => {
+                    Line::from("Game Over, it's a draw")
+                }
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerConnectFourCommand> {
+        bincode::decode_from_slice::<ServerConnectFourCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: ConnectFourClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
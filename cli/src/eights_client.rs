@@ -0,0 +1,604 @@
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Clear, Padding, Paragraph, Row, Table},
+};
+use rpc::{
+    cards::{PlayingCard, Rank, Suit},
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    crazy_eights::{
+        EightsAction, EightsClientAction, EightsClientGameState, ServerEightsCommand, can_play,
+    },
+    game_state::{GameStartState, GameUserState},
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    AppMessage, GameResult,
+    card_table::{self, TableLayout},
+    chat_pane::ChatPane,
+    room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+When it's your turn, use the arrow keys to select a card.
+Press enter to play it.
+A card can go down if it matches the suit or the rank
+ of the last card played.
+Eights are wild, play one and pick the suit to follow.
+
+If you've got nothing to play, press "p" to draw
+ until you have.
+
+First to get rid of all of their cards wins.
+"#;
+
+/// Playing an eight asks for the suit before it's sent
+struct SuitPick {
+    card: PlayingCard,
+    suit_idx: usize,
+}
+
+/// Crazy Eights, laid out the same way as Uno
+pub struct EightsClient;
+
+impl EightsClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerEightsCommand::GameState(cards, state) = Self::decode(data)?;
+                    server_state = Some((cards, state));
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some((my_cards, server_state)) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            my_cards,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: EightsClientGameState,
+        mut my_cards: Vec<PlayingCard>,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<EightsAction> = server_state.action.drain(..).collect();
+        let mut card_idx: usize = 0;
+        let mut suit_pick: Option<SuitPick> = None;
+        let mut chat = ChatPane::new(ChatChannel::Room);
+        my_cards.sort_by_key(|card| (card.suit, card.rank));
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &my_cards,
+                &events,
+                card_idx,
+                spectating,
+                &chat,
+            );
+            if let Some(pick) = &suit_pick {
+                Self::render_suit_pick(frame, pick);
+            }
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerEightsCommand::GameState(cards, mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+                        my_cards = cards;
+                        my_cards.sort_by_key(|card| (card.suit, card.rank));
+
+                        if card_idx >= my_cards.len() {
+                            card_idx = my_cards.len().saturating_sub(1);
+                        }
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let is_turn = server_state.game_state == GameStartState::Active
+                        && server_state
+                            .active_users
+                            .get(server_state.user_turn as usize)
+                            .is_some_and(|user| user.id == user_id);
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Char('/') if suit_pick.is_none() => chat.start_typing(),
+                        KeyCode::Left | KeyCode::Right => {
+                            let right = key_event.code == KeyCode::Right;
+
+                            if let Some(pick) = &mut suit_pick {
+                                pick.suit_idx = if right {
+                                    (pick.suit_idx + 1) % Suit::ALL.len()
+                                } else {
+                                    (pick.suit_idx + Suit::ALL.len() - 1) % Suit::ALL.len()
+                                };
+                            } else if !my_cards.is_empty() {
+                                card_idx = if right {
+                                    (card_idx + 1) % my_cards.len()
+                                } else {
+                                    (card_idx + my_cards.len() - 1) % my_cards.len()
+                                };
+                            }
+                        }
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup {
+                                if server_state.host_user == user_id {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                        ))
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            if !is_turn {
+                                continue;
+                            }
+
+                            if let Some(pick) = suit_pick.take() {
+                                tcp_sender
+                                    .send(&Self::encode(
+                                        user_id,
+                                        EightsClientAction::Play(
+                                            pick.card,
+                                            Some(Suit::ALL[pick.suit_idx]),
+                                        ),
+                                    )?)
+                                    .await?;
+                            } else if let Some(card) = my_cards.get(card_idx).copied() {
+                                if card.rank == Rank::Eight {
+                                    suit_pick = Some(SuitPick { card, suit_idx: 0 });
+                                } else {
+                                    tcp_sender
+                                        .send(&Self::encode(
+                                            user_id,
+                                            EightsClientAction::Play(card, None),
+                                        )?)
+                                        .await?;
+                                }
+                            }
+                        }
+                        KeyCode::Char('p') if is_turn && suit_pick.is_none() => {
+                            tcp_sender
+                                .send(&Self::encode(user_id, EightsClientAction::Draw)?)
+                                .await?;
+                        }
+                        KeyCode::Esc => {
+                            if suit_pick.is_some() {
+                                suit_pick = None;
+                            } else {
+                                return Ok(GameResult::None);
+                            }
+                        }
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &my_cards,
+                    &events,
+                    card_idx,
+                    spectating,
+                    &chat,
+                );
+                if let Some(pick) = &suit_pick {
+                    Self::render_suit_pick(frame, pick);
+                }
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712355"))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &EightsClientGameState,
+        my_cards: &[PlayingCard],
+        events: &[EightsAction],
+        card_idx: usize,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active => match server_state
+                .active_users
+                .get(server_state.user_turn as usize)
+            {
+                Some(user) if user.id == user_id => " My Turn ".to_string(),
+                Some(user) => format!(" {}'s Turn ", user.name),
+                None => String::new(),
+            },
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Crazy Eights ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let user_rows = server_state.active_users.len() + server_state.standings.len();
+        let layout = TableLayout::split(inner, user_rows, false);
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::RIGHT)),
+            layout.users,
+        );
+
+        let top_block = Block::default().borders(Borders::RIGHT);
+        match (server_state.game_state, server_state.top_card) {
+            (GameStartState::Active, Some(top_card)) => {
+                let mut text = Text::from("Last Card");
+                for _ in 0..4 {
+                    text.push_line(Line::from(""));
+                }
+                text.push_line(Line::from(vec![
+                    Span::from("Suit: "),
+                    Self::suit_span(server_state.suit),
+                ]));
+                text.push_line(format!("Draw Pile: {}", server_state.draw_pile));
+
+                frame.render_widget(Paragraph::new(text).block(top_block), layout.last_card);
+
+                let mut card_area = layout.last_card.inner(Margin::new(8, 1));
+                card_area.width = 5;
+                card_area.height = 4;
+                frame.render_widget(card_table::playing_card_text(&top_card, true), card_area);
+            }
+            (GameStartState::Ending, _) => {
+                frame.render_widget(
+                    Paragraph::new("Game over\nThank you for playing!")
+                        .centered()
+                        .block(top_block),
+                    layout.last_card,
+                );
+            }
+            _ => {
+                frame.render_widget(
+                    Paragraph::new("Waiting for host\nTwo to six players")
+                        .centered()
+                        .block(top_block),
+                    layout.last_card,
+                );
+            }
+        }
+
+        Self::event_list(frame, layout.events, events);
+
+        let hand_block = Block::default().borders(Borders::RIGHT);
+        match server_state.game_state {
+            GameStartState::Setup => {
+                let text = if server_state.host_user == user_id {
+                    "You can start the game by pressing Enter\nif there are at least two people in the lobby"
+                } else {
+                    "Waiting for the host to start the game"
+                };
+
+                frame.render_widget(
+                    Paragraph::new(text).centered().block(hand_block),
+                    layout.hand,
+                );
+            }
+            GameStartState::Active if !my_cards.is_empty() => {
+                frame.render_widget(Paragraph::new("My Cards").block(hand_block), layout.hand);
+
+                let top_card = server_state.top_card;
+                card_table::hand(frame, layout.hand, my_cards, card_idx, |card| {
+                    let playable = top_card
+                        .is_some_and(|top_card| can_play(card, &top_card, server_state.suit));
+                    card_table::playing_card_text(card, playable)
+                });
+            }
+            GameStartState::Active if spectating => {
+                frame.render_widget(
+                    Paragraph::new("Spectating, hands are hidden").block(hand_block),
+                    layout.hand,
+                );
+            }
+            GameStartState::Active | GameStartState::Ending => {
+                frame.render_widget(
+                    Paragraph::new("Game is over").block(hand_block),
+                    layout.hand,
+                );
+            }
+        }
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        frame.render_widget(Paragraph::new(HELP_TEXT), help_rows[0]);
+        chat.render(frame, help_rows[1]);
+    }
+
+    fn user_list(server_state: &EightsClientGameState) -> Table<'_> {
+        let mut rows: Vec<Row> = server_state
+            .standings
+            .iter()
+            .enumerate()
+            .map(|(i, (_, name))| {
+                Row::new(vec![
+                    Cell::new((i + 1).to_string()).light_green(),
+                    Cell::new(name.clone()).light_green(),
+                    Cell::new(""),
+                ])
+            })
+            .collect();
+
+        if server_state.game_state != GameStartState::Ending {
+            for (i, user) in server_state.active_users.iter().enumerate() {
+                let is_turn = server_state.game_state == GameStartState::Active
+                    && i == server_state.user_turn as usize;
+
+                let mut name = vec![Span::from(user.name.clone())];
+                if let Some(rating) = user.rating {
+                    name.push(Span::from(format!(" {rating}")).gray());
+                }
+                if user.id == server_state.host_user {
+                    name.push(Span::from(" H").light_yellow());
+                }
+
+                rows.push(Row::new(vec![
+                    Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                    Cell::new(Line::from(name)),
+                    Cell::new(user.card_count.to_string()),
+                ]));
+            }
+        }
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(20),
+            Constraint::Fill(1),
+        ];
+
+        Table::new(rows, widths).style(Style::default().white())
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[EightsAction]) {
+        let skip = events.len().saturating_sub(5);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                EightsAction::Init => Line::from("Server Started"),
+                EightsAction::InitialCard(card) => Line::from(vec![
+                    Span::from("Initial Card: "),
+                    card_table::playing_card_span(card),
+                ]),
+                EightsAction::UserPlayed(user, card) => Line::from(vec![
+                    Span::from(format!("{user} played: ")),
+                    card_table::playing_card_span(card),
+                ]),
+                EightsAction::UserNamedSuit(user, suit) => Line::from(vec![
+                    Span::from(format!("{user} changed the suit to ")),
+                    Self::suit_span(*suit),
+                ]),
+                EightsAction::UserDrew(user, count) => {
+                    Line::from(format!("{user} drew {count} card(s) "))
+                }
+                EightsAction::UserPassed(user) => Line::from(format!("{user} Passed ")),
+                EightsAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                EightsAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                EightsAction::UserWon(user) => Line::from(format!("{user} Won! ")).light_green(),
+                EightsAction::GameEnded => Line::from("Game Over"),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn suit_span(suit: Suit) -> Span<'static> {
+        let span = Span::from(format!("{} {}", suit.symbol(), suit.name()));
+
+        if suit.is_red() {
+            span.light_red()
+        } else {
+            span.white()
+        }
+    }
+
+    fn render_suit_pick(frame: &mut Frame, pick: &SuitPick) {
+        let mut inner = frame.area().inner(Margin {
+            horizontal: 20,
+            vertical: 5,
+        });
+
+        inner.width = 25;
+        inner.height = 5;
+
+        frame.render_widget(
+            Clear,
+            Rect {
+                x: inner.x - 1,
+                y: inner.y - 1,
+                width: 27,
+                height: 7,
+            },
+        );
+
+        let select = format!("{:width$}v", " ", width = (pick.suit_idx * 4) + 3);
+        let suits: Vec<Span> = Suit::ALL
+            .iter()
+            .map(|suit| {
+                let span = Span::from(format!("   {}", suit.symbol()));
+                if suit.is_red() {
+                    span.light_red()
+                } else {
+                    span.white()
+                }
+            })
+            .collect();
+
+        frame.render_widget(
+            Paragraph::new(Text::from(vec![
+                Line::from(format!("Suit to play {} as", pick.card)),
+                Line::from(select).bold().white(),
+                Line::from(suits),
+            ]))
+            .block(
+                Block::bordered()
+                    .border_style(Style::new().light_blue())
+                    .title_top(Line::from(" Play Eight? ").bold().white())
+                    .title_bottom(Line::from(" Esc to return ").bold().white().right_aligned())
+                    .bg(Color::Reset)
+                    .padding(Padding::horizontal(1)),
+            ),
+            inner,
+        );
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerEightsCommand> {
+        bincode::decode_from_slice::<ServerEightsCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: EightsClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
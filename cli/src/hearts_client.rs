@@ -0,0 +1,617 @@
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    cards::PlayingCard,
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+    hearts::{
+        HEARTS_PASS_COUNT, HEARTS_PLAYERS, HeartsAction, HeartsClientAction, HeartsClientGameState,
+        HeartsPhase, PassDirection, ServerHeartsCommand, can_play,
+    },
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    AppMessage, GameResult,
+    card_table::{self, TableLayout},
+    chat_pane::ChatPane,
+    room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Use the arrow keys to pick a card, Enter to play it.
+You have to follow the suit that was led if you can.
+Hearts are a point each, the queen of spades is 13.
+
+At the start of a round pick three cards to pass
+ with Space, then press Enter to send them.
+
+Take every point to shoot the moon.
+Lowest score once someone reaches 100 wins.
+"#;
+
+/// Hearts, always four at the table
+pub struct HeartsClient;
+
+impl HeartsClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerHeartsCommand::GameState(cards, state) = Self::decode(data)?;
+                    server_state = Some((cards, state));
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some((my_cards, server_state)) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            my_cards,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: HeartsClientGameState,
+        mut my_cards: Vec<PlayingCard>,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<HeartsAction> = server_state.action.drain(..).collect();
+        let mut card_idx: usize = 0;
+        // Cards picked to pass, sent once there are three
+        let mut passing: Vec<PlayingCard> = vec![];
+        let mut chat = ChatPane::new(ChatChannel::Room);
+        my_cards.sort_by_key(|card| (card.suit, card.rank));
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &my_cards,
+                &events,
+                card_idx,
+                &passing,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerHeartsCommand::GameState(cards, mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+                        my_cards = cards;
+                        my_cards.sort_by_key(|card| (card.suit, card.rank));
+                        passing.retain(|card| my_cards.contains(card));
+
+                        if card_idx >= my_cards.len() {
+                            card_idx = my_cards.len().saturating_sub(1);
+                        }
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let my_seat = server_state
+                        .players
+                        .iter()
+                        .position(|player| player.id == user_id);
+                    let passing_phase = server_state.game_state == GameStartState::Active
+                        && server_state.phase == HeartsPhase::Passing
+                        && my_seat.is_some_and(|seat| !server_state.players[seat].passed);
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Char('/') => chat.start_typing(),
+                        KeyCode::Left if !my_cards.is_empty() => {
+                            card_idx = (card_idx + my_cards.len() - 1) % my_cards.len();
+                        }
+                        KeyCode::Right if !my_cards.is_empty() => {
+                            card_idx = (card_idx + 1) % my_cards.len();
+                        }
+                        KeyCode::Char(' ') if passing_phase => {
+                            let Some(card) = my_cards.get(card_idx) else {
+                                continue;
+                            };
+
+                            if let Some(idx) = passing.iter().position(|picked| picked == card) {
+                                passing.remove(idx);
+                            } else if passing.len() < HEARTS_PASS_COUNT {
+                                passing.push(*card);
+                            }
+                        }
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup {
+                                if server_state.host_user == user_id {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                        ))
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            if passing_phase {
+                                if passing.len() == HEARTS_PASS_COUNT {
+                                    let cards = std::mem::take(&mut passing);
+                                    tcp_sender
+                                        .send(&Self::encode(
+                                            user_id,
+                                            HeartsClientAction::Pass(cards),
+                                        )?)
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            if my_seat
+                                .is_some_and(|seat| server_state.user_turn == Some(seat as u8))
+                                && let Some(card) = my_cards.get(card_idx)
+                            {
+                                tcp_sender
+                                    .send(&Self::encode(user_id, HeartsClientAction::Play(*card))?)
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &my_cards,
+                    &events,
+                    card_idx,
+                    &passing,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712357"))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &HeartsClientGameState,
+        my_cards: &[PlayingCard],
+        events: &[HeartsAction],
+        card_idx: usize,
+        passing: &[PlayingCard],
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let turn_name = match (server_state.game_state, server_state.phase) {
+            (GameStartState::Setup, _) => " Waiting To Start ".to_string(),
+            (GameStartState::Active, HeartsPhase::Passing) => format!(
+                " Round {} ~ Passing {} ",
+                server_state.round,
+                server_state.pass_direction.name()
+            ),
+            (GameStartState::Active, HeartsPhase::Playing) => match server_state
+                .user_turn
+                .and_then(|turn| server_state.players.get(turn as usize))
+            {
+                Some(player) if player.id == user_id => " My Turn ".to_string(),
+                Some(player) => format!(" {}'s Turn ", player.name),
+                None => String::new(),
+            },
+            (GameStartState::Ending, _) => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Hearts ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let layout = TableLayout::split(inner, server_state.players.len() + 1, false);
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::RIGHT)),
+            layout.users,
+        );
+
+        Self::trick(frame, layout.last_card, server_state);
+        Self::event_list(frame, layout.events, events);
+
+        let hand_block = Block::default().borders(Borders::RIGHT);
+        match server_state.game_state {
+            GameStartState::Setup => {
+                let text = if server_state.host_user == user_id {
+                    "You can start the game by pressing Enter\nonce all four seats are filled"
+                } else {
+                    "Waiting for the host to start the game"
+                };
+
+                frame.render_widget(
+                    Paragraph::new(text).centered().block(hand_block),
+                    layout.hand,
+                );
+            }
+            GameStartState::Active if !my_cards.is_empty() => {
+                let title = if server_state.phase == HeartsPhase::Passing {
+                    match server_state
+                        .players
+                        .iter()
+                        .find(|player| player.id == user_id)
+                    {
+                        Some(player) if player.passed => {
+                            "My Cards ~ Waiting for everyone to pass".to_string()
+                        }
+                        _ => format!(
+                            "My Cards ~ Pick {} to pass {} ( {} / {HEARTS_PASS_COUNT} )",
+                            HEARTS_PASS_COUNT,
+                            server_state.pass_direction.name(),
+                            passing.len()
+                        ),
+                    }
+                } else {
+                    "My Cards".to_string()
+                };
+
+                frame.render_widget(Paragraph::new(title).block(hand_block), layout.hand);
+
+                let my_turn = server_state
+                    .user_turn
+                    .and_then(|turn| server_state.players.get(turn as usize))
+                    .is_some_and(|player| player.id == user_id);
+
+                card_table::hand(frame, layout.hand, my_cards, card_idx, |card| {
+                    if passing.contains(card) {
+                        return card_table::playing_card_text(card, true)
+                            .block(Block::bordered().light_yellow());
+                    }
+
+                    let playable = server_state.phase == HeartsPhase::Passing
+                        || !my_turn
+                        || can_play(
+                            card,
+                            my_cards,
+                            &server_state.trick,
+                            server_state.hearts_broken,
+                            server_state.first_trick,
+                        );
+                    card_table::playing_card_text(card, playable)
+                });
+            }
+            GameStartState::Active if spectating => {
+                frame.render_widget(
+                    Paragraph::new("Spectating, hands are hidden").block(hand_block),
+                    layout.hand,
+                );
+            }
+            GameStartState::Active | GameStartState::Ending => {
+                frame.render_widget(
+                    Paragraph::new("Game is over").block(hand_block),
+                    layout.hand,
+                );
+            }
+        }
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        frame.render_widget(Paragraph::new(HELP_TEXT), help_rows[0]);
+        chat.render(frame, help_rows[1]);
+    }
+
+    fn user_list(server_state: &HeartsClientGameState) -> Table<'_> {
+        let rows: Vec<Row> = if server_state.game_state == GameStartState::Ending {
+            server_state
+                .standings
+                .iter()
+                .enumerate()
+                .map(|(i, (id, name))| {
+                    let score = server_state
+                        .players
+                        .iter()
+                        .find(|player| player.id == *id)
+                        .map(|player| player.score.to_string())
+                        .unwrap_or_else(|| "Left".to_string());
+
+                    Row::new(vec![
+                        Cell::new((i + 1).to_string()).light_green(),
+                        Cell::new(name.clone()).light_green(),
+                        Cell::new(score),
+                        Cell::new(""),
+                    ])
+                })
+                .collect()
+        } else {
+            server_state
+                .players
+                .iter()
+                .enumerate()
+                .map(|(i, player)| {
+                    let is_turn = server_state.user_turn == Some(i as u8);
+
+                    let mut name = vec![Span::from(player.name.clone())];
+                    if let Some(rating) = player.rating {
+                        name.push(Span::from(format!(" {rating}")).gray());
+                    }
+                    if player.id == server_state.host_user {
+                        name.push(Span::from(" H").light_yellow());
+                    }
+
+                    let status = match server_state.phase {
+                        HeartsPhase::Passing
+                            if server_state.game_state == GameStartState::Active =>
+                        {
+                            if player.passed {
+                                Span::from("Passed").light_green()
+                            } else {
+                                Span::from("Picking").gray()
+                            }
+                        }
+                        _ if player.round_points > 0 => {
+                            Span::from(format!("+{}", player.round_points)).light_red()
+                        }
+                        _ => Span::from(""),
+                    };
+
+                    Row::new(vec![
+                        Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                        Cell::new(Line::from(name)),
+                        Cell::new(player.score.to_string()),
+                        Cell::new(status),
+                    ])
+                })
+                .collect()
+        };
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(20),
+            Constraint::Length(4),
+            Constraint::Fill(1),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "Player", "Pts", ""]).bold())
+            .style(Style::default().white())
+    }
+
+    /// The trick being played, or the one just taken until the next card goes down
+    fn trick(frame: &mut Frame, area: Rect, server_state: &HeartsClientGameState) {
+        let block = Block::default().borders(Borders::RIGHT);
+
+        if server_state.game_state != GameStartState::Active {
+            let text = match server_state.game_state {
+                GameStartState::Setup => {
+                    format!("Waiting for host\nNeeds {HEARTS_PLAYERS} players")
+                }
+                _ => "Game over\nThank you for playing!".to_string(),
+            };
+            frame.render_widget(Paragraph::new(text).centered().block(block), area);
+            return;
+        }
+
+        let (title, trick) = if server_state.trick.is_empty() {
+            ("Last Trick", &server_state.last_trick)
+        } else {
+            ("Trick", &server_state.trick)
+        };
+
+        let mut text = Text::from(title);
+        for _ in 0..4 {
+            text.push_line(Line::from(""));
+        }
+        text.push_line(if server_state.hearts_broken {
+            Line::from("Hearts broken").light_red()
+        } else if server_state.pass_direction == PassDirection::Hold
+            || server_state.phase == HeartsPhase::Playing
+        {
+            Line::from("Hearts not broken").gray()
+        } else {
+            Line::from("")
+        });
+
+        frame.render_widget(Paragraph::new(text).block(block), area);
+
+        for (i, (_, card)) in trick.iter().enumerate() {
+            let rect = Rect {
+                x: area.x + (i * 6) as u16,
+                y: area.y + 1,
+                width: 5,
+                height: 4,
+            };
+
+            frame.render_widget(card_table::playing_card_text(card, true), rect);
+        }
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[HeartsAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                HeartsAction::Init => Line::from("Server Started"),
+                HeartsAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                HeartsAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                HeartsAction::RoundStarted(round, direction) => {
+                    Line::from(format!("~ Round {round} ~ Passing {} ~", direction.name()))
+                        .light_cyan()
+                }
+                HeartsAction::UserPassed(user) => Line::from(format!("{user} picked their cards")),
+                HeartsAction::CardsExchanged => Line::from("Cards have been passed"),
+                HeartsAction::UserPlayed(user, card) => Line::from(vec![
+                    Span::from(format!("{user} played: ")),
+                    card_table::playing_card_span(card),
+                ]),
+                HeartsAction::HeartsBroken => Line::from("Hearts have been broken!").light_red(),
+                HeartsAction::TrickTaken(user, 0) => Line::from(format!("{user} took the trick")),
+                HeartsAction::TrickTaken(user, points) => {
+                    Line::from(format!("{user} took the trick for {points}")).light_yellow()
+                }
+                HeartsAction::ShotTheMoon(user) => {
+                    Line::from(format!("{user} shot the moon!")).light_magenta()
+                }
+                HeartsAction::RoundScored(points) => Line::from(format!(
+                    "Round over: {}",
+                    points
+                        .iter()
+                        .map(|(user, points)| format!("{user} +{points}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+                HeartsAction::UserWon(user) => Line::from(format!("{user} Won! ")).light_green(),
+                HeartsAction::GameEnded => Line::from("Game Over"),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerHeartsCommand> {
+        bincode::decode_from_slice::<ServerHeartsCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: HeartsClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
@@ -0,0 +1,677 @@
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    cards::PlayingCard,
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+    holdem::{
+        BIG_BLIND, HoldemAction, HoldemClientAction, HoldemClientGameState, HoldemStage,
+        ServerHoldemCommand, evaluate,
+    },
+};
+use tokio::sync::mpsc;
+
+use crate::{AppMessage, GameResult, card_table, chat_pane::ChatPane, room_message};
+
+const HELP_TEXT: &str = r#"How to play:
+Make the best five card hand out of your two cards
+ and the five on the board.
+
+On your turn:
+"f" to fold, "c" to check or call
+"r" to raise, Up / Down to change how much,
+ Enter to bet it, Esc to back out
+"a" to go all in
+
+The next hand is dealt a few seconds after the last.
+Last player with chips wins.
+"#;
+
+/// Texas Hold'em, one table of up to eight
+pub struct HoldemClient;
+
+impl HoldemClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerHoldemCommand::GameState(cards, state) = Self::decode(data)?;
+                    server_state = Some((cards, state));
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some((my_cards, server_state)) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            my_cards,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: HoldemClientGameState,
+        mut my_cards: Vec<PlayingCard>,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<HoldemAction> = server_state.action.drain(..).collect();
+        // Picking how much to raise to, only while it's our turn
+        let mut raise_to: Option<u32> = None;
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &my_cards,
+                &events,
+                raise_to,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerHoldemCommand::GameState(cards, mut state) = Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+                        my_cards = cards;
+
+                        if !Self::is_turn(&server_state, user_id) {
+                            raise_to = None;
+                        }
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let is_turn = Self::is_turn(&server_state, user_id);
+                    let me = server_state
+                        .players
+                        .iter()
+                        .find(|player| player.id == user_id);
+                    // Everything a raise can go up to
+                    let max_bet = me.map(|me| me.bet + me.chips).unwrap_or(0);
+
+                    let action = match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                            None
+                        }
+                        KeyCode::Char('/') if raise_to.is_none() => {
+                            chat.start_typing();
+                            None
+                        }
+                        KeyCode::Enter if server_state.game_state == GameStartState::Setup => {
+                            if server_state.host_user == user_id {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                    ))
+                                    .await?;
+                            }
+                            None
+                        }
+                        KeyCode::Esc => {
+                            if raise_to.is_none() {
+                                return Ok(GameResult::None);
+                            }
+                            raise_to = None;
+                            None
+                        }
+                        _ if !is_turn => continue,
+                        KeyCode::Up | KeyCode::Down if raise_to.is_some() => {
+                            let min = server_state.current_bet + server_state.min_raise;
+                            raise_to = raise_to.map(|amount| {
+                                if key_event.code == KeyCode::Up {
+                                    (amount + BIG_BLIND).min(max_bet)
+                                } else {
+                                    amount.saturating_sub(BIG_BLIND).max(min).min(max_bet)
+                                }
+                            });
+                            None
+                        }
+                        KeyCode::Enter => raise_to.take().map(|amount| {
+                            if amount >= max_bet {
+                                HoldemClientAction::AllIn
+                            } else {
+                                HoldemClientAction::Raise(amount)
+                            }
+                        }),
+                        KeyCode::Char('r') => {
+                            let min = server_state.current_bet + server_state.min_raise;
+                            raise_to = Some(min.min(max_bet));
+                            None
+                        }
+                        KeyCode::Char('f') => Some(HoldemClientAction::Fold),
+                        KeyCode::Char('c') => {
+                            if me.is_some_and(|me| me.bet < server_state.current_bet) {
+                                Some(HoldemClientAction::Call)
+                            } else {
+                                Some(HoldemClientAction::Check)
+                            }
+                        }
+                        KeyCode::Char('a') => Some(HoldemClientAction::AllIn),
+                        _ => continue,
+                    };
+
+                    if let Some(action) = action {
+                        raise_to = None;
+                        tcp_sender.send(&Self::encode(user_id, action)?).await?;
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &my_cards,
+                    &events,
+                    raise_to,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712356"))
+    }
+
+    fn is_turn(server_state: &HoldemClientGameState, user_id: u32) -> bool {
+        server_state
+            .user_turn
+            .and_then(|turn| server_state.players.get(turn as usize))
+            .is_some_and(|player| player.id == user_id)
+    }
+
+    /// +---------+-------+-------~
+    /// | Players | Board | Events
+    /// +---------+-------+-------~
+    /// | Hole Cards      | Help
+    /// | Actions         | Chat
+    /// +-----------------+-------~
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &HoldemClientGameState,
+        my_cards: &[PlayingCard],
+        events: &[HoldemAction],
+        raise_to: Option<u32>,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active => match server_state
+                .user_turn
+                .and_then(|turn| server_state.players.get(turn as usize))
+            {
+                Some(player) if player.id == user_id => " My Turn ".to_string(),
+                Some(player) => format!(" {}'s Turn ", player.name),
+                None => format!(" Hand {} ", server_state.hand_number),
+            },
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Texas Hold'em ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(server_state.players.len().max(8) as u16 + 1),
+                Constraint::Fill(1),
+            ])
+            .split(inner);
+
+        let top_columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(52),
+                Constraint::Length(34),
+                Constraint::Fill(1),
+            ])
+            .split(rows[0]);
+
+        let bottom_columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(60), Constraint::Fill(40)])
+            .split(rows[1]);
+
+        frame.render_widget(
+            Self::player_list(server_state).block(Block::default().borders(Borders::RIGHT)),
+            top_columns[0],
+        );
+
+        Self::board(frame, top_columns[1], server_state);
+        Self::event_list(frame, top_columns[2], events);
+        Self::my_hand(
+            frame,
+            bottom_columns[0],
+            user_id,
+            server_state,
+            my_cards,
+            raise_to,
+            spectating,
+        );
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(bottom_columns[1]);
+
+        frame.render_widget(Paragraph::new(HELP_TEXT), help_rows[0]);
+        chat.render(frame, help_rows[1]);
+    }
+
+    fn player_list(server_state: &HoldemClientGameState) -> Table<'_> {
+        let rows: Vec<Row> = if server_state.game_state == GameStartState::Ending {
+            server_state
+                .standings
+                .iter()
+                .enumerate()
+                .map(|(i, (_, name))| {
+                    Row::new(vec![
+                        Cell::new((i + 1).to_string()).light_green(),
+                        Cell::new(name.clone()).light_green(),
+                    ])
+                })
+                .collect()
+        } else {
+            server_state
+                .players
+                .iter()
+                .enumerate()
+                .map(|(i, player)| {
+                    let is_turn = server_state.user_turn == Some(i as u8);
+
+                    let mut name = vec![Span::from(player.name.clone())];
+                    if player.id == server_state.host_user {
+                        name.push(Span::from(" H").light_yellow());
+                    }
+                    if server_state.game_state == GameStartState::Active
+                        && server_state.dealer as usize == i
+                    {
+                        name.push(Span::from(" D").light_cyan());
+                    }
+
+                    let status = if player.busted {
+                        Span::from("Busted").dark_gray()
+                    } else if player.all_in {
+                        Span::from("All In").light_red()
+                    } else if player.folded {
+                        Span::from("Folded").dark_gray()
+                    } else if player.bet > 0 {
+                        Span::from(format!("Bet {}", player.bet)).light_yellow()
+                    } else {
+                        Span::from("")
+                    };
+
+                    let shown: Vec<Span> = server_state
+                        .revealed
+                        .iter()
+                        .find(|(id, _)| *id == player.id)
+                        .map(|(_, cards)| {
+                            cards
+                                .iter()
+                                .flat_map(|card| [card_table::playing_card_span(card), " ".into()])
+                                .collect()
+                        })
+                        .unwrap_or_default();
+
+                    Row::new(vec![
+                        Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                        Cell::new(Line::from(name)),
+                        Cell::new(player.chips.to_string()),
+                        Cell::new(status),
+                        Cell::new(Line::from(shown)),
+                    ])
+                })
+                .collect()
+        };
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(20),
+            Constraint::Length(6),
+            Constraint::Length(9),
+            Constraint::Fill(1),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "Player", "Chips", "", ""]).bold())
+            .style(Style::default().white())
+    }
+
+    fn board(frame: &mut Frame, area: Rect, server_state: &HoldemClientGameState) {
+        let block = Block::default().borders(Borders::RIGHT);
+
+        if server_state.game_state != GameStartState::Active {
+            let text = match server_state.game_state {
+                GameStartState::Setup => "Waiting for host\nTwo to eight players",
+                _ => "Game over\nThank you for playing!",
+            };
+            frame.render_widget(Paragraph::new(text).centered().block(block), area);
+            return;
+        }
+
+        let pots = match &server_state.pots[..] {
+            [] => "Pot: 0".to_string(),
+            [main] => format!("Pot: {main}"),
+            [main, side @ ..] => format!(
+                "Pot: {main}  Side: {}",
+                side.iter()
+                    .map(|pot| pot.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+
+        let stage = match server_state.stage {
+            HoldemStage::PreFlop => "Pre-Flop",
+            HoldemStage::Flop => "Flop",
+            HoldemStage::Turn => "Turn",
+            HoldemStage::River => "River",
+            HoldemStage::Showdown => "Showdown",
+        };
+
+        let mut text = Text::from(format!("Board ~ {stage}"));
+        for _ in 0..4 {
+            text.push_line(Line::from(""));
+        }
+        text.push_line(pots);
+        if server_state.current_bet > 0 {
+            text.push_line(format!("To match: {}", server_state.current_bet));
+        }
+
+        frame.render_widget(Paragraph::new(text).block(block), area);
+
+        for (i, card) in server_state.board.iter().enumerate() {
+            let rect = Rect {
+                x: area.x + (i * 6) as u16,
+                y: area.y + 1,
+                width: 5,
+                height: 4,
+            };
+
+            frame.render_widget(card_table::playing_card_text(card, true), rect);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn my_hand(
+        frame: &mut Frame,
+        area: Rect,
+        user_id: u32,
+        server_state: &HoldemClientGameState,
+        my_cards: &[PlayingCard],
+        raise_to: Option<u32>,
+        spectating: bool,
+    ) {
+        let block = Block::default().borders(Borders::RIGHT);
+
+        match server_state.game_state {
+            GameStartState::Setup => {
+                let text = if server_state.host_user == user_id {
+                    "You can start the game by pressing Enter\nif there are at least two people in the lobby"
+                } else {
+                    "Waiting for the host to start the game"
+                };
+                frame.render_widget(Paragraph::new(text).centered().block(block), area);
+                return;
+            }
+            GameStartState::Ending => {
+                frame.render_widget(Paragraph::new("Game is over").block(block), area);
+                return;
+            }
+            GameStartState::Active if spectating || my_cards.is_empty() => {
+                frame.render_widget(Paragraph::new("Sitting out this hand").block(block), area);
+                return;
+            }
+            GameStartState::Active => {}
+        }
+
+        let all_cards: Vec<PlayingCard> = my_cards
+            .iter()
+            .chain(&server_state.board)
+            .copied()
+            .collect();
+
+        let mut text = Text::from("My Cards");
+        for _ in 0..4 {
+            text.push_line(Line::from(""));
+        }
+        if let Some(value) = evaluate(&all_cards) {
+            text.push_line(Line::from(format!("Best hand: {}", value.rank.name())).light_cyan());
+        }
+
+        let me = server_state
+            .players
+            .iter()
+            .find(|player| player.id == user_id);
+
+        if Self::is_turn(server_state, user_id)
+            && let Some(me) = me
+        {
+            text.push_line(Line::from(""));
+
+            let to_call = server_state.current_bet.saturating_sub(me.bet);
+            text.push_line(match raise_to {
+                Some(amount) if amount >= me.bet + me.chips => {
+                    Line::from(format!("Raise to {amount} ( All In )")).light_red()
+                }
+                Some(amount) => Line::from(format!("Raise to {amount}")).light_yellow(),
+                None if to_call > 0 => Line::from(format!(
+                    "{to_call} to call ~ f: Fold  c: Call  r: Raise  a: All In"
+                )),
+                None => Line::from("f: Fold  c: Check  r: Raise  a: All In"),
+            });
+        }
+
+        frame.render_widget(Paragraph::new(text).block(block), area);
+
+        for (i, card) in my_cards.iter().enumerate() {
+            let rect = Rect {
+                x: area.x + (i * 6) as u16,
+                y: area.y + 1,
+                width: 5,
+                height: 4,
+            };
+
+            frame.render_widget(card_table::playing_card_text(card, true), rect);
+        }
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[HoldemAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                HoldemAction::Init => Line::from("Server Started"),
+                HoldemAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                HoldemAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                HoldemAction::HandStarted(hand) => {
+                    Line::from(format!("~ Hand {hand} ~")).light_cyan()
+                }
+                HoldemAction::PostedBlind(user, amount) => {
+                    Line::from(format!("{user} posted a blind of {amount}"))
+                }
+                HoldemAction::Folded(user) => Line::from(format!("{user} folded")).dark_gray(),
+                HoldemAction::Checked(user) => Line::from(format!("{user} checked")),
+                HoldemAction::Called(user, amount) => Line::from(format!("{user} called {amount}")),
+                HoldemAction::Raised(user, amount) => {
+                    Line::from(format!("{user} raised to {amount}")).light_yellow()
+                }
+                HoldemAction::AllIn(user, amount) => {
+                    Line::from(format!("{user} is all in for {amount}")).light_red()
+                }
+                HoldemAction::Dealt(stage) => Line::from(match stage {
+                    HoldemStage::Flop => "The flop is dealt",
+                    HoldemStage::Turn => "The turn is dealt",
+                    HoldemStage::River => "The river is dealt",
+                    HoldemStage::PreFlop | HoldemStage::Showdown => "Cards are dealt",
+                }),
+                HoldemAction::Showed(user, rank) => {
+                    Line::from(format!("{user} shows {}", rank.name()))
+                }
+                HoldemAction::WonPot(user, amount, Some(rank)) => {
+                    Line::from(format!("{user} wins {amount} with {}", rank.name())).light_green()
+                }
+                HoldemAction::WonPot(user, amount, None) => {
+                    Line::from(format!("{user} wins {amount}")).light_green()
+                }
+                HoldemAction::UserBusted(user) => {
+                    Line::from(format!("{user} is out of chips")).light_red()
+                }
+                HoldemAction::UserWon(user) => Line::from(format!("{user} Won! ")).light_green(),
+                HoldemAction::GameEnded => Line::from("Game Over"),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerHoldemCommand> {
+        bincode::decode_from_slice::<ServerHoldemCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: HoldemClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
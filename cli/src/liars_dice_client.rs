@@ -0,0 +1,600 @@
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+    liars_dice::{
+        Bid, LiarsDiceAction, LiarsDiceClientAction, LiarsDiceClientGameState,
+        ServerLiarsDiceCommand, die_symbol,
+    },
+};
+use tokio::sync::mpsc;
+
+use crate::{
+    AppMessage, GameResult,
+    card_table::{self, TableLayout},
+    chat_pane::ChatPane,
+    room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Bid on how many of a face are under
+ everyone's cups put together, ones are wild.
+Up and Down change how many, Left and Right
+ change the face, Enter makes the bid.
+
+Think the last bid is a lie? Press L to call it.
+Whoever was wrong loses a die.
+"#;
+
+/// Liar's Dice, only our own cup is ever sent to us
+pub struct LiarsDiceClient;
+
+impl LiarsDiceClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    let ServerLiarsDiceCommand::GameState(dice, state) = Self::decode(data)?;
+                    server_state = Some((dice, state));
+                    break;
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some((my_dice, server_state)) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            my_dice,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: LiarsDiceClientGameState,
+        mut my_dice: Vec<u8>,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<LiarsDiceAction> = server_state.action.drain(..).collect();
+        let mut bid = Self::lowest_bid(&server_state);
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &my_dice,
+                &events,
+                bid,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => {
+                        let ServerLiarsDiceCommand::GameState(dice, mut state) =
+                            Self::decode(data)?;
+
+                        events.append(&mut state.action);
+                        server_state = state;
+                        my_dice = dice;
+
+                        // Whatever was being picked may not beat the bid any more
+                        if server_state
+                            .bid
+                            .is_some_and(|(_, current)| !bid.beats(&current))
+                            || server_state.bid.is_none()
+                        {
+                            bid = Self::lowest_bid(&server_state);
+                        }
+                    }
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    let total_dice: u32 = server_state
+                        .players
+                        .iter()
+                        .map(|player| player.dice_count as u32)
+                        .sum();
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Char('/') => chat.start_typing(),
+                        KeyCode::Up if (bid.quantity as u32) < total_dice => bid.quantity += 1,
+                        KeyCode::Down if bid.quantity > 1 => bid.quantity -= 1,
+                        KeyCode::Left if bid.face > 2 => bid.face -= 1,
+                        KeyCode::Right if bid.face < 6 => bid.face += 1,
+                        KeyCode::Char('l') | KeyCode::Char('L')
+                            if Self::my_turn(&server_state, user_id)
+                                && server_state.bid.is_some() =>
+                        {
+                            tcp_sender
+                                .send(&Self::encode(user_id, LiarsDiceClientAction::Call)?)
+                                .await?;
+                        }
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup {
+                                if server_state.host_user == user_id {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                        ))
+                                        .await?;
+                                }
+                                continue;
+                            }
+
+                            let beats = server_state
+                                .bid
+                                .is_none_or(|(_, current)| bid.beats(&current));
+
+                            if Self::my_turn(&server_state, user_id) && beats {
+                                tcp_sender
+                                    .send(&Self::encode(user_id, LiarsDiceClientAction::Bid(bid))?)
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &my_dice,
+                    &events,
+                    bid,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712361"))
+    }
+
+    /// Where the bid picker starts, just above whatever's on the table
+    fn lowest_bid(server_state: &LiarsDiceClientGameState) -> Bid {
+        match server_state.bid {
+            Some((_, current)) => current.next(),
+            None => Bid {
+                quantity: 1,
+                face: 2,
+            },
+        }
+    }
+
+    fn my_turn(server_state: &LiarsDiceClientGameState, user_id: u32) -> bool {
+        server_state
+            .user_turn
+            .and_then(|turn| server_state.players.get(turn as usize))
+            .is_some_and(|player| player.id == user_id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &LiarsDiceClientGameState,
+        my_dice: &[u8],
+        events: &[LiarsDiceAction],
+        bid: Bid,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active => match server_state
+                .user_turn
+                .and_then(|turn| server_state.players.get(turn as usize))
+            {
+                Some(player) if player.id == user_id => " My Turn ".to_string(),
+                Some(player) => format!(" {}'s Turn ", player.name),
+                None => String::new(),
+            },
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Liar's Dice ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let layout = TableLayout::split(inner, server_state.players.len() + 1, false);
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::RIGHT)),
+            layout.users,
+        );
+
+        Self::current_bid(frame, layout.last_card, server_state);
+        Self::event_list(frame, layout.events, events);
+
+        let hand_block = Block::default().borders(Borders::RIGHT);
+        match server_state.game_state {
+            GameStartState::Setup => {
+                let text = if server_state.host_user == user_id {
+                    "You can start the game by pressing Enter\nonce there are at least two players"
+                } else {
+                    "Waiting for the host to start the game"
+                };
+
+                frame.render_widget(
+                    Paragraph::new(text).centered().block(hand_block),
+                    layout.hand,
+                );
+            }
+            GameStartState::Active if !my_dice.is_empty() => {
+                let mut text = Text::from("My Cup");
+                for _ in 0..4 {
+                    text.push_line(Line::from(""));
+                }
+
+                if Self::my_turn(server_state, user_id) {
+                    text.push_line(Line::from(vec![
+                        Span::from("My bid: "),
+                        Span::from(format!("{} x {} ", bid.quantity, die_symbol(bid.face)))
+                            .bold()
+                            .light_yellow(),
+                        Span::from(format!("( {}s )", bid.face)).gray(),
+                    ]));
+
+                    if server_state.bid.is_some() {
+                        text.push_line(Line::from("Enter to bid, or L to call the last one"));
+                    } else {
+                        text.push_line(Line::from("Enter to open the bidding"));
+                    }
+                }
+
+                frame.render_widget(Paragraph::new(text).block(hand_block), layout.hand);
+
+                // Dice that count towards the bid being picked stand out
+                for (i, die) in my_dice.iter().enumerate() {
+                    let rect = Rect {
+                        x: layout.hand.x + (i * 6) as u16,
+                        y: layout.hand.y + 1,
+                        width: 5,
+                        height: 3,
+                    };
+
+                    let block = if *die == bid.face || *die == 1 {
+                        Block::bordered().light_green()
+                    } else {
+                        Block::bordered().white()
+                    };
+
+                    frame.render_widget(
+                        Paragraph::new(die_symbol(*die)).centered().block(block),
+                        rect,
+                    );
+                }
+            }
+            GameStartState::Active if spectating => {
+                frame.render_widget(
+                    Paragraph::new("Spectating, cups are hidden").block(hand_block),
+                    layout.hand,
+                );
+            }
+            GameStartState::Active => {
+                frame.render_widget(
+                    Paragraph::new("Out of dice, watching the rest").block(hand_block),
+                    layout.hand,
+                );
+            }
+            GameStartState::Ending => {
+                frame.render_widget(
+                    Paragraph::new("Game is over").block(hand_block),
+                    layout.hand,
+                );
+            }
+        }
+
+        let help_rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        frame.render_widget(Paragraph::new(HELP_TEXT), help_rows[0]);
+        chat.render(frame, help_rows[1]);
+    }
+
+    /// The last cups lifted are shown next to each player until someone calls again
+    fn user_list(server_state: &LiarsDiceClientGameState) -> Table<'_> {
+        let rows: Vec<Row> = if server_state.game_state == GameStartState::Ending {
+            server_state
+                .standings
+                .iter()
+                .enumerate()
+                .map(|(i, (_, name))| {
+                    Row::new(vec![
+                        Cell::new((i + 1).to_string()).light_green(),
+                        Cell::new(name.clone()).light_green(),
+                        Cell::new(""),
+                        Cell::new(""),
+                    ])
+                })
+                .collect()
+        } else {
+            server_state
+                .players
+                .iter()
+                .enumerate()
+                .map(|(i, player)| {
+                    let is_turn = server_state.user_turn == Some(i as u8);
+
+                    let mut name = vec![Span::from(player.name.clone())];
+                    if let Some(rating) = player.rating {
+                        name.push(Span::from(format!(" {rating}")).gray());
+                    }
+                    if player.id == server_state.host_user {
+                        name.push(Span::from(" H").light_yellow());
+                    }
+
+                    let dice = if player.dice_count == 0
+                        && server_state.game_state == GameStartState::Active
+                    {
+                        Span::from("Out").light_red()
+                    } else {
+                        Span::from(player.dice_count.to_string())
+                    };
+
+                    let reveal = server_state
+                        .last_reveal
+                        .iter()
+                        .find(|(id, _)| *id == player.id)
+                        .map(|(_, dice)| {
+                            dice.iter()
+                                .map(|die| die_symbol(*die))
+                                .collect::<Vec<_>>()
+                                .join(" ")
+                        })
+                        .unwrap_or_default();
+
+                    Row::new(vec![
+                        Cell::new(if is_turn { ">" } else { " " }).light_green(),
+                        Cell::new(Line::from(name)),
+                        Cell::new(dice),
+                        Cell::new(Span::from(reveal).gray()),
+                    ])
+                })
+                .collect()
+        };
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Fill(1),
+            Constraint::Length(4),
+            Constraint::Length(9),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "Player", "Dice", "Last Cup"]).bold())
+            .style(Style::default().white())
+    }
+
+    fn current_bid(frame: &mut Frame, area: Rect, server_state: &LiarsDiceClientGameState) {
+        let block = Block::default().borders(Borders::RIGHT);
+
+        let text = match server_state.game_state {
+            GameStartState::Setup => Text::from("Waiting for host\nNeeds 2 or more players"),
+            GameStartState::Ending => Text::from("Game over\nThank you for playing!"),
+            GameStartState::Active => {
+                let total_dice: u32 = server_state
+                    .players
+                    .iter()
+                    .map(|player| player.dice_count as u32)
+                    .sum();
+
+                let mut text = Text::from(format!("Round {}", server_state.round));
+                text.push_line(Line::from(format!("{total_dice} dice in play")).gray());
+                text.push_line(Line::from(""));
+
+                match server_state.bid {
+                    Some((bidder, bid)) => {
+                        let name = server_state
+                            .players
+                            .iter()
+                            .find(|player| player.id == bidder)
+                            .map(|player| player.name.as_str())
+                            .unwrap_or("Someone");
+
+                        text.push_line(Line::from(format!("{name} bid")));
+                        text.push_line(
+                            Line::from(format!(
+                                "{} x {}  ( {}s )",
+                                bid.quantity,
+                                die_symbol(bid.face),
+                                bid.face
+                            ))
+                            .bold()
+                            .light_yellow(),
+                        );
+                    }
+                    None => text.push_line(Line::from("No bids yet")),
+                }
+
+                text
+            }
+        };
+
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[LiarsDiceAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                LiarsDiceAction::Init => Line::from("Server Started"),
+                LiarsDiceAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                LiarsDiceAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                LiarsDiceAction::RoundStarted(round) => {
+                    Line::from(format!("~ Round {round}, everyone rolls ~")).light_cyan()
+                }
+                LiarsDiceAction::UserBid(user, bid) => Line::from(format!(
+                    "{user} bid {} x {}",
+                    bid.quantity,
+                    die_symbol(bid.face)
+                )),
+                LiarsDiceAction::UserCalled(caller, bidder, actual) => Line::from(format!(
+                    "{caller} called {bidder} a liar, there were {actual}"
+                ))
+                .light_yellow(),
+                LiarsDiceAction::UserLostDie(user) => {
+                    Line::from(format!("{user} lost a die")).light_red()
+                }
+                LiarsDiceAction::UserKnockedOut(user) => {
+                    Line::from(format!("{user} is out of dice")).light_red()
+                }
+                LiarsDiceAction::UserWon(user) => Line::from(format!("{user} Won! ")).light_green(),
+                LiarsDiceAction::GameEnded => Line::from("Game Over"),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerLiarsDiceCommand> {
+        bincode::decode_from_slice::<ServerLiarsDiceCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: LiarsDiceClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
@@ -5,14 +5,37 @@ use encr::{EncryptedReceiver, EncryptedSender};
 use ratatui::DefaultTerminal;
 use rpc::{
     comms::{ClientAuthedCommand, ClientMessage, ServerMessage},
-    game_state::GameType,
+    game_state::{GameType, GameUserState},
 };
 use tokio::sync::mpsc;
 
-use crate::{app_auth::AppAuth, app_lobby::LobbyResult, uno_client::UnoClient};
+use crate::{
+    app_auth::AppAuth, app_lobby::LobbyResult, app_queue::AppQueue, app_stats::AppStats,
+    battleship_client::BattleshipClient, blackjack_client::BlackjackClient, chat_pane::ChatPane,
+    chess_client::ChessClient, codenames_client::CodenamesClient,
+    connect_four_client::ConnectFourClient, eights_client::EightsClient,
+    hearts_client::HeartsClient, holdem_client::HoldemClient, liars_dice_client::LiarsDiceClient,
+    replay_viewer::ReplayViewer, snake_client::SnakeClient, uno_client::UnoClient,
+};
 
 mod app_auth;
 mod app_lobby;
+mod app_queue;
+mod app_stats;
+mod battleship_client;
+mod blackjack_client;
+mod board_table;
+mod card_table;
+mod chat_pane;
+mod chess_client;
+mod codenames_client;
+mod connect_four_client;
+mod eights_client;
+mod hearts_client;
+mod holdem_client;
+mod liars_dice_client;
+mod replay_viewer;
+mod snake_client;
 mod uno_client;
 
 /// This architecture may be a bit off, the main idea is:
@@ -62,7 +85,23 @@ pub enum GameResult {
     Exit,
     None,
     NoGame,
-    Game(String, GameType),
+    Game(String, GameType, GameUserState),
+    /// Back to the lobby, showing why
+    Failed(String),
+}
+
+/// What every game does with a server message that isn't its own state.
+/// Being removed from the game hands back the reason, so the lobby can show it.
+pub fn room_message(message: ServerMessage, chat: &mut ChatPane) -> Option<GameResult> {
+    match message {
+        ServerMessage::RemovedFromGame(reason) => return Some(GameResult::Failed(reason)),
+        ServerMessage::Chat(message) => chat.push(message),
+        ServerMessage::ChatHistory(channel, history) => chat.set_history(channel, history),
+        ServerMessage::ChatRejected(reason) => chat.rejected(reason),
+        _ => {}
+    }
+
+    None
 }
 
 impl App {
@@ -91,44 +130,205 @@ impl App {
         Self::start_rpc_receiver(app_sender.clone(), tcp_receiver);
 
         // let mut game_result = GameResult::None;
+        let mut notice = None;
 
         loop {
-            let lobby_result = app_lobby
-                .clone()
-                .start(&mut terminal, &mut app_receiver)
+            let lobby = match notice.take() {
+                Some(notice) => app_lobby.clone().with_notice(notice),
+                None => app_lobby.clone(),
+            };
+
+            let lobby_result = lobby
+                .start(&mut terminal, &mut app_receiver, &mut tcp_sender)
                 .await
                 .map_err(|err| Error::msg(err))?;
 
-            if let LobbyResult::Exit = lobby_result {
-                break;
+            match lobby_result {
+                LobbyResult::Exit => break,
+                LobbyResult::Replays => {
+                    ReplayViewer::start(
+                        app_lobby.id,
+                        &mut tcp_sender,
+                        &mut app_receiver,
+                        &mut terminal,
+                    )
+                    .await
+                    .map_err(|err| Error::msg(err))?;
+                    continue;
+                }
+                LobbyResult::Stats => {
+                    AppStats::start(
+                        &app_lobby.name,
+                        app_lobby.id,
+                        &mut tcp_sender,
+                        &mut app_receiver,
+                        &mut terminal,
+                    )
+                    .await
+                    .map_err(|err| Error::msg(err))?;
+                    continue;
+                }
+                _ => {}
             }
 
-            let game_result = Self::handle_lobby_result(
-                app_lobby.id,
-                lobby_result,
-                &mut tcp_sender,
-                &mut app_receiver,
-            )
-            .await
+            let game_result = match lobby_result {
+                LobbyResult::QuickPlay => {
+                    AppQueue::start(
+                        app_lobby.id,
+                        &mut tcp_sender,
+                        &mut app_receiver,
+                        &mut terminal,
+                    )
+                    .await
+                }
+                _ => {
+                    Self::handle_lobby_result(
+                        app_lobby.id,
+                        lobby_result,
+                        &mut tcp_sender,
+                        &mut app_receiver,
+                    )
+                    .await
+                }
+            }
             .map_err(|err| Error::msg(err))?;
 
             match game_result {
                 GameResult::Exit => break,
                 GameResult::None | GameResult::NoGame => continue,
-                GameResult::Game(lobby, game_type) => match game_type {
-                    GameType::Uno => {
-                        UnoClient::try_start(
+                GameResult::Failed(reason) => {
+                    notice = Some(reason);
+                    continue;
+                }
+                GameResult::Game(lobby, game_type, user_state) => {
+                    let left = match game_type {
+                        GameType::Uno | GameType::UnoVariant(_) => UnoClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::CrazyEights => EightsClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::Holdem => HoldemClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::Hearts => HeartsClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::ConnectFour => ConnectFourClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::Chess => ChessClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::Snake => SnakeClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::LiarsDice => LiarsDiceClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::Codenames => CodenamesClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::Blackjack => BlackjackClient::try_start(
+                            lobby,
+                            app_lobby.name.clone(),
+                            app_lobby.id,
+                            user_state,
+                            &mut tcp_sender,
+                            &mut app_receiver,
+                            &mut terminal,
+                        )
+                        .await
+                        .map_err(|err| Error::msg(err))?,
+                        GameType::Battleship => BattleshipClient::try_start(
                             lobby,
                             app_lobby.name.clone(),
                             app_lobby.id,
+                            user_state,
                             &mut tcp_sender,
                             &mut app_receiver,
                             &mut terminal,
                         )
                         .await
-                        .map_err(|err| Error::msg(err))?;
+                        .map_err(|err| Error::msg(err))?,
+                    };
+
+                    if let GameResult::Failed(reason) = left {
+                        notice = Some(reason);
                     }
-                },
+                }
             }
         }
 
@@ -143,6 +343,12 @@ impl App {
     ) -> anyhow::Result<GameResult> {
         match lobby_result {
             app_lobby::LobbyResult::Exit => return Ok(GameResult::Exit),
+            // These screens are shown from the main loop, no game is joined
+            app_lobby::LobbyResult::Replays
+            | app_lobby::LobbyResult::Stats
+            | app_lobby::LobbyResult::QuickPlay => {
+                return Ok(GameResult::None);
+            }
             app_lobby::LobbyResult::Create(game_create) => {
                 tcp_sender
                     .send(&ClientMessage::Authed(
@@ -150,15 +356,32 @@ impl App {
                         ClientAuthedCommand::CreateGame(
                             String::from_iter(game_create.name),
                             game_create.game_type,
+                            game_create.visibility,
                         ),
                     ))
                     .await?;
             }
-            app_lobby::LobbyResult::Join(game_id) => {
+            app_lobby::LobbyResult::Join(game_id, password) => {
+                tcp_sender
+                    .send(&ClientMessage::Authed(
+                        user_id,
+                        ClientAuthedCommand::JoinGame(game_id, password),
+                    ))
+                    .await?;
+            }
+            app_lobby::LobbyResult::Spectate(game_id, password) => {
+                tcp_sender
+                    .send(&ClientMessage::Authed(
+                        user_id,
+                        ClientAuthedCommand::SpectateGame(game_id, password),
+                    ))
+                    .await?;
+            }
+            app_lobby::LobbyResult::JoinByCode(code) => {
                 tcp_sender
                     .send(&ClientMessage::Authed(
                         user_id,
-                        ClientAuthedCommand::JoinGame(game_id),
+                        ClientAuthedCommand::JoinByCode(code),
                     ))
                     .await?;
             }
@@ -172,17 +395,33 @@ impl App {
 
             match msg {
                 AppMessage::RpcEvent(server_message) => match server_message {
-                    ServerMessage::AuthResponse(_)
-                    | ServerMessage::LobbyState(_)
-                    | ServerMessage::GameState(_)
-                    | ServerMessage::NewPlayerCount(_) => {
+                    ServerMessage::AuthResponse(_) => {
                         return Err(anyhow!(
                             "Received redundant server message {server_message:?}"
                         ));
                     }
-                    ServerMessage::JoinedGame(lobby, game_type) => {
-                        return Ok(GameResult::Game(lobby, game_type));
+                    ServerMessage::JoinedGame(lobby, game_type, user_state) => {
+                        return Ok(GameResult::Game(lobby, game_type, user_state));
+                    }
+                    ServerMessage::JoinFailed(reason) | ServerMessage::RemovedFromGame(reason) => {
+                        return Ok(GameResult::Failed(reason));
                     }
+                    // The lobby keeps updating and chat keeps coming in while waiting to get
+                    //  into the game, anything left over from the last game or the queue is
+                    //  just late, the lobby asks again when it's next shown
+                    ServerMessage::LobbyState(_)
+                    | ServerMessage::NewPlayerCount(_)
+                    | ServerMessage::GameState(_)
+                    | ServerMessage::ReplayList(_)
+                    | ServerMessage::Replay(_, _)
+                    | ServerMessage::ReplayError(_)
+                    | ServerMessage::Profile(_, _)
+                    | ServerMessage::Leaderboard(_, _)
+                    | ServerMessage::QueueStatus(_)
+                    | ServerMessage::QueueError(_)
+                    | ServerMessage::Chat(_)
+                    | ServerMessage::ChatHistory(_, _)
+                    | ServerMessage::ChatRejected(_) => {}
                 },
                 AppMessage::TerminalEvent(event) => {
                     if let Event::Key(key_event) = event
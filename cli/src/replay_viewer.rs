@@ -0,0 +1,310 @@
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    comms::{ClientAuthedCommand, ClientMessage, ReplaySummary, ServerMessage},
+    game_state::GameType,
+    uno::{UnoAction, UnoReplayFrame},
+};
+use tokio::sync::mpsc;
+
+use crate::{AppMessage, uno_client::UnoClient};
+
+enum ReplayView {
+    Loading,
+    List(Vec<ReplaySummary>, usize),
+    Uno(UnoReplay),
+    Failed(String),
+}
+
+struct UnoReplay {
+    name: String,
+    frames: Vec<UnoReplayFrame>,
+    step: usize,
+    /// Everyone dealt in, the replay is shown from one of their seats
+    seats: Vec<u32>,
+    seat_idx: usize,
+}
+
+/// Replays are played back on the server, we get every state of the game
+///  and just step through them.
+///
+/// The game itself is drawn the same way as a live game, from the point
+///  of view of whichever seat is picked.
+pub struct ReplayViewer;
+
+impl ReplayViewer {
+    pub async fn start(
+        user_id: u32,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<()> {
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::ListReplays,
+            ))
+            .await?;
+
+        let mut view = ReplayView::Loading;
+        let mut replays: Vec<ReplaySummary> = vec![];
+
+        terminal.draw(|frame| Self::render(frame, &view))?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::ReplayList(list) => {
+                        replays = list.clone();
+                        view = ReplayView::List(list, 0);
+                    }
+                    ServerMessage::Replay(GameType::Uno | GameType::UnoVariant(_), data) => {
+                        let name = match &view {
+                            ReplayView::List(list, idx) => list
+                                .get(*idx)
+                                .map(|replay| replay.name.clone())
+                                .unwrap_or_default(),
+                            _ => String::new(),
+                        };
+
+                        let (frames, _) =
+                            bincode::decode_from_slice::<Vec<UnoReplayFrame>, Configuration>(
+                                &data,
+                                bincode::config::standard(),
+                            )?;
+
+                        let seats = frames
+                            .first()
+                            .map(|frame| frame.hands.iter().map(|(id, _)| *id).collect())
+                            .unwrap_or_default();
+
+                        view = ReplayView::Uno(UnoReplay {
+                            name,
+                            frames,
+                            step: 0,
+                            seats,
+                            seat_idx: 0,
+                        });
+                    }
+                    ServerMessage::ReplayError(err) => view = ReplayView::Failed(err),
+                    _ => {}
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    match &mut view {
+                        ReplayView::Loading | ReplayView::Failed(_) => {
+                            if key_event.code == KeyCode::Esc {
+                                if replays.is_empty() {
+                                    return Ok(());
+                                }
+                                view = ReplayView::List(replays.clone(), 0);
+                            }
+                        }
+                        ReplayView::List(list, idx) => match key_event.code {
+                            KeyCode::Up if !list.is_empty() => {
+                                *idx = if *idx == 0 { list.len() - 1 } else { *idx - 1 };
+                            }
+                            KeyCode::Down if !list.is_empty() => {
+                                *idx = if *idx >= list.len() - 1 { 0 } else { *idx + 1 };
+                            }
+                            KeyCode::Enter => {
+                                if let Some(replay) = list.get(*idx) {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::FetchReplay(replay.id.clone()),
+                                        ))
+                                        .await?;
+                                }
+                            }
+                            KeyCode::Esc => return Ok(()),
+                            _ => continue,
+                        },
+                        ReplayView::Uno(replay) => match key_event.code {
+                            KeyCode::Right => {
+                                replay.step = (replay.step + 1).min(replay.frames.len() - 1);
+                            }
+                            KeyCode::Left => replay.step = replay.step.saturating_sub(1),
+                            KeyCode::Home => replay.step = 0,
+                            KeyCode::End => replay.step = replay.frames.len() - 1,
+                            KeyCode::Up | KeyCode::Down if !replay.seats.is_empty() => {
+                                let seat_count = replay.seats.len();
+                                replay.seat_idx = if key_event.code == KeyCode::Up {
+                                    (replay.seat_idx + seat_count - 1) % seat_count
+                                } else {
+                                    (replay.seat_idx + 1) % seat_count
+                                };
+                            }
+                            KeyCode::Esc => view = ReplayView::List(replays.clone(), 0),
+                            _ => continue,
+                        },
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| Self::render(frame, &view))?;
+        }
+
+        Ok(())
+    }
+
+    fn render(frame: &mut Frame, view: &ReplayView) {
+        match view {
+            ReplayView::Loading => Self::render_message(frame, "Loading replays"),
+            ReplayView::Failed(err) => Self::render_message(frame, err),
+            ReplayView::List(list, idx) => Self::render_list(frame, list, *idx),
+            ReplayView::Uno(replay) => Self::render_uno(frame, replay),
+        }
+    }
+
+    fn render_message(frame: &mut Frame, message: &str) {
+        frame.render_widget(
+            Paragraph::new(message).centered().block(Self::get_block()),
+            frame.area(),
+        );
+    }
+
+    fn render_list(frame: &mut Frame, list: &[ReplaySummary], idx: usize) {
+        let block = Self::get_block();
+        let inner = block.inner(frame.area());
+        frame.render_widget(block, frame.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(2), Constraint::Fill(1)])
+            .split(inner);
+
+        frame.render_widget(
+            Text::from("Press Up / Down to pick a replay, Enter to watch it"),
+            chunks[0],
+        );
+
+        if list.is_empty() {
+            frame.render_widget(Text::from("No games have been saved yet"), chunks[1]);
+            return;
+        }
+
+        let rows: Vec<Row> = list
+            .iter()
+            .enumerate()
+            .map(|(i, replay)| {
+                let game_cell = match replay.game_type {
+                    GameType::Uno => Cell::new("Uno").light_cyan(),
+                    GameType::CrazyEights => Cell::new("Eights").light_red(),
+                    GameType::Holdem => Cell::new("Poker").light_green(),
+                    GameType::Hearts => Cell::new("Hearts").light_magenta(),
+                    GameType::ConnectFour => Cell::new("Conn4").light_yellow(),
+                    GameType::Chess => Cell::new("Chess").light_blue(),
+                    GameType::Snake => Cell::new("Snake").green(),
+                    GameType::LiarsDice => Cell::new("Dice").yellow(),
+                    GameType::Codenames => Cell::new("Codes").light_red(),
+                    GameType::Blackjack => Cell::new("BJack").cyan(),
+                    GameType::Battleship => Cell::new("Ships").blue(),
+                    GameType::UnoVariant(_) => Cell::new("Uno+").light_cyan(),
+                };
+
+                Row::new(vec![
+                    Cell::new(if i == idx { ">" } else { " " }).blue(),
+                    Cell::new(replay.name.clone()),
+                    game_cell,
+                    Cell::new(replay.players.join(", ")).gray(),
+                ])
+            })
+            .collect();
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(15),
+            Constraint::Length(4),
+            Constraint::Fill(1),
+        ];
+
+        frame.render_widget(
+            Table::new(rows, widths)
+                .style(Style::default().white())
+                .block(Block::default().borders(Borders::TOP)),
+            chunks[1],
+        );
+    }
+
+    /// Events build up the same way they would have live, every step's actions up to now
+    fn render_uno(frame: &mut Frame, replay: &UnoReplay) {
+        let Some(current) = replay.frames.get(replay.step) else {
+            return;
+        };
+
+        let events: Vec<UnoAction> = replay.frames[..=replay.step]
+            .iter()
+            .flat_map(|frame| frame.state.action.iter().cloned())
+            .collect();
+
+        let seat_id = replay.seats.get(replay.seat_idx).copied().unwrap_or(0);
+        let hand = current
+            .hands
+            .iter()
+            .find(|(id, _)| *id == seat_id)
+            .map(|(_, cards)| cards.as_slice())
+            .unwrap_or_default();
+
+        UnoClient::render(
+            frame,
+            seat_id,
+            &replay.name,
+            &current.state,
+            hand,
+            &events,
+            0,
+            None,
+            false,
+            None,
+        );
+
+        // Sits on the bottom border, where a live game shows who is spectating
+        let area = frame.area();
+        frame.render_widget(
+            Line::from(format!(
+                " Replay {} / {} ~ Left / Right to step ~ Up / Down to change seat ",
+                replay.step + 1,
+                replay.frames.len()
+            ))
+            .bold()
+            .light_yellow(),
+            Rect {
+                x: area.x + 1,
+                y: area.y + area.height.saturating_sub(1),
+                width: area.width.saturating_sub(16),
+                height: 1,
+            },
+        );
+    }
+
+    fn get_block() -> Block<'static> {
+        Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(Line::from(" Tempest ~ Replays ").bold().white())
+            .title_bottom(
+                Line::from(" Esc to go back ")
+                    .bold()
+                    .white()
+                    .right_aligned(),
+            )
+    }
+}
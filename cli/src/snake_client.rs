@@ -0,0 +1,547 @@
+use std::collections::{HashMap, VecDeque};
+
+use anyhow::anyhow;
+use bincode::config::Configuration;
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use encr::EncryptedSender;
+use ratatui::{
+    DefaultTerminal, Frame,
+    layout::{Constraint, Direction as LayoutDirection, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table},
+};
+use rpc::{
+    chat::ChatChannel,
+    comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
+    game_state::{GameStartState, GameUserState},
+    snake::{
+        Direction, SNAKE_HEIGHT, SNAKE_TICK_MS, SNAKE_WIDTH, ServerSnakeCommand, SnakeAction,
+        SnakeClientAction, SnakeClientGameState, SnakeTick,
+    },
+};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    AppMessage, GameResult, board_table::BoardLayout, card_table, chat_pane::ChatPane, room_message,
+};
+
+const HELP_TEXT: &str = r#"How to play:
+Steer with the arrow keys or WASD.
+Eat the food to grow.
+
+Don't hit the walls, yourself,
+ or anyone else.
+Last snake moving wins.
+"#;
+
+/// Snakes are told apart by where their player sits
+const SNAKE_COLOURS: [Color; 6] = [
+    Color::LightGreen,
+    Color::LightCyan,
+    Color::LightMagenta,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightRed,
+];
+
+/// Multiplayer snake. Unlike the other games this redraws every time the server ticks.
+pub struct SnakeClient;
+
+/// Inputs are numbered so we can tell how long the server took to act on them
+#[derive(Default)]
+struct InputLag {
+    next_input: u32,
+    sent: VecDeque<(u32, Instant)>,
+    last_ms: Option<u128>,
+}
+
+impl InputLag {
+    fn send(&mut self) -> u32 {
+        self.next_input += 1;
+        self.sent.push_back((self.next_input, Instant::now()));
+        self.next_input
+    }
+
+    fn acknowledged(&mut self, last_input: u32) {
+        while let Some((input, sent_at)) = self.sent.front().copied() {
+            if input > last_input {
+                break;
+            }
+            self.sent.pop_front();
+
+            if input == last_input {
+                self.last_ms = Some(sent_at.elapsed().as_millis());
+            }
+        }
+    }
+}
+
+impl SnakeClient {
+    pub async fn try_start(
+        lobby: String,
+        user_name: String,
+        user_id: u32,
+        user_state: GameUserState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+
+        let mut server_state = None;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                // A tick can arrive before the full state if we join late, it's skipped
+                AppMessage::RpcEvent(ServerMessage::GameState(data)) => {
+                    if let ServerSnakeCommand::GameState(state) = Self::decode(data)? {
+                        server_state = Some(state);
+                        break;
+                    }
+                }
+                AppMessage::RpcEvent(_) | AppMessage::TerminalEvent(_) => {}
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+        }
+
+        let Some(server_state) = server_state else {
+            return Err(anyhow!("Didn't get game state"));
+        };
+
+        let res = Self::start(
+            lobby,
+            user_id,
+            user_state == GameUserState::Spectator,
+            server_state,
+            tcp_sender,
+            app_receiver,
+            terminal,
+        )
+        .await;
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::Game(ClientGameCommand::Leave),
+            ))
+            .await?;
+
+        res
+    }
+
+    async fn start(
+        lobby: String,
+        user_id: u32,
+        spectating: bool,
+        mut server_state: SnakeClientGameState,
+        tcp_sender: &mut EncryptedSender<ClientMessage>,
+        app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
+        terminal: &mut DefaultTerminal,
+    ) -> anyhow::Result<GameResult> {
+        let mut events: Vec<SnakeAction> = server_state.action.drain(..).collect();
+        let mut lag = InputLag::default();
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        terminal.draw(|frame| {
+            Self::render(
+                frame,
+                user_id,
+                &lobby,
+                &server_state,
+                &events,
+                &lag,
+                spectating,
+                &chat,
+            )
+        })?;
+
+        while let Some(msg) = app_receiver.recv().await {
+            match msg {
+                AppMessage::RpcEvent(server_message) => match server_message {
+                    ServerMessage::GameState(data) => match Self::decode(data)? {
+                        ServerSnakeCommand::GameState(mut state) => {
+                            events.append(&mut state.action);
+                            server_state = state;
+                        }
+                        ServerSnakeCommand::Tick(delta) => {
+                            if let Some(mine) =
+                                delta.snakes.iter().find(|snake| snake.id == user_id)
+                            {
+                                lag.acknowledged(mine.last_input);
+                            }
+                            if let Some(tick) = server_state.tick.as_mut() {
+                                tick.apply(delta);
+                            }
+                        }
+                    },
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
+                },
+                AppMessage::TerminalEvent(event) => {
+                    let Event::Key(key_event) = event else {
+                        continue;
+                    };
+
+                    // Waiting for the key to come back up feels sluggish when steering,
+                    //  so turns go on the press instead
+                    let turn = match key_event.code {
+                        _ if chat.is_typing() => None,
+                        KeyCode::Up | KeyCode::Char('w') => Some(Direction::Up),
+                        KeyCode::Down | KeyCode::Char('s') => Some(Direction::Down),
+                        KeyCode::Left | KeyCode::Char('a') => Some(Direction::Left),
+                        KeyCode::Right | KeyCode::Char('d') => Some(Direction::Right),
+                        _ => None,
+                    };
+
+                    if let Some(direction) = turn {
+                        if key_event.kind == KeyEventKind::Press
+                            && !spectating
+                            && server_state.game_state == GameStartState::Active
+                        {
+                            let input = lag.send();
+                            tcp_sender
+                                .send(&Self::encode(
+                                    user_id,
+                                    SnakeClientAction::Turn(direction, input),
+                                )?)
+                                .await?;
+                        }
+                        continue;
+                    }
+
+                    if key_event.kind != KeyEventKind::Release {
+                        continue;
+                    }
+
+                    // Spectators can only watch, and chat
+                    if spectating
+                        && !chat.is_typing()
+                        && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                    {
+                        continue;
+                    }
+
+                    match key_event.code {
+                        code if chat.is_typing() => {
+                            if let Some(text) = chat.key(code) {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Char('/') => chat.start_typing(),
+                        KeyCode::Enter => {
+                            if server_state.game_state == GameStartState::Setup
+                                && server_state.host_user == user_id
+                            {
+                                tcp_sender
+                                    .send(&ClientMessage::Authed(
+                                        user_id,
+                                        ClientAuthedCommand::Game(ClientGameCommand::Start),
+                                    ))
+                                    .await?;
+                            }
+                        }
+                        KeyCode::Esc => return Ok(GameResult::None),
+                        _ => continue,
+                    }
+                }
+                AppMessage::Failure(err) => {
+                    return Err(err);
+                }
+            }
+
+            terminal.draw(|frame| {
+                Self::render(
+                    frame,
+                    user_id,
+                    &lobby,
+                    &server_state,
+                    &events,
+                    &lag,
+                    spectating,
+                    &chat,
+                )
+            })?;
+        }
+
+        Err(anyhow!("Internal Failure 6712360"))
+    }
+
+    fn colour(server_state: &SnakeClientGameState, user_id: u32) -> Color {
+        server_state
+            .players
+            .iter()
+            .position(|player| player.id == user_id)
+            .map(|idx| SNAKE_COLOURS[idx % SNAKE_COLOURS.len()])
+            .unwrap_or(Color::Gray)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render(
+        frame: &mut Frame,
+        user_id: u32,
+        lobby: &str,
+        server_state: &SnakeClientGameState,
+        events: &[SnakeAction],
+        lag: &InputLag,
+        spectating: bool,
+        chat: &ChatPane,
+    ) {
+        let tick = server_state.tick.as_ref();
+
+        let turn_name = match server_state.game_state {
+            GameStartState::Setup => " Waiting To Start ".to_string(),
+            GameStartState::Active => match tick {
+                Some(tick) if tick.countdown > 0 => format!(
+                    " Starting In {} ",
+                    (tick.countdown as u64 * SNAKE_TICK_MS).div_ceil(1000)
+                ),
+                _ => " Go! ".to_string(),
+            },
+            GameStartState::Ending => " Finished ".to_string(),
+        };
+
+        let mut outer_block = Block::bordered()
+            .border_style(Style::new().light_blue())
+            .title_top(
+                Line::from(format!(" Tempest ~ {lobby} ( Snake ) "))
+                    .bold()
+                    .white(),
+            )
+            .title(Line::from(turn_name).bold().white().centered())
+            .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
+
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        } else if let Some(ms) = lag.last_ms {
+            outer_block =
+                outer_block.title_bottom(Line::from(format!(" Input lag {ms} ms ")).gray());
+        }
+
+        let area = frame.area();
+        let inner = outer_block.inner(area);
+        frame.render_widget(outer_block, area);
+
+        let layout = BoardLayout::split(
+            inner,
+            SNAKE_WIDTH as u16 * 2 + 3,
+            server_state.players.len(),
+        );
+
+        Self::board(frame, layout.board, server_state);
+
+        frame.render_widget(
+            Self::user_list(server_state).block(Block::default().borders(Borders::BOTTOM)),
+            layout.users,
+        );
+
+        Self::event_list(frame, layout.events, events);
+
+        let help_rows = Layout::default()
+            .direction(LayoutDirection::Vertical)
+            .constraints([Constraint::Fill(1), Constraint::Length(9)])
+            .split(layout.help);
+
+        let help = match server_state.game_state {
+            GameStartState::Setup if server_state.host_user == user_id => {
+                format!("{HELP_TEXT}\nPress Enter to start")
+            }
+            GameStartState::Setup => format!("{HELP_TEXT}\nWaiting for the host to start"),
+            _ => HELP_TEXT.to_string(),
+        };
+
+        let mut help = Text::from(help);
+        if !spectating {
+            help.push_line(Line::from(vec![
+                Span::from("You are "),
+                Span::from("██").fg(Self::colour(server_state, user_id)),
+            ]));
+        }
+
+        frame.render_widget(
+            Paragraph::new(help).block(Block::default().borders(Borders::TOP)),
+            help_rows[0],
+        );
+        chat.render(frame, help_rows[1]);
+    }
+
+    /// Each cell is two characters wide so the board comes out roughly square
+    fn board(frame: &mut Frame, area: Rect, server_state: &SnakeClientGameState) {
+        let mut cells: HashMap<(u8, u8), Span> = HashMap::new();
+
+        if let Some(SnakeTick { snakes, food, .. }) = &server_state.tick {
+            for cell in food {
+                cells.insert(*cell, Span::from("<>").light_red().bold());
+            }
+
+            for snake in snakes {
+                let colour = Self::colour(server_state, snake.id);
+
+                for (i, cell) in snake.body.iter().enumerate() {
+                    let span = if i == 0 {
+                        Span::from("██").fg(colour).bold()
+                    } else {
+                        Span::from("▓▓").fg(colour)
+                    };
+                    cells.insert(*cell, span);
+                }
+            }
+        }
+
+        let lines: Vec<Line> = (0..SNAKE_HEIGHT)
+            .map(|row| {
+                Line::from(
+                    (0..SNAKE_WIDTH)
+                        .map(|column| {
+                            cells
+                                .get(&(column, row))
+                                .cloned()
+                                .unwrap_or_else(|| Span::from("  "))
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
+
+        let board_area = Rect {
+            height: area.height.min(SNAKE_HEIGHT as u16 + 2),
+            width: area.width.min(SNAKE_WIDTH as u16 * 2 + 2),
+            ..area
+        };
+
+        frame.render_widget(
+            Paragraph::new(Text::from(lines)).block(Block::bordered().light_blue()),
+            board_area,
+        );
+    }
+
+    fn user_list(server_state: &SnakeClientGameState) -> Table<'_> {
+        let snakes = server_state
+            .tick
+            .as_ref()
+            .map(|tick| tick.snakes.as_slice())
+            .unwrap_or_default();
+
+        let rows: Vec<Row> = if server_state.game_state == GameStartState::Ending {
+            server_state
+                .standings
+                .iter()
+                .map(|(id, name, rank)| {
+                    Row::new(vec![
+                        Cell::new(rank.to_string()).light_green(),
+                        Cell::new(Span::from("██").fg(Self::colour(server_state, *id))),
+                        Cell::new(name.clone()).light_green(),
+                        Cell::new(""),
+                    ])
+                })
+                .collect()
+        } else {
+            server_state
+                .players
+                .iter()
+                .map(|player| {
+                    let mut name = vec![Span::from(player.name.clone())];
+                    if let Some(rating) = player.rating {
+                        name.push(Span::from(format!(" {rating}")).gray());
+                    }
+                    if player.id == server_state.host_user {
+                        name.push(Span::from(" H").light_yellow());
+                    }
+
+                    let status = match snakes.iter().find(|snake| snake.id == player.id) {
+                        Some(snake) if snake.alive => {
+                            Span::from(snake.body.len().to_string()).white()
+                        }
+                        Some(_) => Span::from("Crashed").light_red(),
+                        None => Span::from(""),
+                    };
+
+                    Row::new(vec![
+                        Cell::new(" "),
+                        Cell::new(Span::from("██").fg(Self::colour(server_state, player.id))),
+                        Cell::new(Line::from(name)),
+                        Cell::new(status),
+                    ])
+                })
+                .collect()
+        };
+
+        let widths = vec![
+            Constraint::Length(1),
+            Constraint::Length(2),
+            Constraint::Fill(1),
+            Constraint::Length(8),
+        ];
+
+        Table::new(rows, widths)
+            .header(Row::new(vec!["", "", "Player", "Length"]).bold())
+            .style(Style::default().white())
+    }
+
+    fn event_list(frame: &mut Frame, area: Rect, events: &[SnakeAction]) {
+        let skip = events.len().saturating_sub(area.height as usize);
+
+        let lines: Vec<Line> = events
+            .iter()
+            .skip(skip)
+            .map(|ev| match ev {
+                SnakeAction::Init => Line::from("Server Started"),
+                SnakeAction::UserJoined(user) => Line::from(format!("{user} Joined ")),
+                SnakeAction::UserLeft(user) => Line::from(format!("{user} Left ")),
+                SnakeAction::GameStarted => Line::from("~ Game started ~").light_cyan(),
+                SnakeAction::UserDied(user, length) => {
+                    Line::from(format!("{user} crashed at length {length}")).light_red()
+                }
+                SnakeAction::UserWon(user) => Line::from(format!("{user} Won! ")).light_green(),
+                SnakeAction::GameEnded => Line::from("Game Over"),
+            })
+            .collect();
+
+        frame.render_widget(Paragraph::new(Text::from(lines)), area);
+    }
+
+    fn decode(data: Vec<u8>) -> anyhow::Result<ServerSnakeCommand> {
+        bincode::decode_from_slice::<ServerSnakeCommand, Configuration>(
+            &data,
+            bincode::config::standard(),
+        )
+        .map(|(command, _)| command)
+        .map_err(|err| anyhow!("Failed decode").context(err))
+    }
+
+    fn encode(user_id: u32, action: SnakeClientAction) -> anyhow::Result<ClientMessage> {
+        let raw_enc = bincode::encode_to_vec(action, bincode::config::standard())?;
+
+        Ok(ClientMessage::Authed(
+            user_id,
+            ClientAuthedCommand::Game(ClientGameCommand::Raw(raw_enc)),
+        ))
+    }
+}
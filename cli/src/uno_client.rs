@@ -7,44 +7,96 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, Cell, Clear, Padding, Paragraph, Row, Table},
+    widgets::{Block, Borders, Cell, Clear, LineGauge, Padding, Paragraph, Row, Table, Wrap},
 };
 use rpc::{
+    chat::{ChatChannel, Reaction},
     comms::{ClientAuthedCommand, ClientGameCommand, ClientMessage, ServerMessage},
-    game_state::{self, GameStartState},
+    game_state::{self, GameStartState, GameUserState},
     uno::{
-        ServerUnoCommand, UnoAction, UnoCard, UnoCardColour, UnoCardPower, UnoClientAction,
-        UnoClientGameState,
+        ServerUnoCommand, UNO_MAX_DECKS, UnoAction, UnoBotDifficulty, UnoCard, UnoCardColour,
+        UnoCardPower, UnoClientAction, UnoClientGameState, UnoEffect, UnoPartnerView, UnoRules,
+        UnoSettings,
     },
 };
-use std::cmp::min;
-use tokio::sync::mpsc;
-
-use crate::AppMessage;
+use std::{cmp::min, time::Duration};
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{
+    AppMessage, GameResult,
+    card_table::{self, TableLayout},
+    chat_pane::ChatPane,
+    room_message,
+};
 
 struct PlayCard {
     card: UnoCard,
     clr_idx: usize,
 }
 
-const HELP_TEXT: &str = r#"How to play:
+/// The rules can change from room to room, so the help is written out for the one being played
+fn help_text(rules: &UnoRules) -> String {
+    let matching = match (rules.match_colour, rules.match_value) {
+        (true, true) => "either the colour\n or the value",
+        (true, false) => "the colour",
+        (false, _) => "the value",
+    };
+
+    let mut actions = String::new();
+    for power in UnoCardPower::ALL {
+        let card = UnoCard::encode(true, UnoCardColour::Red, power as u8);
+        if !rules
+            .deck
+            .iter()
+            .any(|(other, _)| other.is_power() && other.get_value() == power as u8)
+        {
+            continue;
+        }
+
+        let mut does: Vec<String> = rules
+            .effects_of(card)
+            .iter()
+            .map(|effect| match effect {
+                UnoEffect::Draw(count) => format!("Next user takes {count} cards"),
+                UnoEffect::Skip => "Skip next user's turn".to_string(),
+                UnoEffect::Reverse => "Reverse the turn order".to_string(),
+            })
+            .collect();
+        if power.is_black() {
+            does.push("Change the colour of the stack".to_string());
+        }
+        if does.is_empty() {
+            does.push("Nothing".to_string());
+        }
+
+        actions.push_str(&format!(
+            " {} ~ {}\n",
+            power.short_name(),
+            does.join(", then ")
+        ));
+    }
+
+    format!(
+        r#"{} ~ {}
+
+How to play:
 When it's your turn, use the arrow keys to select a card.
 Press enter to select, and then also to confirm your card.
-To play a card it will need to match either the colour
- or the value of the last played card.
+To play a card it will need to match {matching} of the last played card.
 Blank "P" cards can be place on top of anything.
 Using a "P" card requires picking a colour to change the stack to.
 
 You win when you use up all of your cards.
-You go bust if you acquire more than 20 cards.
+You go bust if you acquire more than {} cards.
+
+React with n ~ Nice, o ~ Ouch, u ~ Hurry,
+ w ~ Wow, y ~ Thanks or g ~ Good game.
 
 Some Cards can have actions which are:
- Sk ~ Skip next user's turn
- Rv ~ Reverse the turn order
- +2 ~ Next suer takes 2 cards and skips turn
- +4 ~ Next user takes 4 cards and skips turn
- Cc ~ Change the colour of the stack
-"#;
+{actions}"#,
+        rules.name, rules.description, rules.bust_over
+    )
+}
 
 pub struct UnoClient;
 
@@ -53,11 +105,13 @@ impl UnoClient {
         lobby: String,
         user_name: String,
         user_id: u32,
+        user_state: GameUserState,
         tcp_sender: &mut EncryptedSender<ClientMessage>,
         app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
         terminal: &mut DefaultTerminal,
-    ) -> anyhow::Result<()> {
-        terminal.draw(|frame| Self::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
+    ) -> anyhow::Result<GameResult> {
+        terminal
+            .draw(|frame| card_table::render_loading(frame, user_name.as_str(), lobby.as_str()))?;
 
         let mut server_state = None;
 
@@ -92,6 +146,7 @@ impl UnoClient {
         let res = Self::start(
             lobby,
             user_id,
+            user_state == GameUserState::Spectator,
             server_state.1,
             server_state.0,
             tcp_sender,
@@ -110,18 +165,32 @@ impl UnoClient {
         res
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn start(
         lobby: String,
         user_id: u32,
+        spectating: bool,
         mut server_state: UnoClientGameState,
         mut my_cards: Vec<UnoCard>,
         tcp_sender: &mut EncryptedSender<ClientMessage>,
         app_receiver: &mut mpsc::UnboundedReceiver<AppMessage>,
         terminal: &mut DefaultTerminal,
-    ) -> anyhow::Result<()> {
+    ) -> anyhow::Result<GameResult> {
         let mut events: Vec<UnoAction> = server_state.action.drain(..).collect();
         let mut card_idx: usize = 0;
         let mut card_to_play: Option<PlayCard> = None;
+        let mut turn_deadline = Self::turn_deadline(&server_state);
+        let mut chat = ChatPane::new(ChatChannel::Room);
+
+        tcp_sender
+            .send(&ClientMessage::Authed(
+                user_id,
+                ClientAuthedCommand::FetchChat(ChatChannel::Room),
+            ))
+            .await?;
+
+        // The turn timer needs redrawing even when nothing else is happening
+        let mut redraw = tokio::time::interval(Duration::from_millis(250));
 
         terminal.draw(|frame| {
             Self::render(
@@ -132,13 +201,47 @@ impl UnoClient {
                 &my_cards,
                 &events,
                 card_idx,
+                turn_deadline,
+                spectating,
+                Some(&chat),
             );
             if let Some(play_card) = &card_to_play {
                 Self::render_play_card(frame, play_card);
             }
         })?;
 
-        while let Some(msg) = app_receiver.recv().await {
+        loop {
+            let msg = tokio::select! {
+                msg = app_receiver.recv() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    msg
+                }
+                _ = redraw.tick() => {
+                    if turn_deadline.is_some() {
+                        terminal.draw(|frame| {
+                            Self::render(
+                                frame,
+                                user_id,
+                                &lobby,
+                                &server_state,
+                                &my_cards,
+                                &events,
+                                card_idx,
+                                turn_deadline,
+                                spectating,
+                                Some(&chat),
+                            );
+                            if let Some(play_card) = &card_to_play {
+                                Self::render_play_card(frame, play_card);
+                            }
+                        })?;
+                    }
+                    continue;
+                }
+            };
+
             match msg {
                 AppMessage::RpcEvent(server_message) => match server_message {
                     ServerMessage::GameState(items) => {
@@ -150,17 +253,51 @@ impl UnoClient {
                                 events.append(&mut server_actions);
 
                                 server_state = uno_client_game_state;
+                                turn_deadline = Self::turn_deadline(&server_state);
+
+                                // A timed out turn can take the card being picked,
+                                //  before the game starts it's the seat being picked instead
+                                let choices = match server_state.game_state {
+                                    GameStartState::Setup => server_state.active_users.len(),
+                                    _ => my_cards.len(),
+                                };
+                                if card_idx >= choices {
+                                    card_idx = choices.saturating_sub(1);
+                                }
                             }
                         }
                     }
-                    _ => {}
+                    other => {
+                        if let Some(result) = room_message(other, &mut chat) {
+                            return Ok(result);
+                        }
+                    }
                 },
                 AppMessage::TerminalEvent(event) => match event {
                     Event::Key(key_event) => {
                         if key_event.kind != KeyEventKind::Release {
                             continue;
                         }
+
+                        // Spectators can only watch, and chat
+                        if spectating
+                            && !chat.is_typing()
+                            && !matches!(key_event.code, KeyCode::Esc | KeyCode::Char('/'))
+                        {
+                            continue;
+                        }
                         match key_event.code {
+                            code if chat.is_typing() => {
+                                if let Some(text) = chat.key(code) {
+                                    tcp_sender
+                                        .send(&ClientMessage::Authed(
+                                            user_id,
+                                            ClientAuthedCommand::Chat(ChatChannel::Room, text),
+                                        ))
+                                        .await?;
+                                }
+                            }
+                            KeyCode::Char('/') if card_to_play.is_none() => chat.start_typing(),
                             KeyCode::Enter => {
                                 match server_state.game_state {
                                     game_state::GameStartState::Setup => {
@@ -258,6 +395,21 @@ impl UnoClient {
                                     }
                                 }
                             }
+                            // The host picks who to kick or hand the room to
+                            KeyCode::Up | KeyCode::Down
+                                if server_state.game_state == GameStartState::Setup =>
+                            {
+                                let seats = server_state.active_users.len();
+                                if seats == 0 {
+                                    continue;
+                                }
+
+                                card_idx = if key_event.code == KeyCode::Up {
+                                    (card_idx + seats - 1) % seats
+                                } else {
+                                    (card_idx + 1) % seats
+                                };
+                            }
                             KeyCode::Up | KeyCode::Down => {
                                 if card_to_play.is_some() || my_cards.len() <= 10 {
                                     continue;
@@ -270,6 +422,25 @@ impl UnoClient {
                                 }
                             }
                             KeyCode::Char(c) => {
+                                if server_state.game_state == GameStartState::Setup
+                                    && server_state.host_user == user_id
+                                    && let Some(action) =
+                                        Self::setup_action_for_key(c, &server_state, card_idx)
+                                {
+                                    tcp_sender
+                                        .send(&Self::encode_uno_client_command(user_id, action)?)
+                                        .await?;
+                                }
+
+                                if let Some(reaction) = Self::reaction_for_key(c) {
+                                    tcp_sender
+                                        .send(&Self::encode_uno_client_command(
+                                            user_id,
+                                            UnoClientAction::React(reaction),
+                                        )?)
+                                        .await?;
+                                }
+
                                 if c == 'p' {
                                     let is_turn = server_state
                                         .active_users
@@ -292,7 +463,7 @@ impl UnoClient {
                                 if card_to_play.is_some() {
                                     card_to_play = None;
                                 } else {
-                                    return Ok(());
+                                    return Ok(GameResult::None);
                                 }
                             }
                             _ => {
@@ -319,6 +490,9 @@ impl UnoClient {
                     &my_cards,
                     &events,
                     card_idx,
+                    turn_deadline,
+                    spectating,
+                    Some(&chat),
                 );
                 if let Some(play_card) = &card_to_play {
                     Self::render_play_card(frame, play_card);
@@ -329,17 +503,17 @@ impl UnoClient {
         Err(anyhow!("Internal Failure 6712354"))
     }
 
-    /// Idea for playing field:
-    ///
-    /// +------+------+------~
-    /// | User | Last | Events
-    /// | List | Card |
-    /// +------+------+------~
-    /// |             |
-    /// | Local Cards | Help
-    /// |             |
-    /// +-------------+------~
-    fn render(
+    /// The server sends how long is left, we keep our own deadline so the bar
+    ///  can count down between updates
+    fn turn_deadline(server_state: &UnoClientGameState) -> Option<Instant> {
+        server_state
+            .turn_time_left_ms
+            .map(|left| Instant::now() + Duration::from_millis(left as u64))
+    }
+
+    /// Laid out with the shared `TableLayout`, hand at the bottom and the table up top
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
         frame: &mut Frame,
         user_id: u32,
         lobby: &str,
@@ -347,6 +521,9 @@ impl UnoClient {
         my_cards: &[UnoCard],
         events: &[UnoAction],
         card_idx: usize,
+        turn_deadline: Option<Instant>,
+        spectating: bool,
+        chat: Option<&ChatPane>,
     ) {
         let turn_name = match server_state.game_state {
             game_state::GameStartState::Setup => " Waiting To Start ".to_string(),
@@ -365,16 +542,32 @@ impl UnoClient {
         };
 
         // Making the Main Container here, and everything will sit inside of it
-        let outer_block = Block::bordered()
+        let mut outer_block = Block::bordered()
             .border_style(Style::new().light_blue())
             .title_top(
-                Line::from(format!(" Tempest ~ {lobby} ( Uno ) "))
-                    .bold()
-                    .white(),
+                Line::from(format!(
+                    " Tempest ~ {lobby} ( {} ) ",
+                    server_state.rules.name
+                ))
+                .bold()
+                .white(),
             )
             .title(Line::from(turn_name).bold().white().centered())
             .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned());
 
+        if let Some(code) = &server_state.invite_code {
+            outer_block = outer_block.title_bottom(
+                Line::from(format!(" Invite Code: {code} "))
+                    .bold()
+                    .light_magenta(),
+            );
+        }
+
+        if spectating {
+            outer_block =
+                outer_block.title_bottom(Line::from(" Spectating ").bold().light_yellow());
+        }
+
         let area = frame.area();
         frame.render_widget(outer_block.clone(), area);
 
@@ -383,43 +576,55 @@ impl UnoClient {
         //  some other display if I don't find a fix
         let inner = outer_block.inner(area);
 
-        let row_chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(6), Constraint::Fill(1)])
-            .split(inner);
-
-        let top_columns = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Length(35),
-                Constraint::Length(25),
-                Constraint::Fill(1),
-            ])
-            .split(row_chunks[0]);
+        // Bigger tables need more room for the user list
+        let user_rows = server_state.finished_users.len()
+            + server_state.bust_users.len()
+            + server_state.active_users.len()
+            + server_state.team_standings.len();
+
+        let layout = TableLayout::split(inner, user_rows, turn_deadline.is_some());
+
+        if let Some(deadline) = turn_deadline {
+            Self::turn_timer(frame, layout.timer, server_state, deadline);
+        }
+
+        // User List, the host gets a cursor for picking out players before the game starts
+        let selected_seat = (server_state.game_state == GameStartState::Setup
+            && server_state.host_user == user_id
+            && !spectating)
+            .then_some(card_idx);
 
-        // User List
         frame.render_widget(
-            Self::user_list(server_state).block(Block::default().borders(Borders::RIGHT)),
-            top_columns[0],
+            Self::user_list(server_state, selected_seat)
+                .block(Block::default().borders(Borders::RIGHT)),
+            layout.users,
         );
 
         match server_state.game_state {
             GameStartState::Setup => {
+                let mut setup_text = Text::from("Waiting for host");
+                if server_state.locked {
+                    setup_text.push_line(Line::from("Room Locked").light_red());
+                }
+                for line in Self::settings_summary(&server_state.settings).lines() {
+                    setup_text.push_line(line.to_string());
+                }
+
                 frame.render_widget(
-                    Paragraph::new("Waiting for host\nto start")
+                    Paragraph::new(setup_text)
                         .centered()
                         .block(Block::default().borders(Borders::RIGHT)),
-                    top_columns[1],
+                    layout.last_card,
                 );
             }
             GameStartState::Active => {
                 // Last Played
                 frame.render_widget(
                     Paragraph::new("Last Card").block(Block::default().borders(Borders::RIGHT)),
-                    top_columns[1],
+                    layout.last_card,
                 );
 
-                let mut card_inner = top_columns[1].inner(Margin::new(8, 1));
+                let mut card_inner = layout.last_card.inner(Margin::new(8, 1));
 
                 card_inner.width = 5;
                 card_inner.height = 4;
@@ -427,40 +632,40 @@ impl UnoClient {
                 frame.render_widget(Self::card_text(&server_state.last_card, false), card_inner);
             }
             GameStartState::Ending => {
+                let mut ending_text = Text::from("Game over\nThank you for playing!");
+
+                if let Some(seed) = server_state.seed {
+                    ending_text.push_line(Line::from(format!("Seed: {seed}")).gray());
+                }
+
                 frame.render_widget(
-                    Paragraph::new("Game over\nThank you for playing!")
+                    Paragraph::new(ending_text)
                         .centered()
                         .block(Block::default().borders(Borders::RIGHT)),
-                    top_columns[1],
+                    layout.last_card,
                 );
             }
         }
 
         // Events
-        Self::event_list(frame, top_columns[2], events);
-
-        // --- Bottom row: 2 columns --
-        let bottom_columns = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Length(60), Constraint::Fill(40)])
-            .split(row_chunks[1]);
+        Self::event_list(frame, layout.events, events);
 
         // Local Cards
         match server_state.game_state {
             GameStartState::Setup => {
                 if server_state.host_user == user_id {
                     frame.render_widget(
-                Paragraph::new("You can start the game by pressing Enter\nif there are at least two people in the lobby")
+                Paragraph::new("You can start the game by pressing Enter\nif there are at least two people in the lobby\n\nPress \"d\" to change the number of decks\nPress \"t\" to play in teams\nPress \"h\" to share hands with partners\nPress \"m\" to change the turn timer\nPress \"k\" to kick players who keep timing out\nPress 1 / 2 / 3 to add an easy / medium / hard bot\nPress \"x\" to remove a bot\nPress \"r\" to have bots take over from leavers\n\nUse Up / Down to pick a player, then press\n\"K\" to kick, \"B\" to ban or \"H\" to make them host\nPress \"L\" to lock or unlock the room")
                     .centered()
                     .block(Block::default().borders(Borders::RIGHT)),
-                bottom_columns[0],
+                layout.hand,
             );
                 } else {
                     frame.render_widget(
                         Paragraph::new("Waiting for the host to start the game")
                             .centered()
                             .block(Block::default().borders(Borders::RIGHT)),
-                        bottom_columns[0],
+                        layout.hand,
                     );
                 }
             }
@@ -468,10 +673,12 @@ impl UnoClient {
                 if server_state.active_users.iter().any(|u| u.id == user_id) {
                     frame.render_widget(
                         Paragraph::new("My Cards").block(Block::default().borders(Borders::RIGHT)),
-                        bottom_columns[0],
+                        layout.hand,
                     );
 
-                    Self::my_cards(frame, bottom_columns[0], my_cards, card_idx);
+                    card_table::hand(frame, layout.hand, my_cards, card_idx, |card| {
+                        Self::card_text(card, true)
+                    });
                 } else if server_state
                     .finished_users
                     .iter()
@@ -480,22 +687,249 @@ impl UnoClient {
                     frame.render_widget(
                         Paragraph::new("You're finished!")
                             .block(Block::default().borders(Borders::RIGHT)),
-                        bottom_columns[0],
+                        layout.hand,
+                    );
+                } else if spectating {
+                    frame.render_widget(
+                        Paragraph::new("Spectating, hands are hidden")
+                            .block(Block::default().borders(Borders::RIGHT)),
+                        layout.hand,
                     );
                 }
             }
             GameStartState::Ending => {
                 frame.render_widget(
                     Paragraph::new("Game is over").block(Block::default().borders(Borders::RIGHT)),
-                    bottom_columns[0],
+                    layout.hand,
                 );
             }
         }
 
+        // Chat sits under the help, replays don't have any
+        let help_area = match chat {
+            Some(chat) => {
+                let help_rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Fill(1), Constraint::Length(9)])
+                    .split(layout.help);
+
+                chat.render(frame, help_rows[1]);
+                help_rows[0]
+            }
+            None => layout.help,
+        };
+
         // Help
+        if server_state.partner_hands.is_empty() {
+            frame.render_widget(
+                Paragraph::new(help_text(&server_state.rules)).block(Block::default()),
+                help_area,
+            );
+        } else {
+            let help_rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([
+                    Constraint::Length(2 * server_state.partner_hands.len() as u16 + 1),
+                    Constraint::Fill(1),
+                ])
+                .split(help_area);
+
+            frame.render_widget(Self::partner_hands(server_state), help_rows[0]);
+            frame.render_widget(
+                Paragraph::new(help_text(&server_state.rules)).block(Block::default()),
+                help_rows[1],
+            );
+        }
+    }
+
+    fn partner_hands(server_state: &UnoClientGameState) -> Paragraph<'_> {
+        let mut lines = vec![];
+
+        for (partner_id, cards) in server_state.partner_hands.iter() {
+            let name = server_state
+                .active_users
+                .iter()
+                .find(|user| user.id == *partner_id)
+                .map(|user| user.name.as_str())
+                .unwrap_or("Partner");
+
+            let mut card_spans = vec![];
+            for card in cards.iter() {
+                card_spans.push(Self::card_for_event(card));
+                card_spans.push(Span::from(", "));
+            }
+            card_spans.pop();
+
+            lines.push(Line::from(format!("{name}'s Hand:")).bold());
+            lines.push(Line::from(card_spans));
+        }
+
+        Paragraph::new(Text::from(lines))
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::BOTTOM))
+    }
+
+    fn team_cell(team: Option<u8>) -> Cell<'static> {
+        match team {
+            Some(0) => Cell::new("A").light_cyan(),
+            Some(_) => Cell::new("B").light_magenta(),
+            None => Cell::new(""),
+        }
+    }
+
+    fn team_of(server_state: &UnoClientGameState, user_id: u32) -> Option<u8> {
+        server_state
+            .team_standings
+            .iter()
+            .find(|standing| standing.user_ids.contains(&user_id))
+            .map(|standing| standing.team)
+    }
+
+    /// One setting per line
+    pub fn settings_summary(settings: &UnoSettings) -> String {
+        let decks = match settings.deck_count {
+            1 => "1 Deck".to_string(),
+            count => format!("{count} Decks"),
+        };
+
+        let timer = match settings.turn_timer_secs {
+            Some(secs) => format!("{secs}s Turns"),
+            None => "No Turn Timer".to_string(),
+        };
+
+        let kick = match settings.kick_after_timeouts {
+            Some(count) => format!("Kick After {count}"),
+            None => "No Kicking".to_string(),
+        };
+
+        let replace = match settings.replace_leavers {
+            Some(difficulty) => format!("{difficulty:?} Bot Subs"),
+            None => "No Bot Subs".to_string(),
+        };
+
+        let teams = match (settings.team_mode, settings.partner_view) {
+            (false, _) => "No Teams",
+            (true, UnoPartnerView::CardCount) => "Teams",
+            (true, UnoPartnerView::Hand) => "Teams, Hands Shared",
+        };
+
+        format!("{decks}\n{teams}\n{timer}\n{kick}\n{replace}")
+    }
+
+    /// The host sets up the room with a single key each.
+    /// Kicking, banning and handing over the room go to the selected seat.
+    fn setup_action_for_key(
+        key: char,
+        server_state: &UnoClientGameState,
+        selected_seat: usize,
+    ) -> Option<UnoClientAction> {
+        // Only other people can be picked out, bots are removed with "x"
+        let selected = server_state
+            .active_users
+            .get(selected_seat)
+            .filter(|user| user.bot.is_none() && user.id != server_state.host_user)
+            .map(|user| user.id);
+
+        match key {
+            'K' => selected.map(UnoClientAction::KickUser),
+            'B' => selected.map(UnoClientAction::BanUser),
+            'H' => selected.map(UnoClientAction::TransferHost),
+            'L' => Some(UnoClientAction::SetLocked(!server_state.locked)),
+            '1' => Some(UnoClientAction::AddBot(UnoBotDifficulty::Random)),
+            '2' => Some(UnoClientAction::AddBot(UnoBotDifficulty::Greedy)),
+            '3' => Some(UnoClientAction::AddBot(UnoBotDifficulty::Heuristic)),
+            'x' => server_state
+                .active_users
+                .iter()
+                .rev()
+                .find(|user| user.bot.is_some())
+                .map(|user| UnoClientAction::RemoveBot(user.id)),
+            _ => Self::settings_for_key(key, server_state.settings)
+                .map(UnoClientAction::UpdateSettings),
+        }
+    }
+
+    fn reaction_for_key(key: char) -> Option<Reaction> {
+        match key {
+            'n' => Some(Reaction::Nice),
+            'o' => Some(Reaction::Ouch),
+            'u' => Some(Reaction::Hurry),
+            'w' => Some(Reaction::Wow),
+            'y' => Some(Reaction::Thanks),
+            'g' => Some(Reaction::GoodGame),
+            _ => None,
+        }
+    }
+
+    /// Shared by the room setup and the quick play queue
+    pub fn settings_for_key(key: char, mut settings: UnoSettings) -> Option<UnoSettings> {
+        match key {
+            'r' => {
+                settings.replace_leavers = match settings.replace_leavers {
+                    None => Some(UnoBotDifficulty::Random),
+                    Some(UnoBotDifficulty::Random) => Some(UnoBotDifficulty::Greedy),
+                    Some(UnoBotDifficulty::Greedy) => Some(UnoBotDifficulty::Heuristic),
+                    Some(UnoBotDifficulty::Heuristic) => None,
+                }
+            }
+            'd' => settings.deck_count = settings.deck_count % UNO_MAX_DECKS + 1,
+            't' => settings.team_mode = !settings.team_mode,
+            'h' => {
+                settings.partner_view = match settings.partner_view {
+                    UnoPartnerView::CardCount => UnoPartnerView::Hand,
+                    UnoPartnerView::Hand => UnoPartnerView::CardCount,
+                }
+            }
+            'm' => {
+                settings.turn_timer_secs = match settings.turn_timer_secs {
+                    None => Some(15),
+                    Some(15) => Some(30),
+                    Some(30) => Some(60),
+                    Some(_) => None,
+                }
+            }
+            'k' => {
+                settings.kick_after_timeouts = match settings.kick_after_timeouts {
+                    None => Some(1),
+                    Some(1) => Some(2),
+                    Some(2) => Some(3),
+                    Some(3) => Some(5),
+                    Some(_) => None,
+                }
+            }
+            _ => return None,
+        }
+
+        Some(settings)
+    }
+
+    fn turn_timer(
+        frame: &mut Frame,
+        area: Rect,
+        server_state: &UnoClientGameState,
+        deadline: Instant,
+    ) {
+        let total = server_state.settings.turn_timer_secs.unwrap_or(1).max(1) as f64;
+        let left = deadline
+            .saturating_duration_since(Instant::now())
+            .as_secs_f64();
+        let ratio = (left / total).clamp(0.0, 1.0);
+
+        let colour = if ratio > 0.5 {
+            Color::LightGreen
+        } else if ratio > 0.2 {
+            Color::LightYellow
+        } else {
+            Color::LightRed
+        };
+
         frame.render_widget(
-            Paragraph::new(HELP_TEXT).block(Block::default()),
-            bottom_columns[1],
+            LineGauge::default()
+                .label(format!("Turn {:>3}s ", left.ceil() as u32))
+                .filled_style(Style::new().fg(colour))
+                .unfilled_style(Style::new().dark_gray())
+                .ratio(ratio),
+            area,
         );
     }
 
@@ -584,6 +1018,16 @@ impl UnoClient {
                 UnoAction::UserLeft(user) => Line::from(format!("{user} Left ")),
                 UnoAction::UserFinished(user) => Line::from(format!("{user} Finished ")),
                 UnoAction::UserBust(user) => Line::from(format!("{user} Bust ")),
+                UnoAction::UserTimedOut(user) => Line::from(format!("{user} Ran out of time ")),
+                UnoAction::UserKicked(user) => Line::from(format!("{user} Was kicked ")),
+                UnoAction::UserBanned(user) => Line::from(format!("{user} Was banned ")),
+                UnoAction::HostChanged(user) => Line::from(format!("{user} Is now the host ")),
+                UnoAction::RoomLocked(true) => Line::from("Room Locked"),
+                UnoAction::RoomLocked(false) => Line::from("Room Unlocked"),
+                UnoAction::Reacted(user, reaction) => Line::from(vec![
+                    Span::from(format!("{user}: ")),
+                    Span::from(reaction.text()).light_yellow().bold(),
+                ]),
                 UnoAction::GameEnded => Line::from("Game Over"),
             })
             .collect();
@@ -627,45 +1071,84 @@ impl UnoClient {
         }
     }
 
-    fn user_list(server_state: &UnoClientGameState) -> Table<'_> {
+    fn user_list(server_state: &UnoClientGameState, selected_seat: Option<usize>) -> Table<'_> {
         let idx = server_state.user_turn as usize;
 
         let mut rows: Vec<Row<'_>> = server_state
             .finished_users
             .iter()
             .enumerate()
-            .map(|(i, (_, name))| {
+            .map(|(i, (id, name))| {
                 Row::new(vec![
                     Cell::new((i + 1).to_string()).light_green(),
                     Cell::new(name.to_string()).light_green(),
+                    Cell::new(""),
+                    Self::team_cell(Self::team_of(server_state, *id)),
                 ])
             })
             .collect();
 
         let ord_str = if server_state.is_ord { "v " } else { "^ " };
 
-        for (_, user) in server_state.bust_users.iter() {
+        for (id, user) in server_state.bust_users.iter() {
             let new_row = Row::new(vec![
                 Cell::new("x").light_red(),
                 Cell::new(user.to_string()).light_red(),
+                Cell::new(""),
+                Self::team_cell(Self::team_of(server_state, *id)),
             ]);
 
             rows.push(new_row);
         }
 
         for (i, user) in server_state.active_users.iter().enumerate() {
+            let marker = match server_state.game_state {
+                GameStartState::Setup if selected_seat == Some(i) => ">",
+                GameStartState::Setup => " ",
+                _ if i == idx => ord_str,
+                _ => " ",
+            };
+
+            let mut name = vec![Span::from(user.name.clone())];
+            if let Some(rating) = user.rating {
+                name.push(Span::from(format!(" {rating}")).gray());
+            }
+            if user.id == server_state.host_user {
+                name.push(Span::from(" H").light_yellow());
+            }
+
             let new_row = Row::new(vec![
-                Cell::new(if i == idx { ord_str } else { " " }).light_green(),
-                Cell::new(user.name.clone()),
+                Cell::new(marker).light_green(),
+                if user.bot.is_some() {
+                    Cell::new(Line::from(name)).italic()
+                } else {
+                    Cell::new(Line::from(name))
+                },
                 Cell::new(user.card_count.to_string()),
+                Self::team_cell(user.team),
             ]);
 
             rows.push(new_row);
         }
 
+        for standing in server_state.team_standings.iter() {
+            let status = if standing.has_won {
+                "Won".to_string()
+            } else {
+                format!("{} cards", standing.card_count)
+            };
+
+            rows.push(Row::new(vec![
+                Cell::new("#"),
+                Cell::new(format!("Team {status}")).bold(),
+                Cell::new(""),
+                Self::team_cell(Some(standing.team)),
+            ]));
+        }
+
         let widths = vec![
             Constraint::Length(1),
-            Constraint::Length(15),
+            Constraint::Length(20),
             Constraint::Length(4),
             Constraint::Fill(1),
         ];
@@ -673,27 +1156,6 @@ impl UnoClient {
         Table::new(rows, widths).style(Style::default().white())
     }
 
-    fn my_cards(frame: &mut Frame, mut area: Rect, cards: &[UnoCard], card_idx: usize) {
-        for (i, card) in cards.iter().enumerate() {
-            let rect = Rect {
-                x: area.x + ((i % 10) * 6) as u16,
-                y: area.y + ((i / 10) * 5) as u16 + 2,
-                width: 5,
-                height: 4,
-            };
-            let para = Self::card_text(card, true);
-
-            frame.render_widget(para, rect);
-        }
-
-        area.x += ((card_idx % 10) * 6) as u16 + 2;
-        area.y += ((card_idx / 10) * 5) as u16 + 1;
-        area.height = 1;
-        area.width = 1;
-
-        frame.render_widget(Paragraph::new("v").bold().white(), area);
-    }
-
     fn card_text(card: &UnoCard, allow_black: bool) -> Paragraph<'_> {
         let (power, colour, value) = card.decode();
 
@@ -737,22 +1199,6 @@ impl UnoClient {
         Paragraph::new(Text::from(format!("{clr_char}\n{value_str}"))).block(inner_block)
     }
 
-    fn render_loading(frame: &mut Frame, user_name: &str, lobby: &str) {
-        frame.render_widget(
-            Paragraph::new(Text::from(Line::from("Loading Into Server").bold())).block(
-                Block::bordered()
-                    .border_style(Style::new().light_blue())
-                    .title_top(
-                        Line::from(format!(" Tempest ~ {user_name} ~ {lobby} "))
-                            .bold()
-                            .white(),
-                    )
-                    .title_bottom(Line::from(" Esc to quit ").bold().white().right_aligned()),
-            ),
-            frame.area(),
-        )
-    }
-
     fn decode_uno_server_command(data: Vec<u8>) -> anyhow::Result<(ServerUnoCommand, usize)> {
         bincode::decode_from_slice::<ServerUnoCommand, Configuration>(
             &data,
@@ -14,17 +14,23 @@ pub struct EncryptedClient<S: Encode, R: Decode<()>> {
     pub receiver: EncryptedReceiver<R>,
 }
 
+/// A new static private key, keeping it between runs is what lets the server
+/// recognise the client again
+pub fn generate_key() -> Result<Vec<u8>> {
+    Ok(Builder::new(PARAMS.clone()).generate_keypair()?.private)
+}
+
 impl<S: Encode, R: Decode<()>> EncryptedClient<S, R> {
-    pub async fn connect(addr: &str) -> Result<Self> {
+    pub async fn connect(addr: &str, static_key: &[u8]) -> Result<Self> {
         let stream = TcpStream::connect(addr)
             .await
             .context("Failed to connect to server")?;
 
         let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
 
-        let builder = Builder::new(PARAMS.clone());
-        let static_key = builder.generate_keypair()?.private;
-        let mut noise = builder.local_private_key(&static_key)?.build_initiator()?;
+        let mut noise = Builder::new(PARAMS.clone())
+            .local_private_key(static_key)?
+            .build_initiator()?;
 
         let mut buf = vec![0u8; 65535];
 
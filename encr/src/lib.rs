@@ -2,6 +2,6 @@ pub mod client;
 mod connection;
 pub mod server;
 
-pub use client::EncryptedClient;
+pub use client::{EncryptedClient, generate_key};
 pub use connection::{EncryptedReceiver, EncryptedSender};
 pub use server::{ClientConnection, EncryptedServer};
@@ -20,6 +20,8 @@ pub struct EncryptedServer<S: Encode, R: Decode<()>> {
 pub struct ClientConnection<S: Encode, R: Decode<()>> {
     pub sender: EncryptedSender<S>,
     pub receiver: EncryptedReceiver<R>,
+    /// The client's static public key, the handshake proves they hold the private half
+    pub remote_key: Vec<u8>,
 }
 
 impl<S: Encode, R: Decode<()>> EncryptedServer<S, R> {
@@ -43,16 +45,23 @@ impl<S: Encode, R: Decode<()>> EncryptedServer<S, R> {
         let (stream, addr) = self.listener.accept().await?;
         println!("New connection from: {}", addr);
 
-        let connection = Self::perform_handshake(stream, &self.static_key).await?;
+        let (connection, remote_key) = Self::perform_handshake(stream, &self.static_key).await?;
         let (sender, receiver) = connection.consume();
 
-        Ok((ClientConnection { sender, receiver }, addr))
+        Ok((
+            ClientConnection {
+                sender,
+                receiver,
+                remote_key,
+            },
+            addr,
+        ))
     }
 
     async fn perform_handshake(
         stream: TcpStream,
         static_key: &[u8],
-    ) -> Result<NoEncryptConnection> {
+    ) -> Result<(NoEncryptConnection, Vec<u8>)> {
         let mut framed = Framed::new(stream, LengthDelimitedCodec::new());
 
         // The example I saw does this for every connection.
@@ -78,6 +87,11 @@ impl<S: Encode, R: Decode<()>> EncryptedServer<S, R> {
         let msg = NoEncryptConnection::recv(&mut framed).await?;
         noise.read_message(&msg, &mut buf)?;
 
+        let remote_key = noise
+            .get_remote_static()
+            .context("Client didn't send a static key")?
+            .to_vec();
+
         // Now that we have received the server client public key from the client
         // we can transition to transport mode for normal usage
         let transport = noise
@@ -86,6 +100,6 @@ impl<S: Encode, R: Decode<()>> EncryptedServer<S, R> {
 
         // Unwrap the framed stream back to TcpStream
         let stream = framed.into_inner();
-        Ok(NoEncryptConnection::new(transport, stream))
+        Ok((NoEncryptConnection::new(transport, stream), remote_key))
     }
 }
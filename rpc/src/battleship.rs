@@ -0,0 +1,275 @@
+use bincode::{Decode, Encode};
+
+use crate::game_state::GameStartState;
+
+pub const BATTLESHIP_SIZE: u8 = 10;
+
+/// Battleship, both players hide their fleets at the same time then take turns firing.
+///
+/// Nobody is sent anyone else's fleet, only where they've been shot and what's sunk,
+///  until the game is over and everything is shown.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct BattleshipClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<BattleshipAction>,
+    pub players: Vec<BattleshipPlayer>,
+    pub host_user: u32,
+    pub phase: BattleshipPhase,
+    /// The user id of whoever fires next
+    pub turn: Option<u32>,
+    pub winner: Option<u32>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct BattleshipPlayer {
+    pub id: u32,
+    pub name: String,
+    /// Has their fleet locked in, where it is stays hidden
+    pub placed: bool,
+    /// Every shot fired into this player's waters
+    pub shots: Vec<Shot>,
+    /// All of it for its owner and once the game is over, otherwise just what's been sunk
+    pub ships: Vec<Ship>,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum BattleshipPhase {
+    /// Both players at once, neither waits on the other
+    Placing,
+    Firing,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct Shot {
+    /// Column then row
+    pub at: (u8, u8),
+    pub result: ShotResult,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum ShotResult {
+    Miss,
+    Hit,
+    /// The shot that finished off a ship says which one
+    Sunk(ShipKind),
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum ShipKind {
+    Carrier,
+    Battleship,
+    Cruiser,
+    Submarine,
+    Destroyer,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct Ship {
+    pub kind: ShipKind,
+    /// The top or left end, column then row
+    pub at: (u8, u8),
+    pub horizontal: bool,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum BattleshipAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    GameStarted,
+    UserPlacedFleet(String),
+    /// Who fires first
+    FiringStarted(String),
+    UserFired(String, (u8, u8), ShotResult),
+    UserWon(String),
+    GameEnded,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerBattleshipCommand {
+    GameState(BattleshipClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum BattleshipClientAction {
+    /// The whole fleet at once, it can't be moved afterwards
+    PlaceFleet(Vec<Ship>),
+    Fire((u8, u8)),
+}
+
+impl ShipKind {
+    /// Every fleet is one of each, placed in this order
+    pub const FLEET: [ShipKind; 5] = [
+        ShipKind::Carrier,
+        ShipKind::Battleship,
+        ShipKind::Cruiser,
+        ShipKind::Submarine,
+        ShipKind::Destroyer,
+    ];
+
+    pub fn size(&self) -> u8 {
+        match self {
+            ShipKind::Carrier => 5,
+            ShipKind::Battleship => 4,
+            ShipKind::Cruiser | ShipKind::Submarine => 3,
+            ShipKind::Destroyer => 2,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShipKind::Carrier => "Carrier",
+            ShipKind::Battleship => "Battleship",
+            ShipKind::Cruiser => "Cruiser",
+            ShipKind::Submarine => "Submarine",
+            ShipKind::Destroyer => "Destroyer",
+        }
+    }
+}
+
+impl Ship {
+    pub fn cells(&self) -> Vec<(u8, u8)> {
+        (0..self.kind.size())
+            .map(|offset| {
+                if self.horizontal {
+                    (self.at.0 + offset, self.at.1)
+                } else {
+                    (self.at.0, self.at.1 + offset)
+                }
+            })
+            .collect()
+    }
+
+    pub fn in_bounds(&self) -> bool {
+        self.cells()
+            .iter()
+            .all(|(column, row)| *column < BATTLESHIP_SIZE && *row < BATTLESHIP_SIZE)
+    }
+
+    pub fn overlaps(&self, other: &Ship) -> bool {
+        let cells = other.cells();
+        self.cells().iter().any(|cell| cells.contains(cell))
+    }
+
+    pub fn is_sunk(&self, shots: &[Shot]) -> bool {
+        self.cells()
+            .iter()
+            .all(|cell| shots.iter().any(|shot| shot.at == *cell))
+    }
+}
+
+/// One of each ship, all on the board and none on top of each other.
+/// Ships are allowed to touch.
+pub fn fleet_is_valid(ships: &[Ship]) -> bool {
+    ships.len() == ShipKind::FLEET.len()
+        && ShipKind::FLEET
+            .iter()
+            .all(|kind| ships.iter().filter(|ship| ship.kind == *kind).count() == 1)
+        && ships.iter().all(|ship| ship.in_bounds())
+        && ships
+            .iter()
+            .enumerate()
+            .all(|(i, ship)| ships[i + 1..].iter().all(|other| !ship.overlaps(other)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One of each ship, each lying along its own row from the left edge
+    fn fleet() -> Vec<Ship> {
+        ShipKind::FLEET
+            .iter()
+            .enumerate()
+            .map(|(row, kind)| Ship {
+                kind: *kind,
+                at: (0, row as u8 * 2),
+                horizontal: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cells_run_along_the_ship() {
+        let ship = Ship {
+            kind: ShipKind::Cruiser,
+            at: (4, 2),
+            horizontal: false,
+        };
+        assert_eq!(ship.cells(), vec![(4, 2), (4, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn standard_fleet_is_valid() {
+        assert!(fleet_is_valid(&fleet()));
+
+        // Touching is allowed
+        let mut touching = fleet();
+        touching[1].at = (0, 1);
+        assert!(fleet_is_valid(&touching));
+    }
+
+    #[test]
+    fn overlapping_ships_are_invalid() {
+        let mut ships = fleet();
+        ships[4] = Ship {
+            kind: ShipKind::Destroyer,
+            at: (2, 0),
+            horizontal: false,
+        };
+        assert!(!fleet_is_valid(&ships));
+    }
+
+    #[test]
+    fn ships_off_the_board_are_invalid() {
+        let mut ships = fleet();
+        ships[0].at = (BATTLESHIP_SIZE - 4, 0);
+        assert!(!fleet_is_valid(&ships));
+
+        let mut ships = fleet();
+        ships[1] = Ship {
+            kind: ShipKind::Battleship,
+            at: (9, BATTLESHIP_SIZE - 3),
+            horizontal: false,
+        };
+        assert!(!fleet_is_valid(&ships));
+    }
+
+    #[test]
+    fn wrong_ships_are_invalid() {
+        let mut missing = fleet();
+        missing.pop();
+        assert!(!fleet_is_valid(&missing));
+
+        let mut doubled = fleet();
+        doubled[4].kind = ShipKind::Submarine;
+        assert!(!fleet_is_valid(&doubled));
+
+        let mut extra = fleet();
+        extra.push(Ship {
+            kind: ShipKind::Destroyer,
+            at: (7, 9),
+            horizontal: true,
+        });
+        assert!(!fleet_is_valid(&extra));
+    }
+
+    #[test]
+    fn sunk_once_every_cell_is_hit() {
+        let ship = Ship {
+            kind: ShipKind::Destroyer,
+            at: (3, 3),
+            horizontal: true,
+        };
+        let shot = |at| Shot {
+            at,
+            result: ShotResult::Hit,
+        };
+
+        assert!(!ship.is_sunk(&[shot((3, 3)), shot((5, 3))]));
+        assert!(ship.is_sunk(&[shot((4, 3)), shot((0, 0)), shot((3, 3))]));
+    }
+}
@@ -0,0 +1,191 @@
+use bincode::{Decode, Encode};
+
+use crate::{
+    cards::{PlayingCard, Rank},
+    game_state::GameStartState,
+};
+
+pub const BLACKJACK_MAX_PLAYERS: usize = 6;
+/// What a player's account starts with
+pub const BLACKJACK_STARTING_CHIPS: u64 = 1000;
+pub const BLACKJACK_MIN_BET: u64 = 10;
+pub const BLACKJACK_MAX_BET: u64 = 500;
+/// How many decks the host can put in the shoe
+pub const BLACKJACK_SHOE_SIZES: [u8; 5] = [1, 2, 4, 6, 8];
+pub const BLACKJACK_DEFAULT_DECKS: u8 = 6;
+/// Splitting can go on until a player has this many hands
+pub const BLACKJACK_MAX_HANDS: usize = 4;
+/// Starts with the first bet, anyone who hasn't bet by then sits the round out
+pub const BLACKJACK_BET_MS: u32 = 15_000;
+/// For insurance and for playing the hands, anything still undecided stands
+pub const BLACKJACK_ACT_MS: u32 = 30_000;
+
+/// Blackjack, everyone plays their own hands at the same time against the server's dealer.
+///
+/// There's no taking turns, each phase waits until everyone is done or the clock runs out.
+/// Chips belong to the player's name rather than the table, so they carry between games.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct BlackjackClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<BlackjackAction>,
+    pub players: Vec<BlackjackPlayer>,
+    pub host_user: u32,
+    pub decks: u8,
+    pub phase: BlackjackPhase,
+    pub round: u32,
+    /// The hole card is left out until the dealer turns it over
+    pub dealer: Vec<PlayingCard>,
+    pub dealer_hidden: bool,
+    /// Left on the phase's clock as this was sent, nothing if it isn't running
+    pub time_left_ms: Option<u32>,
+    pub shoe_left: u32,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct BlackjackPlayer {
+    pub id: u32,
+    pub name: String,
+    /// Whatever isn't out on the table right now
+    pub chips: u64,
+    /// Placed for the next deal, nothing if they're sitting it out
+    pub bet: Option<u64>,
+    /// From the last deal, kept up until the next one
+    pub hands: Vec<BlackjackHand>,
+    /// The hand being played, nothing once they're done
+    pub active_hand: Option<u8>,
+    /// Nothing until they've answered, Some(0) if they turned it down
+    pub insurance: Option<u64>,
+    /// How they came out of the last round overall
+    pub last_net: Option<i64>,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct BlackjackHand {
+    pub cards: Vec<PlayingCard>,
+    pub bet: u64,
+    pub doubled: bool,
+    /// Split hands can't be a blackjack, and split aces only get the one card
+    pub split: bool,
+    pub done: bool,
+    pub outcome: Option<HandOutcome>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum BlackjackPhase {
+    /// Waiting for bets, the last round's hands are still on the table
+    Betting,
+    /// The dealer is showing an ace
+    Insurance,
+    Playing,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum HandOutcome {
+    /// Pays three to two
+    Blackjack,
+    Won,
+    Push,
+    Lost,
+    Bust,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum BlackjackAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    DecksChanged(u8),
+    GameStarted,
+    ShoeShuffled,
+    RoundDealt(u32),
+    UserInsured(String, u64),
+    /// Whether the dealer had it
+    DealerPeeked(bool),
+    UserHit(String, PlayingCard),
+    UserStood(String),
+    UserDoubled(String, PlayingCard),
+    UserSplit(String),
+    UserBusted(String),
+    /// The dealer's final total
+    DealerStood(u8),
+    DealerBusted,
+    /// The whole round for one player, winnings less what they put in
+    UserSettled(String, i64),
+    /// Can't cover the minimum bet, sat out until they can
+    UserBroke(String),
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerBlackjackCommand {
+    GameState(BlackjackClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum BlackjackClientAction {
+    /// Only the host, before the game starts
+    SetDecks(u8),
+    Bet(u64),
+    Insurance(bool),
+    Hit,
+    Stand,
+    Double,
+    Split,
+}
+
+/// Tens and faces are all ten, aces are eleven until that would bust
+pub fn card_value(rank: Rank) -> u8 {
+    match rank {
+        Rank::Ace => 11,
+        Rank::Jack | Rank::Queen | Rank::King => 10,
+        rank => rank.value(),
+    }
+}
+
+/// The best total for the cards, and whether an ace is still being counted as eleven
+pub fn hand_total(cards: &[PlayingCard]) -> (u8, bool) {
+    let mut total: u8 = cards.iter().map(|card| card_value(card.rank)).sum();
+    let mut soft_aces = cards.iter().filter(|card| card.rank == Rank::Ace).count();
+
+    while total > 21 && soft_aces > 0 {
+        total -= 10;
+        soft_aces -= 1;
+    }
+
+    (total, soft_aces > 0)
+}
+
+impl BlackjackHand {
+    pub fn total(&self) -> u8 {
+        hand_total(&self.cards).0
+    }
+
+    pub fn is_blackjack(&self) -> bool {
+        !self.split && self.cards.len() == 2 && self.total() == 21
+    }
+
+    pub fn can_double(&self) -> bool {
+        !self.done && self.cards.len() == 2
+    }
+
+    /// Any two cards worth the same, so a king and a ten can be split
+    pub fn can_split(&self) -> bool {
+        !self.done
+            && self.cards.len() == 2
+            && card_value(self.cards[0].rank) == card_value(self.cards[1].rank)
+    }
+}
+
+impl HandOutcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HandOutcome::Blackjack => "Blackjack",
+            HandOutcome::Won => "Won",
+            HandOutcome::Push => "Push",
+            HandOutcome::Lost => "Lost",
+            HandOutcome::Bust => "Bust",
+        }
+    }
+}
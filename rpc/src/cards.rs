@@ -0,0 +1,125 @@
+use std::fmt::Display;
+
+use bincode::{Decode, Encode};
+
+/// A standard 52 card deck, no jokers.
+/// Shared by every game played with a normal pack of cards.
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+/// Lowest to highest, aces are high
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rank {
+    Two,
+    Three,
+    Four,
+    Five,
+    Six,
+    Seven,
+    Eight,
+    Nine,
+    Ten,
+    Jack,
+    Queen,
+    King,
+    Ace,
+}
+
+/// Sorting puts cards in rank order, then by suit
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PlayingCard {
+    pub rank: Rank,
+    pub suit: Suit,
+}
+
+impl Suit {
+    pub const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Suit::Clubs => "♣",
+            Suit::Diamonds => "♦",
+            Suit::Hearts => "♥",
+            Suit::Spades => "♠",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Suit::Clubs => "Clubs",
+            Suit::Diamonds => "Diamonds",
+            Suit::Hearts => "Hearts",
+            Suit::Spades => "Spades",
+        }
+    }
+
+    pub fn is_red(&self) -> bool {
+        matches!(self, Suit::Diamonds | Suit::Hearts)
+    }
+}
+
+impl Rank {
+    pub const ALL: [Rank; 13] = [
+        Rank::Two,
+        Rank::Three,
+        Rank::Four,
+        Rank::Five,
+        Rank::Six,
+        Rank::Seven,
+        Rank::Eight,
+        Rank::Nine,
+        Rank::Ten,
+        Rank::Jack,
+        Rank::Queen,
+        Rank::King,
+        Rank::Ace,
+    ];
+
+    /// 2 to 10 as printed, then 11 for a jack up to 14 for an ace
+    pub fn value(&self) -> u8 {
+        *self as u8 + 2
+    }
+
+    pub fn short(&self) -> &'static str {
+        match self {
+            Rank::Two => "2",
+            Rank::Three => "3",
+            Rank::Four => "4",
+            Rank::Five => "5",
+            Rank::Six => "6",
+            Rank::Seven => "7",
+            Rank::Eight => "8",
+            Rank::Nine => "9",
+            Rank::Ten => "10",
+            Rank::Jack => "J",
+            Rank::Queen => "Q",
+            Rank::King => "K",
+            Rank::Ace => "A",
+        }
+    }
+}
+
+impl PlayingCard {
+    pub fn new(rank: Rank, suit: Suit) -> PlayingCard {
+        PlayingCard { rank, suit }
+    }
+
+    /// Every card once, in order, shuffling is left to whoever deals
+    pub fn deck() -> Vec<PlayingCard> {
+        Suit::ALL
+            .iter()
+            .flat_map(|suit| Rank::ALL.iter().map(|rank| PlayingCard::new(*rank, *suit)))
+            .collect()
+    }
+}
+
+impl Display for PlayingCard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.rank.short(), self.suit.symbol())
+    }
+}
@@ -0,0 +1,45 @@
+use bincode::{Decode, Encode};
+
+/// Anything longer is turned away by the server
+pub const CHAT_MAX_LEN: usize = 200;
+
+/// How many messages are kept for each channel, and sent to anyone coming in
+pub const CHAT_HISTORY: usize = 50;
+
+/// The lobby is everyone not in a game, a room is everyone in the same game, spectators included
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum ChatChannel {
+    Lobby,
+    Room,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ChatMessage {
+    pub channel: ChatChannel,
+    pub from: String,
+    pub text: String,
+}
+
+/// Quick reactions, sent with a single key during a game
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum Reaction {
+    Nice,
+    Ouch,
+    Hurry,
+    Wow,
+    Thanks,
+    GoodGame,
+}
+
+impl Reaction {
+    pub fn text(&self) -> &'static str {
+        match self {
+            Reaction::Nice => "Nice!",
+            Reaction::Ouch => "Ouch!",
+            Reaction::Hurry => "Hurry up!",
+            Reaction::Wow => "Wow!",
+            Reaction::Thanks => "Thanks!",
+            Reaction::GoodGame => "Good game!",
+        }
+    }
+}
@@ -0,0 +1,1049 @@
+use bincode::{Decode, Encode};
+
+use crate::game_state::GameStartState;
+
+/// Minutes each and seconds added after every move, or no clock at all
+pub const TIME_CONTROLS: [Option<(u32, u32)>; 5] = [
+    Some((1, 0)),
+    Some((3, 2)),
+    Some((5, 3)),
+    Some((10, 5)),
+    None,
+];
+
+/// Chess between two players, white is picked when the game starts.
+///
+/// The whole position is sent every move, the client works out the legal moves itself
+///  to show them, the server checks them again before anything is played.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ChessClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<ChessAction>,
+    pub players: Vec<ChessPlayer>,
+    pub host_user: u32,
+    pub position: Position,
+    /// Every move so far in algebraic notation, white's first
+    pub moves: Vec<String>,
+    pub last_move: Option<ChessMove>,
+    pub time_control: Option<(u32, u32)>,
+    /// What's left on white's and black's clocks as this was sent.
+    /// Only the side to move's is running.
+    pub clocks_ms: Option<[u32; 2]>,
+    pub outcome: Option<ChessOutcome>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ChessPlayer {
+    pub id: u32,
+    pub name: String,
+    /// Given out when the game starts
+    pub color: Option<Color>,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum ChessAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    /// White then black
+    GameStarted(String, String),
+    Moved(String, String),
+    GameEnded(ChessOutcome),
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerChessCommand {
+    GameState(ChessClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum ChessClientAction {
+    /// Only the host, before the game starts
+    CycleTimeControl,
+    Move(ChessMove),
+    Resign,
+}
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub enum ChessOutcome {
+    Checkmate(Color),
+    Timeout(Color),
+    Resigned(Color),
+    /// The other player left part way through
+    Abandoned(Color),
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+}
+
+impl ChessOutcome {
+    pub fn winner(&self) -> Option<Color> {
+        match self {
+            ChessOutcome::Checkmate(color)
+            | ChessOutcome::Timeout(color)
+            | ChessOutcome::Resigned(color)
+            | ChessOutcome::Abandoned(color) => Some(*color),
+            ChessOutcome::Stalemate
+            | ChessOutcome::Repetition
+            | ChessOutcome::FiftyMoves
+            | ChessOutcome::InsufficientMaterial => None,
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            ChessOutcome::Checkmate(color) => format!("Checkmate, {} wins", color.name()),
+            ChessOutcome::Timeout(color) => format!("{} wins on time", color.name()),
+            ChessOutcome::Resigned(color) => format!("{} resigned", color.other().name()),
+            ChessOutcome::Abandoned(color) => {
+                format!("{} left, {} wins", color.other().name(), color.name())
+            }
+            ChessOutcome::Stalemate => "Draw by stalemate".to_string(),
+            ChessOutcome::Repetition => "Draw by repetition".to_string(),
+            ChessOutcome::FiftyMoves => "Draw by the fifty move rule".to_string(),
+            ChessOutcome::InsufficientMaterial => "Draw, nobody can mate".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn other(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Color::White => "White",
+            Color::Black => "Black",
+        }
+    }
+
+    /// Index into anything kept per side, white first
+    pub fn idx(&self) -> usize {
+        match self {
+            Color::White => 0,
+            Color::Black => 1,
+        }
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    pub const PROMOTIONS: [PieceKind; 4] = [
+        PieceKind::Queen,
+        PieceKind::Rook,
+        PieceKind::Bishop,
+        PieceKind::Knight,
+    ];
+
+    /// The same shape is used for both sides, the colour is drawn on
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            PieceKind::Pawn => "♟",
+            PieceKind::Knight => "♞",
+            PieceKind::Bishop => "♝",
+            PieceKind::Rook => "♜",
+            PieceKind::Queen => "♛",
+            PieceKind::King => "♚",
+        }
+    }
+
+    pub fn letter(&self) -> &'static str {
+        match self {
+            PieceKind::Pawn => "",
+            PieceKind::Knight => "N",
+            PieceKind::Bishop => "B",
+            PieceKind::Rook => "R",
+            PieceKind::Queen => "Q",
+            PieceKind::King => "K",
+        }
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub kind: PieceKind,
+    pub color: Color,
+}
+
+/// Squares count from a1 as 0 along the rank to h1 as 7, up to h8 as 63
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChessMove {
+    pub from: u8,
+    pub to: u8,
+    pub promotion: Option<PieceKind>,
+}
+
+pub fn square_name(square: u8) -> String {
+    format!("{}{}", (b'a' + square % 8) as char, square / 8 + 1)
+}
+
+/// The square a file and rank along, if it's still on the board
+fn offset(square: u8, d_file: i8, d_rank: i8) -> Option<u8> {
+    let file = (square % 8) as i8 + d_file;
+    let rank = (square / 8) as i8 + d_rank;
+
+    ((0..8).contains(&file) && (0..8).contains(&rank)).then(|| (rank * 8 + file) as u8)
+}
+
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+const ROOK_LINES: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+const BISHOP_LINES: [(i8, i8); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+/// White king side, white queen side, black king side, black queen side
+const CASTLING_ROOKS: [u8; 4] = [7, 0, 63, 56];
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub board: [Option<Piece>; 64],
+    pub to_move: Color,
+    /// Same order as `CASTLING_ROOKS`
+    pub castling: [bool; 4],
+    /// The square a pawn can be taken on, after it moved two and only if someone can take it
+    pub en_passant: Option<u8>,
+    /// Moves since the last capture or pawn move, counted per side
+    pub halfmove_clock: u32,
+    pub fullmove: u32,
+}
+
+impl Position {
+    pub fn start() -> Position {
+        let mut board = [None; 64];
+        let back_rank = [
+            PieceKind::Rook,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Queen,
+            PieceKind::King,
+            PieceKind::Bishop,
+            PieceKind::Knight,
+            PieceKind::Rook,
+        ];
+
+        for (file, kind) in back_rank.iter().enumerate() {
+            board[file] = Some(Piece {
+                kind: *kind,
+                color: Color::White,
+            });
+            board[8 + file] = Some(Piece {
+                kind: PieceKind::Pawn,
+                color: Color::White,
+            });
+            board[48 + file] = Some(Piece {
+                kind: PieceKind::Pawn,
+                color: Color::Black,
+            });
+            board[56 + file] = Some(Piece {
+                kind: *kind,
+                color: Color::Black,
+            });
+        }
+
+        Position {
+            board,
+            to_move: Color::White,
+            castling: [true; 4],
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove: 1,
+        }
+    }
+
+    fn king_square(&self, color: Color) -> Option<u8> {
+        (0..64).find(|square| {
+            self.board[*square as usize]
+                == Some(Piece {
+                    kind: PieceKind::King,
+                    color,
+                })
+        })
+    }
+
+    pub fn in_check(&self, color: Color) -> bool {
+        self.king_square(color)
+            .is_some_and(|king| self.attacked(king, color.other()))
+    }
+
+    /// Whether any piece of `by` could take on the square
+    pub fn attacked(&self, square: u8, by: Color) -> bool {
+        let holds = |square: Option<u8>, kinds: &[PieceKind]| {
+            square
+                .and_then(|square| self.board[square as usize])
+                .is_some_and(|piece| piece.color == by && kinds.contains(&piece.kind))
+        };
+
+        // Pawns take towards the other side, so look back the way they came
+        let pawn_rank = match by {
+            Color::White => -1,
+            Color::Black => 1,
+        };
+        if holds(offset(square, -1, pawn_rank), &[PieceKind::Pawn])
+            || holds(offset(square, 1, pawn_rank), &[PieceKind::Pawn])
+        {
+            return true;
+        }
+
+        if KNIGHT_JUMPS
+            .iter()
+            .any(|(f, r)| holds(offset(square, *f, *r), &[PieceKind::Knight]))
+        {
+            return true;
+        }
+
+        if KING_STEPS
+            .iter()
+            .any(|(f, r)| holds(offset(square, *f, *r), &[PieceKind::King]))
+        {
+            return true;
+        }
+
+        let slides = [
+            (ROOK_LINES, [PieceKind::Rook, PieceKind::Queen]),
+            (BISHOP_LINES, [PieceKind::Bishop, PieceKind::Queen]),
+        ];
+
+        for (lines, kinds) in slides {
+            for (f, r) in lines {
+                let mut current = square;
+                while let Some(next) = offset(current, f, r) {
+                    if let Some(piece) = self.board[next as usize] {
+                        if piece.color == by && kinds.contains(&piece.kind) {
+                            return true;
+                        }
+                        break;
+                    }
+                    current = next;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Every move that doesn't leave the mover's own king in check
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        self.pseudo_moves()
+            .into_iter()
+            .filter(|mv| {
+                let mut after = self.clone();
+                after.apply(*mv);
+                !after.in_check(self.to_move)
+            })
+            .collect()
+    }
+
+    pub fn is_legal(&self, mv: &ChessMove) -> bool {
+        self.legal_moves().contains(mv)
+    }
+
+    fn pseudo_moves(&self) -> Vec<ChessMove> {
+        let mut moves = vec![];
+        let color = self.to_move;
+
+        for from in 0..64u8 {
+            let Some(piece) = self.board[from as usize] else {
+                continue;
+            };
+            if piece.color != color {
+                continue;
+            }
+
+            let mut add = |to: u8| {
+                moves.push(ChessMove {
+                    from,
+                    to,
+                    promotion: None,
+                })
+            };
+
+            match piece.kind {
+                PieceKind::Pawn => self.pawn_moves(from, &mut moves),
+                PieceKind::Knight | PieceKind::King => {
+                    let steps = if piece.kind == PieceKind::Knight {
+                        KNIGHT_JUMPS
+                    } else {
+                        KING_STEPS
+                    };
+
+                    for (f, r) in steps {
+                        if let Some(to) = offset(from, f, r)
+                            && self.board[to as usize].is_none_or(|other| other.color != color)
+                        {
+                            add(to);
+                        }
+                    }
+
+                    if piece.kind == PieceKind::King {
+                        self.castling_moves(from, &mut moves);
+                    }
+                }
+                PieceKind::Bishop | PieceKind::Rook | PieceKind::Queen => {
+                    let lines: Vec<(i8, i8)> = match piece.kind {
+                        PieceKind::Bishop => BISHOP_LINES.to_vec(),
+                        PieceKind::Rook => ROOK_LINES.to_vec(),
+                        _ => [ROOK_LINES, BISHOP_LINES].concat(),
+                    };
+
+                    for (f, r) in lines {
+                        let mut current = from;
+                        while let Some(to) = offset(current, f, r) {
+                            match self.board[to as usize] {
+                                None => add(to),
+                                Some(other) => {
+                                    if other.color != color {
+                                        add(to);
+                                    }
+                                    break;
+                                }
+                            }
+                            current = to;
+                        }
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    fn pawn_moves(&self, from: u8, moves: &mut Vec<ChessMove>) {
+        let color = self.to_move;
+        let (forward, start_rank, last_rank) = match color {
+            Color::White => (1, 1, 7),
+            Color::Black => (-1, 6, 0),
+        };
+
+        let mut add = |to: u8| {
+            if to / 8 == last_rank {
+                for promotion in PieceKind::PROMOTIONS {
+                    moves.push(ChessMove {
+                        from,
+                        to,
+                        promotion: Some(promotion),
+                    });
+                }
+            } else {
+                moves.push(ChessMove {
+                    from,
+                    to,
+                    promotion: None,
+                });
+            }
+        };
+
+        if let Some(one) = offset(from, 0, forward)
+            && self.board[one as usize].is_none()
+        {
+            add(one);
+
+            if from / 8 == start_rank
+                && let Some(two) = offset(from, 0, forward * 2)
+                && self.board[two as usize].is_none()
+            {
+                add(two);
+            }
+        }
+
+        for side in [-1, 1] {
+            let Some(to) = offset(from, side, forward) else {
+                continue;
+            };
+
+            let takes = self.board[to as usize].is_some_and(|other| other.color != color);
+            if takes || self.en_passant == Some(to) {
+                add(to);
+            }
+        }
+    }
+
+    /// The king can't castle out of, through, or into check
+    fn castling_moves(&self, from: u8, moves: &mut Vec<ChessMove>) {
+        let color = self.to_move;
+        let (home, rights) = match color {
+            Color::White => (4, [0, 1]),
+            Color::Black => (60, [2, 3]),
+        };
+
+        if from != home || self.attacked(home, color.other()) {
+            return;
+        }
+
+        for right in rights {
+            if !self.castling[right] {
+                continue;
+            }
+
+            let rook = CASTLING_ROOKS[right];
+            if self.board[rook as usize]
+                != Some(Piece {
+                    kind: PieceKind::Rook,
+                    color,
+                })
+            {
+                continue;
+            }
+
+            let (step, between): (i8, Vec<u8>) = if rook > home {
+                (1, (home + 1..rook).collect())
+            } else {
+                (-1, (rook + 1..home).collect())
+            };
+
+            if between
+                .iter()
+                .any(|square| self.board[*square as usize].is_some())
+            {
+                continue;
+            }
+
+            let Some(through) = offset(home, step, 0) else {
+                continue;
+            };
+            let Some(to) = offset(home, step * 2, 0) else {
+                continue;
+            };
+
+            if self.attacked(through, color.other()) || self.attacked(to, color.other()) {
+                continue;
+            }
+
+            moves.push(ChessMove {
+                from,
+                to,
+                promotion: None,
+            });
+        }
+    }
+
+    /// Plays the move without checking it, use `is_legal` first
+    pub fn apply(&mut self, mv: ChessMove) {
+        let Some(mut piece) = self.board[mv.from as usize].take() else {
+            return;
+        };
+
+        let mut captured = self.board[mv.to as usize].is_some();
+
+        if piece.kind == PieceKind::Pawn && self.en_passant == Some(mv.to) && !captured {
+            // The pawn taken is next to the one taking, not on the square moved to
+            let taken = (mv.from / 8) * 8 + mv.to % 8;
+            self.board[taken as usize] = None;
+            captured = true;
+        }
+
+        if piece.kind == PieceKind::King && (mv.from % 8).abs_diff(mv.to % 8) == 2 {
+            let (rook_from, rook_to) = if mv.to > mv.from {
+                (mv.from + 3, mv.from + 1)
+            } else {
+                (mv.from - 4, mv.from - 1)
+            };
+            self.board[rook_to as usize] = self.board[rook_from as usize].take();
+        }
+
+        if let Some(promotion) = mv.promotion {
+            piece.kind = promotion;
+        }
+
+        self.board[mv.to as usize] = Some(piece);
+
+        if piece.kind == PieceKind::King {
+            let rights = match piece.color {
+                Color::White => [0, 1],
+                Color::Black => [2, 3],
+            };
+            for right in rights {
+                self.castling[right] = false;
+            }
+        }
+        for (right, rook) in CASTLING_ROOKS.iter().enumerate() {
+            if mv.from == *rook || mv.to == *rook {
+                self.castling[right] = false;
+            }
+        }
+
+        self.en_passant = (piece.kind == PieceKind::Pawn && mv.from.abs_diff(mv.to) == 16)
+            .then(|| (mv.from + mv.to) / 2);
+
+        if piece.kind == PieceKind::Pawn || captured {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        if self.to_move == Color::Black {
+            self.fullmove += 1;
+        }
+        self.to_move = self.to_move.other();
+
+        // Only kept while it can really be taken, otherwise the same position
+        //  after a double push wouldn't count towards a repetition
+        if let Some(square) = self.en_passant
+            && !self.can_take_en_passant(square)
+        {
+            self.en_passant = None;
+        }
+    }
+
+    /// Whether the side to move has a pawn that can take on the square without leaving its king in check
+    fn can_take_en_passant(&self, square: u8) -> bool {
+        let color = self.to_move;
+        let back = match color {
+            Color::White => -1,
+            Color::Black => 1,
+        };
+
+        [-1, 1]
+            .into_iter()
+            .filter_map(|side| offset(square, side, back))
+            .any(|from| {
+                if self.board[from as usize]
+                    != Some(Piece {
+                        kind: PieceKind::Pawn,
+                        color,
+                    })
+                {
+                    return false;
+                }
+
+                // Taking isn't a double push, so this doesn't come back round
+                let mut after = self.clone();
+                after.apply(ChessMove {
+                    from,
+                    to: square,
+                    promotion: None,
+                });
+                !after.in_check(color)
+            })
+    }
+
+    /// What's compared for repetition, the move counters don't matter
+    pub fn repetition_key(&self) -> Position {
+        Position {
+            halfmove_clock: 0,
+            fullmove: 0,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the game is over on the board, repetition is left to whoever keeps the history
+    pub fn outcome(&self) -> Option<ChessOutcome> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.in_check(self.to_move) {
+                ChessOutcome::Checkmate(self.to_move.other())
+            } else {
+                ChessOutcome::Stalemate
+            });
+        }
+
+        if self.halfmove_clock >= 100 {
+            return Some(ChessOutcome::FiftyMoves);
+        }
+
+        if self.insufficient_material() {
+            return Some(ChessOutcome::InsufficientMaterial);
+        }
+
+        None
+    }
+
+    /// Kings alone, or with a single knight or bishop between them
+    fn insufficient_material(&self) -> bool {
+        let others: Vec<PieceKind> = self
+            .board
+            .iter()
+            .flatten()
+            .map(|piece| piece.kind)
+            .filter(|kind| *kind != PieceKind::King)
+            .collect();
+
+        match others[..] {
+            [] => true,
+            [kind] => matches!(kind, PieceKind::Knight | PieceKind::Bishop),
+            _ => false,
+        }
+    }
+
+    /// Standard algebraic notation for a legal move, worked out before it's played
+    pub fn notation(&self, mv: &ChessMove) -> String {
+        let Some(piece) = self.board[mv.from as usize] else {
+            return String::new();
+        };
+
+        let mut text = if piece.kind == PieceKind::King && (mv.from % 8).abs_diff(mv.to % 8) == 2 {
+            if mv.to > mv.from { "O-O" } else { "O-O-O" }.to_string()
+        } else {
+            let captures = self.board[mv.to as usize].is_some()
+                || (piece.kind == PieceKind::Pawn && mv.from % 8 != mv.to % 8);
+
+            let mut text = piece.kind.letter().to_string();
+
+            if piece.kind == PieceKind::Pawn {
+                if captures {
+                    text.push((b'a' + mv.from % 8) as char);
+                }
+            } else {
+                // Only as much of the starting square as it takes to tell them apart
+                let others: Vec<ChessMove> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|other| {
+                        other.to == mv.to
+                            && other.from != mv.from
+                            && self.board[other.from as usize] == Some(piece)
+                    })
+                    .collect();
+
+                if !others.is_empty() {
+                    let file = mv.from % 8;
+                    let rank = mv.from / 8;
+                    let name = square_name(mv.from);
+
+                    if others.iter().all(|other| other.from % 8 != file) {
+                        text.push_str(&name[..1]);
+                    } else if others.iter().all(|other| other.from / 8 != rank) {
+                        text.push_str(&name[1..]);
+                    } else {
+                        text.push_str(&name);
+                    }
+                }
+            }
+
+            if captures {
+                text.push('x');
+            }
+            text.push_str(&square_name(mv.to));
+
+            if let Some(promotion) = mv.promotion {
+                text.push('=');
+                text.push_str(promotion.letter());
+            }
+
+            text
+        };
+
+        let mut after = self.clone();
+        after.apply(*mv);
+        if after.in_check(after.to_move) {
+            text.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> u8 {
+        let bytes = name.as_bytes();
+        (bytes[1] - b'1') * 8 + (bytes[0] - b'a')
+    }
+
+    fn mv(from: &str, to: &str) -> ChessMove {
+        ChessMove {
+            from: square(from),
+            to: square(to),
+            promotion: None,
+        }
+    }
+
+    /// Only as much FEN as the tests need, the counters are optional
+    fn from_fen(fen: &str) -> Position {
+        let mut fields = fen.split_whitespace();
+        let mut board = [None; 64];
+
+        for (row, pieces) in fields.next().unwrap().split('/').enumerate() {
+            let mut file = 0;
+            for letter in pieces.chars() {
+                if let Some(skip) = letter.to_digit(10) {
+                    file += skip as usize;
+                    continue;
+                }
+
+                let kind = match letter.to_ascii_lowercase() {
+                    'p' => PieceKind::Pawn,
+                    'n' => PieceKind::Knight,
+                    'b' => PieceKind::Bishop,
+                    'r' => PieceKind::Rook,
+                    'q' => PieceKind::Queen,
+                    'k' => PieceKind::King,
+                    other => panic!("{other} isn't a piece"),
+                };
+                let color = if letter.is_ascii_uppercase() {
+                    Color::White
+                } else {
+                    Color::Black
+                };
+
+                board[(7 - row) * 8 + file] = Some(Piece { kind, color });
+                file += 1;
+            }
+        }
+
+        let to_move = match fields.next().unwrap() {
+            "w" => Color::White,
+            _ => Color::Black,
+        };
+        let rights = fields.next().unwrap();
+        let en_passant = match fields.next().unwrap() {
+            "-" => None,
+            name => Some(square(name)),
+        };
+
+        Position {
+            board,
+            to_move,
+            castling: ['K', 'Q', 'k', 'q'].map(|right| rights.contains(right)),
+            en_passant,
+            halfmove_clock: fields.next().map_or(0, |n| n.parse().unwrap()),
+            fullmove: fields.next().map_or(1, |n| n.parse().unwrap()),
+        }
+    }
+
+    fn perft(position: &Position, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+
+        position
+            .legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut after = position.clone();
+                after.apply(mv);
+                perft(&after, depth - 1)
+            })
+            .sum()
+    }
+
+    fn play(position: &mut Position, moves: &[(&str, &str)]) {
+        for (from, to) in moves {
+            let mv = mv(from, to);
+            assert!(position.is_legal(&mv), "{from}{to} should be legal");
+            position.apply(mv);
+        }
+    }
+
+    #[test]
+    fn perft_start() {
+        let position = Position::start();
+
+        assert_eq!(perft(&position, 1), 20);
+        assert_eq!(perft(&position, 2), 400);
+        assert_eq!(perft(&position, 3), 8902);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        let position =
+            from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+
+        assert_eq!(perft(&position, 1), 48);
+        assert_eq!(perft(&position, 2), 2039);
+        assert_eq!(perft(&position, 3), 97862);
+    }
+
+    #[test]
+    fn perft_pins_and_en_passant() {
+        let position = from_fen("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1");
+
+        assert_eq!(perft(&position, 1), 14);
+        assert_eq!(perft(&position, 2), 191);
+        assert_eq!(perft(&position, 3), 2812);
+        assert_eq!(perft(&position, 4), 43238);
+    }
+
+    #[test]
+    fn perft_promotions_and_castling() {
+        let position = from_fen("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1");
+
+        assert_eq!(perft(&position, 1), 6);
+        assert_eq!(perft(&position, 2), 264);
+        assert_eq!(perft(&position, 3), 9467);
+    }
+
+    #[test]
+    fn perft_discovered_checks() {
+        let position = from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8");
+
+        assert_eq!(perft(&position, 1), 44);
+        assert_eq!(perft(&position, 2), 1486);
+        assert_eq!(perft(&position, 3), 62379);
+    }
+
+    #[test]
+    fn no_castling_through_or_out_of_check() {
+        // The rook on f8 covers f1, the queen side is still fine
+        let position = from_fen("4kr2/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(!position.is_legal(&mv("e1", "g1")));
+        assert!(position.is_legal(&mv("e1", "c1")));
+
+        // b1 being covered doesn't matter, the king never crosses it
+        let position = from_fen("1r2k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(position.is_legal(&mv("e1", "c1")));
+
+        let position = from_fen("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        assert!(!position.is_legal(&mv("e1", "g1")));
+        assert!(!position.is_legal(&mv("e1", "c1")));
+    }
+
+    #[test]
+    fn castling_moves_the_rook() {
+        let mut position = from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+        play(&mut position, &[("e1", "g1")]);
+
+        assert_eq!(
+            position.board[square("f1") as usize].map(|piece| piece.kind),
+            Some(PieceKind::Rook)
+        );
+        assert!(position.board[square("h1") as usize].is_none());
+        assert_eq!(position.castling, [false; 4]);
+    }
+
+    #[test]
+    fn en_passant_takes_the_pawn_beside() {
+        let mut position = from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1");
+        play(&mut position, &[("d7", "d5")]);
+
+        assert_eq!(position.en_passant, Some(square("d6")));
+
+        play(&mut position, &[("e5", "d6")]);
+
+        assert!(position.board[square("d5") as usize].is_none());
+        assert_eq!(position.halfmove_clock, 0);
+    }
+
+    #[test]
+    fn en_passant_only_when_it_can_be_taken() {
+        // Nothing beside it to take
+        let mut position = Position::start();
+        play(&mut position, &[("e2", "e4")]);
+        assert_eq!(position.en_passant, None);
+
+        // Taking would leave both pawns off the rank and the king open to the rook
+        let mut position = from_fen("4k3/3p4/8/K3P2r/8/8/8/8 b - - 0 1");
+        play(&mut position, &[("d7", "d5")]);
+        assert_eq!(position.en_passant, None);
+        assert!(!position.is_legal(&mv("e5", "d6")));
+    }
+
+    #[test]
+    fn double_push_repeats_like_any_other_move() {
+        let mut position = Position::start();
+        play(&mut position, &[("e2", "e4")]);
+        let first = position.repetition_key();
+
+        play(
+            &mut position,
+            &[("g8", "f6"), ("g1", "f3"), ("f6", "g8"), ("f3", "g1")],
+        );
+
+        assert_eq!(position.repetition_key(), first);
+    }
+
+    #[test]
+    fn promotion_needs_a_piece() {
+        let mut position = from_fen("8/P7/8/8/8/8/8/k1K5 w - - 0 1");
+
+        let promotions: Vec<ChessMove> = position
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.from == square("a7"))
+            .collect();
+        assert_eq!(promotions.len(), 4);
+        assert!(!position.is_legal(&mv("a7", "a8")));
+
+        position.apply(ChessMove {
+            promotion: Some(PieceKind::Knight),
+            ..mv("a7", "a8")
+        });
+        assert_eq!(
+            position.board[square("a8") as usize],
+            Some(Piece {
+                kind: PieceKind::Knight,
+                color: Color::White,
+            })
+        );
+    }
+
+    #[test]
+    fn checkmate() {
+        let mut position = Position::start();
+        play(
+            &mut position,
+            &[("f2", "f3"), ("e7", "e5"), ("g2", "g4"), ("d8", "h4")],
+        );
+
+        assert_eq!(
+            position.outcome(),
+            Some(ChessOutcome::Checkmate(Color::Black))
+        );
+    }
+
+    #[test]
+    fn stalemate() {
+        let position = from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+
+        assert!(!position.in_check(Color::Black));
+        assert_eq!(position.outcome(), Some(ChessOutcome::Stalemate));
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let mut position = from_fen("4k3/8/8/8/8/8/4P3/R3K3 w - - 99 60");
+        assert_eq!(position.outcome(), None);
+
+        // A pawn move starts the count again
+        let mut pushed = position.clone();
+        play(&mut pushed, &[("e2", "e3")]);
+        assert_eq!(pushed.outcome(), None);
+
+        play(&mut position, &[("a1", "a2")]);
+        assert_eq!(position.outcome(), Some(ChessOutcome::FiftyMoves));
+    }
+}
@@ -0,0 +1,153 @@
+use bincode::{Decode, Encode};
+
+use crate::game_state::GameStartState;
+
+pub const CODENAMES_MAX_PLAYERS: usize = 10;
+pub const CODENAMES_GRID_SIZE: usize = 5;
+pub const CODENAMES_CARDS: usize = CODENAMES_GRID_SIZE * CODENAMES_GRID_SIZE;
+/// The team going first gets one more card to find than the other
+pub const CODENAMES_FIRST_TEAM_CARDS: usize = 9;
+pub const CODENAMES_MAX_CLUE_LEN: usize = 20;
+
+/// Codenames, two teams race to find their own words on a five by five grid.
+///
+/// The key of which word belongs to who is only put in a spymaster's state,
+///  operatives (and spectators) only see a card's colour once it's been guessed.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct CodenamesClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<CodenamesAction>,
+    pub players: Vec<CodenamesPlayer>,
+    pub host_user: u32,
+    pub cards: Vec<CodenamesCard>,
+    /// Only there for spymasters, and for everyone once the game is over
+    pub key: Option<Vec<CardKind>>,
+    pub turn: Team,
+    /// The clue being guessed on, nothing while the spymaster is still thinking
+    pub clue: Option<Clue>,
+    pub guesses_left: u8,
+    /// Cards still to find for red then blue
+    pub remaining: [u8; 2],
+    pub winner: Option<Team>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct CodenamesPlayer {
+    pub id: u32,
+    pub name: String,
+    pub team: Team,
+    pub role: Role,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct CodenamesCard {
+    pub word: String,
+    /// Filled in once somebody guesses it
+    pub revealed: Option<CardKind>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Team {
+    Red,
+    Blue,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Spymaster,
+    Operative,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum CardKind {
+    Agent(Team),
+    Bystander,
+    /// Guessing this loses the game on the spot
+    Assassin,
+}
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct Clue {
+    pub word: String,
+    /// How many cards the clue is meant for, the team get one more guess than this
+    pub count: u8,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum CodenamesAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    UserMoved(String, Team, Role),
+    GameStarted(Team),
+    ClueGiven(String, Team, Clue),
+    /// Who guessed, the word and what it turned out to be
+    UserGuessed(String, String, CardKind),
+    UserEndedTurn(String),
+    TeamWon(Team),
+    GameEnded,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerCodenamesCommand {
+    GameState(CodenamesClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum CodenamesClientAction {
+    /// Only before the game starts, picks a side and what to play as
+    Pick(Team, Role),
+    GiveClue(Clue),
+    /// The card's index in the grid, row by row
+    Guess(u8),
+    /// Stops guessing early, only once at least one guess has been made
+    EndTurn,
+}
+
+impl Team {
+    pub fn idx(&self) -> usize {
+        match self {
+            Team::Red => 0,
+            Team::Blue => 1,
+        }
+    }
+
+    pub fn other(&self) -> Team {
+        match self {
+            Team::Red => Team::Blue,
+            Team::Blue => Team::Red,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Team::Red => "Red",
+            Team::Blue => "Blue",
+        }
+    }
+}
+
+impl Role {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Role::Spymaster => "Spymaster",
+            Role::Operative => "Operative",
+        }
+    }
+}
+
+impl Clue {
+    /// A single word of letters, that isn't one of the words still on the board
+    pub fn is_valid(&self, cards: &[CodenamesCard]) -> bool {
+        !self.word.is_empty()
+            && self.word.len() <= CODENAMES_MAX_CLUE_LEN
+            && self.word.chars().all(|ch| ch.is_alphabetic())
+            && self.count <= 9
+            && !cards
+                .iter()
+                .any(|card| card.revealed.is_none() && card.word.eq_ignore_ascii_case(&self.word))
+    }
+}
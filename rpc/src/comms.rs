@@ -1,6 +1,10 @@
 use bincode::{Decode, Encode};
 
-use crate::game_state::{GameStartState, GameType};
+use crate::{
+    chat::{ChatChannel, ChatMessage},
+    game_state::{GameStartState, GameType, GameUserState},
+    stats::{LeaderboardEntry, PlayerProfile},
+};
 
 #[derive(Debug, Encode, Decode)]
 pub enum ClientMessage {
@@ -10,9 +14,48 @@ pub enum ClientMessage {
 
 #[derive(Debug, Encode, Decode)]
 pub enum ClientAuthedCommand {
-    CreateGame(String, GameType),
+    CreateGame(String, GameType, RoomVisibility),
     Game(ClientGameCommand),
-    JoinGame(u32),
+    /// Private rooms need the password or the invite code
+    JoinGame(u32, Option<String>),
+    /// Watch a game without taking a seat, any game that has started can be watched
+    SpectateGame(u32, Option<String>),
+    /// Takes a seat in a room still being set up, or watches one that's already running
+    JoinByCode(String),
+    ListReplays,
+    FetchReplay(String),
+    /// Looked up by name, whoever by that name has played the most
+    FetchProfile(String),
+    /// No game type gives the leaderboard across every game
+    FetchLeaderboard(Option<GameType>),
+    /// Wait for the server to put together a game, instead of picking one from the lobby
+    JoinQueue(QuickPlayRequest),
+    LeaveQueue,
+    /// Room messages go to whichever game the user is in
+    Chat(ChatChannel, String),
+    /// Asked for when opening the lobby or a room
+    FetchChat(ChatChannel),
+}
+
+/// Who can see and get into a room.
+/// Every room has an invite code, even public ones.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub enum RoomVisibility {
+    Public,
+    /// Listed in the lobby, getting in needs the password or the invite code
+    Private(String),
+    /// Not listed at all, only the invite code gets in
+    Hidden,
+}
+
+/// Players are only grouped with others queueing for the same game and rules
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct QuickPlayRequest {
+    pub game_type: GameType,
+    /// Game specific rules, encoded the same way as `ClientGameCommand::Raw`
+    pub rules: Vec<u8>,
+    /// Happy for the table to be filled up with bots after waiting a while
+    pub allow_bots: bool,
 }
 
 #[derive(Debug, Encode, Decode)]
@@ -28,14 +71,57 @@ pub enum ServerMessage {
     AuthResponse(u32),
     LobbyState(ClientLobbyState),
     NewPlayerCount(usize),
-    JoinedGame(String, GameType),
+    /// Spectators join with `GameUserState::Spectator`, players with `Active`
+    JoinedGame(String, GameType, GameUserState),
     GameState(Vec<u8>),
+    /// The game removed the user, the reason is given
+    RemovedFromGame(String),
+    /// Most recent first
+    ReplayList(Vec<ReplaySummary>),
+    /// Game specific replay data, decoded by the game's client like `GameState`
+    Replay(GameType, Vec<u8>),
+    ReplayError(String),
+    /// Nobody by that name has finished a game yet when there's no profile
+    Profile(String, Option<PlayerProfile>),
+    Leaderboard(Option<GameType>, Vec<LeaderboardEntry>),
+    /// Sent while queued, a match being found comes through as `JoinedGame`
+    QueueStatus(QueueStatus),
+    /// The user was not queued, the reason is given
+    QueueError(String),
+    /// Joining or spectating didn't work out, the reason is given
+    JoinFailed(String),
+    Chat(ChatMessage),
+    /// The last few messages, oldest first
+    ChatHistory(ChatChannel, Vec<ChatMessage>),
+    /// The message wasn't sent, the reason is given
+    ChatRejected(String),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct QueueStatus {
+    pub game_type: GameType,
+    /// Everyone waiting for the same rules, including the user
+    pub queued: u32,
+    pub waited_secs: u32,
+    pub estimated_wait_secs: u32,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ReplaySummary {
+    pub id: String,
+    pub name: String,
+    pub game_type: GameType,
+    pub players: Vec<String>,
 }
 
 #[derive(Debug, Encode, Decode, Default, Clone)]
 pub struct ClientLobbyState {
     pub player_count: usize,
     pub games: Vec<LobbyGame>,
+    /// Names of the Uno variants the server has loaded, a `GameType::UnoVariant` is a place in here
+    pub uno_variants: Vec<String>,
+    /// Games this server can't host, they're left out when creating a room
+    pub unavailable_games: Vec<GameType>,
 }
 
 #[derive(Debug, Encode, Decode, Clone)]
@@ -45,4 +131,18 @@ pub struct LobbyGame {
     pub game_type: GameType,
     pub start_state: GameStartState,
     pub active_players: u32,
+    pub max_players: u32,
+    pub spectator_count: u32,
+    /// Whoever has a seat, bots don't have a rating
+    pub players: Vec<LobbyPlayer>,
+    /// Needs a password or invite code to get in
+    pub private: bool,
+    /// The host isn't letting anyone else in
+    pub locked: bool,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct LobbyPlayer {
+    pub name: String,
+    pub rating: Option<u32>,
 }
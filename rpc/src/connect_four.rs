@@ -0,0 +1,240 @@
+use bincode::{Decode, Encode};
+
+use crate::game_state::GameStartState;
+
+pub const CONNECT_FOUR_COLUMNS: usize = 7;
+pub const CONNECT_FOUR_ROWS: usize = 6;
+
+/// Row 0 is the bottom of the board, discs fall down to the lowest empty row
+pub type ConnectFourBoard = [[Option<Disc>; CONNECT_FOUR_COLUMNS]; CONNECT_FOUR_ROWS];
+
+/// Connect Four, two players dropping discs until one gets four in a row.
+/// Red always goes first, who gets red is picked when the game starts.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ConnectFourClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<ConnectFourAction>,
+    pub players: Vec<ConnectFourPlayer>,
+    pub host_user: u32,
+    pub board: ConnectFourBoard,
+    pub turn: Disc,
+    /// Row and column of the last disc dropped
+    pub last_drop: Option<(u8, u8)>,
+    /// The four in a row that won it, to pick out on the board
+    pub winning_line: Vec<(u8, u8)>,
+    pub result: Option<ConnectFourResult>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct ConnectFourPlayer {
+    pub id: u32,
+    pub name: String,
+    /// Given out when the game starts
+    pub disc: Option<Disc>,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum Disc {
+    Red,
+    Yellow,
+}
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub enum ConnectFourResult {
+    Won(String),
+    Draw,
+    /// The other player left part way through
+    Forfeit(String),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum ConnectFourAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    GameStarted(String),
+    Dropped(String, u8),
+    GameEnded(ConnectFourResult),
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerConnectFourCommand {
+    GameState(ConnectFourClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum ConnectFourClientAction {
+    Drop(u8),
+}
+
+impl Disc {
+    pub fn other(&self) -> Disc {
+        match self {
+            Disc::Red => Disc::Yellow,
+            Disc::Yellow => Disc::Red,
+        }
+    }
+}
+
+pub fn empty_board() -> ConnectFourBoard {
+    [[None; CONNECT_FOUR_COLUMNS]; CONNECT_FOUR_ROWS]
+}
+
+/// Where a disc dropped in the column would land, nothing if it's full
+pub fn drop_row(board: &ConnectFourBoard, column: usize) -> Option<usize> {
+    if column >= CONNECT_FOUR_COLUMNS {
+        return None;
+    }
+
+    (0..CONNECT_FOUR_ROWS).find(|row| board[*row][column].is_none())
+}
+
+/// Every line of four through the given disc, only the last disc dropped can have made one
+pub fn winning_line(board: &ConnectFourBoard, row: usize, column: usize) -> Option<Vec<(u8, u8)>> {
+    let disc = board[row][column]?;
+
+    for (d_row, d_column) in [(0, 1), (1, 0), (1, 1), (1, -1)] {
+        let mut line = vec![(row as u8, column as u8)];
+
+        // Walk out both ways from the disc for as long as the colour matches
+        for sign in [1, -1] {
+            let mut r = row as isize;
+            let mut c = column as isize;
+
+            loop {
+                r += d_row * sign;
+                c += d_column * sign;
+
+                if r < 0
+                    || c < 0
+                    || r >= CONNECT_FOUR_ROWS as isize
+                    || c >= CONNECT_FOUR_COLUMNS as isize
+                    || board[r as usize][c as usize] != Some(disc)
+                {
+                    break;
+                }
+
+                line.push((r as u8, c as u8));
+            }
+        }
+
+        if line.len() >= 4 {
+            return Some(line);
+        }
+    }
+
+    None
+}
+
+pub fn board_full(board: &ConnectFourBoard) -> bool {
+    board[CONNECT_FOUR_ROWS - 1]
+        .iter()
+        .all(|cell| cell.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drawn the way it looks, the top row first, R and Y for discs and . for empty.
+    /// Rows that aren't given are empty ones at the top.
+    fn board(rows: &[&str]) -> ConnectFourBoard {
+        let mut board = empty_board();
+
+        for (row, text) in rows.iter().rev().enumerate() {
+            for (column, cell) in text.chars().enumerate() {
+                board[row][column] = match cell {
+                    'R' => Some(Disc::Red),
+                    'Y' => Some(Disc::Yellow),
+                    _ => None,
+                };
+            }
+        }
+
+        board
+    }
+
+    fn sorted(mut line: Vec<(u8, u8)>) -> Vec<(u8, u8)> {
+        line.sort();
+        line
+    }
+
+    #[test]
+    fn horizontal_win() {
+        let board = board(&["YY.....", "RRRR..."]);
+
+        let line = winning_line(&board, 0, 2).unwrap();
+        assert_eq!(sorted(line), vec![(0, 0), (0, 1), (0, 2), (0, 3)]);
+        assert_eq!(winning_line(&board, 1, 0), None);
+    }
+
+    #[test]
+    fn vertical_win() {
+        let board = board(&["Y......", "Y......", "YR.....", "YR.....", "RR....."]);
+
+        let line = winning_line(&board, 4, 0).unwrap();
+        assert_eq!(sorted(line), vec![(1, 0), (2, 0), (3, 0), (4, 0)]);
+        // Only three reds up the next column
+        assert_eq!(winning_line(&board, 2, 1), None);
+    }
+
+    #[test]
+    fn rising_diagonal_win() {
+        let board = board(&["...R...", "..RY...", ".RYY...", "RYYR..."]);
+
+        let line = winning_line(&board, 1, 1).unwrap();
+        assert_eq!(sorted(line), vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+    }
+
+    #[test]
+    fn falling_diagonal_win() {
+        let board = board(&["Y......", "RY.....", "RRY....", "RYRY..."]);
+
+        let line = winning_line(&board, 0, 3).unwrap();
+        assert_eq!(sorted(line), vec![(0, 3), (1, 2), (2, 1), (3, 0)]);
+    }
+
+    #[test]
+    fn longer_lines_count() {
+        let board = board(&["RRRRRRR"]);
+
+        assert_eq!(winning_line(&board, 0, 3).unwrap().len(), 7);
+    }
+
+    #[test]
+    fn no_win_without_four() {
+        let board = board(&["RRR.YYY", "YYYRRRY"]);
+
+        for column in 0..CONNECT_FOUR_COLUMNS {
+            assert_eq!(winning_line(&board, 0, column), None);
+            assert_eq!(winning_line(&board, 1, column), None);
+        }
+        assert_eq!(winning_line(&board, 2, 0), None);
+    }
+
+    #[test]
+    fn discs_land_on_the_lowest_empty_row() {
+        let board = board(&[
+            "R......", "Y......", "R......", "Y......", "RY.....", "YR.....",
+        ]);
+
+        assert_eq!(drop_row(&board, 0), None);
+        assert_eq!(drop_row(&board, 1), Some(2));
+        assert_eq!(drop_row(&board, 2), Some(0));
+        assert_eq!(drop_row(&board, CONNECT_FOUR_COLUMNS), None);
+    }
+
+    #[test]
+    fn full_board() {
+        let mut board = board(&[
+            "YYRRYYR", "RRYYRRY", "YYRRYYR", "RRYYRRY", "YYRRYYR", "RRYYRRY",
+        ]);
+        assert!(board_full(&board));
+
+        board[CONNECT_FOUR_ROWS - 1][3] = None;
+        assert!(!board_full(&board));
+    }
+}
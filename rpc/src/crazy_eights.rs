@@ -0,0 +1,72 @@
+use bincode::{Decode, Encode};
+
+use crate::{
+    cards::{PlayingCard, Rank, Suit},
+    game_state::GameStartState,
+};
+
+pub const EIGHTS_MAX_PLAYERS: usize = 6;
+
+/// Crazy Eights, played with a single standard deck.
+///
+/// A card can go down if it matches the suit or the rank of the last card played.
+/// Eights are wild, whoever plays one names the suit to follow.
+/// Anyone who can't play draws until they can, first to empty their hand wins.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct EightsClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<EightsAction>,
+    pub active_users: Vec<EightsActiveUser>,
+    pub host_user: u32,
+    pub user_turn: u8,
+    /// Nothing has been played before the game starts
+    pub top_card: Option<PlayingCard>,
+    /// The suit to follow, only different from the top card's after an eight
+    pub suit: Suit,
+    pub draw_pile: u32,
+    /// Once the game is over, best placed first
+    pub standings: Vec<(u32, String)>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct EightsActiveUser {
+    pub id: u32,
+    pub name: String,
+    pub card_count: u32,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum EightsAction {
+    Init,
+    InitialCard(PlayingCard),
+    UserPlayed(String, PlayingCard),
+    UserNamedSuit(String, Suit),
+    UserDrew(String, u8),
+    /// Nothing left to draw and nothing to play
+    UserPassed(String),
+    UserJoined(String),
+    UserLeft(String),
+    UserWon(String),
+    GameEnded,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerEightsCommand {
+    GameState(Vec<PlayingCard>, EightsClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum EightsClientAction {
+    /// Eights need the suit to change to, it's ignored for anything else
+    Play(PlayingCard, Option<Suit>),
+    /// Only allowed with nothing to play, cards are drawn until one can be played
+    Draw,
+}
+
+/// Whether the card can go on top of the pile
+pub fn can_play(card: &PlayingCard, top_card: &PlayingCard, suit: Suit) -> bool {
+    card.rank == Rank::Eight || card.suit == suit || card.rank == top_card.rank
+}
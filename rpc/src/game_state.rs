@@ -1,8 +1,77 @@
 use bincode::{Decode, Encode};
 
-#[derive(Debug, Encode, Decode, Clone, Copy)]
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum GameType {
     Uno,
+    CrazyEights,
+    Holdem,
+    Hearts,
+    ConnectFour,
+    Chess,
+    Snake,
+    LiarsDice,
+    Codenames,
+    Blackjack,
+    Battleship,
+    /// Uno played by one of the server's variant rules, by its place in the lobby's list.
+    /// They're loaded when the server starts so they aren't part of `ALL`.
+    UnoVariant(u8),
+}
+
+impl GameType {
+    /// In the order they're offered when creating a game
+    pub const ALL: [GameType; 11] = [
+        GameType::Uno,
+        GameType::CrazyEights,
+        GameType::Holdem,
+        GameType::Hearts,
+        GameType::ConnectFour,
+        GameType::Chess,
+        GameType::Snake,
+        GameType::LiarsDice,
+        GameType::Codenames,
+        GameType::Blackjack,
+        GameType::Battleship,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameType::Uno => "Uno",
+            GameType::CrazyEights => "Crazy Eights",
+            GameType::Holdem => "Texas Hold'em",
+            GameType::Hearts => "Hearts",
+            GameType::ConnectFour => "Connect Four",
+            GameType::Chess => "Chess",
+            GameType::Snake => "Snake",
+            GameType::LiarsDice => "Liar's Dice",
+            GameType::Codenames => "Codenames",
+            GameType::Blackjack => "Blackjack",
+            GameType::Battleship => "Battleship",
+            GameType::UnoVariant(_) => "Uno Variant",
+        }
+    }
+
+    /// Only plain Uno can be queued for, everything else needs a room made for it
+    pub fn supports_quick_play(&self) -> bool {
+        matches!(self, GameType::Uno)
+    }
+
+    /// The next game along, wrapping back to the first
+    pub fn next(&self) -> GameType {
+        let idx = GameType::ALL
+            .iter()
+            .position(|game| game == self)
+            .unwrap_or(0);
+        GameType::ALL[(idx + 1) % GameType::ALL.len()]
+    }
+
+    pub fn previous(&self) -> GameType {
+        let idx = GameType::ALL
+            .iter()
+            .position(|game| game == self)
+            .unwrap_or(0);
+        GameType::ALL[(idx + GameType::ALL.len() - 1) % GameType::ALL.len()]
+    }
 }
 
 #[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
@@ -12,7 +81,7 @@ pub enum GameStartState {
     Ending,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
 pub enum GameUserState {
     Active,
     Disconnected,
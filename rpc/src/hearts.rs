@@ -0,0 +1,180 @@
+use bincode::{Decode, Encode};
+
+use crate::{
+    cards::{PlayingCard, Rank, Suit},
+    game_state::GameStartState,
+};
+
+pub const HEARTS_PLAYERS: usize = 4;
+pub const HEARTS_PASS_COUNT: usize = 3;
+/// The game is over at the end of the round someone reaches this
+pub const HEARTS_END_SCORE: u32 = 100;
+/// Every heart and the queen of spades
+pub const HEARTS_ROUND_POINTS: u32 = 26;
+
+/// Hearts for exactly four, lowest score wins.
+///
+/// Each round starts by passing three cards, then thirteen tricks are played.
+/// Every heart taken is a point, the queen of spades is thirteen.
+/// Taking all of them shoots the moon, everyone else gets the points instead.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct HeartsClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<HeartsAction>,
+    /// In seat order, passing left goes to the next one along
+    pub players: Vec<HeartsPlayer>,
+    pub host_user: u32,
+    pub round: u32,
+    pub phase: HeartsPhase,
+    pub pass_direction: PassDirection,
+    /// Nobody plays while cards are being passed
+    pub user_turn: Option<u8>,
+    /// Led first
+    pub trick: Vec<(u32, PlayingCard)>,
+    /// Kept on screen once a trick is taken, so everyone can see what went
+    pub last_trick: Vec<(u32, PlayingCard)>,
+    pub hearts_broken: bool,
+    /// No tricks have been played yet this round, the two of clubs leads
+    pub first_trick: bool,
+    /// Once the game is over, best placed first
+    pub standings: Vec<(u32, String)>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct HeartsPlayer {
+    pub id: u32,
+    pub name: String,
+    pub score: u32,
+    /// Taken so far this round
+    pub round_points: u32,
+    pub card_count: u32,
+    /// Picked their cards to pass this round
+    pub passed: bool,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum HeartsPhase {
+    Passing,
+    Playing,
+}
+
+/// Goes round left, right, across, then a round without passing
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum PassDirection {
+    Left,
+    Right,
+    Across,
+    Hold,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum HeartsAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    RoundStarted(u32, PassDirection),
+    UserPassed(String),
+    CardsExchanged,
+    UserPlayed(String, PlayingCard),
+    HeartsBroken,
+    /// Along with the points in the trick
+    TrickTaken(String, u32),
+    ShotTheMoon(String),
+    /// The points each player took this round, in seat order
+    RoundScored(Vec<(String, u32)>),
+    UserWon(String),
+    GameEnded,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerHeartsCommand {
+    GameState(Vec<PlayingCard>, HeartsClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum HeartsClientAction {
+    /// Exactly three cards
+    Pass(Vec<PlayingCard>),
+    Play(PlayingCard),
+}
+
+impl PassDirection {
+    pub fn for_round(round: u32) -> PassDirection {
+        match round.saturating_sub(1) % 4 {
+            0 => PassDirection::Left,
+            1 => PassDirection::Right,
+            2 => PassDirection::Across,
+            _ => PassDirection::Hold,
+        }
+    }
+
+    /// How many seats along the cards go
+    pub fn offset(&self) -> usize {
+        match self {
+            PassDirection::Left => 1,
+            PassDirection::Right => HEARTS_PLAYERS - 1,
+            PassDirection::Across => 2,
+            PassDirection::Hold => 0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PassDirection::Left => "Left",
+            PassDirection::Right => "Right",
+            PassDirection::Across => "Across",
+            PassDirection::Hold => "No Passing",
+        }
+    }
+}
+
+pub fn card_points(card: &PlayingCard) -> u32 {
+    match (card.suit, card.rank) {
+        (Suit::Hearts, _) => 1,
+        (Suit::Spades, Rank::Queen) => 13,
+        _ => 0,
+    }
+}
+
+/// Whether the card can go on the trick, following suit comes first.
+/// Hearts can't be led until one has been thrown on another suit,
+///  and nothing worth points goes on the first trick unless there's no choice.
+pub fn can_play(
+    card: &PlayingCard,
+    hand: &[PlayingCard],
+    trick: &[(u32, PlayingCard)],
+    hearts_broken: bool,
+    first_trick: bool,
+) -> bool {
+    let two_of_clubs = PlayingCard::new(Rank::Two, Suit::Clubs);
+
+    let Some((_, led)) = trick.first() else {
+        if first_trick && hand.contains(&two_of_clubs) {
+            return *card == two_of_clubs;
+        }
+
+        return card.suit != Suit::Hearts
+            || hearts_broken
+            || hand.iter().all(|held| held.suit == Suit::Hearts);
+    };
+
+    if hand.iter().any(|held| held.suit == led.suit) {
+        return card.suit == led.suit;
+    }
+
+    !first_trick || card_points(card) == 0 || hand.iter().all(|held| card_points(held) > 0)
+}
+
+/// The highest card of the suit that was led takes it
+pub fn trick_winner(trick: &[(u32, PlayingCard)]) -> Option<u32> {
+    let (_, led) = trick.first()?;
+
+    trick
+        .iter()
+        .filter(|(_, card)| card.suit == led.suit)
+        .max_by_key(|(_, card)| card.rank)
+        .map(|(user_id, _)| *user_id)
+}
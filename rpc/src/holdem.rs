@@ -0,0 +1,338 @@
+use bincode::{Decode, Encode};
+
+use crate::{
+    cards::{PlayingCard, Rank},
+    game_state::GameStartState,
+};
+
+pub const HOLDEM_MAX_PLAYERS: usize = 8;
+pub const STARTING_CHIPS: u32 = 1000;
+pub const SMALL_BLIND: u32 = 10;
+pub const BIG_BLIND: u32 = 20;
+
+/// No limit Texas Hold'em, played hand after hand until one player has every chip.
+///
+/// Everyone's hole cards are kept on the server, the client only ever gets their own,
+///  and everyone's that's still in at showdown.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct HoldemClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<HoldemAction>,
+    /// In seat order, busted players keep their seat
+    pub players: Vec<HoldemPlayer>,
+    pub host_user: u32,
+    pub hand_number: u32,
+    pub stage: HoldemStage,
+    pub dealer: u8,
+    /// Nobody is acting between hands, or once all in
+    pub user_turn: Option<u8>,
+    pub board: Vec<PlayingCard>,
+    /// Main pot first, then any side pots.
+    /// Bets still in front of the players aren't counted until the round is over.
+    pub pots: Vec<u32>,
+    /// The bet everyone has to match this round
+    pub current_bet: u32,
+    /// A raise has to go at least this much over the current bet
+    pub min_raise: u32,
+    /// Hole cards turned over at showdown
+    pub revealed: Vec<(u32, Vec<PlayingCard>)>,
+    /// Once the game is over, best placed first
+    pub standings: Vec<(u32, String)>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct HoldemPlayer {
+    pub id: u32,
+    pub name: String,
+    pub chips: u32,
+    /// Put in this betting round
+    pub bet: u32,
+    pub folded: bool,
+    pub all_in: bool,
+    /// Out of chips, they sit out every hand from now on
+    pub busted: bool,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum HoldemStage {
+    PreFlop,
+    Flop,
+    Turn,
+    River,
+    /// The hand is over, the next one is dealt after a short pause
+    Showdown,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum HoldemAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    HandStarted(u32),
+    PostedBlind(String, u32),
+    Folded(String),
+    Checked(String),
+    Called(String, u32),
+    /// The total bet raised to
+    Raised(String, u32),
+    AllIn(String, u32),
+    Dealt(HoldemStage),
+    Showed(String, HandRank),
+    /// Nobody has to show when everyone else folded
+    WonPot(String, u32, Option<HandRank>),
+    UserBusted(String),
+    UserWon(String),
+    GameEnded,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerHoldemCommand {
+    GameState(Vec<PlayingCard>, HoldemClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum HoldemClientAction {
+    Fold,
+    Check,
+    Call,
+    /// The total to bet this round, not how much is added
+    Raise(u32),
+    AllIn,
+}
+
+/// Worst to best
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HandRank {
+    HighCard,
+    Pair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+impl HandRank {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HandRank::HighCard => "High Card",
+            HandRank::Pair => "Pair",
+            HandRank::TwoPair => "Two Pair",
+            HandRank::ThreeOfAKind => "Three of a Kind",
+            HandRank::Straight => "Straight",
+            HandRank::Flush => "Flush",
+            HandRank::FullHouse => "Full House",
+            HandRank::FourOfAKind => "Four of a Kind",
+            HandRank::StraightFlush => "Straight Flush",
+        }
+    }
+}
+
+/// Hands compare by rank, then card values most important first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HandValue {
+    pub rank: HandRank,
+    kickers: [u8; 5],
+}
+
+/// The best five card hand out of the hole cards and the board
+pub fn evaluate(cards: &[PlayingCard]) -> Option<HandValue> {
+    let mut best: Option<HandValue> = None;
+
+    // At most 21 ways to pick 5 from 7, it's quicker to just try them all
+    for a in 0..cards.len() {
+        for b in a + 1..cards.len() {
+            for c in b + 1..cards.len() {
+                for d in c + 1..cards.len() {
+                    for e in d + 1..cards.len() {
+                        let value =
+                            evaluate_five([cards[a], cards[b], cards[c], cards[d], cards[e]]);
+                        if best.is_none_or(|best| value > best) {
+                            best = Some(value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    best
+}
+
+fn evaluate_five(cards: [PlayingCard; 5]) -> HandValue {
+    let mut values: Vec<u8> = cards.iter().map(|card| card.rank.value()).collect();
+    values.sort_by(|a, b| b.cmp(a));
+
+    let flush = cards.iter().all(|card| card.suit == cards[0].suit);
+
+    let mut unique = values.clone();
+    unique.dedup();
+    let straight_high = if unique.len() < 5 {
+        None
+    } else if values[0] - values[4] == 4 {
+        Some(values[0])
+    } else if values == [Rank::Ace.value(), 5, 4, 3, 2] {
+        // The wheel, the ace plays low
+        Some(5)
+    } else {
+        None
+    };
+
+    // Most copies first, then highest
+    let mut groups: Vec<(u8, u8)> = unique
+        .iter()
+        .map(|value| {
+            let count = values.iter().filter(|other| *other == value).count() as u8;
+            (count, *value)
+        })
+        .collect();
+    groups.sort_by(|a, b| b.cmp(a));
+
+    let mut kickers = [0; 5];
+    for (kicker, (_, value)) in kickers.iter_mut().zip(groups.iter()) {
+        *kicker = *value;
+    }
+
+    let rank = match (
+        straight_high,
+        flush,
+        groups[0].0,
+        groups.get(1).map(|g| g.0),
+    ) {
+        (Some(_), true, _, _) => HandRank::StraightFlush,
+        (_, _, 4, _) => HandRank::FourOfAKind,
+        (_, _, 3, Some(2)) => HandRank::FullHouse,
+        (_, true, _, _) => HandRank::Flush,
+        (Some(_), _, _, _) => HandRank::Straight,
+        (_, _, 3, _) => HandRank::ThreeOfAKind,
+        (_, _, 2, Some(2)) => HandRank::TwoPair,
+        (_, _, 2, _) => HandRank::Pair,
+        _ => HandRank::HighCard,
+    };
+
+    if let Some(high) = straight_high {
+        kickers = [high, 0, 0, 0, 0];
+    }
+
+    HandValue { rank, kickers }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cards::Suit;
+
+    use super::*;
+
+    /// Cards written like "As Td 2c", rank then suit
+    fn cards(text: &str) -> Vec<PlayingCard> {
+        text.split_whitespace()
+            .map(|card| {
+                let (rank, suit) = card.split_at(1);
+                let rank = match rank {
+                    "T" => Rank::Ten,
+                    "J" => Rank::Jack,
+                    "Q" => Rank::Queen,
+                    "K" => Rank::King,
+                    "A" => Rank::Ace,
+                    digit => Rank::ALL[digit.parse::<usize>().unwrap() - 2],
+                };
+                let suit = match suit {
+                    "c" => Suit::Clubs,
+                    "d" => Suit::Diamonds,
+                    "h" => Suit::Hearts,
+                    _ => Suit::Spades,
+                };
+                PlayingCard::new(rank, suit)
+            })
+            .collect()
+    }
+
+    fn value(text: &str) -> HandValue {
+        evaluate(&cards(text)).unwrap()
+    }
+
+    #[test]
+    fn categories_in_order() {
+        // Each hand is made of lower cards than the one before, so only the category can order them
+        let hands = [
+            ("As Kd 9c 7h 3s", HandRank::HighCard),
+            ("Qs Qd 5c 7h 9s", HandRank::Pair),
+            ("Js Jd 4c 4h 9s", HandRank::TwoPair),
+            ("Ts Td Tc 7h 2s", HandRank::ThreeOfAKind),
+            ("6s 7d 8c 9h Ts", HandRank::Straight),
+            ("2h 5h 7h 9h Jh", HandRank::Flush),
+            ("3s 3d 3c 2h 2s", HandRank::FullHouse),
+            ("2s 2d 2c 2h 3s", HandRank::FourOfAKind),
+            ("Ad 2d 3d 4d 5d", HandRank::StraightFlush),
+        ];
+
+        for window in hands.windows(2) {
+            let (lower, lower_rank) = window[0];
+            let (higher, higher_rank) = window[1];
+
+            assert_eq!(value(lower).rank, lower_rank);
+            assert_eq!(value(higher).rank, higher_rank);
+            assert!(
+                value(lower) < value(higher),
+                "{lower} should lose to {higher}"
+            );
+        }
+    }
+
+    #[test]
+    fn kickers_break_ties() {
+        assert!(value("As Ad Kc 7h 3s") > value("Ac Ah Qc 7d 3d"));
+        assert!(value("As Ad 9c 9h 3s") > value("Ac Ah 9s 9d 2d"));
+        assert!(value("As Kd 9c 7h 3s") > value("Ac Kh 9s 7d 2d"));
+        // The trips count before the pair
+        assert!(value("4s 4d 4c 2h 2s") > value("3s 3d 3c Ah As"));
+        assert!(value("Kh Jh 9h 7h 3h") < value("Kd Jd 9d 7d 4d"));
+
+        // Suits never matter
+        assert_eq!(value("As Kd 9c 7h 3s"), value("Ah Kc 9d 7s 3c"));
+    }
+
+    #[test]
+    fn wheel_is_the_lowest_straight() {
+        let wheel = value("Ac 2d 3h 4s 5c");
+        assert_eq!(wheel.rank, HandRank::Straight);
+        assert!(wheel < value("2c 3d 4h 5s 6c"));
+        assert!(wheel > value("As Ad Ac Kh Qs"));
+
+        let steel_wheel = value("Ad 2d 3d 4d 5d");
+        assert_eq!(steel_wheel.rank, HandRank::StraightFlush);
+        assert!(steel_wheel < value("2d 3d 4d 5d 6d"));
+
+        // Straights don't wrap round the ace
+        assert_eq!(value("Qc Kd Ah 2s 3c").rank, HandRank::HighCard);
+    }
+
+    #[test]
+    fn best_five_of_seven() {
+        // The flush is only there using both hole cards
+        assert_eq!(value("Ah 9h 2h 5h Kh 5c 5d").rank, HandRank::Flush);
+        // Two pair out of three pairs keeps the best kicker
+        assert_eq!(value("Ks Kd Qs Qd 2c 2d Ac"), value("Kc Kh Qc Qh As 3d 4d"));
+    }
+
+    #[test]
+    fn board_plays_is_a_split() {
+        let board = "Ah Kd Qc Js Tc";
+
+        let first = value(&format!("{board} 2c 3d"));
+        let second = value(&format!("{board} 4h 5s"));
+
+        assert_eq!(first.rank, HandRank::Straight);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn needs_five_cards() {
+        assert_eq!(evaluate(&cards("As Ad Ac Ah")), None);
+    }
+}
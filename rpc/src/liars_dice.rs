@@ -0,0 +1,170 @@
+use bincode::{Decode, Encode};
+
+use crate::game_state::GameStartState;
+
+pub const LIARS_DICE_MAX_PLAYERS: usize = 6;
+pub const LIARS_DICE_START_DICE: u8 = 5;
+
+/// Liar's Dice, everyone rolls in secret then bids on what's under all the cups.
+///
+/// Ones are wild and count towards every bid, so bids are only ever made on two to six.
+/// Calling a bid reveals everything, whoever was wrong loses a die and starts the next round.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct LiarsDiceClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<LiarsDiceAction>,
+    /// In seat order, bidding goes round to the next one along
+    pub players: Vec<LiarsDicePlayer>,
+    pub host_user: u32,
+    pub round: u32,
+    pub user_turn: Option<u8>,
+    /// The bid to beat, and who made it
+    pub bid: Option<(u32, Bid)>,
+    /// Every cup from the last round that was called, kept up until the next one is
+    pub last_reveal: Vec<(u32, Vec<u8>)>,
+    /// Once the game is over, best placed first
+    pub standings: Vec<(u32, String)>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct LiarsDicePlayer {
+    pub id: u32,
+    pub name: String,
+    /// Nobody sees anyone else's dice, only how many they've got left
+    pub dice_count: u8,
+    pub rating: Option<u32>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct Bid {
+    pub quantity: u8,
+    pub face: u8,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum LiarsDiceAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    RoundStarted(u32),
+    UserBid(String, Bid),
+    /// Caller, bidder and how many there really were
+    UserCalled(String, String, u8),
+    UserLostDie(String),
+    UserKnockedOut(String),
+    UserWon(String),
+    GameEnded,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerLiarsDiceCommand {
+    /// Only the receiving player's own dice are sent
+    GameState(Vec<u8>, LiarsDiceClientGameState),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum LiarsDiceClientAction {
+    Bid(Bid),
+    /// Calls the last bid a lie
+    Call,
+}
+
+impl Bid {
+    /// More dice of any face, or the same number of a higher face
+    pub fn beats(&self, other: &Bid) -> bool {
+        (self.quantity, self.face) > (other.quantity, other.face)
+    }
+
+    pub fn is_valid(&self, total_dice: u32) -> bool {
+        (2..=6).contains(&self.face) && self.quantity >= 1 && self.quantity as u32 <= total_dice
+    }
+
+    /// The smallest bid there is that beats this one
+    pub fn next(&self) -> Bid {
+        if self.face < 6 {
+            Bid {
+                quantity: self.quantity,
+                face: self.face + 1,
+            }
+        } else {
+            Bid {
+                quantity: self.quantity + 1,
+                face: 2,
+            }
+        }
+    }
+}
+
+/// How many of the dice show the face, counting the wild ones
+pub fn count_face<'a>(dice: impl IntoIterator<Item = &'a u8>, face: u8) -> u8 {
+    dice.into_iter()
+        .filter(|die| **die == face || **die == 1)
+        .count() as u8
+}
+
+pub fn die_symbol(face: u8) -> &'static str {
+    match face {
+        1 => "⚀",
+        2 => "⚁",
+        3 => "⚂",
+        4 => "⚃",
+        5 => "⚄",
+        6 => "⚅",
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bid(quantity: u8, face: u8) -> Bid {
+        Bid { quantity, face }
+    }
+
+    #[test]
+    fn higher_face_or_more_dice_beats() {
+        assert!(bid(3, 5).beats(&bid(3, 4)));
+        assert!(bid(4, 2).beats(&bid(3, 6)));
+        assert!(!bid(3, 4).beats(&bid(3, 4)));
+        assert!(!bid(3, 3).beats(&bid(3, 4)));
+        assert!(!bid(2, 6).beats(&bid(3, 2)));
+    }
+
+    #[test]
+    fn next_is_the_smallest_raise() {
+        assert_eq!(bid(3, 4).next(), bid(3, 5));
+        assert_eq!(bid(3, 6).next(), bid(4, 2));
+
+        // Nothing fits between a bid and its next
+        let current = bid(2, 3);
+        for quantity in 1..=10 {
+            for face in 2..=6 {
+                let raise = bid(quantity, face);
+                if raise.beats(&current) {
+                    assert!(raise == current.next() || raise.beats(&current.next()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bids_on_ones_or_too_many_dice_are_invalid() {
+        assert!(bid(1, 2).is_valid(10));
+        assert!(bid(10, 6).is_valid(10));
+        assert!(!bid(11, 6).is_valid(10));
+        assert!(!bid(0, 3).is_valid(10));
+        assert!(!bid(3, 1).is_valid(10));
+        assert!(!bid(3, 7).is_valid(10));
+    }
+
+    #[test]
+    fn ones_are_wild() {
+        let dice = [1, 1, 3, 4, 4, 6];
+        assert_eq!(count_face(&dice, 4), 4);
+        assert_eq!(count_face(&dice, 3), 3);
+        assert_eq!(count_face(&dice, 2), 2);
+    }
+}
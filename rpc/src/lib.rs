@@ -1,7 +1,20 @@
 use bincode::{Decode, Encode};
 
+pub mod battleship;
+pub mod blackjack;
+pub mod cards;
+pub mod chat;
+pub mod chess;
+pub mod codenames;
 pub mod command;
+pub mod connect_four;
+pub mod crazy_eights;
 pub mod game_state;
+pub mod hearts;
+pub mod holdem;
+pub mod liars_dice;
+pub mod snake;
+pub mod stats;
 pub mod user_state;
 
 pub mod comms;
@@ -0,0 +1,175 @@
+use bincode::{Decode, Encode};
+
+use crate::game_state::GameStartState;
+
+pub const SNAKE_MAX_PLAYERS: usize = 6;
+pub const SNAKE_WIDTH: u8 = 32;
+pub const SNAKE_HEIGHT: u8 = 16;
+/// The server moves every snake once this often, whether anyone pressed anything or not
+pub const SNAKE_TICK_MS: u64 = 150;
+/// Ticks between the game starting and the snakes moving, about three seconds
+pub const SNAKE_COUNTDOWN_TICKS: u32 = 20;
+
+/// Multiplayer snake, played in real time rather than in turns.
+///
+/// This full state only goes out when something other than the snakes moving happens,
+///  joins and leaves, the game starting and ending. Everything else comes in a `SnakeDelta`.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct SnakeClientGameState {
+    pub game_state: GameStartState,
+    pub action: Vec<SnakeAction>,
+    pub players: Vec<SnakePlayer>,
+    pub host_user: u32,
+    /// Once the game is over, best placed first with their rank
+    pub standings: Vec<(u32, String, u32)>,
+    /// The whole board, kept up to date between full states by applying each `SnakeDelta`
+    pub tick: Option<SnakeTick>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct SnakePlayer {
+    pub id: u32,
+    pub name: String,
+    pub rating: Option<u32>,
+}
+
+/// Where everything is on the board
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct SnakeTick {
+    pub tick: u32,
+    /// Ticks until the snakes start moving
+    pub countdown: u32,
+    pub snakes: Vec<SnakeBody>,
+    pub food: Vec<(u8, u8)>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct SnakeBody {
+    pub id: u32,
+    /// Head first, as column and row
+    pub body: Vec<(u8, u8)>,
+    pub direction: Direction,
+    pub alive: bool,
+    /// The last input of theirs the server has dealt with, so they can see how far behind it is
+    pub last_input: u32,
+}
+
+/// Sent every tick while the game is running, kept small since it goes out so often.
+/// Only the snakes still going are in it, and only the ends of them.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct SnakeDelta {
+    pub tick: u32,
+    pub countdown: u32,
+    pub snakes: Vec<SnakeStep>,
+    /// All of it, but only when some was eaten or put down
+    pub food: Option<Vec<(u8, u8)>>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct SnakeStep {
+    pub id: u32,
+    pub movement: SnakeMovement,
+    pub direction: Direction,
+    pub last_input: u32,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum SnakeMovement {
+    /// Still counting down
+    Still,
+    /// The new head, and whether it grew, the tail stays put on the tick a snake eats
+    Moved((u8, u8), bool),
+    Crashed,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum SnakeAction {
+    Init,
+    UserJoined(String),
+    UserLeft(String),
+    GameStarted,
+    /// Along with how long they got
+    UserDied(String, u32),
+    UserWon(String),
+    GameEnded,
+}
+
+#[derive(Debug, Encode, Decode)]
+pub enum ServerSnakeCommand {
+    GameState(SnakeClientGameState),
+    Tick(SnakeDelta),
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum SnakeClientAction {
+    /// Numbered by the client, the number comes back in `SnakeBody::last_input`
+    Turn(Direction, u32),
+}
+
+impl SnakeTick {
+    /// Moves the board on by one tick
+    pub fn apply(&mut self, delta: SnakeDelta) {
+        self.tick = delta.tick;
+        self.countdown = delta.countdown;
+
+        if let Some(food) = delta.food {
+            self.food = food;
+        }
+
+        for step in delta.snakes {
+            let Some(snake) = self.snakes.iter_mut().find(|snake| snake.id == step.id) else {
+                continue;
+            };
+
+            snake.direction = step.direction;
+            snake.last_input = step.last_input;
+
+            match step.movement {
+                SnakeMovement::Still => {}
+                SnakeMovement::Moved(head, grew) => {
+                    snake.body.insert(0, head);
+                    if !grew {
+                        snake.body.pop();
+                    }
+                }
+                SnakeMovement::Crashed => {
+                    snake.body.clear();
+                    snake.alive = false;
+                }
+            }
+        }
+    }
+}
+
+impl Direction {
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// The next cell along, nothing if that's off the board
+    pub fn step(&self, (column, row): (u8, u8)) -> Option<(u8, u8)> {
+        let next = match self {
+            Direction::Up => (column, row.checked_sub(1)?),
+            Direction::Down => (column, row + 1),
+            Direction::Left => (column.checked_sub(1)?, row),
+            Direction::Right => (column + 1, row),
+        };
+
+        (next.0 < SNAKE_WIDTH && next.1 < SNAKE_HEIGHT).then_some(next)
+    }
+}
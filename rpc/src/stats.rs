@@ -0,0 +1,68 @@
+use bincode::{Decode, Encode};
+
+use crate::game_state::GameType;
+
+/// Where everyone starts before they've played a rated game
+pub const DEFAULT_RATING: u32 = 1500;
+
+/// Everything is kept as running totals, averages are worked out when shown
+#[derive(Debug, Encode, Decode, Clone, Default)]
+pub struct PlayerStats {
+    pub games_played: u32,
+    pub wins: u32,
+    pub busts: u32,
+    pub cards_played: u64,
+    pub total_game_secs: u64,
+    /// How many times the player finished in each place, first place first
+    pub finishes: Vec<u32>,
+}
+
+impl PlayerStats {
+    pub fn average_game_secs(&self) -> u64 {
+        self.total_game_secs
+            .checked_div(self.games_played as u64)
+            .unwrap_or(0)
+    }
+
+    /// Stats from every game type added together
+    pub fn add(&mut self, other: &PlayerStats) {
+        self.games_played += other.games_played;
+        self.wins += other.wins;
+        self.busts += other.busts;
+        self.cards_played += other.cards_played;
+        self.total_game_secs += other.total_game_secs;
+
+        if self.finishes.len() < other.finishes.len() {
+            self.finishes.resize(other.finishes.len(), 0);
+        }
+        for (total, count) in self.finishes.iter_mut().zip(other.finishes.iter()) {
+            *total += count;
+        }
+    }
+}
+
+/// How a single game moved a player's rating
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct RatingChange {
+    pub game_type: GameType,
+    /// The rating after the game
+    pub rating: u32,
+    pub change: i32,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct PlayerProfile {
+    pub name: String,
+    pub rating: u32,
+    /// Most recent game last
+    pub rating_history: Vec<RatingChange>,
+    pub overall: PlayerStats,
+    pub per_game: Vec<(GameType, PlayerStats)>,
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub rating: u32,
+    pub stats: PlayerStats,
+}
@@ -1,6 +1,6 @@
 use bincode::{Decode, Encode};
 
-use crate::game_state::GameStartState;
+use crate::{chat::Reaction, game_state::GameStartState};
 
 /// Let's consider an uno card.
 /// There are 3 parts to what can happen in a card.
@@ -26,6 +26,12 @@ use crate::game_state::GameStartState;
 #[derive(PartialEq, Eq, Debug, Copy, Clone, Encode, Decode, PartialOrd, Ord)]
 pub struct UnoCard(pub u8);
 
+/// Uno tables can seat up to 10, more than that and a game takes forever
+pub const UNO_MAX_PLAYERS: usize = 10;
+
+/// Each deck is a full set of the rules' cards shuffled together
+pub const UNO_MAX_DECKS: u8 = 3;
+
 pub enum UnoCommand {}
 
 pub enum PlayerUnoCommand {
@@ -44,6 +50,124 @@ pub struct UnoClientGameState {
     pub user_turn: u8,
     pub is_ord: bool,
     pub last_card: UnoCard,
+    /// Only sent once the game is over, with it a game can be replayed
+    pub seed: Option<u64>,
+    pub settings: UnoSettings,
+    /// Empty unless playing in teams, best placed team first
+    pub team_standings: Vec<UnoTeamStanding>,
+    /// Hands of the receiving user's partners, only when the settings allow it
+    pub partner_hands: Vec<(u32, Vec<UnoCard>)>,
+    /// Time left for the current turn when the state was sent
+    pub turn_time_left_ms: Option<u32>,
+    /// Only players get the room's invite code, spectators can't hand it out
+    pub invite_code: Option<String>,
+    /// The host has stopped anyone else from joining
+    pub locked: bool,
+    /// The standard rules unless the room is for one of the server's variants
+    pub rules: UnoRules,
+}
+
+/// Everything about how a game of Uno plays that isn't a room setting.
+///
+/// The standard rules are built in, the server can load more from files as variants.
+/// The black cards are always wild, picking the colour is part of playing them.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct UnoRules {
+    pub name: String,
+    pub description: String,
+    /// Every different card in a single deck and how many copies of it there are.
+    /// Black cards are stored as red, same as in a hand.
+    pub deck: Vec<(UnoCard, u8)>,
+    pub hand_size: u8,
+    /// Anyone holding more cards than this goes bust
+    pub bust_over: u8,
+    /// Whether a card can go on one of the same colour
+    pub match_colour: bool,
+    /// Whether a card can go on one of the same value
+    pub match_value: bool,
+    /// What each power card does when it's played, in order.
+    /// Power cards that aren't listed just move play on.
+    pub effects: Vec<(UnoCardPower, Vec<UnoEffect>)>,
+}
+
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum UnoEffect {
+    /// The next player picks up this many
+    Draw(u8),
+    /// The next player misses their turn
+    Skip,
+    /// Play changes direction, with only two players left that's the same as a skip
+    Reverse,
+}
+
+/// Room settings the host can change before the game starts
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct UnoSettings {
+    pub deck_count: u8,
+    /// Players are split into 2 teams, seated alternately.
+    /// A team wins as soon as one of its players runs out of cards.
+    pub team_mode: bool,
+    pub partner_view: UnoPartnerView,
+    /// When a turn runs out the player picks up a card and play moves on
+    pub turn_timer_secs: Option<u16>,
+    /// Players are removed from the game after this many timeouts in a row
+    pub kick_after_timeouts: Option<u8>,
+    /// A player leaving mid game has their seat taken over by a bot
+    pub replace_leavers: Option<UnoBotDifficulty>,
+}
+
+/// How hard the server's bot players try
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum UnoBotDifficulty {
+    /// Any card that is allowed
+    Random,
+    /// Gets rid of the most valuable card it can
+    Greedy,
+    /// Keeps track of what has been played and plays around the other players
+    Heuristic,
+}
+
+/// What a player gets to see of their partner in team mode
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum UnoPartnerView {
+    /// Same as everyone else, just the card count
+    CardCount,
+    /// The partner's whole hand
+    Hand,
+}
+
+impl Default for UnoSettings {
+    fn default() -> Self {
+        UnoSettings {
+            deck_count: 1,
+            team_mode: false,
+            partner_view: UnoPartnerView::CardCount,
+            turn_timer_secs: None,
+            kick_after_timeouts: None,
+            replace_leavers: None,
+        }
+    }
+}
+
+impl UnoSettings {
+    pub fn validate(&self) -> bool {
+        (1..=UNO_MAX_DECKS).contains(&self.deck_count)
+            && self
+                .turn_timer_secs
+                .is_none_or(|secs| (5..=300).contains(&secs))
+            && self
+                .kick_after_timeouts
+                .is_none_or(|count| (1..=10).contains(&count))
+    }
+}
+
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct UnoTeamStanding {
+    pub team: u8,
+    pub user_ids: Vec<u32>,
+    /// Cards held by the team's players still in the game
+    pub card_count: u32,
+    pub has_won: bool,
 }
 
 #[derive(Debug, Encode, Decode, Clone)]
@@ -56,6 +180,12 @@ pub enum UnoAction {
     UserLeft(String),
     UserFinished(String),
     UserBust(String),
+    UserTimedOut(String),
+    UserKicked(String),
+    UserBanned(String),
+    HostChanged(String),
+    RoomLocked(bool),
+    Reacted(String, Reaction),
     GameEnded,
 }
 
@@ -64,6 +194,17 @@ pub struct UnoActiveUser {
     pub id: u32,
     pub name: String,
     pub card_count: u32,
+    pub team: Option<u8>,
+    pub bot: Option<UnoBotDifficulty>,
+    pub rating: Option<u32>,
+}
+
+/// One step of a replayed game.
+/// Every hand is included so the replay can be watched from any seat.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct UnoReplayFrame {
+    pub hands: Vec<(u32, Vec<UnoCard>)>,
+    pub state: UnoClientGameState,
 }
 
 #[derive(Debug, Encode, Decode)]
@@ -75,10 +216,22 @@ pub enum ServerUnoCommand {
     GameState(Vec<UnoCard>, UnoClientGameState),
 }
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Debug, Encode, Decode, Clone)]
 pub enum UnoClientAction {
     PickupCard,
     PlayCard(UnoCard),
+    UpdateSettings(UnoSettings),
+    /// Only the host can add or remove bots, before the game starts
+    AddBot(UnoBotDifficulty),
+    RemoveBot(u32),
+    /// Also host only, players can only be kicked or banned before the game starts.
+    /// A ban keeps them out of the room for as long as it's open.
+    KickUser(u32),
+    BanUser(u32),
+    SetLocked(bool),
+    TransferHost(u32),
+    /// Anyone at the table can react, as long as they haven't just done so
+    React(Reaction),
 }
 
 #[repr(u8)]
@@ -90,7 +243,7 @@ pub enum UnoCardColour {
     Yellow = 3,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
 #[repr(u8)]
 pub enum UnoCardPower {
     PlusTwo = 0,
@@ -178,3 +331,114 @@ impl UnoCard {
         self.0 & cmp
     }
 }
+
+impl UnoCardPower {
+    pub const ALL: [UnoCardPower; 5] = [
+        UnoCardPower::PlusTwo,
+        UnoCardPower::Skip,
+        UnoCardPower::Reverse,
+        UnoCardPower::PlusFour,
+        UnoCardPower::ClrChange,
+    ];
+
+    /// Black cards don't have a colour of their own
+    pub fn is_black(self) -> bool {
+        matches!(self, UnoCardPower::PlusFour | UnoCardPower::ClrChange)
+    }
+
+    /// How the card is shown in a hand
+    pub fn short_name(self) -> &'static str {
+        match self {
+            UnoCardPower::PlusTwo => "+2",
+            UnoCardPower::Skip => "Sk",
+            UnoCardPower::Reverse => "Rv",
+            UnoCardPower::PlusFour => "+4",
+            UnoCardPower::ClrChange => "Cc",
+        }
+    }
+}
+
+impl UnoRules {
+    /// 108 cards, 10 each and bust with more than 20
+    pub fn standard() -> UnoRules {
+        let mut deck = vec![];
+
+        for colour in 0..4 {
+            let colour = UnoCardColour::from(colour);
+
+            deck.push((UnoCard::encode(false, colour, 0), 1));
+            for value in 1..=9 {
+                deck.push((UnoCard::encode(false, colour, value), 2));
+            }
+            for power in [
+                UnoCardPower::PlusTwo,
+                UnoCardPower::Skip,
+                UnoCardPower::Reverse,
+            ] {
+                deck.push((UnoCard::encode(true, colour, power as u8), 2));
+            }
+        }
+
+        for power in [UnoCardPower::PlusFour, UnoCardPower::ClrChange] {
+            deck.push((UnoCard::encode(true, UnoCardColour::Red, power as u8), 4));
+        }
+        deck.sort_by_key(|(card, _)| *card);
+
+        UnoRules {
+            name: "Uno".to_string(),
+            description: "The standard rules".to_string(),
+            deck,
+            hand_size: 10,
+            bust_over: 20,
+            match_colour: true,
+            match_value: true,
+            effects: vec![
+                (
+                    UnoCardPower::PlusTwo,
+                    vec![UnoEffect::Draw(2), UnoEffect::Skip],
+                ),
+                (UnoCardPower::Skip, vec![UnoEffect::Skip]),
+                (UnoCardPower::Reverse, vec![UnoEffect::Reverse]),
+                (
+                    UnoCardPower::PlusFour,
+                    vec![UnoEffect::Draw(4), UnoEffect::Skip],
+                ),
+            ],
+        }
+    }
+
+    /// The number of cards in a single deck
+    pub fn deck_size(&self) -> usize {
+        self.deck.iter().map(|(_, copies)| *copies as usize).sum()
+    }
+
+    /// Whether a card can go on top of the last played card.
+    /// Black cards go on anything, otherwise it's down to what the rules match on.
+    pub fn allows(&self, card: UnoCard, last_card: UnoCard) -> bool {
+        let (_, colour, value) = card.decode();
+        let (_, last_colour, last_value) = last_card.decode();
+
+        card.is_black()
+            || (self.match_colour && colour == last_colour)
+            || (self.match_value && value == last_value)
+    }
+
+    pub fn effects_of(&self, card: UnoCard) -> &[UnoEffect] {
+        if !card.is_power() {
+            return &[];
+        }
+
+        let power = UnoCardPower::from(card.get_value());
+        self.effects
+            .iter()
+            .find(|(effect_power, _)| *effect_power == power)
+            .map(|(_, effects)| effects.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+impl Default for UnoRules {
+    fn default() -> Self {
+        UnoRules::standard()
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use rpc::chat::{CHAT_HISTORY, CHAT_MAX_LEN, ChatChannel, ChatMessage};
+
+/// Each user can send this many messages in the window, across every channel
+const RATE_LIMIT_COUNT: usize = 5;
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(10);
+
+/// Lives on the main loop next to the users, rooms are keyed by game id.
+/// Nothing is saved, chat is gone once the server stops.
+#[derive(Debug, Default)]
+pub struct ChatStore {
+    lobby: VecDeque<ChatMessage>,
+    rooms: HashMap<u32, VecDeque<ChatMessage>>,
+    /// When each user's recent messages were sent, oldest first
+    recent_sends: HashMap<u32, VecDeque<Instant>>,
+}
+
+impl ChatStore {
+    /// Checks the message is fine to send and keeps it in the history.
+    /// No room means the lobby.
+    pub fn post(
+        &mut self,
+        user_id: u32,
+        from: &str,
+        room: Option<u32>,
+        text: &str,
+        now: Instant,
+    ) -> anyhow::Result<ChatMessage> {
+        // Control characters would mess with everyone else's terminal
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        let text = text.trim();
+
+        if text.is_empty() {
+            return Err(anyhow!("There's nothing to send"));
+        }
+
+        if text.chars().count() > CHAT_MAX_LEN {
+            return Err(anyhow!("Messages can be at most {CHAT_MAX_LEN} characters"));
+        }
+
+        let recent = self.recent_sends.entry(user_id).or_default();
+        while recent
+            .front()
+            .is_some_and(|sent| now.saturating_duration_since(*sent) >= RATE_LIMIT_WINDOW)
+        {
+            recent.pop_front();
+        }
+
+        if recent.len() >= RATE_LIMIT_COUNT {
+            return Err(anyhow!("Slow down, too many messages"));
+        }
+        recent.push_back(now);
+
+        let message = ChatMessage {
+            channel: if room.is_some() {
+                ChatChannel::Room
+            } else {
+                ChatChannel::Lobby
+            },
+            from: from.to_string(),
+            text: text.to_string(),
+        };
+
+        let history = match room {
+            Some(game_id) => self.rooms.entry(game_id).or_default(),
+            None => &mut self.lobby,
+        };
+
+        history.push_back(message.clone());
+        if history.len() > CHAT_HISTORY {
+            history.pop_front();
+        }
+
+        Ok(message)
+    }
+
+    pub fn history(&self, room: Option<u32>) -> Vec<ChatMessage> {
+        match room {
+            Some(game_id) => self
+                .rooms
+                .get(&game_id)
+                .map(|history| history.iter().cloned().collect())
+                .unwrap_or_default(),
+            None => self.lobby.iter().cloned().collect(),
+        }
+    }
+
+    pub fn close_room(&mut self, game_id: u32) {
+        self.rooms.remove(&game_id);
+    }
+
+    pub fn forget_user(&mut self, user_id: u32) {
+        self.recent_sends.remove(&user_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_messages_are_refused() {
+        let mut chat = ChatStore::default();
+        let now = Instant::now();
+
+        assert!(chat.post(1, "Alice", None, "", now).is_err());
+        assert!(chat.post(1, "Alice", None, "   \t ", now).is_err());
+        assert!(chat.post(1, "Alice", None, "\x1b\x07", now).is_err());
+        assert!(chat.history(None).is_empty());
+    }
+
+    #[test]
+    fn messages_are_cleaned_up() {
+        let mut chat = ChatStore::default();
+
+        let message = chat
+            .post(1, "Alice", None, "  hi\x1b[2J there \n", Instant::now())
+            .unwrap();
+        assert_eq!(message.text, "hi[2J there");
+        assert_eq!(message.from, "Alice");
+    }
+
+    #[test]
+    fn long_messages_are_refused() {
+        let mut chat = ChatStore::default();
+        let now = Instant::now();
+
+        let longest = "é".repeat(CHAT_MAX_LEN);
+        assert!(chat.post(1, "Alice", None, &longest, now).is_ok());
+        assert!(
+            chat.post(1, "Alice", None, &format!("{longest}a"), now)
+                .is_err()
+        );
+        // Surrounding whitespace doesn't count
+        assert!(
+            chat.post(1, "Alice", None, &format!(" {longest} "), now)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn sends_are_limited_within_the_window() {
+        let mut chat = ChatStore::default();
+        let start = Instant::now();
+
+        for _ in 0..RATE_LIMIT_COUNT {
+            chat.post(1, "Alice", None, "hi", start).unwrap();
+        }
+        // Across every channel
+        assert!(chat.post(1, "Alice", Some(7), "hi", start).is_err());
+        assert!(
+            chat.post(1, "Alice", None, "hi", start + RATE_LIMIT_WINDOW / 2)
+                .is_err()
+        );
+        // Someone else isn't held up
+        assert!(chat.post(2, "Bob", None, "hi", start).is_ok());
+
+        // The first sends drop out of the window once it's passed
+        assert!(
+            chat.post(1, "Alice", None, "hi", start + RATE_LIMIT_WINDOW)
+                .is_ok()
+        );
+        assert_eq!(chat.history(None).len(), RATE_LIMIT_COUNT + 2);
+    }
+
+    #[test]
+    fn refused_sends_count_against_nothing() {
+        let mut chat = ChatStore::default();
+        let now = Instant::now();
+
+        for _ in 0..RATE_LIMIT_COUNT {
+            assert!(chat.post(1, "Alice", None, " ", now).is_err());
+        }
+        assert!(chat.post(1, "Alice", None, "hi", now).is_ok());
+    }
+
+    #[test]
+    fn rooms_are_kept_apart_and_capped() {
+        let mut chat = ChatStore::default();
+        let start = Instant::now();
+
+        for i in 0..CHAT_HISTORY + 3 {
+            let now = start + RATE_LIMIT_WINDOW * i as u32;
+            chat.post(1, "Alice", Some(7), &format!("{i}"), now)
+                .unwrap();
+        }
+        chat.post(2, "Bob", None, "lobby", start).unwrap();
+
+        let room = chat.history(Some(7));
+        assert_eq!(room.len(), CHAT_HISTORY);
+        assert_eq!(room[0].text, "3");
+        assert_eq!(room[0].channel, ChatChannel::Room);
+        assert_eq!(chat.history(None).len(), 1);
+
+        chat.close_room(7);
+        assert!(chat.history(Some(7)).is_empty());
+    }
+}
@@ -6,7 +6,7 @@ use tokio::{
     time::sleep,
 };
 
-use crate::{AuthIntraMessage, ServerIntraMessage};
+use crate::{AuthIntraMessage, ServerIntraMessage, stats::AccountId};
 
 // This struct is to create a listener loop used to accept connections
 // and register them in the main game server.
@@ -72,11 +72,17 @@ impl ConnectionNode {
 
         println!("Now have some name {name}");
 
+        let Some(account) = AccountId::from_key(&client.remote_key) else {
+            println!("Static key from {client_addr} isn't a public key, close connection");
+            return;
+        };
+
         let (client_sender, sender_channel) = mpsc::unbounded_channel::<ServerMessage>();
 
         event_sender
             .send(ServerIntraMessage::RegisterUser(
                 name,
+                account,
                 client_addr,
                 client_sender,
             ))
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use bincode::{Decode, Encode, config::Configuration};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    comms::{ClientGameCommand, LobbyPlayer, RoomVisibility, ServerMessage},
+    game_state::{GameStartState, GameType, GameUserState},
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{Instant, sleep_until},
+};
+
+use crate::{
+    GameServerMessage, GameServerState, GameServerStateUpdate, PlayerState, ServerGameCommand,
+    ServerIntraMessage,
+    stats::{AccountId, GameResults, PlayerResult},
+};
+
+/// Who a state is being put together for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Viewer {
+    Player(u32),
+    Spectator,
+}
+
+/// The rules of a game, everything else about running a room is left to `GameRoom`.
+///
+/// Games only hold their own state, who is connected and who is host belong to the room.
+pub trait RoomGame: Send + 'static {
+    const GAME_TYPE: GameType;
+    const MAX_PLAYERS: usize;
+    /// Actions that only show up on the next timer, like Snake's turns, don't send a state
+    const QUIET_ACTIONS: bool = false;
+
+    /// Sent by clients as `ClientGameCommand::Raw`
+    type Action: Decode<()>;
+    /// Everything anyone in the room could see, worked out once for each update
+    type State: Clone;
+    /// What each user is actually sent
+    type Message: Encode;
+
+    fn start_state(&self) -> GameStartState;
+
+    /// Everyone with a seat, in seat order
+    fn seated(&self) -> Vec<u32>;
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer>;
+
+    /// Seats are taken while the game is being set up, as long as there's one free
+    fn can_seat(&self) -> bool {
+        self.start_state() == GameStartState::Setup && self.seated().len() < Self::MAX_PLAYERS
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState);
+
+    /// Only the host gets to start the game
+    fn start(&mut self) -> anyhow::Result<()>;
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        is_host: bool,
+        action: Self::Action,
+    ) -> anyhow::Result<()>;
+
+    fn user_left(&mut self, user_id: u32);
+
+    /// How everyone got on, asked for once when the game ends
+    fn results(&self) -> Vec<PlayerResult> {
+        vec![]
+    }
+
+    /// Chips won or lost since last asked, by user id
+    fn take_chip_changes(&mut self) -> Vec<(u32, i64)> {
+        vec![]
+    }
+
+    /// When the game moves on by itself if nobody does anything
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Called once `deadline` has passed.
+    /// Anything given back goes to everyone in place of the full state.
+    fn timer_fired(&mut self) -> Option<Self::Message> {
+        None
+    }
+
+    /// Takes the actions since the last state with it
+    fn client_state(&mut self, host_user: u32) -> Self::State;
+
+    fn set_invite_code(state: &mut Self::State, invite_code: String);
+
+    /// Cuts the state down to what the viewer is allowed to see,
+    ///  anyone's hand, cup, fleet or key that isn't theirs is kept back here
+    fn project(&self, state: Self::State, viewer: Viewer) -> Self::Message;
+}
+
+/// Everything needed to open a new room, whatever game ends up being played in it
+pub struct NewRoom<'a> {
+    pub game_id: u32,
+    pub host_id: u32,
+    pub host: &'a PlayerState,
+    pub lobby_name: String,
+    pub invite_code: String,
+    pub visibility: RoomVisibility,
+    pub service_sender: UnboundedSender<ServerIntraMessage>,
+}
+
+impl NewRoom<'_> {
+    /// The game is made from the room's rng, the seed is logged so a game can be reproduced
+    pub fn launch<G: RoomGame>(
+        self,
+        new_game: impl FnOnce(ChaCha8Rng) -> anyhow::Result<G>,
+    ) -> anyhow::Result<GameServerState> {
+        let seed: u64 = rand::random();
+
+        println!(
+            "Creating {} game {} with seed {seed}",
+            G::GAME_TYPE.name(),
+            self.game_id
+        );
+
+        let mut room = GameRoom {
+            id: self.game_id,
+            lobby_name: self.lobby_name.clone(),
+            game: new_game(ChaCha8Rng::seed_from_u64(seed))?,
+            user_senders: HashMap::from([(self.host_id, self.host.sender.clone())]),
+            spectator_senders: HashMap::new(),
+            accounts: HashMap::from([(self.host_id, self.host.account)]),
+            host_user: self.host_id,
+            invite_code: self.invite_code,
+            listed_state: GameStartState::Setup,
+            started_at: None,
+            results_reported: false,
+        };
+        room.game.seat(self.host_id, self.host);
+
+        let _x = self.host.sender.send(ServerMessage::JoinedGame(
+            self.lobby_name,
+            G::GAME_TYPE,
+            GameUserState::Active,
+        ));
+
+        let (send_channel, receive_channel) = mpsc::unbounded_channel::<GameServerMessage>();
+
+        let state = GameServerState {
+            name: room.lobby_name.clone(),
+            player_count: room.game.seated().len() as u32,
+            game_type: G::GAME_TYPE,
+            channel: send_channel,
+            start_state: room.game.start_state(),
+            max_players: G::MAX_PLAYERS as u32,
+            spectator_count: 0,
+            players: room.game.lobby_players(),
+            visibility: self.visibility,
+            invite_code: room.invite_code.clone(),
+            locked: false,
+            seed,
+        };
+
+        room.update_user_state();
+
+        let service_sender = self.service_sender;
+        tokio::spawn(async move {
+            room.start_server(receive_channel, service_sender).await;
+        });
+
+        Ok(state)
+    }
+}
+
+/// Runs a game on its own task the same way as `ServerUno`.
+/// Joining, watching, leaving, passing on the host and reporting results
+///  work the same whatever the game, only the rules come from `G`.
+pub struct GameRoom<G: RoomGame> {
+    id: u32,
+    lobby_name: String,
+    game: G,
+    user_senders: HashMap<u32, UnboundedSender<ServerMessage>>,
+    spectator_senders: HashMap<u32, UnboundedSender<ServerMessage>>,
+    /// Everyone who ever took a seat, results still count for those who left
+    accounts: HashMap<u32, AccountId>,
+    host_user: u32,
+    invite_code: String,
+    /// What the lobby was last told, it hears again whenever the game moves on
+    listed_state: GameStartState,
+    started_at: Option<Instant>,
+    results_reported: bool,
+}
+
+impl<G: RoomGame> GameRoom<G> {
+    async fn start_server(
+        mut self,
+        mut receiver_channel: UnboundedReceiver<GameServerMessage>,
+        service_sender: UnboundedSender<ServerIntraMessage>,
+    ) {
+        loop {
+            // Games with a clock move on even if nobody sends anything
+            let msg = tokio::select! {
+                msg = receiver_channel.recv() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    msg
+                }
+                _ = Self::wait_for_timer(self.game.deadline()) => {
+                    match self.game.timer_fired() {
+                        Some(msg) => self.broadcast(&msg),
+                        None => {
+                            self.report(&service_sender);
+                            self.update_user_state();
+                        }
+                    }
+                    continue;
+                }
+            };
+
+            let cmd = match msg.command {
+                ServerGameCommand::UserJoin(user) => {
+                    if !self.game.can_seat() {
+                        let _x = user.sender.send(ServerMessage::JoinFailed(
+                            "The game is full or has already started".to_string(),
+                        ));
+                        continue;
+                    }
+
+                    let _x = user.sender.send(ServerMessage::JoinedGame(
+                        self.lobby_name.clone(),
+                        G::GAME_TYPE,
+                        GameUserState::Active,
+                    ));
+
+                    self.user_senders.insert(msg.user_id, user.sender.clone());
+                    self.accounts.insert(msg.user_id, user.account);
+                    self.game.seat(msg.user_id, &user);
+
+                    let _x = service_sender
+                        .send(ServerIntraMessage::UserJoinedGame(msg.user_id, self.id));
+                    let _x = service_sender.send(self.service_update_state());
+
+                    self.report(&service_sender);
+                    self.update_user_state();
+                    continue;
+                }
+                ServerGameCommand::UserSpectate(user) => {
+                    let _x = user.sender.send(ServerMessage::JoinedGame(
+                        self.lobby_name.clone(),
+                        G::GAME_TYPE,
+                        GameUserState::Spectator,
+                    ));
+
+                    self.spectator_senders.insert(msg.user_id, user.sender);
+
+                    let _x = service_sender
+                        .send(ServerIntraMessage::UserJoinedGame(msg.user_id, self.id));
+                    let _x = service_sender.send(self.service_update_state());
+
+                    self.update_user_state();
+                    continue;
+                }
+                ServerGameCommand::Cmd(cmd) => cmd,
+            };
+
+            // Spectators can only watch, and leave
+            if self.spectator_senders.contains_key(&msg.user_id) {
+                if let ClientGameCommand::Leave = cmd {
+                    self.spectator_senders.remove(&msg.user_id);
+
+                    let _x =
+                        service_sender.send(ServerIntraMessage::UserLeftGame(msg.user_id, self.id));
+                    let _x = service_sender.send(self.service_update_state());
+                }
+                continue;
+            }
+
+            match cmd {
+                ClientGameCommand::Start => {
+                    if let Err(err) = self.start_game(msg.user_id) {
+                        println!(
+                            "Couldn't start {} game {} : {err:?}",
+                            G::GAME_TYPE.name(),
+                            self.id
+                        );
+                        continue;
+                    }
+                }
+                ClientGameCommand::Raw(raw_data) => {
+                    let Ok((action, _)) = bincode::decode_from_slice::<G::Action, Configuration>(
+                        &raw_data,
+                        bincode::config::standard(),
+                    )
+                    .inspect_err(|err| {
+                        println!(
+                            "Failed to decode {} action for user {} : {err:?}",
+                            G::GAME_TYPE.name(),
+                            msg.user_id
+                        );
+                    }) else {
+                        continue;
+                    };
+
+                    let is_host = msg.user_id == self.host_user;
+                    if let Err(err) = self.game.handle_action(msg.user_id, is_host, action) {
+                        println!(
+                            "Rejected {} action from user {} : {err:?}",
+                            G::GAME_TYPE.name(),
+                            msg.user_id
+                        );
+                        continue;
+                    }
+
+                    if G::QUIET_ACTIONS {
+                        continue;
+                    }
+                }
+                ClientGameCommand::Leave => {
+                    self.game.user_left(msg.user_id);
+                    let _ = self.user_senders.remove(&msg.user_id);
+                    self.migrate_host();
+
+                    let _x =
+                        service_sender.send(ServerIntraMessage::UserLeftGame(msg.user_id, self.id));
+                    let _x = service_sender.send(self.service_update_state());
+                }
+            }
+
+            self.report(&service_sender);
+
+            // Nobody is in the server at this point
+            if self.user_senders.is_empty() {
+                break;
+            }
+
+            self.update_user_state();
+        }
+
+        // Nobody is left to watch, spectators are sent back to the lobby
+        for (user_id, sender) in self.spectator_senders.drain() {
+            let _ = sender.send(ServerMessage::RemovedFromGame(
+                "Everyone has left the game".to_string(),
+            ));
+            let _ = service_sender.send(ServerIntraMessage::UserLeftGame(user_id, self.id));
+        }
+
+        let _ = service_sender.send(ServerIntraMessage::GameFinished(self.id));
+    }
+
+    async fn wait_for_timer(deadline: Option<Instant>) {
+        match deadline {
+            Some(deadline) => sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn start_game(&mut self, user_id: u32) -> anyhow::Result<()> {
+        if user_id != self.host_user {
+            return Err(anyhow::anyhow!("Only the host can start the game"));
+        }
+
+        if self.game.start_state() != GameStartState::Setup {
+            return Err(anyhow::anyhow!("The game has already started"));
+        }
+
+        self.game.start()?;
+        self.started_at = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// When the host goes, the room is handed to the next person still in it
+    fn migrate_host(&mut self) {
+        if self.user_senders.contains_key(&self.host_user) {
+            return;
+        }
+
+        if let Some(next_host) = self
+            .game
+            .seated()
+            .into_iter()
+            .find(|id| self.user_senders.contains_key(id))
+            .or_else(|| self.user_senders.keys().min().copied())
+        {
+            self.host_user = next_host;
+        }
+    }
+
+    /// Anything the main server should hear about after the game has moved on
+    fn report(&mut self, service_sender: &UnboundedSender<ServerIntraMessage>) {
+        if let Some(results) = self.take_results() {
+            let _x = service_sender.send(ServerIntraMessage::GameResults(results));
+        }
+
+        let chip_changes: Vec<(AccountId, i64)> = self
+            .game
+            .take_chip_changes()
+            .into_iter()
+            .filter_map(|(user_id, change)| Some((*self.accounts.get(&user_id)?, change)))
+            .collect();
+        if !chip_changes.is_empty() {
+            let _x = service_sender.send(ServerIntraMessage::ChipsSettled(chip_changes));
+        }
+
+        if self.game.start_state() != self.listed_state {
+            let _x = service_sender.send(self.service_update_state());
+        }
+    }
+
+    /// Once the game is over, how everyone got on is handed back once for the stats
+    fn take_results(&mut self) -> Option<GameResults> {
+        if self.game.start_state() != GameStartState::Ending || self.results_reported {
+            return None;
+        }
+        self.results_reported = true;
+
+        Some(GameResults {
+            game_type: G::GAME_TYPE,
+            game_secs: self
+                .started_at
+                .map(|started| started.elapsed().as_secs())
+                .unwrap_or(0),
+            players: self
+                .game
+                .results()
+                .into_iter()
+                .filter_map(|result| Some((*self.accounts.get(&result.user_id)?, result)))
+                .collect(),
+        })
+    }
+
+    fn service_update_state(&mut self) -> ServerIntraMessage {
+        self.listed_state = self.game.start_state();
+
+        ServerIntraMessage::UpdateGameServer(
+            self.id,
+            GameServerStateUpdate {
+                name: self.lobby_name.clone(),
+                player_count: self.game.seated().len() as u32,
+                spectator_count: self.spectator_senders.len() as u32,
+                players: self.game.lobby_players(),
+                game_type: G::GAME_TYPE,
+                start_state: self.listed_state,
+                locked: false,
+            },
+        )
+    }
+
+    fn update_user_state(&mut self) {
+        let state = self.game.client_state(self.host_user);
+
+        for (user_id, sender) in self.user_senders.iter().chain(&self.spectator_senders) {
+            let mut state = state.clone();
+
+            let viewer = if self.user_senders.contains_key(user_id) {
+                G::set_invite_code(&mut state, self.invite_code.clone());
+                Viewer::Player(*user_id)
+            } else {
+                Viewer::Spectator
+            };
+
+            Self::send(*user_id, sender, &self.game.project(state, viewer));
+        }
+    }
+
+    /// The same message to everyone, nothing in it can be private
+    fn broadcast(&self, msg: &G::Message) {
+        for (user_id, sender) in self.user_senders.iter().chain(&self.spectator_senders) {
+            Self::send(*user_id, sender, msg);
+        }
+    }
+
+    fn send(user_id: u32, sender: &UnboundedSender<ServerMessage>, msg: &G::Message) {
+        let Ok(encoded) = bincode::encode_to_vec(msg, bincode::config::standard())
+            .inspect_err(|err| println!("Failed to encode state for user {user_id} : {err:?}"))
+        else {
+            return;
+        };
+
+        let _x = sender
+            .send(ServerMessage::GameState(encoded))
+            .inspect_err(|err| {
+                println!("Failed to send state to user {user_id} : {err:?}");
+            });
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+/// For the parts of a game where everyone acts at once instead of taking turns.
+///
+/// Whatever each player hands in is held here and nobody else gets to see it,
+///  the game only takes them out once everyone it's waiting on has made their choice.
+#[derive(Debug)]
+pub struct HiddenChoices<T> {
+    choices: HashMap<u32, T>,
+}
+
+impl<T> Default for HiddenChoices<T> {
+    fn default() -> Self {
+        HiddenChoices {
+            choices: HashMap::new(),
+        }
+    }
+}
+
+impl<T> HiddenChoices<T> {
+    /// A choice can't be changed once it's in
+    pub fn submit(&mut self, user_id: u32, choice: T) -> anyhow::Result<()> {
+        if self.choices.contains_key(&user_id) {
+            return Err(anyhow!("User {user_id} has already made their choice"));
+        }
+
+        self.choices.insert(user_id, choice);
+        Ok(())
+    }
+
+    pub fn has_chosen(&self, user_id: u32) -> bool {
+        self.choices.contains_key(&user_id)
+    }
+
+    /// Only for showing a player their own choice back to them
+    pub fn get(&self, user_id: u32) -> Option<&T> {
+        self.choices.get(&user_id)
+    }
+
+    /// Anyone who leaves before everyone's in shouldn't be counted
+    pub fn withdraw(&mut self, user_id: u32) {
+        self.choices.remove(&user_id);
+    }
+
+    pub fn all_chosen(&self, mut user_ids: impl Iterator<Item = u32>) -> bool {
+        user_ids.all(|user_id| self.choices.contains_key(&user_id))
+    }
+
+    /// Everything handed in so far, ready for the next round of choices
+    pub fn reveal(&mut self) -> HashMap<u32, T> {
+        std::mem::take(&mut self.choices)
+    }
+}
@@ -0,0 +1,33 @@
+use rand::seq::IndexedRandom;
+
+/// No 0 / O or 1 / I, codes get read out loud and typed in by hand
+const CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+const CODE_LENGTH: usize = 6;
+
+/// Keeps trying until it finds a code no other room is using.
+/// With 32^6 codes that's almost always the first go.
+pub fn generate(in_use: impl Fn(&str) -> bool) -> String {
+    let mut rng = rand::rng();
+
+    loop {
+        let code: String = (0..CODE_LENGTH)
+            .map(|_| {
+                *CODE_CHARS
+                    .choose(&mut rng)
+                    .expect("Code chars aren't empty") as char
+            })
+            .collect();
+
+        if !in_use(&code) {
+            return code;
+        }
+    }
+}
+
+/// Codes are typed in by people, spaces and case don't matter
+pub fn normalise(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
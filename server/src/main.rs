@@ -1,19 +1,68 @@
-use crate::{connection_receiver::ConnectionReceiver, server_uno::ServerUno};
+use crate::{
+    chat::ChatStore,
+    connection_receiver::ConnectionReceiver,
+    game_room::NewRoom,
+    matchmaking::{MATCH_TICK, Match, Matchmaker},
+    server_battleship::ServerBattleship,
+    server_blackjack::ServerBlackjack,
+    server_chess::ServerChess,
+    server_codenames::ServerCodenames,
+    server_connect_four::ServerConnectFour,
+    server_eights::ServerEights,
+    server_hearts::ServerHearts,
+    server_holdem::ServerHoldem,
+    server_liars_dice::ServerLiarsDice,
+    server_snake::ServerSnake,
+    server_uno::ServerUno,
+    stats::{AccountId, GameResults, StatsStore},
+    uno_variants::UnoVariants,
+};
+use anyhow::anyhow;
 use rpc::{
-    comms::{ClientAuthedCommand, ClientGameCommand, ClientLobbyState, LobbyGame, ServerMessage},
+    chat::{ChatChannel, ChatMessage},
+    comms::{
+        ClientAuthedCommand, ClientGameCommand, ClientLobbyState, LobbyGame, LobbyPlayer,
+        RoomVisibility, ServerMessage,
+    },
     game_state::{GameStartState, GameType},
 };
-use std::{collections::HashMap, net::SocketAddr};
+use std::{collections::HashMap, net::SocketAddr, time::Instant};
 use tokio::sync::mpsc::{self, UnboundedSender};
 
+mod chat;
 mod connection_receiver;
+mod game_room;
+mod hidden_choices;
+mod invite_codes;
+mod matchmaking;
+mod replays;
+mod server_battleship;
+mod server_blackjack;
+mod server_chess;
+mod server_codenames;
+mod server_connect_four;
+mod server_eights;
+mod server_hearts;
+mod server_holdem;
+mod server_liars_dice;
+mod server_snake;
 mod server_uno;
+mod stats;
+#[cfg(test)]
+mod test_cards;
+mod uno_bot;
+mod uno_variants;
 
 struct TempestServer;
 
 #[derive(Debug)]
 pub enum ServerIntraMessage {
-    RegisterUser(String, SocketAddr, UnboundedSender<ServerMessage>),
+    RegisterUser(
+        String,
+        AccountId,
+        SocketAddr,
+        UnboundedSender<ServerMessage>,
+    ),
     Auth(AuthIntraMessage),
     UpdateUserLobbies,
     Disconnected(SocketAddr),
@@ -21,6 +70,10 @@ pub enum ServerIntraMessage {
     UserJoinedGame(u32, u32),
     UserLeftGame(u32, u32),
     GameFinished(u32),
+    GameResults(GameResults),
+    /// Blackjack winnings and losses, sent after every round
+    ChipsSettled(Vec<(AccountId, i64)>),
+    MatchmakingTick,
 }
 
 #[derive(Debug)]
@@ -33,9 +86,15 @@ pub struct AuthIntraMessage {
 #[derive(Debug, Clone)]
 pub struct PlayerState {
     pub name: String,
+    /// What their stats are kept against, the name is just for show
+    pub account: AccountId,
     pub addr: SocketAddr,
     pub sender: UnboundedSender<ServerMessage>,
     pub game_id: Option<u32>,
+    /// Copied from the stats when the user registers and after each of their games
+    pub rating: u32,
+    /// Copied the same way as the rating, only Blackjack spends them
+    pub chips: u64,
 }
 
 #[derive(Debug)]
@@ -45,6 +104,14 @@ pub struct GameServerState {
     pub game_type: GameType,
     pub channel: UnboundedSender<GameServerMessage>,
     pub start_state: GameStartState,
+    pub max_players: u32,
+    pub spectator_count: u32,
+    pub players: Vec<LobbyPlayer>,
+    pub visibility: RoomVisibility,
+    pub invite_code: String,
+    pub locked: bool,
+    /// The seed driving the game's rng, kept so a game can be replayed
+    pub seed: u64,
 }
 
 /// Maybe switch this out and not store the channel
@@ -53,8 +120,11 @@ pub struct GameServerState {
 pub struct GameServerStateUpdate {
     pub name: String,
     pub player_count: u32,
+    pub spectator_count: u32,
+    pub players: Vec<LobbyPlayer>,
     pub game_type: GameType,
     pub start_state: GameStartState,
+    pub locked: bool,
 }
 
 #[derive(Debug)]
@@ -68,6 +138,7 @@ pub enum ServerGameCommand {
     // Start,
     // End,
     UserJoin(PlayerState),
+    UserSpectate(PlayerState),
     Cmd(ClientGameCommand),
 }
 
@@ -89,6 +160,18 @@ impl TempestServer {
         //  for this use case.
         let mut last_id: u32 = 0;
 
+        let mut stats = StatsStore::load().await;
+        let stats_saver = stats::spawn_saver();
+        let codenames_words = server_codenames::load_words().await;
+        let unavailable_games = if codenames_words.is_empty() {
+            vec![GameType::Codenames]
+        } else {
+            vec![]
+        };
+        let uno_variants = uno_variants::load().await;
+        let mut matchmaker = Matchmaker::default();
+        let mut chat = ChatStore::default();
+
         let (event_sender, mut event_receiver) = mpsc::unbounded_channel::<ServerIntraMessage>();
 
         {
@@ -97,20 +180,43 @@ impl TempestServer {
             tokio::spawn(async move { ConnectionReceiver::start_listener(event_sender).await });
         }
 
+        {
+            let event_sender = event_sender.clone();
+
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(MATCH_TICK);
+                loop {
+                    interval.tick().await;
+                    if event_sender
+                        .send(ServerIntraMessage::MatchmakingTick)
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+
         while let Some(msg) = event_receiver.recv().await {
             match msg {
-                ServerIntraMessage::RegisterUser(name, addr, sender) => {
+                ServerIntraMessage::RegisterUser(name, account, addr, sender) => {
                     last_id += 1;
                     let id = last_id;
 
                     let _ = sender.send(ServerMessage::AuthResponse(id));
+                    stats.register(account, &name);
+                    let rating = stats.rating(&account);
+                    let chips = stats.chips(&account);
                     users.insert(
                         id,
                         PlayerState {
                             name,
+                            account,
                             addr,
                             sender,
                             game_id: None,
+                            rating,
+                            chips,
                         },
                     );
 
@@ -125,27 +231,93 @@ impl TempestServer {
 
                         println!("Found user {user:?}");
 
+                        // Going into a game some other way takes the user out of the queue
+                        if matches!(
+                            msg.message,
+                            ClientAuthedCommand::CreateGame(_, _, _)
+                                | ClientAuthedCommand::JoinGame(_, _)
+                                | ClientAuthedCommand::SpectateGame(_, _)
+                                | ClientAuthedCommand::JoinByCode(_)
+                        ) {
+                            matchmaker.leave(msg.user_id);
+                        }
+
                         match msg.message {
-                            ClientAuthedCommand::CreateGame(lobby_name, game_type) => {
+                            ClientAuthedCommand::CreateGame(lobby_name, game_type, visibility) => {
                                 if user.game_id.is_some() {
                                     println!("!!! >> User created Game when in game");
                                 }
 
+                                // The idx comes from the client, it might not be a variant this server has
+                                if matches!(game_type, GameType::UnoVariant(_))
+                                    && uno_variants.rules(game_type).is_none()
+                                {
+                                    let _ = user.sender.send(ServerMessage::JoinFailed(
+                                        "That Uno variant isn't on this server".to_string(),
+                                    ));
+                                    continue;
+                                }
+
+                                if unavailable_games.contains(&game_type) {
+                                    let _ = user.sender.send(ServerMessage::JoinFailed(format!(
+                                        "{} isn't available on this server",
+                                        game_type.name()
+                                    )));
+                                    continue;
+                                }
+
                                 println!("Now Create New Game {lobby_name} -> {game_type:?}");
                                 last_id += 1;
                                 let game_id = last_id;
 
-                                // I really need to fix these switch cases.
-                                // These big sections should be moved to their own functions
-                                // Having 10 indentations is a bit crazy
+                                let invite_code = invite_codes::generate(|code| {
+                                    games.values().any(|game| game.invite_code == code)
+                                });
+
+                                let room = NewRoom {
+                                    game_id,
+                                    host_id: msg.user_id,
+                                    host: user,
+                                    lobby_name,
+                                    invite_code,
+                                    visibility,
+                                    service_sender: event_sender.clone(),
+                                };
+
                                 let server = match game_type {
-                                    GameType::Uno => ServerUno::create(
-                                        game_id,
-                                        msg.user_id,
-                                        user,
-                                        lobby_name,
-                                        event_sender.clone(),
-                                    ),
+                                    GameType::Uno | GameType::UnoVariant(_) => {
+                                        match uno_variants.rules(game_type) {
+                                            Some(rules) => {
+                                                ServerUno::create(room, game_type, rules.clone())
+                                            }
+                                            None => Err(anyhow!("No such Uno variant")),
+                                        }
+                                    }
+                                    GameType::CrazyEights => {
+                                        room.launch(|rng| Ok(ServerEights::new(rng)))
+                                    }
+                                    GameType::Holdem => {
+                                        room.launch(|rng| Ok(ServerHoldem::new(rng)))
+                                    }
+                                    GameType::Hearts => {
+                                        room.launch(|rng| Ok(ServerHearts::new(rng)))
+                                    }
+                                    GameType::ConnectFour => {
+                                        room.launch(|rng| Ok(ServerConnectFour::new(rng)))
+                                    }
+                                    GameType::Chess => room.launch(|rng| Ok(ServerChess::new(rng))),
+                                    GameType::Snake => room.launch(|rng| Ok(ServerSnake::new(rng))),
+                                    GameType::LiarsDice => {
+                                        room.launch(|rng| Ok(ServerLiarsDice::new(rng)))
+                                    }
+                                    GameType::Codenames => room
+                                        .launch(|rng| ServerCodenames::new(rng, &codenames_words)),
+                                    GameType::Blackjack => {
+                                        room.launch(|rng| Ok(ServerBlackjack::new(rng)))
+                                    }
+                                    GameType::Battleship => {
+                                        room.launch(|rng| Ok(ServerBattleship::new(rng)))
+                                    }
                                 };
 
                                 match server {
@@ -160,7 +332,10 @@ impl TempestServer {
                                     }
                                     Err(err) => {
                                         println!("Failed to create Game Server {err:?}");
-                                        todo!("Handle Game Server Creation Fail")
+                                        let _ = user
+                                            .sender
+                                            .send(ServerMessage::JoinFailed(err.to_string()));
+                                        continue;
                                     }
                                 }
                             }
@@ -187,27 +362,128 @@ impl TempestServer {
                                         );
                                     });
                             }
-                            ClientAuthedCommand::JoinGame(game_id) => {
+                            ClientAuthedCommand::JoinGame(game_id, secret) => {
+                                Self::enter_game(
+                                    msg.user_id,
+                                    user,
+                                    games.get(&game_id),
+                                    secret,
+                                    false,
+                                );
+                            }
+                            ClientAuthedCommand::SpectateGame(game_id, secret) => {
+                                Self::enter_game(
+                                    msg.user_id,
+                                    user,
+                                    games.get(&game_id),
+                                    secret,
+                                    true,
+                                );
+                            }
+                            ClientAuthedCommand::JoinByCode(code) => {
+                                let code = invite_codes::normalise(&code);
+                                let game = games.values().find(|game| game.invite_code == code);
+
+                                // Too late to take a seat, but the game can still be watched
+                                let spectate = game
+                                    .is_some_and(|game| game.start_state != GameStartState::Setup);
+
+                                Self::enter_game(msg.user_id, user, game, Some(code), spectate);
+                            }
+                            ClientAuthedCommand::ListReplays => {
+                                Self::send_replay_list(user.sender.clone());
+                            }
+                            ClientAuthedCommand::FetchReplay(replay_id) => {
+                                Self::send_replay(replay_id, user.sender.clone());
+                            }
+                            ClientAuthedCommand::FetchProfile(name) => {
+                                let profile = stats.profile(&name);
+                                let _ = user.sender.send(ServerMessage::Profile(name, profile));
+                            }
+                            ClientAuthedCommand::FetchLeaderboard(game_type) => {
+                                let _ = user.sender.send(ServerMessage::Leaderboard(
+                                    game_type,
+                                    stats.leaderboard(game_type),
+                                ));
+                            }
+                            ClientAuthedCommand::JoinQueue(request) => {
                                 if user.game_id.is_some() {
-                                    println!(
-                                        "User tried to Join a game when already in a game {} -> {} : {:?}",
-                                        msg.user_id, game_id, user.game_id
-                                    );
+                                    let _ = user.sender.send(ServerMessage::QueueError(
+                                        "Already in a game".to_string(),
+                                    ));
                                     continue;
                                 }
 
-                                let Some(game) = games.get(&game_id) else {
-                                    println!(
-                                        "User tired to join a non existing game {} -> {}",
-                                        msg.user_id, game_id
-                                    );
+                                let size = if request.game_type.supports_quick_play() {
+                                    ServerUno::quick_play_size(&request.rules)
+                                } else {
+                                    Err(anyhow!("No quick play for {}", request.game_type.name()))
+                                };
+
+                                match size {
+                                    Ok(size) => {
+                                        matchmaker.join(msg.user_id, user.rating, request, size);
+                                        let _ =
+                                            event_sender.send(ServerIntraMessage::MatchmakingTick);
+                                    }
+                                    Err(err) => {
+                                        println!(
+                                            "User {} queued with bad rules {err:?}",
+                                            msg.user_id
+                                        );
+                                        let _ = user.sender.send(ServerMessage::QueueError(
+                                            "Those rules can't be queued for".to_string(),
+                                        ));
+                                    }
+                                }
+                            }
+                            ClientAuthedCommand::LeaveQueue => {
+                                matchmaker.leave(msg.user_id);
+                            }
+                            ClientAuthedCommand::Chat(channel, text) => {
+                                let room = match channel {
+                                    ChatChannel::Lobby => None,
+                                    ChatChannel::Room => user.game_id,
+                                };
+
+                                if channel == ChatChannel::Room && room.is_none() {
+                                    let _ = user.sender.send(ServerMessage::ChatRejected(
+                                        "Not in a game".to_string(),
+                                    ));
                                     continue;
+                                }
+
+                                match chat.post(
+                                    msg.user_id,
+                                    &user.name,
+                                    room,
+                                    &text,
+                                    Instant::now(),
+                                ) {
+                                    Ok(message) => Self::send_chat(&users, room, message),
+                                    Err(err) => {
+                                        let _ = user
+                                            .sender
+                                            .send(ServerMessage::ChatRejected(err.to_string()));
+                                    }
+                                }
+                            }
+                            ClientAuthedCommand::FetchChat(channel) => {
+                                let room = match channel {
+                                    ChatChannel::Lobby => None,
+                                    ChatChannel::Room => user.game_id,
                                 };
 
-                                let _x = game.channel.send(GameServerMessage {
-                                    user_id: msg.user_id,
-                                    command: ServerGameCommand::UserJoin(user.clone()),
-                                });
+                                // Someone not in a game has no room to see
+                                let history = if channel == ChatChannel::Room && room.is_none() {
+                                    vec![]
+                                } else {
+                                    chat.history(room)
+                                };
+
+                                let _ = user
+                                    .sender
+                                    .send(ServerMessage::ChatHistory(channel, history));
                             }
                         }
                     }
@@ -215,22 +491,8 @@ impl TempestServer {
                 // Realistically this would be better if I just sent down the specific
                 // game / user count updates
                 ServerIntraMessage::UpdateUserLobbies => {
-                    let lobby_state = ClientLobbyState {
-                        player_count: users.len(),
-                        games: games
-                            .iter()
-                            .filter(|(_, game)| {
-                                game.start_state == GameStartState::Setup && game.player_count < 4
-                            })
-                            .map(|(game_id, game)| LobbyGame {
-                                name: game.name.clone(),
-                                id: *game_id,
-                                game_type: game.game_type,
-                                start_state: game.start_state,
-                                active_players: game.player_count,
-                            })
-                            .collect(),
-                    };
+                    let lobby_state =
+                        Self::lobby_state(users.len(), &games, &uno_variants, &unavailable_games);
 
                     for (_, state) in users.iter() {
                         if state.game_id.is_none() {
@@ -247,20 +509,22 @@ impl TempestServer {
                         let user_id = *user_id;
                         let game_id = user.game_id;
 
+                        chat.forget_user(user_id);
+
                         println!(
                             "Disconnect User~{user_id}: {} game: {:?}",
                             user.name, game_id
                         );
 
-                        if let Some(game_id) = game_id {
-                            if let Some(game) = games.get(&game_id) {
-                                let _ = game.channel.send(GameServerMessage {
-                                    user_id,
-                                    command: ServerGameCommand::Cmd(ClientGameCommand::Leave),
-                                }).inspect_err(|err| {
-                                    println!("Failed to send leave message to game {game_id} for disconnected user {user_id}: {err:?}");
-                                });
-                            }
+                        if let Some(game_id) = game_id
+                            && let Some(game) = games.get(&game_id)
+                        {
+                            let _ = game.channel.send(GameServerMessage {
+                                user_id,
+                                command: ServerGameCommand::Cmd(ClientGameCommand::Leave),
+                            }).inspect_err(|err| {
+                                println!("Failed to send leave message to game {game_id} for disconnected user {user_id}: {err:?}");
+                            });
                         }
                     } else {
                         println!(
@@ -269,6 +533,7 @@ impl TempestServer {
                     }
 
                     users.retain(|_, user| user.addr != socket_addr);
+                    matchmaker.retain_users(|user_id| users.contains_key(&user_id));
 
                     let _ = event_sender.send(ServerIntraMessage::UpdateUserLobbies);
                 }
@@ -280,7 +545,10 @@ impl TempestServer {
 
                     game.name = updated.name;
                     game.player_count = updated.player_count;
+                    game.spectator_count = updated.spectator_count;
+                    game.players = updated.players;
                     game.start_state = updated.start_state;
+                    game.locked = updated.locked;
 
                     let _ = event_sender.send(ServerIntraMessage::UpdateUserLobbies);
                 }
@@ -305,22 +573,8 @@ impl TempestServer {
                     }
                     user.game_id = None;
 
-                    let lobby_state = ClientLobbyState {
-                        player_count,
-                        games: games
-                            .iter()
-                            .filter(|(_, game)| {
-                                game.start_state == GameStartState::Setup && game.player_count < 4
-                            })
-                            .map(|(game_id, game)| LobbyGame {
-                                name: game.name.clone(),
-                                id: *game_id,
-                                game_type: game.game_type,
-                                start_state: game.start_state,
-                                active_players: game.player_count,
-                            })
-                            .collect(),
-                    };
+                    let lobby_state =
+                        Self::lobby_state(player_count, &games, &uno_variants, &unavailable_games);
 
                     let _ = user
                         .sender
@@ -329,12 +583,269 @@ impl TempestServer {
                             println!("Failed to send message to user {user_id} : {err:?}")
                         });
                 }
-                ServerIntraMessage::GameFinished(game_id) => {
-                    if games.remove(&game_id).is_none() {
+                ServerIntraMessage::GameFinished(game_id) => match games.remove(&game_id) {
+                    Some(game) => {
+                        println!("Game {game_id} finished, played with seed {}", game.seed);
+                        chat.close_room(game_id);
+                    }
+                    None => {
                         println!("Received game end message for game that doesn't exist");
                     }
+                },
+                ServerIntraMessage::MatchmakingTick => {
+                    let now = Instant::now();
+
+                    for found in matchmaker.find_matches(now) {
+                        last_id += 1;
+                        let game_id = last_id;
+
+                        Self::create_match(
+                            game_id,
+                            found,
+                            &mut users,
+                            &mut games,
+                            &uno_variants,
+                            &event_sender,
+                        );
+                    }
+
+                    for (user_id, status) in matchmaker.statuses(now) {
+                        if let Some(user) = users.get(&user_id) {
+                            let _ = user.sender.send(ServerMessage::QueueStatus(status));
+                        }
+                    }
+                }
+                ServerIntraMessage::GameResults(results) => {
+                    stats.record(&results);
+
+                    for user in users.values_mut() {
+                        if results
+                            .players
+                            .iter()
+                            .any(|(account, _)| *account == user.account)
+                        {
+                            user.rating = stats.rating(&user.account);
+                        }
+                    }
+
+                    stats.save(&stats_saver);
+                }
+                ServerIntraMessage::ChipsSettled(changes) => {
+                    stats.settle_chips(&changes);
+
+                    for user in users.values_mut() {
+                        if changes.iter().any(|(account, _)| *account == user.account) {
+                            user.chips = stats.chips(&user.account);
+                        }
+                    }
+
+                    stats.save(&stats_saver);
+                }
+            }
+        }
+    }
+
+    /// Public rooms are open to anyone, the rest need the password or invite code
+    fn enter_game(
+        user_id: u32,
+        user: &PlayerState,
+        game: Option<&GameServerState>,
+        secret: Option<String>,
+        spectate: bool,
+    ) {
+        let fail = |reason: &str| {
+            let _ = user
+                .sender
+                .send(ServerMessage::JoinFailed(reason.to_string()));
+        };
+
+        if user.game_id.is_some() {
+            println!(
+                "User tried to enter a game when already in a game {user_id} : {:?}",
+                user.game_id
+            );
+            return fail("Already in a game");
+        }
+
+        let Some(game) = game else {
+            println!("User tried to enter a non existing game {user_id}");
+            return fail("That game doesn't exist");
+        };
+
+        if !Self::can_enter(&game.visibility, &game.invite_code, secret.as_deref()) {
+            return fail("Wrong password or invite code");
+        }
+
+        let command = if spectate {
+            ServerGameCommand::UserSpectate(user.clone())
+        } else {
+            ServerGameCommand::UserJoin(user.clone())
+        };
+
+        let _x = game.channel.send(GameServerMessage { user_id, command });
+    }
+
+    /// Anyone can walk into a public room, a private one takes its password and
+    ///  a hidden one can only be found by its invite code, which also gets past a password
+    fn can_enter(visibility: &RoomVisibility, invite_code: &str, secret: Option<&str>) -> bool {
+        let by_code = secret.is_some_and(|secret| invite_codes::normalise(secret) == invite_code);
+
+        match visibility {
+            RoomVisibility::Public => true,
+            RoomVisibility::Private(password) => {
+                by_code || secret.is_some_and(|secret| secret == password)
+            }
+            RoomVisibility::Hidden => by_code,
+        }
+    }
+
+    /// Everyone in the match is sat down at a new table, if that goes wrong
+    ///  they're sent back to waiting in the lobby
+    fn create_match(
+        game_id: u32,
+        found: Match,
+        users: &mut HashMap<u32, PlayerState>,
+        games: &mut HashMap<u32, GameServerState>,
+        uno_variants: &UnoVariants,
+        event_sender: &UnboundedSender<ServerIntraMessage>,
+    ) {
+        let players: Vec<(u32, &PlayerState)> = found
+            .user_ids
+            .iter()
+            .filter_map(|user_id| users.get(user_id).map(|user| (*user_id, user)))
+            .collect();
+
+        let invite_code =
+            invite_codes::generate(|code| games.values().any(|game| game.invite_code == code));
+
+        let server = if found.game_type.supports_quick_play() {
+            ServerUno::create_matched(
+                game_id,
+                &players,
+                &found.rules,
+                uno_variants.standard().clone(),
+                found.bot_count,
+                invite_code,
+                event_sender.clone(),
+            )
+        } else {
+            Err(anyhow!("No quick play for {}", found.game_type.name()))
+        };
+
+        match server {
+            Ok(server) => {
+                println!("Matched {:?} into game {game_id}", found.user_ids);
+
+                for user_id in found.user_ids.iter() {
+                    if let Some(user) = users.get_mut(user_id) {
+                        user.game_id = Some(game_id);
+                    }
                 }
+                games.insert(game_id, server);
+
+                let _ = event_sender.send(ServerIntraMessage::UpdateUserLobbies);
             }
+            Err(err) => {
+                println!("Failed to create matched game {err:?}");
+
+                for (_, user) in players {
+                    let _ = user.sender.send(ServerMessage::QueueError(
+                        "Failed to create the game, please queue again".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Lobby messages go to everyone not in a game, room messages to everyone in that game
+    fn send_chat(users: &HashMap<u32, PlayerState>, room: Option<u32>, message: ChatMessage) {
+        for user in users.values().filter(|user| user.game_id == room) {
+            let _ = user.sender.send(ServerMessage::Chat(message.clone()));
+        }
+    }
+
+    /// Replays live on disk, reading them happens off the main loop
+    fn send_replay_list(sender: UnboundedSender<ServerMessage>) {
+        tokio::spawn(async move {
+            let msg = match replays::list().await {
+                Ok(list) => ServerMessage::ReplayList(list),
+                Err(err) => {
+                    println!("Failed to list replays {err:?}");
+                    ServerMessage::ReplayError("Replays are unavailable".to_string())
+                }
+            };
+
+            let _ = sender.send(msg);
+        });
+    }
+
+    /// The game is played back on the server, the client only gets the states to step through
+    fn send_replay(replay_id: String, sender: UnboundedSender<ServerMessage>) {
+        tokio::spawn(async move {
+            let frames = replays::load(&replay_id).await.and_then(|stored| {
+                let frames = match stored.game_type {
+                    GameType::Uno | GameType::UnoVariant(_) => bincode::encode_to_vec(
+                        ServerUno::replay_frames(&stored.data)?,
+                        bincode::config::standard(),
+                    )?,
+                    GameType::CrazyEights
+                    | GameType::Holdem
+                    | GameType::Hearts
+                    | GameType::ConnectFour
+                    | GameType::Chess
+                    | GameType::Snake
+                    | GameType::LiarsDice
+                    | GameType::Codenames
+                    | GameType::Blackjack
+                    | GameType::Battleship => {
+                        return Err(anyhow!("{} games aren't recorded", stored.game_type.name()));
+                    }
+                };
+                Ok((stored.game_type, frames))
+            });
+
+            let msg = match frames {
+                Ok((game_type, frames)) => ServerMessage::Replay(game_type, frames),
+                Err(err) => {
+                    println!("Failed to play back replay {replay_id} {err:?}");
+                    ServerMessage::ReplayError(format!("Could not load replay {replay_id}"))
+                }
+            };
+
+            let _ = sender.send(msg);
+        });
+    }
+
+    /// Games still being setup with a free seat can be joined, running games can be watched.
+    /// Hidden rooms are never listed, they can only be found with their invite code.
+    fn lobby_state(
+        player_count: usize,
+        games: &HashMap<u32, GameServerState>,
+        uno_variants: &UnoVariants,
+        unavailable_games: &[GameType],
+    ) -> ClientLobbyState {
+        ClientLobbyState {
+            player_count,
+            games: games
+                .iter()
+                .filter(|(_, game)| game.visibility != RoomVisibility::Hidden)
+                // A full room that hasn't started is still listed, it can be watched
+                .filter(|(_, game)| game.start_state != GameStartState::Ending)
+                .map(|(game_id, game)| LobbyGame {
+                    name: game.name.clone(),
+                    id: *game_id,
+                    game_type: game.game_type,
+                    start_state: game.start_state,
+                    active_players: game.player_count,
+                    max_players: game.max_players,
+                    spectator_count: game.spectator_count,
+                    players: game.players.clone(),
+                    private: matches!(game.visibility, RoomVisibility::Private(_)),
+                    locked: game.locked,
+                })
+                .collect(),
+            uno_variants: uno_variants.names(),
+            unavailable_games: unavailable_games.to_vec(),
         }
     }
 }
@@ -343,3 +854,61 @@ impl TempestServer {
 async fn main() {
     TempestServer::start_server().await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "K7QXM2";
+
+    fn private() -> RoomVisibility {
+        RoomVisibility::Private("hunter2".to_string())
+    }
+
+    #[test]
+    fn public_rooms_are_open() {
+        assert!(TempestServer::can_enter(
+            &RoomVisibility::Public,
+            CODE,
+            None
+        ));
+        assert!(TempestServer::can_enter(
+            &RoomVisibility::Public,
+            CODE,
+            Some("anything")
+        ));
+    }
+
+    #[test]
+    fn private_rooms_need_the_password() {
+        assert!(TempestServer::can_enter(&private(), CODE, Some("hunter2")));
+        assert!(!TempestServer::can_enter(&private(), CODE, Some("hunter3")));
+        assert!(!TempestServer::can_enter(&private(), CODE, None));
+    }
+
+    #[test]
+    fn invite_codes_skip_the_password() {
+        assert!(TempestServer::can_enter(&private(), CODE, Some(CODE)));
+        assert!(TempestServer::can_enter(&private(), CODE, Some("k7q xm2")));
+        assert!(!TempestServer::can_enter(&private(), CODE, Some("K7QXM3")));
+    }
+
+    #[test]
+    fn hidden_rooms_need_the_code() {
+        assert!(TempestServer::can_enter(
+            &RoomVisibility::Hidden,
+            CODE,
+            Some(CODE)
+        ));
+        assert!(!TempestServer::can_enter(
+            &RoomVisibility::Hidden,
+            CODE,
+            None
+        ));
+        assert!(!TempestServer::can_enter(
+            &RoomVisibility::Hidden,
+            CODE,
+            Some("hunter2")
+        ));
+    }
+}
@@ -0,0 +1,390 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use rpc::{
+    comms::{QueueStatus, QuickPlayRequest},
+    game_state::GameType,
+};
+
+/// How often the queue is checked for new matches
+pub const MATCH_TICK: Duration = Duration::from_secs(1);
+
+/// After this long a table that isn't full is started, as long as it has enough to play
+const PARTIAL_MATCH_WAIT: Duration = Duration::from_secs(20);
+
+/// After this long the empty seats are given to bots, if everyone at the table is fine with it
+const BOT_FILL_WAIT: Duration = Duration::from_secs(45);
+
+/// Players are only matched with others close to their rating to begin with,
+///  the longer someone waits the further out they'll look
+const BASE_RATING_SPREAD: u32 = 100;
+const RATING_SPREAD_PER_SEC: u32 = 10;
+
+/// The wait estimate is the average of the last few matches made
+const RECENT_WAITS: usize = 10;
+
+/// How many players a game needs, and how many make a full table
+#[derive(Debug, Clone, Copy)]
+pub struct TableSize {
+    pub min: usize,
+    pub ideal: usize,
+}
+
+#[derive(Debug)]
+struct QueuedPlayer {
+    user_id: u32,
+    rating: u32,
+    request: QuickPlayRequest,
+    size: TableSize,
+    joined_at: Instant,
+}
+
+/// A table ready to be created, the players in the order they queued
+#[derive(Debug)]
+pub struct Match {
+    pub game_type: GameType,
+    pub rules: Vec<u8>,
+    pub user_ids: Vec<u32>,
+    pub bot_count: usize,
+}
+
+/// Lives on the main loop, it's only looked at on a tick or when someone
+///  joins or leaves, so there's nothing to lock.
+#[derive(Debug, Default)]
+pub struct Matchmaker {
+    queue: Vec<QueuedPlayer>,
+    recent_waits: HashMap<GameType, VecDeque<Duration>>,
+}
+
+impl Matchmaker {
+    /// Queueing again just replaces what the user asked for before
+    pub fn join(&mut self, user_id: u32, rating: u32, request: QuickPlayRequest, size: TableSize) {
+        self.leave(user_id);
+
+        self.queue.push(QueuedPlayer {
+            user_id,
+            rating,
+            request,
+            size,
+            joined_at: Instant::now(),
+        });
+    }
+
+    /// Whether the user was queued
+    pub fn leave(&mut self, user_id: u32) -> bool {
+        let queued = self.queue.len();
+        self.queue.retain(|player| player.user_id != user_id);
+        queued != self.queue.len()
+    }
+
+    /// Drops anyone who isn't connected any more
+    pub fn retain_users(&mut self, is_connected: impl Fn(u32) -> bool) {
+        self.queue.retain(|player| is_connected(player.user_id));
+    }
+
+    /// The longest waiting player anchors each table, whoever is closest to
+    ///  their rating fills it up.
+    pub fn find_matches(&mut self, now: Instant) -> Vec<Match> {
+        let mut matches = vec![];
+
+        // Oldest first, they're the ones that should get a game soonest
+        self.queue.sort_by_key(|player| player.joined_at);
+
+        let mut anchor_idx = 0;
+        while anchor_idx < self.queue.len() {
+            let anchor = &self.queue[anchor_idx];
+            let waited = now.saturating_duration_since(anchor.joined_at);
+            let spread = BASE_RATING_SPREAD + RATING_SPREAD_PER_SEC * waited.as_secs() as u32;
+
+            let mut candidates: Vec<usize> = self
+                .queue
+                .iter()
+                .enumerate()
+                .filter(|(idx, player)| {
+                    *idx != anchor_idx
+                        && player.request.game_type == anchor.request.game_type
+                        && player.request.rules == anchor.request.rules
+                        && player.rating.abs_diff(anchor.rating) <= spread
+                })
+                .map(|(idx, _)| idx)
+                .collect();
+            candidates.sort_by_key(|idx| self.queue[*idx].rating.abs_diff(anchor.rating));
+            candidates.truncate(anchor.size.ideal - 1);
+            candidates.insert(0, anchor_idx);
+
+            let table = candidates.len();
+            let allow_bots = candidates
+                .iter()
+                .all(|idx| self.queue[*idx].request.allow_bots);
+
+            let bot_count = if table >= anchor.size.ideal {
+                Some(0)
+            } else if waited >= BOT_FILL_WAIT && allow_bots {
+                Some(anchor.size.ideal - table)
+            } else if waited >= PARTIAL_MATCH_WAIT && table >= anchor.size.min {
+                Some(0)
+            } else {
+                None
+            };
+
+            let Some(bot_count) = bot_count else {
+                anchor_idx += 1;
+                continue;
+            };
+
+            let game_type = anchor.request.game_type;
+            let rules = anchor.request.rules.clone();
+
+            // Taken out back to front so the indexes stay right
+            candidates.sort_unstable_by(|a, b| b.cmp(a));
+            let mut players: Vec<QueuedPlayer> = candidates
+                .into_iter()
+                .map(|idx| self.queue.remove(idx))
+                .collect();
+            players.sort_by_key(|player| player.joined_at);
+
+            let recent = self.recent_waits.entry(game_type).or_default();
+            for player in players.iter() {
+                recent.push_back(now.saturating_duration_since(player.joined_at));
+                if recent.len() > RECENT_WAITS {
+                    recent.pop_front();
+                }
+            }
+
+            matches.push(Match {
+                game_type,
+                rules,
+                user_ids: players.iter().map(|player| player.user_id).collect(),
+                bot_count,
+            });
+
+            // Whoever is now at this index hasn't been looked at yet
+        }
+
+        matches
+    }
+
+    /// Every queued user with how their wait is going
+    pub fn statuses(&self, now: Instant) -> Vec<(u32, QueueStatus)> {
+        self.queue
+            .iter()
+            .map(|player| {
+                let waited = now.saturating_duration_since(player.joined_at);
+
+                let queued = self
+                    .queue
+                    .iter()
+                    .filter(|other| {
+                        other.request.game_type == player.request.game_type
+                            && other.request.rules == player.request.rules
+                    })
+                    .count();
+
+                (
+                    player.user_id,
+                    QueueStatus {
+                        game_type: player.request.game_type,
+                        queued: queued as u32,
+                        waited_secs: waited.as_secs() as u32,
+                        estimated_wait_secs: self.estimate(player, queued, waited).as_secs() as u32,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// How much longer the player is likely to wait.
+    /// With no recent matches to go on it's however long until a smaller table would start.
+    fn estimate(&self, player: &QueuedPlayer, queued: usize, waited: Duration) -> Duration {
+        let expected = match self.recent_waits.get(&player.request.game_type) {
+            Some(recent) if !recent.is_empty() => {
+                recent.iter().sum::<Duration>() / recent.len() as u32
+            }
+            _ if queued >= player.size.min => PARTIAL_MATCH_WAIT,
+            _ => BOT_FILL_WAIT,
+        };
+
+        expected.saturating_sub(waited)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: TableSize = TableSize { min: 2, ideal: 4 };
+
+    fn request(game_type: GameType, allow_bots: bool) -> QuickPlayRequest {
+        QuickPlayRequest {
+            game_type,
+            rules: vec![],
+            allow_bots,
+        }
+    }
+
+    /// Queues each (user id, rating) for Uno in order, returns when they joined
+    fn queue(matchmaker: &mut Matchmaker, players: &[(u32, u32)], size: TableSize) -> Instant {
+        for (user_id, rating) in players {
+            matchmaker.join(*user_id, *rating, request(GameType::Uno, false), size);
+        }
+        Instant::now()
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn full_table_starts_straight_away() {
+        let mut matchmaker = Matchmaker::default();
+        let now = queue(
+            &mut matchmaker,
+            &[(1, 1000), (2, 1020), (3, 980), (4, 1050)],
+            TABLE,
+        );
+
+        let matches = matchmaker.find_matches(now);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].user_ids, vec![1, 2, 3, 4]);
+        assert_eq!(matches[0].bot_count, 0);
+        assert!(matchmaker.queue.is_empty());
+    }
+
+    #[test]
+    fn rating_window_widens_with_waiting() {
+        let mut matchmaker = Matchmaker::default();
+        let size = TableSize { min: 2, ideal: 2 };
+        let now = queue(&mut matchmaker, &[(1, 1000), (2, 1250)], size);
+
+        // 150 a side is needed, the window starts at 100 and grows 10 a second
+        assert!(matchmaker.find_matches(now).is_empty());
+        assert!(matchmaker.find_matches(now + secs(10)).is_empty());
+
+        let matches = matchmaker.find_matches(now + secs(16));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].user_ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn closest_ratings_fill_the_table() {
+        let mut matchmaker = Matchmaker::default();
+        let now = queue(
+            &mut matchmaker,
+            &[
+                (1, 1000),
+                (2, 1090),
+                (3, 1010),
+                (4, 1080),
+                (5, 1020),
+                (6, 1030),
+            ],
+            TABLE,
+        );
+
+        // Nobody gets more than the ideal table, the two furthest out are left waiting
+        let matches = matchmaker.find_matches(now);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].user_ids, vec![1, 3, 5, 6]);
+
+        assert!(matchmaker.find_matches(now + secs(5)).is_empty());
+
+        let matches = matchmaker.find_matches(now + PARTIAL_MATCH_WAIT);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].user_ids, vec![2, 4]);
+        assert_eq!(matches[0].bot_count, 0);
+    }
+
+    #[test]
+    fn partial_table_needs_the_minimum() {
+        let mut matchmaker = Matchmaker::default();
+        let size = TableSize { min: 3, ideal: 4 };
+        let now = queue(&mut matchmaker, &[(1, 1000), (2, 1000)], size);
+
+        assert!(matchmaker.find_matches(now + PARTIAL_MATCH_WAIT).is_empty());
+        // Without bots there's nothing to do but keep waiting
+        assert!(matchmaker.find_matches(now + BOT_FILL_WAIT * 2).is_empty());
+
+        matchmaker.join(3, 1000, request(GameType::Uno, false), size);
+        let matches = matchmaker.find_matches(now + PARTIAL_MATCH_WAIT);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].user_ids, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn partial_table_waits_first() {
+        let mut matchmaker = Matchmaker::default();
+        let now = queue(&mut matchmaker, &[(1, 1000), (2, 1000)], TABLE);
+
+        assert!(matchmaker.find_matches(now + secs(5)).is_empty());
+        assert_eq!(matchmaker.find_matches(now + PARTIAL_MATCH_WAIT).len(), 1);
+    }
+
+    #[test]
+    fn bots_fill_in_once_everyone_allows_them() {
+        let mut matchmaker = Matchmaker::default();
+        let size = TableSize { min: 3, ideal: 4 };
+        matchmaker.join(1, 1000, request(GameType::Uno, true), size);
+        matchmaker.join(2, 1000, request(GameType::Uno, false), size);
+        let now = Instant::now();
+
+        assert!(matchmaker.find_matches(now + BOT_FILL_WAIT).is_empty());
+
+        matchmaker.join(2, 1000, request(GameType::Uno, true), size);
+        assert!(matchmaker.find_matches(now + secs(5)).is_empty());
+
+        let matches = matchmaker.find_matches(now + BOT_FILL_WAIT);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].bot_count, 2);
+    }
+
+    #[test]
+    fn different_games_and_rules_are_kept_apart() {
+        let mut matchmaker = Matchmaker::default();
+        let size = TableSize { min: 2, ideal: 2 };
+        matchmaker.join(1, 1000, request(GameType::Uno, false), size);
+        matchmaker.join(2, 1000, request(GameType::Chess, false), size);
+        matchmaker.join(
+            3,
+            1000,
+            QuickPlayRequest {
+                rules: vec![1],
+                ..request(GameType::Uno, false)
+            },
+            size,
+        );
+
+        assert!(
+            matchmaker
+                .find_matches(Instant::now() + secs(60))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn leaving_takes_players_out_of_the_queue() {
+        let mut matchmaker = Matchmaker::default();
+        let size = TableSize { min: 2, ideal: 2 };
+        let now = queue(&mut matchmaker, &[(1, 1000), (2, 1000), (3, 1000)], size);
+
+        assert!(matchmaker.leave(1));
+        assert!(!matchmaker.leave(1));
+
+        matchmaker.retain_users(|user_id| user_id != 2);
+
+        assert!(matchmaker.find_matches(now + secs(60)).is_empty());
+        assert_eq!(matchmaker.statuses(now).len(), 1);
+    }
+
+    #[test]
+    fn queueing_again_replaces_the_request() {
+        let mut matchmaker = Matchmaker::default();
+        let now = queue(&mut matchmaker, &[(1, 1000), (1, 1000)], TABLE);
+
+        let statuses = matchmaker.statuses(now);
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].1.queued, 1);
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use bincode::{Decode, Encode, config::Configuration};
+use rpc::{comms::ReplaySummary, game_state::GameType};
+use tokio::fs;
+
+/// Replays are kept next to wherever the server is run from
+const REPLAY_DIR: &str = "replays";
+
+/// Only the newest replays are listed, there could be a lot of them
+const REPLAY_LIST_LIMIT: usize = 50;
+
+/// Every replay file has the same header so they can be listed without
+///  knowing about each game, the game's own replay is kept encoded in `data`
+#[derive(Debug, Encode, Decode)]
+pub struct StoredReplay {
+    pub name: String,
+    pub game_type: GameType,
+    pub players: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+/// The file name starts with the time it was saved so the names sort by age
+pub async fn save(
+    name: &str,
+    game_type: GameType,
+    players: Vec<String>,
+    replay: &impl Encode,
+) -> anyhow::Result<()> {
+    let stored = StoredReplay {
+        name: name.to_string(),
+        game_type,
+        players,
+        data: bincode::encode_to_vec(replay, bincode::config::standard())?,
+    };
+
+    let saved_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let id = format!("{saved_at}-{:08x}", rand::random::<u32>());
+
+    fs::create_dir_all(REPLAY_DIR).await?;
+    fs::write(
+        format!("{REPLAY_DIR}/{id}.replay"),
+        bincode::encode_to_vec(&stored, bincode::config::standard())?,
+    )
+    .await?;
+
+    println!("Saved replay {id}");
+    Ok(())
+}
+
+pub async fn list() -> anyhow::Result<Vec<ReplaySummary>> {
+    let mut ids = vec![];
+
+    let mut entries = match fs::read_dir(REPLAY_DIR).await {
+        Ok(entries) => entries,
+        // Nothing has been saved yet
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        if let Some(id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_suffix(".replay"))
+        {
+            ids.push(id.to_string());
+        }
+    }
+
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    ids.truncate(REPLAY_LIST_LIMIT);
+
+    let mut summaries = vec![];
+    for id in ids {
+        match load(&id).await {
+            Ok(stored) => summaries.push(ReplaySummary {
+                id,
+                name: stored.name,
+                game_type: stored.game_type,
+                players: stored.players,
+            }),
+            Err(err) => println!("Skipping unreadable replay {id} : {err:?}"),
+        }
+    }
+
+    Ok(summaries)
+}
+
+pub async fn load(id: &str) -> anyhow::Result<StoredReplay> {
+    // Ids come from clients, they can't be allowed to wander out of the replay folder
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(anyhow!("Invalid replay id {id}"));
+    }
+
+    let data = fs::read(format!("{REPLAY_DIR}/{id}.replay")).await?;
+    let (stored, _) = bincode::decode_from_slice::<StoredReplay, Configuration>(
+        &data,
+        bincode::config::standard(),
+    )?;
+
+    Ok(stored)
+}
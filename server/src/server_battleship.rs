@@ -0,0 +1,503 @@
+use anyhow::anyhow;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    battleship::{
+        BATTLESHIP_SIZE, BattleshipAction, BattleshipClientAction, BattleshipClientGameState,
+        BattleshipPhase, BattleshipPlayer, ServerBattleshipCommand, Ship, Shot, ShotResult,
+        fleet_is_valid,
+    },
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+};
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    hidden_choices::HiddenChoices,
+    stats::PlayerResult,
+};
+
+const BATTLESHIP_PLAYERS: usize = 2;
+
+/// Played in a `GameRoom`.
+/// Fleets are held back in `placements` until both are in, so neither player
+///  can see where the other put theirs or wait to place around it.
+pub struct ServerBattleship {
+    seats: Vec<BattleshipSeat>,
+    phase: BattleshipPhase,
+    placements: HiddenChoices<Vec<Ship>>,
+    turn: Option<u32>,
+    winner: Option<u32>,
+    start_state: GameStartState,
+    action: Vec<BattleshipAction>,
+    /// Seeded by the room, enough to reproduce who fired first
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct BattleshipSeat {
+    id: u32,
+    name: String,
+    /// Empty until both fleets are placed
+    fleet: Vec<Ship>,
+    /// Fired at this seat by the other one
+    shots: Vec<Shot>,
+    rating: Option<u32>,
+    left: bool,
+}
+
+impl BattleshipSeat {
+    fn new(id: u32, name: String, rating: u32) -> BattleshipSeat {
+        BattleshipSeat {
+            id,
+            name,
+            fleet: vec![],
+            shots: vec![],
+            rating: Some(rating),
+            left: false,
+        }
+    }
+
+    fn all_sunk(&self) -> bool {
+        !self.fleet.is_empty() && self.fleet.iter().all(|ship| ship.is_sunk(&self.shots))
+    }
+}
+
+impl ServerBattleship {
+    pub fn new(rng: ChaCha8Rng) -> ServerBattleship {
+        ServerBattleship {
+            seats: vec![],
+            phase: BattleshipPhase::Placing,
+            placements: HiddenChoices::default(),
+            turn: None,
+            winner: None,
+            start_state: GameStartState::Setup,
+            action: vec![BattleshipAction::Init],
+            rng,
+        }
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if self.seats.len() != BATTLESHIP_PLAYERS {
+            return Err(anyhow!(
+                "Battleship needs exactly {BATTLESHIP_PLAYERS} players"
+            ));
+        }
+
+        self.start_state = GameStartState::Active;
+        self.phase = BattleshipPhase::Placing;
+        self.action.push(BattleshipAction::GameStarted);
+
+        Ok(())
+    }
+
+    fn act(&mut self, user_id: u32, action: BattleshipClientAction) -> anyhow::Result<()> {
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("The game isn't being played"));
+        }
+
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return Err(anyhow!("User isn't seated"));
+        };
+
+        match action {
+            BattleshipClientAction::PlaceFleet(fleet) => self.place_fleet(seat_idx, fleet),
+            BattleshipClientAction::Fire(at) => self.fire(seat_idx, at),
+        }
+    }
+
+    fn place_fleet(&mut self, seat_idx: usize, fleet: Vec<Ship>) -> anyhow::Result<()> {
+        if self.phase != BattleshipPhase::Placing {
+            return Err(anyhow!("Fleets have already been placed"));
+        }
+
+        if !fleet_is_valid(&fleet) {
+            return Err(anyhow!("That isn't a valid fleet"));
+        }
+
+        let user_id = self.seats[seat_idx].id;
+        self.placements.submit(user_id, fleet)?;
+        self.action.push(BattleshipAction::UserPlacedFleet(
+            self.seats[seat_idx].name.clone(),
+        ));
+
+        if !self
+            .placements
+            .all_chosen(self.seats.iter().map(|seat| seat.id))
+        {
+            return Ok(());
+        }
+
+        let mut fleets = self.placements.reveal();
+        for seat in &mut self.seats {
+            seat.fleet = fleets.remove(&seat.id).unwrap_or_default();
+        }
+
+        let first = self.rng.random_range(0..BATTLESHIP_PLAYERS);
+        self.phase = BattleshipPhase::Firing;
+        self.turn = Some(self.seats[first].id);
+        self.action.push(BattleshipAction::FiringStarted(
+            self.seats[first].name.clone(),
+        ));
+
+        Ok(())
+    }
+
+    fn fire(&mut self, seat_idx: usize, at: (u8, u8)) -> anyhow::Result<()> {
+        if self.phase != BattleshipPhase::Firing {
+            return Err(anyhow!("Fleets are still being placed"));
+        }
+
+        if self.turn != Some(self.seats[seat_idx].id) {
+            return Err(anyhow!("It's not this user's turn"));
+        }
+
+        if at.0 >= BATTLESHIP_SIZE || at.1 >= BATTLESHIP_SIZE {
+            return Err(anyhow!("{at:?} is off the board"));
+        }
+
+        let target = &mut self.seats[1 - seat_idx];
+        if target.shots.iter().any(|shot| shot.at == at) {
+            return Err(anyhow!("{at:?} has already been fired at"));
+        }
+
+        // The result has to be worked out with the shot already in, or the last hit wouldn't sink it
+        target.shots.push(Shot {
+            at,
+            result: ShotResult::Miss,
+        });
+        let result = match target.fleet.iter().find(|ship| ship.cells().contains(&at)) {
+            None => ShotResult::Miss,
+            Some(ship) if ship.is_sunk(&target.shots) => ShotResult::Sunk(ship.kind),
+            Some(_) => ShotResult::Hit,
+        };
+        if let Some(shot) = target.shots.last_mut() {
+            shot.result = result;
+        }
+
+        let target_id = target.id;
+        let sunk_all = target.all_sunk();
+
+        self.action.push(BattleshipAction::UserFired(
+            self.seats[seat_idx].name.clone(),
+            at,
+            result,
+        ));
+
+        if sunk_all {
+            self.end_game(self.seats[seat_idx].id);
+            return Ok(());
+        }
+
+        self.turn = Some(target_id);
+
+        Ok(())
+    }
+
+    fn end_game(&mut self, winner: u32) {
+        if let Some(seat) = self.seats.iter().find(|seat| seat.id == winner) {
+            self.action
+                .push(BattleshipAction::UserWon(seat.name.clone()));
+        }
+
+        self.action.push(BattleshipAction::GameEnded);
+        self.winner = Some(winner);
+        self.turn = None;
+        self.start_state = GameStartState::Ending;
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return;
+        };
+
+        self.action.push(BattleshipAction::UserLeft(
+            self.seats[seat_idx].name.clone(),
+        ));
+
+        match self.start_state {
+            GameStartState::Setup => {
+                self.seats.remove(seat_idx);
+            }
+            // The seat is kept so the boards still show who was who
+            GameStartState::Active => {
+                self.seats[seat_idx].left = true;
+                self.placements.withdraw(user_id);
+                let winner = self.seats[1 - seat_idx].id;
+                self.end_game(winner);
+            }
+            GameStartState::Ending => {
+                self.seats[seat_idx].left = true;
+            }
+        }
+    }
+}
+
+impl RoomGame for ServerBattleship {
+    const GAME_TYPE: GameType = GameType::Battleship;
+    const MAX_PLAYERS: usize = BATTLESHIP_PLAYERS;
+
+    type Action = BattleshipClientAction;
+    type State = BattleshipClientGameState;
+    type Message = ServerBattleshipCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.seats
+            .iter()
+            .filter(|seat| !seat.left)
+            .map(|seat| seat.id)
+            .collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.action
+            .push(BattleshipAction::UserJoined(user.name.clone()));
+        self.seats
+            .push(BattleshipSeat::new(user_id, user.name.clone(), user.rating));
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: BattleshipClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        self.seats
+            .iter()
+            .enumerate()
+            .map(|(seat_idx, seat)| {
+                let rank = if self.winner == Some(seat.id) { 1 } else { 2 };
+
+                PlayerResult {
+                    user_id: seat.id,
+                    placement: (!seat.left).then_some(rank),
+                    rank,
+                    won: rank == 1,
+                    busted: false,
+                    cards_played: self.seats[1 - seat_idx].shots.len() as u32,
+                }
+            })
+            .collect()
+    }
+
+    /// What everyone can see, each player gets their own fleet added in afterwards
+    fn client_state(&mut self, host_user: u32) -> BattleshipClientGameState {
+        let game_over = self.start_state == GameStartState::Ending;
+
+        BattleshipClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| BattleshipPlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    placed: !seat.fleet.is_empty() || self.placements.has_chosen(seat.id),
+                    shots: seat.shots.clone(),
+                    ships: seat
+                        .fleet
+                        .iter()
+                        .filter(|ship| game_over || ship.is_sunk(&seat.shots))
+                        .copied()
+                        .collect(),
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            phase: self.phase,
+            turn: self.turn,
+            winner: self.winner,
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut BattleshipClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Players get their own fleet, even while it's still waiting on the other one
+    fn project(
+        &self,
+        mut state: BattleshipClientGameState,
+        viewer: Viewer,
+    ) -> ServerBattleshipCommand {
+        if let Viewer::Player(user_id) = viewer
+            && let Some(player) = state.players.iter_mut().find(|player| player.id == user_id)
+            && let Some(seat) = self.seats.iter().find(|seat| seat.id == user_id)
+        {
+            player.ships = match self.placements.get(user_id) {
+                Some(fleet) => fleet.clone(),
+                None => seat.fleet.clone(),
+            };
+        }
+
+        ServerBattleshipCommand::GameState(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rpc::battleship::ShipKind;
+
+    use super::*;
+
+    /// One of each ship, each lying along its own row from the left edge
+    fn fleet() -> Vec<Ship> {
+        ShipKind::FLEET
+            .iter()
+            .enumerate()
+            .map(|(row, kind)| Ship {
+                kind: *kind,
+                at: (0, row as u8 * 2),
+                horizontal: true,
+            })
+            .collect()
+    }
+
+    /// Both fleets placed, user `0` to fire first
+    fn firing() -> ServerBattleship {
+        let mut game = ServerBattleship::new(ChaCha8Rng::seed_from_u64(0));
+        for id in 0..BATTLESHIP_PLAYERS as u32 {
+            game.seats
+                .push(BattleshipSeat::new(id, format!("Player {id}"), 0));
+        }
+        game.start_game().unwrap();
+
+        for id in 0..BATTLESHIP_PLAYERS as u32 {
+            game.act(id, BattleshipClientAction::PlaceFleet(fleet()))
+                .unwrap();
+        }
+        game.turn = Some(0);
+        game
+    }
+
+    fn fire(game: &mut ServerBattleship, user_id: u32, at: (u8, u8)) -> anyhow::Result<()> {
+        game.act(user_id, BattleshipClientAction::Fire(at))
+    }
+
+    fn last_result(game: &ServerBattleship) -> ShotResult {
+        game.seats[1].shots.last().unwrap().result
+    }
+
+    #[test]
+    fn fleets_are_hidden_until_both_are_in() {
+        let mut game = ServerBattleship::new(ChaCha8Rng::seed_from_u64(0));
+        for id in 0..BATTLESHIP_PLAYERS as u32 {
+            game.seats
+                .push(BattleshipSeat::new(id, format!("Player {id}"), 0));
+        }
+        game.start_game().unwrap();
+
+        let mut overlapping = fleet();
+        overlapping[1].at = (0, 0);
+        assert!(
+            game.act(0, BattleshipClientAction::PlaceFleet(overlapping))
+                .is_err()
+        );
+
+        game.act(0, BattleshipClientAction::PlaceFleet(fleet()))
+            .unwrap();
+        assert!(game.seats[0].fleet.is_empty());
+        assert_eq!(game.phase, BattleshipPhase::Placing);
+        assert!(fire(&mut game, 0, (0, 0)).is_err());
+
+        game.act(1, BattleshipClientAction::PlaceFleet(fleet()))
+            .unwrap();
+        assert_eq!(game.seats[0].fleet, fleet());
+        assert_eq!(game.phase, BattleshipPhase::Firing);
+        assert!(game.turn.is_some());
+    }
+
+    #[test]
+    fn turns_alternate() {
+        let mut game = firing();
+
+        assert!(fire(&mut game, 1, (0, 0)).is_err());
+        fire(&mut game, 0, (9, 9)).unwrap();
+        assert_eq!(last_result(&game), ShotResult::Miss);
+        assert_eq!(game.turn, Some(1));
+
+        fire(&mut game, 1, (9, 9)).unwrap();
+        assert_eq!(game.turn, Some(0));
+    }
+
+    #[test]
+    fn repeat_and_off_board_shots_are_refused() {
+        let mut game = firing();
+        fire(&mut game, 0, (0, 0)).unwrap();
+        fire(&mut game, 1, (0, 0)).unwrap();
+
+        assert!(fire(&mut game, 0, (0, 0)).is_err());
+        assert!(fire(&mut game, 0, (BATTLESHIP_SIZE, 0)).is_err());
+        // Still their turn after a refused shot
+        assert_eq!(game.turn, Some(0));
+        assert_eq!(game.seats[1].shots.len(), 1);
+    }
+
+    #[test]
+    fn last_hit_sinks_the_ship() {
+        let mut game = firing();
+
+        fire(&mut game, 0, (0, 8)).unwrap();
+        assert_eq!(last_result(&game), ShotResult::Hit);
+        fire(&mut game, 1, (9, 9)).unwrap();
+
+        fire(&mut game, 0, (1, 8)).unwrap();
+        assert_eq!(last_result(&game), ShotResult::Sunk(ShipKind::Destroyer));
+        assert!(!game.seats[1].all_sunk());
+        assert_eq!(game.start_state, GameStartState::Active);
+    }
+
+    #[test]
+    fn sinking_the_whole_fleet_wins() {
+        let mut game = firing();
+        let cells: Vec<(u8, u8)> = fleet().iter().flat_map(|ship| ship.cells()).collect();
+        let misses = (0..BATTLESHIP_SIZE).flat_map(|column| [(column, 1), (column, 3)]);
+
+        for (at, miss) in cells.iter().zip(misses) {
+            fire(&mut game, 0, *at).unwrap();
+            if game.start_state == GameStartState::Active {
+                fire(&mut game, 1, miss).unwrap();
+            }
+        }
+
+        assert!(game.seats[1].all_sunk());
+        assert_eq!(game.winner, Some(0));
+        assert_eq!(game.start_state, GameStartState::Ending);
+        assert_eq!(game.turn, None);
+        assert!(
+            game.action.iter().any(
+                |action| matches!(action, BattleshipAction::UserWon(name) if name == "Player 0")
+            )
+        );
+    }
+}
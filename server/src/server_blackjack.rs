@@ -0,0 +1,962 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    blackjack::{
+        BLACKJACK_ACT_MS, BLACKJACK_BET_MS, BLACKJACK_DEFAULT_DECKS, BLACKJACK_MAX_BET,
+        BLACKJACK_MAX_HANDS, BLACKJACK_MAX_PLAYERS, BLACKJACK_MIN_BET, BLACKJACK_SHOE_SIZES,
+        BlackjackAction, BlackjackClientAction, BlackjackClientGameState, BlackjackHand,
+        BlackjackPhase, BlackjackPlayer, HandOutcome, ServerBlackjackCommand, card_value,
+        hand_total,
+    },
+    cards::{PlayingCard, Rank},
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+};
+use tokio::time::Instant;
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+};
+
+/// Played in a `GameRoom`, with the dealer played by the server.
+///
+/// Rounds go on for as long as anyone is sat down, so nothing goes to the ratings,
+///  the chips are the score. Whatever changes is sent back after every round.
+pub struct ServerBlackjack {
+    seats: Vec<BlackjackSeat>,
+    decks: u8,
+    /// Dealt from the back, topped back up before a deal once three quarters have gone
+    shoe: Vec<PlayingCard>,
+    phase: BlackjackPhase,
+    round: u32,
+    dealer: Vec<PlayingCard>,
+    dealer_hidden: bool,
+    /// When the current phase gives up waiting on anyone
+    deadline: Option<Instant>,
+    start_state: GameStartState,
+    action: Vec<BlackjackAction>,
+    /// Chip changes waiting to be sent back to the accounts
+    chip_changes: Vec<(u32, i64)>,
+    /// Seeded by the room, enough to reproduce every shoe
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct BlackjackSeat {
+    id: u32,
+    name: String,
+    chips: u64,
+    /// What the account was last told this seat had
+    reported_chips: u64,
+    bet: Option<u64>,
+    hands: Vec<BlackjackHand>,
+    /// Some(0) once insurance has been turned down
+    insurance: Option<u64>,
+    last_net: Option<i64>,
+    rating: Option<u32>,
+}
+
+impl BlackjackSeat {
+    fn new(id: u32, user: &PlayerState) -> BlackjackSeat {
+        BlackjackSeat {
+            id,
+            name: user.name.clone(),
+            chips: user.chips,
+            reported_chips: user.chips,
+            bet: None,
+            hands: vec![],
+            insurance: None,
+            last_net: None,
+            rating: Some(user.rating),
+        }
+    }
+
+    /// Hasn't bet this round but has enough to
+    fn waiting_to_bet(&self) -> bool {
+        self.bet.is_none() && self.chips >= BLACKJACK_MIN_BET
+    }
+
+    /// The first hand still being played, split hands are played left to right
+    fn active_hand(&self) -> Option<usize> {
+        self.hands.iter().position(|hand| !hand.done)
+    }
+}
+
+impl ServerBlackjack {
+    pub fn new(rng: ChaCha8Rng) -> ServerBlackjack {
+        ServerBlackjack {
+            seats: vec![],
+            decks: BLACKJACK_DEFAULT_DECKS,
+            shoe: vec![],
+            phase: BlackjackPhase::Betting,
+            round: 0,
+            dealer: vec![],
+            dealer_hidden: false,
+            deadline: None,
+            start_state: GameStartState::Setup,
+            action: vec![BlackjackAction::Init],
+            chip_changes: vec![],
+            rng,
+        }
+    }
+
+    /// Queues up whatever the seat has won or lost since the account last heard
+    fn report_chips(&mut self, seat_idx: usize) {
+        let seat = &mut self.seats[seat_idx];
+        let change = seat.chips as i64 - seat.reported_chips as i64;
+
+        if change != 0 {
+            self.chip_changes.push((seat.id, change));
+            seat.reported_chips = seat.chips;
+        }
+    }
+
+    /// A seat that can't cover the minimum bet is dealt out, nobody waits on it
+    fn check_broke(&mut self, seat_idx: usize) {
+        let seat = &self.seats[seat_idx];
+
+        if seat.chips < BLACKJACK_MIN_BET {
+            self.action
+                .push(BlackjackAction::UserBroke(seat.name.clone()));
+        }
+    }
+
+    /// Every seat that can bet has, and at least one of them did
+    fn all_bet(&self) -> bool {
+        !self.seats.iter().any(|seat| seat.waiting_to_bet())
+            && self.seats.iter().any(|seat| seat.bet.is_some())
+    }
+
+    fn sit_down(&mut self, user_id: u32, user: &PlayerState) {
+        self.seats.push(BlackjackSeat::new(user_id, user));
+        self.check_broke(self.seats.len() - 1);
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        self.start_state = GameStartState::Active;
+        self.phase = BlackjackPhase::Betting;
+        self.action.push(BlackjackAction::GameStarted);
+        self.shuffle_shoe();
+
+        Ok(())
+    }
+
+    fn shuffle_shoe(&mut self) {
+        self.shoe = (0..self.decks).flat_map(|_| PlayingCard::deck()).collect();
+        self.shoe.shuffle(&mut self.rng);
+        self.action.push(BlackjackAction::ShoeShuffled);
+    }
+
+    /// A fresh shoe is always shuffled before it starts, so this can't run dry mid round
+    fn draw(&mut self) -> PlayingCard {
+        match self.shoe.pop() {
+            Some(card) => card,
+            None => {
+                self.shuffle_shoe();
+                self.shoe.pop().expect("A shuffled shoe has cards")
+            }
+        }
+    }
+
+    fn act(
+        &mut self,
+        user_id: u32,
+        is_host: bool,
+        action: BlackjackClientAction,
+    ) -> anyhow::Result<()> {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return Err(anyhow!("User not in game"));
+        };
+
+        if let BlackjackClientAction::SetDecks(decks) = action {
+            if !is_host || self.start_state != GameStartState::Setup {
+                return Err(anyhow!("Only the host can change the shoe before starting"));
+            }
+
+            if !BLACKJACK_SHOE_SIZES.contains(&decks) {
+                return Err(anyhow!("{decks} decks isn't a shoe size"));
+            }
+
+            self.decks = decks;
+            self.action.push(BlackjackAction::DecksChanged(decks));
+            return Ok(());
+        }
+
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("Received Game message when not active"));
+        }
+
+        match (self.phase, action) {
+            (BlackjackPhase::Betting, BlackjackClientAction::Bet(amount)) => {
+                self.bet(seat_idx, amount)
+            }
+            (BlackjackPhase::Insurance, BlackjackClientAction::Insurance(take)) => {
+                self.insure(seat_idx, take)
+            }
+            (BlackjackPhase::Playing, action) => self.play(seat_idx, action),
+            (phase, action) => Err(anyhow!("Can't {action:?} while {phase:?}")),
+        }
+    }
+
+    fn bet(&mut self, seat_idx: usize, amount: u64) -> anyhow::Result<()> {
+        let seat = &mut self.seats[seat_idx];
+
+        if seat.bet.is_some() {
+            return Err(anyhow!("Already bet this round"));
+        }
+
+        if !(BLACKJACK_MIN_BET..=BLACKJACK_MAX_BET).contains(&amount) || amount > seat.chips {
+            return Err(anyhow!("Can't bet {amount} with {} chips", seat.chips));
+        }
+
+        seat.chips -= amount;
+        seat.bet = Some(amount);
+
+        // The clock only starts once someone's actually waiting to play
+        if self.deadline.is_none() {
+            self.deadline = Some(Instant::now() + Duration::from_millis(BLACKJACK_BET_MS as u64));
+        }
+
+        if self.all_bet() {
+            self.deal();
+        }
+
+        Ok(())
+    }
+
+    /// Everyone who bet gets two cards, the dealer's second is face down
+    fn deal(&mut self) {
+        let shoe_size = self.decks as usize * 52;
+        if self.shoe.len() < shoe_size / 4 {
+            self.shuffle_shoe();
+        }
+
+        self.round += 1;
+        self.action.push(BlackjackAction::RoundDealt(self.round));
+
+        for seat in self.seats.iter_mut() {
+            seat.hands.clear();
+            seat.insurance = None;
+            seat.last_net = None;
+
+            if let Some(bet) = seat.bet.take() {
+                seat.hands.push(BlackjackHand {
+                    cards: vec![],
+                    bet,
+                    doubled: false,
+                    split: false,
+                    done: false,
+                    outcome: None,
+                });
+            }
+        }
+
+        self.dealer.clear();
+        self.dealer_hidden = true;
+
+        for _ in 0..2 {
+            for seat_idx in 0..self.seats.len() {
+                if !self.seats[seat_idx].hands.is_empty() {
+                    let card = self.draw();
+                    self.seats[seat_idx].hands[0].cards.push(card);
+                }
+            }
+
+            let card = self.draw();
+            self.dealer.push(card);
+        }
+
+        // A blackjack has nothing left to decide
+        for seat in self.seats.iter_mut() {
+            for hand in seat.hands.iter_mut() {
+                hand.done = hand.is_blackjack();
+            }
+        }
+
+        match self.dealer[0].rank {
+            Rank::Ace => {
+                self.phase = BlackjackPhase::Insurance;
+                self.deadline =
+                    Some(Instant::now() + Duration::from_millis(BLACKJACK_ACT_MS as u64));
+            }
+            rank if card_value(rank) == 10 => self.peek(),
+            _ => self.start_playing(),
+        }
+    }
+
+    fn insure(&mut self, seat_idx: usize, take: bool) -> anyhow::Result<()> {
+        let seat = &mut self.seats[seat_idx];
+
+        if seat.hands.is_empty() || seat.insurance.is_some() {
+            return Err(anyhow!("Not being offered insurance"));
+        }
+
+        let cost = if take { seat.hands[0].bet / 2 } else { 0 };
+        if cost > seat.chips {
+            return Err(anyhow!("Can't afford {cost} for insurance"));
+        }
+
+        seat.chips -= cost;
+        seat.insurance = Some(cost);
+
+        if cost > 0 {
+            self.action
+                .push(BlackjackAction::UserInsured(seat.name.clone(), cost));
+        }
+
+        if self
+            .seats
+            .iter()
+            .all(|seat| seat.hands.is_empty() || seat.insurance.is_some())
+        {
+            self.peek();
+        }
+
+        Ok(())
+    }
+
+    /// The dealer checks for blackjack under an ace or a ten before anyone plays
+    fn peek(&mut self) {
+        let blackjack = hand_total(&self.dealer).0 == 21;
+        self.action.push(BlackjackAction::DealerPeeked(blackjack));
+
+        if blackjack {
+            self.finish_round();
+        } else {
+            self.start_playing();
+        }
+    }
+
+    fn start_playing(&mut self) {
+        self.phase = BlackjackPhase::Playing;
+        self.deadline = Some(Instant::now() + Duration::from_millis(BLACKJACK_ACT_MS as u64));
+        self.check_all_done();
+    }
+
+    fn check_all_done(&mut self) {
+        if self.phase == BlackjackPhase::Playing
+            && self
+                .seats
+                .iter()
+                .all(|seat| seat.hands.iter().all(|hand| hand.done))
+        {
+            self.finish_round();
+        }
+    }
+
+    fn play(&mut self, seat_idx: usize, action: BlackjackClientAction) -> anyhow::Result<()> {
+        let Some(hand_idx) = self.seats[seat_idx].active_hand() else {
+            return Err(anyhow!("No hand left to play"));
+        };
+
+        let name = self.seats[seat_idx].name.clone();
+
+        match action {
+            BlackjackClientAction::Hit => {
+                let card = self.draw();
+                let hand = &mut self.seats[seat_idx].hands[hand_idx];
+                hand.cards.push(card);
+                self.action
+                    .push(BlackjackAction::UserHit(name.clone(), card));
+
+                // Nobody would hit on 21 anyway
+                if hand.total() >= 21 {
+                    hand.done = true;
+                }
+            }
+            BlackjackClientAction::Stand => {
+                self.seats[seat_idx].hands[hand_idx].done = true;
+                self.action.push(BlackjackAction::UserStood(name.clone()));
+            }
+            BlackjackClientAction::Double => {
+                let seat = &self.seats[seat_idx];
+                let hand = &seat.hands[hand_idx];
+
+                if !hand.can_double() || hand.bet > seat.chips {
+                    return Err(anyhow!("Can't double this hand"));
+                }
+
+                let card = self.draw();
+                let seat = &mut self.seats[seat_idx];
+                let hand = &mut seat.hands[hand_idx];
+                seat.chips -= hand.bet;
+                hand.bet *= 2;
+                hand.doubled = true;
+                hand.cards.push(card);
+                hand.done = true;
+                self.action
+                    .push(BlackjackAction::UserDoubled(name.clone(), card));
+            }
+            BlackjackClientAction::Split => {
+                let seat = &self.seats[seat_idx];
+                let hand = &seat.hands[hand_idx];
+
+                if !hand.can_split()
+                    || seat.hands.len() >= BLACKJACK_MAX_HANDS
+                    || hand.bet > seat.chips
+                {
+                    return Err(anyhow!("Can't split this hand"));
+                }
+
+                let first_card = self.draw();
+                let second_card = self.draw();
+
+                let seat = &mut self.seats[seat_idx];
+                let hand = &mut seat.hands[hand_idx];
+                let bet = hand.bet;
+                let moved = hand.cards.pop().expect("Splitting needs two cards");
+                let aces = moved.rank == Rank::Ace;
+
+                hand.split = true;
+                hand.cards.push(first_card);
+
+                seat.chips -= bet;
+                seat.hands.insert(
+                    hand_idx + 1,
+                    BlackjackHand {
+                        cards: vec![moved, second_card],
+                        bet,
+                        doubled: false,
+                        split: true,
+                        done: false,
+                        outcome: None,
+                    },
+                );
+
+                // Split aces only get the one card each
+                for hand in seat.hands[hand_idx..=hand_idx + 1].iter_mut() {
+                    hand.done = aces || hand.total() == 21;
+                }
+
+                self.action.push(BlackjackAction::UserSplit(name.clone()));
+            }
+            action => return Err(anyhow!("Can't {action:?} while playing")),
+        }
+
+        if self.seats[seat_idx].hands[hand_idx].total() > 21 {
+            self.action.push(BlackjackAction::UserBusted(name));
+        }
+
+        self.check_all_done();
+
+        Ok(())
+    }
+
+    /// Nobody gets held up by someone who's wandered off, they just stand or pass
+    fn timed_out(&mut self) {
+        match self.phase {
+            BlackjackPhase::Betting => {
+                if self.seats.iter().any(|seat| seat.bet.is_some()) {
+                    self.deal();
+                }
+            }
+            BlackjackPhase::Insurance => {
+                for seat in self.seats.iter_mut() {
+                    if !seat.hands.is_empty() && seat.insurance.is_none() {
+                        seat.insurance = Some(0);
+                    }
+                }
+                self.peek();
+            }
+            BlackjackPhase::Playing => {
+                for seat in self.seats.iter_mut() {
+                    if seat.hands.iter().any(|hand| !hand.done) {
+                        self.action
+                            .push(BlackjackAction::UserStood(seat.name.clone()));
+                    }
+
+                    for hand in seat.hands.iter_mut() {
+                        hand.done = true;
+                    }
+                }
+                self.finish_round();
+            }
+        }
+    }
+
+    /// The dealer turns over and draws to 17, standing on a soft 17, then everyone is paid
+    fn finish_round(&mut self) {
+        self.dealer_hidden = false;
+
+        let dealer_blackjack = self.dealer.len() == 2 && hand_total(&self.dealer).0 == 21;
+
+        // There's no need to draw if nobody has a hand left that the dealer could beat
+        let anyone_left = self.seats.iter().any(|seat| {
+            seat.hands
+                .iter()
+                .any(|hand| hand.total() <= 21 && !hand.is_blackjack())
+        });
+
+        if !dealer_blackjack && anyone_left {
+            while hand_total(&self.dealer).0 < 17 {
+                let card = self.draw();
+                self.dealer.push(card);
+            }
+        }
+
+        let dealer_total = hand_total(&self.dealer).0;
+        if dealer_total > 21 {
+            self.action.push(BlackjackAction::DealerBusted);
+        } else {
+            self.action.push(BlackjackAction::DealerStood(dealer_total));
+        }
+
+        for seat_idx in 0..self.seats.len() {
+            let seat = &mut self.seats[seat_idx];
+            if seat.hands.is_empty() {
+                continue;
+            }
+
+            let insurance = seat.insurance.unwrap_or(0);
+            let mut staked = insurance;
+            let mut returned = if dealer_blackjack { insurance * 3 } else { 0 };
+
+            for hand in seat.hands.iter_mut() {
+                let total = hand.total();
+
+                let outcome = if total > 21 {
+                    HandOutcome::Bust
+                } else if hand.is_blackjack() && !dealer_blackjack {
+                    HandOutcome::Blackjack
+                } else if dealer_blackjack {
+                    if hand.is_blackjack() {
+                        HandOutcome::Push
+                    } else {
+                        HandOutcome::Lost
+                    }
+                } else if dealer_total > 21 || total > dealer_total {
+                    HandOutcome::Won
+                } else if total == dealer_total {
+                    HandOutcome::Push
+                } else {
+                    HandOutcome::Lost
+                };
+
+                staked += hand.bet;
+                returned += match outcome {
+                    HandOutcome::Blackjack => hand.bet + hand.bet * 3 / 2,
+                    HandOutcome::Won => hand.bet * 2,
+                    HandOutcome::Push => hand.bet,
+                    HandOutcome::Lost | HandOutcome::Bust => 0,
+                };
+
+                hand.done = true;
+                hand.outcome = Some(outcome);
+            }
+
+            let net = returned as i64 - staked as i64;
+            seat.chips += returned;
+            seat.last_net = Some(net);
+            self.action
+                .push(BlackjackAction::UserSettled(seat.name.clone(), net));
+
+            self.report_chips(seat_idx);
+            self.check_broke(seat_idx);
+        }
+
+        self.phase = BlackjackPhase::Betting;
+        self.deadline = None;
+    }
+
+    /// Anything on the table is lost, apart from a bet that hasn't been dealt yet
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return;
+        };
+
+        if let Some(bet) = self.seats[seat_idx].bet.take() {
+            self.seats[seat_idx].chips += bet;
+        }
+
+        self.report_chips(seat_idx);
+
+        let seat = self.seats.remove(seat_idx);
+        self.action.push(BlackjackAction::UserLeft(seat.name));
+
+        if self.start_state != GameStartState::Active {
+            return;
+        }
+
+        // They might have been the last one everyone was waiting on
+        match self.phase {
+            BlackjackPhase::Betting => {
+                if self.all_bet() {
+                    self.deal();
+                } else if self.seats.iter().all(|seat| seat.bet.is_none()) {
+                    self.deadline = None;
+                }
+            }
+            BlackjackPhase::Insurance => {
+                if self
+                    .seats
+                    .iter()
+                    .all(|seat| seat.hands.is_empty() || seat.insurance.is_some())
+                {
+                    self.peek();
+                }
+            }
+            BlackjackPhase::Playing => self.check_all_done(),
+        }
+    }
+}
+
+impl RoomGame for ServerBlackjack {
+    const GAME_TYPE: GameType = GameType::Blackjack;
+    const MAX_PLAYERS: usize = BLACKJACK_MAX_PLAYERS;
+
+    type Action = BlackjackClientAction;
+    type State = BlackjackClientGameState;
+    type Message = ServerBlackjackCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.seats.iter().map(|seat| seat.id).collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    /// Tables are sat down at between rounds, there's no waiting for a new game
+    fn can_seat(&self) -> bool {
+        self.seats.len() < BLACKJACK_MAX_PLAYERS && self.start_state != GameStartState::Ending
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.action
+            .push(BlackjackAction::UserJoined(user.name.clone()));
+        self.sit_down(user_id, user);
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        is_host: bool,
+        action: BlackjackClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, is_host, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn take_chip_changes(&mut self) -> Vec<(u32, i64)> {
+        self.chip_changes.drain(..).collect()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Whatever phase is running moves on by itself when its time is up
+    fn timer_fired(&mut self) -> Option<ServerBlackjackCommand> {
+        self.deadline = None;
+        self.timed_out();
+        None
+    }
+
+    fn client_state(&mut self, host_user: u32) -> BlackjackClientGameState {
+        let dealer = if self.dealer_hidden {
+            self.dealer.iter().take(1).copied().collect()
+        } else {
+            self.dealer.clone()
+        };
+
+        BlackjackClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| BlackjackPlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    chips: seat.chips,
+                    bet: seat.bet,
+                    hands: seat.hands.clone(),
+                    active_hand: match self.phase {
+                        BlackjackPhase::Playing => seat.active_hand().map(|idx| idx as u8),
+                        _ => None,
+                    },
+                    insurance: seat.insurance,
+                    last_net: seat.last_net,
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            decks: self.decks,
+            phase: self.phase,
+            round: self.round,
+            dealer,
+            dealer_hidden: self.dealer_hidden,
+            time_left_ms: self.deadline.map(|deadline| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_millis() as u32
+            }),
+            shoe_left: self.shoe.len() as u32,
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut BlackjackClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Nothing at the table is private, the hole card is kept back for everyone
+    fn project(&self, state: BlackjackClientGameState, _viewer: Viewer) -> ServerBlackjackCommand {
+        ServerBlackjackCommand::GameState(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::test_cards::cards;
+
+    /// A started one deck table, seat `idx` has user id `idx` and the chips given
+    fn table(chips: &[u64]) -> ServerBlackjack {
+        let mut game = ServerBlackjack::new(ChaCha8Rng::seed_from_u64(0));
+        game.decks = 1;
+
+        for (idx, chips) in chips.iter().enumerate() {
+            game.seats.push(BlackjackSeat {
+                id: idx as u32,
+                name: format!("Player {idx}"),
+                chips: *chips,
+                reported_chips: *chips,
+                bet: None,
+                hands: vec![],
+                insurance: None,
+                last_net: None,
+                rating: None,
+            });
+        }
+
+        game.start_game().unwrap();
+        game
+    }
+
+    /// The shoe deals `draws` in order, with enough left under them that it isn't reshuffled
+    fn rig(game: &mut ServerBlackjack, draws: &str) {
+        game.shoe = PlayingCard::deck()[..20].to_vec();
+        game.shoe.extend(cards(draws).into_iter().rev());
+    }
+
+    fn act(game: &mut ServerBlackjack, user_id: u32, action: BlackjackClientAction) {
+        game.act(user_id, false, action).unwrap();
+    }
+
+    fn outcomes(game: &ServerBlackjack, seat_idx: usize) -> Vec<Option<HandOutcome>> {
+        game.seats[seat_idx]
+            .hands
+            .iter()
+            .map(|hand| hand.outcome)
+            .collect()
+    }
+
+    #[test]
+    fn blackjack_pays_three_to_two() {
+        let mut game = table(&[1000]);
+        // Dealt a card each in turn, the dealer last
+        rig(&mut game, "As 9c Kd 7h");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+
+        assert_eq!(outcomes(&game, 0), vec![Some(HandOutcome::Blackjack)]);
+        assert_eq!(game.seats[0].chips, 1150);
+        assert_eq!(game.seats[0].last_net, Some(150));
+        // Nobody left for the dealer to beat, so no drawing
+        assert_eq!(game.dealer.len(), 2);
+        assert_eq!(game.take_chip_changes(), vec![(0, 150)]);
+    }
+
+    #[test]
+    fn double_doubles_the_bet_for_one_card() {
+        let mut game = table(&[1000]);
+        rig(&mut game, "5c 9c 6d 7h Th Ks");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        act(&mut game, 0, BlackjackClientAction::Double);
+
+        let hand = &game.seats[0].hands[0];
+        assert!(hand.doubled);
+        assert_eq!(hand.bet, 200);
+        assert_eq!(hand.cards.len(), 3);
+        assert_eq!(outcomes(&game, 0), vec![Some(HandOutcome::Won)]);
+        assert_eq!(game.seats[0].chips, 1200);
+    }
+
+    #[test]
+    fn split_hands_settle_separately() {
+        let mut game = table(&[1000]);
+        // The dealer's ten is peeked under, then each split hand gets a card
+        rig(&mut game, "8c Tc 8d 7h 3h 7s Td");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        act(&mut game, 0, BlackjackClientAction::Split);
+        assert_eq!(game.seats[0].chips, 800);
+
+        // 8 3 then a ten makes 21, the other hand stands on 15 against 17
+        act(&mut game, 0, BlackjackClientAction::Hit);
+        act(&mut game, 0, BlackjackClientAction::Stand);
+
+        assert_eq!(
+            outcomes(&game, 0),
+            vec![Some(HandOutcome::Won), Some(HandOutcome::Lost)]
+        );
+        assert_eq!(game.seats[0].chips, 1000);
+        assert_eq!(game.seats[0].last_net, Some(0));
+    }
+
+    #[test]
+    fn split_aces_get_one_card_and_no_blackjack() {
+        let mut game = table(&[1000]);
+        rig(&mut game, "Ac 9c Ad 7h Kh Ks");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        act(&mut game, 0, BlackjackClientAction::Split);
+
+        // Both hands are done straight away, and 21 on a split hand only pays evens
+        assert_eq!(
+            outcomes(&game, 0),
+            vec![Some(HandOutcome::Won), Some(HandOutcome::Won)]
+        );
+        assert_eq!(game.seats[0].chips, 1200);
+    }
+
+    #[test]
+    fn insurance_pays_two_to_one_on_a_dealer_blackjack() {
+        let mut game = table(&[1000]);
+        rig(&mut game, "Tc Ac 9d Kd");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        assert_eq!(game.phase, BlackjackPhase::Insurance);
+        act(&mut game, 0, BlackjackClientAction::Insurance(true));
+
+        // The hand is lost, the insurance wins it back
+        assert_eq!(outcomes(&game, 0), vec![Some(HandOutcome::Lost)]);
+        assert_eq!(game.seats[0].chips, 1000);
+        assert_eq!(game.seats[0].last_net, Some(0));
+    }
+
+    #[test]
+    fn insurance_is_lost_without_a_dealer_blackjack() {
+        let mut game = table(&[1000]);
+        // The dealer stands on a soft 17
+        rig(&mut game, "Tc Ac 9d 6h");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        act(&mut game, 0, BlackjackClientAction::Insurance(true));
+        act(&mut game, 0, BlackjackClientAction::Stand);
+
+        assert_eq!(game.dealer.len(), 2);
+        assert_eq!(outcomes(&game, 0), vec![Some(HandOutcome::Won)]);
+        assert_eq!(game.seats[0].chips, 1050);
+    }
+
+    #[test]
+    fn dealer_bust_pays_everyone_still_in() {
+        let mut game = table(&[1000, 1000]);
+        rig(&mut game, "Tc Th Td 8d 6s 6h Qh Kc");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        act(&mut game, 1, BlackjackClientAction::Bet(100));
+        act(&mut game, 0, BlackjackClientAction::Stand);
+        // A bust loses even when the dealer busts after
+        act(&mut game, 1, BlackjackClientAction::Hit);
+
+        assert_eq!(hand_total(&game.dealer).0, 26);
+        assert_eq!(outcomes(&game, 0), vec![Some(HandOutcome::Won)]);
+        assert_eq!(outcomes(&game, 1), vec![Some(HandOutcome::Bust)]);
+        assert_eq!(game.seats[0].chips, 1100);
+        assert_eq!(game.seats[1].chips, 900);
+    }
+
+    #[test]
+    fn push_returns_the_bet() {
+        let mut game = table(&[1000, 1000]);
+        rig(&mut game, "Tc As Th 8d Kd 8s");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        act(&mut game, 1, BlackjackClientAction::Bet(100));
+        act(&mut game, 0, BlackjackClientAction::Stand);
+
+        // 18 against 18, and a blackjack against a dealer's 18 still pays
+        assert_eq!(outcomes(&game, 0), vec![Some(HandOutcome::Push)]);
+        assert_eq!(outcomes(&game, 1), vec![Some(HandOutcome::Blackjack)]);
+        assert_eq!(game.seats[0].chips, 1000);
+        assert_eq!(game.seats[0].last_net, Some(0));
+    }
+
+    #[test]
+    fn blackjacks_push() {
+        let mut game = table(&[1000]);
+        rig(&mut game, "As Kc Kd Ah");
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+
+        assert_eq!(outcomes(&game, 0), vec![Some(HandOutcome::Push)]);
+        assert_eq!(game.seats[0].chips, 1000);
+    }
+
+    #[test]
+    fn shoe_holds_every_deck() {
+        for decks in BLACKJACK_SHOE_SIZES {
+            let mut game = ServerBlackjack::new(ChaCha8Rng::seed_from_u64(0));
+            game.decks = decks;
+            game.start_game().unwrap();
+
+            assert_eq!(game.shoe.len(), decks as usize * 52);
+        }
+    }
+
+    #[test]
+    fn shoe_reshuffles_once_three_quarters_are_gone() {
+        let mut game = table(&[1000]);
+        game.shoe.truncate(12);
+
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+
+        // A fresh deck less the four cards dealt
+        assert_eq!(game.round, 1);
+        assert_eq!(game.shoe.len() + 4, 52);
+    }
+
+    #[test]
+    fn broke_seats_are_dealt_out() {
+        let mut game = table(&[1000, 5]);
+        rig(&mut game, "Tc 9c 8d 7h");
+
+        assert!(game.act(1, false, BlackjackClientAction::Bet(5)).is_err());
+
+        // Nobody waits on the broke seat, and it isn't given anything
+        act(&mut game, 0, BlackjackClientAction::Bet(100));
+        assert_eq!(game.round, 1);
+        assert!(game.seats[1].hands.is_empty());
+
+        act(&mut game, 0, BlackjackClientAction::Stand);
+        assert_eq!(game.seats[1].chips, 5);
+        assert_eq!(game.take_chip_changes(), vec![(0, 100)]);
+    }
+}
@@ -0,0 +1,389 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::anyhow;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    chess::{
+        ChessAction, ChessClientAction, ChessClientGameState, ChessMove, ChessOutcome, ChessPlayer,
+        Color, Position, ServerChessCommand, TIME_CONTROLS,
+    },
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+};
+use tokio::time::Instant;
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+const CHESS_PLAYERS: usize = 2;
+/// A position turning up this many times is a draw
+const REPETITION_LIMIT: u32 = 3;
+
+/// Played in a `GameRoom`.
+/// Only the side to move's clock runs, it's checked on a timer rather than waiting for a move.
+pub struct ServerChess {
+    seats: Vec<ChessSeat>,
+    position: Position,
+    moves: Vec<String>,
+    last_move: Option<ChessMove>,
+    /// How many times each position has come up, keyed without the move counters
+    seen_positions: HashMap<Position, u32>,
+    time_control: Option<(u32, u32)>,
+    /// White's and black's time left as of `turn_started`
+    clocks: Option<[Duration; 2]>,
+    turn_started: Option<Instant>,
+    outcome: Option<ChessOutcome>,
+    start_state: GameStartState,
+    action: Vec<ChessAction>,
+    /// Seeded by the room, enough to reproduce who got white
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct ChessSeat {
+    id: u32,
+    name: String,
+    color: Option<Color>,
+    rating: Option<u32>,
+    moves_made: u32,
+    left: bool,
+}
+
+impl ChessSeat {
+    fn new(id: u32, name: String, rating: u32) -> ChessSeat {
+        ChessSeat {
+            id,
+            name,
+            color: None,
+            rating: Some(rating),
+            moves_made: 0,
+            left: false,
+        }
+    }
+}
+
+impl ServerChess {
+    pub fn new(rng: ChaCha8Rng) -> ServerChess {
+        ServerChess {
+            seats: vec![],
+            position: Position::start(),
+            moves: vec![],
+            last_move: None,
+            seen_positions: HashMap::new(),
+            time_control: TIME_CONTROLS[2],
+            clocks: None,
+            turn_started: None,
+            outcome: None,
+            start_state: GameStartState::Setup,
+            action: vec![ChessAction::Init],
+            rng,
+        }
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if self.seats.len() != CHESS_PLAYERS {
+            return Err(anyhow!("Chess needs exactly {CHESS_PLAYERS} players"));
+        }
+
+        self.seats.shuffle(&mut self.rng);
+        self.seats[0].color = Some(Color::White);
+        self.seats[1].color = Some(Color::Black);
+
+        self.seen_positions
+            .insert(self.position.repetition_key(), 1);
+
+        self.clocks = self
+            .time_control
+            .map(|(minutes, _)| [Duration::from_secs(minutes as u64 * 60); 2]);
+
+        self.turn_started = Some(Instant::now());
+        self.start_state = GameStartState::Active;
+
+        self.action.push(ChessAction::GameStarted(
+            self.seats[0].name.clone(),
+            self.seats[1].name.clone(),
+        ));
+
+        Ok(())
+    }
+
+    fn act(
+        &mut self,
+        user_id: u32,
+        is_host: bool,
+        action: ChessClientAction,
+    ) -> anyhow::Result<()> {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return Err(anyhow!("User isn't seated"));
+        };
+
+        if let ChessClientAction::CycleTimeControl = action {
+            if !is_host || self.start_state != GameStartState::Setup {
+                return Err(anyhow!(
+                    "Only the host can change the clock before starting"
+                ));
+            }
+
+            let idx = TIME_CONTROLS
+                .iter()
+                .position(|control| *control == self.time_control)
+                .unwrap_or(0);
+            self.time_control = TIME_CONTROLS[(idx + 1) % TIME_CONTROLS.len()];
+
+            return Ok(());
+        }
+
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("The game isn't being played"));
+        }
+
+        let Some(color) = self.seats[seat_idx].color else {
+            return Err(anyhow!("User hasn't got a colour"));
+        };
+
+        match action {
+            ChessClientAction::CycleTimeControl => Ok(()),
+            ChessClientAction::Resign => {
+                self.end_game(ChessOutcome::Resigned(color.other()));
+                Ok(())
+            }
+            ChessClientAction::Move(mv) => {
+                if color != self.position.to_move {
+                    return Err(anyhow!("It's not this user's turn"));
+                }
+
+                self.play_move(seat_idx, mv)
+            }
+        }
+    }
+
+    fn play_move(&mut self, seat_idx: usize, mv: ChessMove) -> anyhow::Result<()> {
+        if !self.position.is_legal(&mv) {
+            return Err(anyhow!("Illegal move {mv:?}"));
+        }
+
+        let mover = self.position.to_move;
+
+        // The move only counts if it was made in time
+        if let (Some(clocks), Some(started)) = (self.clocks.as_mut(), self.turn_started) {
+            let elapsed = started.elapsed();
+            let left = &mut clocks[mover.idx()];
+
+            if elapsed >= *left {
+                self.end_game(ChessOutcome::Timeout(mover.other()));
+                return Ok(());
+            }
+
+            let increment = self.time_control.map(|(_, secs)| secs).unwrap_or(0);
+            *left = *left - elapsed + Duration::from_secs(increment as u64);
+        }
+        self.turn_started = Some(Instant::now());
+
+        let text = self.position.notation(&mv);
+        self.position.apply(mv);
+        self.last_move = Some(mv);
+        self.moves.push(text.clone());
+        self.seats[seat_idx].moves_made += 1;
+
+        self.action
+            .push(ChessAction::Moved(self.seats[seat_idx].name.clone(), text));
+
+        if let Some(outcome) = self.position.outcome() {
+            self.end_game(outcome);
+            return Ok(());
+        }
+
+        let seen = self
+            .seen_positions
+            .entry(self.position.repetition_key())
+            .or_default();
+        *seen += 1;
+
+        if *seen >= REPETITION_LIMIT {
+            self.end_game(ChessOutcome::Repetition);
+        }
+
+        Ok(())
+    }
+
+    fn end_game(&mut self, outcome: ChessOutcome) {
+        // Freeze the clocks where they were when it finished
+        if let (Some(clocks), Some(started)) = (self.clocks.as_mut(), self.turn_started) {
+            let left = &mut clocks[self.position.to_move.idx()];
+            *left = left.saturating_sub(started.elapsed());
+        }
+        self.turn_started = None;
+
+        self.action.push(ChessAction::GameEnded(outcome.clone()));
+        self.outcome = Some(outcome);
+        self.start_state = GameStartState::Ending;
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return;
+        };
+
+        self.action
+            .push(ChessAction::UserLeft(self.seats[seat_idx].name.clone()));
+
+        match self.start_state {
+            GameStartState::Setup => {
+                self.seats.remove(seat_idx);
+            }
+            // The seat is kept so the board still shows who was playing what
+            GameStartState::Active => {
+                self.seats[seat_idx].left = true;
+                if let Some(color) = self.seats[seat_idx].color {
+                    self.end_game(ChessOutcome::Abandoned(color.other()));
+                }
+            }
+            GameStartState::Ending => {
+                self.seats[seat_idx].left = true;
+            }
+        }
+    }
+}
+
+impl RoomGame for ServerChess {
+    const GAME_TYPE: GameType = GameType::Chess;
+    const MAX_PLAYERS: usize = CHESS_PLAYERS;
+
+    type Action = ChessClientAction;
+    type State = ChessClientGameState;
+    type Message = ServerChessCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.seats
+            .iter()
+            .filter(|seat| !seat.left)
+            .map(|seat| seat.id)
+            .collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.seats
+            .push(ChessSeat::new(user_id, user.name.clone(), user.rating));
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        is_host: bool,
+        action: ChessClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, is_host, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        let winner = self.outcome.as_ref().and_then(|outcome| outcome.winner());
+
+        self.seats
+            .iter()
+            .map(|seat| {
+                // A draw puts both players level
+                let rank = match winner {
+                    Some(winner) if seat.color != Some(winner) => 2,
+                    _ => 1,
+                };
+
+                PlayerResult {
+                    user_id: seat.id,
+                    placement: (!seat.left).then_some(rank),
+                    rank,
+                    won: winner.is_some() && rank == 1,
+                    busted: false,
+                    cards_played: seat.moves_made,
+                }
+            })
+            .collect()
+    }
+
+    /// When the side to move runs out of time, if there's a clock running at all
+    fn deadline(&self) -> Option<Instant> {
+        if self.start_state != GameStartState::Active {
+            return None;
+        }
+
+        let clocks = self.clocks?;
+        let started = self.turn_started?;
+
+        Some(started + clocks[self.position.to_move.idx()])
+    }
+
+    /// Running out of time has to end the game even if nobody sends anything
+    fn timer_fired(&mut self) -> Option<ServerChessCommand> {
+        let winner = self.position.to_move.other();
+        self.end_game(ChessOutcome::Timeout(winner));
+        None
+    }
+
+    fn client_state(&mut self, host_user: u32) -> ChessClientGameState {
+        // The running clock is sent as it stands right now, the client counts it down from there
+        let clocks_ms = self.clocks.map(|mut clocks| {
+            if let Some(started) = self.turn_started {
+                let left = &mut clocks[self.position.to_move.idx()];
+                *left = left.saturating_sub(started.elapsed());
+            }
+            clocks.map(|left| left.as_millis() as u32)
+        });
+
+        ChessClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| ChessPlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    color: seat.color,
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            position: self.position.clone(),
+            moves: self.moves.clone(),
+            last_move: self.last_move,
+            time_control: self.time_control,
+            clocks_ms,
+            outcome: self.outcome.clone(),
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut ChessClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Nothing is hidden, everyone sees the same board
+    fn project(&self, state: ChessClientGameState, _viewer: Viewer) -> ServerChessCommand {
+        ServerChessCommand::GameState(state)
+    }
+}
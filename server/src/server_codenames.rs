@@ -0,0 +1,712 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::anyhow;
+use rand::{
+    Rng,
+    seq::{IndexedRandom, SliceRandom},
+};
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    codenames::{
+        CODENAMES_CARDS, CODENAMES_FIRST_TEAM_CARDS, CODENAMES_MAX_PLAYERS, CardKind, Clue,
+        CodenamesAction, CodenamesCard, CodenamesClientAction, CodenamesClientGameState,
+        CodenamesPlayer, Role, ServerCodenamesCommand, Team,
+    },
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+};
+use tokio::fs;
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+/// One word a line, blank lines and lines starting with # are skipped
+const WORDS_FILE: &str = "codenames_words.txt";
+
+/// Read once when the server starts, every game draws its grid from this.
+/// Without enough words there just aren't any Codenames games, so nothing comes back.
+pub async fn load_words() -> Vec<String> {
+    let text = match fs::read_to_string(WORDS_FILE).await {
+        Ok(text) => text,
+        Err(err) => {
+            println!("Failed to read codenames words from {WORDS_FILE} {err:?}");
+            return vec![];
+        }
+    };
+
+    let mut seen = HashSet::new();
+    let words: Vec<String> = text
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_uppercase())
+        .filter(|word| seen.insert(word.clone()))
+        .collect();
+
+    println!("Loaded {} codenames words", words.len());
+
+    if words.len() < CODENAMES_CARDS {
+        println!("Codenames needs at least {CODENAMES_CARDS} different words to make a grid");
+        return vec![];
+    }
+
+    words
+}
+
+/// Played in a `GameRoom`.
+/// Everyone shares the one state apart from the key, which `project` only gives the spymasters.
+pub struct ServerCodenames {
+    players: Vec<CodenamesSeat>,
+    /// Whoever left part way through, along with the team they were on
+    left_users: Vec<(u32, String, Team)>,
+    cards: Vec<CodenamesCard>,
+    /// Drawn when the game starts, since it depends on who goes first
+    key: Vec<CardKind>,
+    turn: Team,
+    clue: Option<Clue>,
+    guesses_left: u8,
+    /// Guesses on the current clue, the turn can only be passed after one
+    guesses_made: u8,
+    winner: Option<Team>,
+    start_state: GameStartState,
+    action: Vec<CodenamesAction>,
+    /// Clues given and guesses made, for the stats
+    moves_made: HashMap<u32, u32>,
+    /// Seeded by the room, enough to reproduce the grid and key
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct CodenamesSeat {
+    id: u32,
+    name: String,
+    team: Team,
+    role: Role,
+    rating: Option<u32>,
+}
+
+impl ServerCodenames {
+    pub fn new(mut rng: ChaCha8Rng, words: &[String]) -> anyhow::Result<ServerCodenames> {
+        if words.len() < CODENAMES_CARDS {
+            return Err(anyhow!("Not enough codenames words loaded to make a grid"));
+        }
+
+        // The words are up from the start so teams can be picked looking at them
+        let cards = words
+            .choose_multiple(&mut rng, CODENAMES_CARDS)
+            .map(|word| CodenamesCard {
+                word: word.clone(),
+                revealed: None,
+            })
+            .collect();
+
+        Ok(ServerCodenames {
+            players: vec![],
+            left_users: vec![],
+            cards,
+            key: vec![],
+            turn: Team::Red,
+            clue: None,
+            guesses_left: 0,
+            guesses_made: 0,
+            winner: None,
+            start_state: GameStartState::Setup,
+            action: vec![CodenamesAction::Init],
+            moves_made: HashMap::new(),
+            rng,
+        })
+    }
+
+    fn team_size(&self, team: Team) -> usize {
+        self.players
+            .iter()
+            .filter(|player| player.team == team)
+            .count()
+    }
+
+    fn spymaster(&self, team: Team) -> Option<&CodenamesSeat> {
+        self.players
+            .iter()
+            .find(|player| player.team == team && player.role == Role::Spymaster)
+    }
+
+    /// A team can only play with someone to give clues and someone to guess them
+    fn team_ready(&self, team: Team) -> bool {
+        self.spymaster(team).is_some()
+            && self
+                .players
+                .iter()
+                .any(|player| player.team == team && player.role == Role::Operative)
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if !self.team_ready(Team::Red) || !self.team_ready(Team::Blue) {
+            return Err(anyhow!(
+                "Both teams need a spymaster and at least one operative"
+            ));
+        }
+
+        self.turn = if self.rng.random_bool(0.5) {
+            Team::Red
+        } else {
+            Team::Blue
+        };
+
+        let mut key = vec![CardKind::Agent(self.turn); CODENAMES_FIRST_TEAM_CARDS];
+        key.extend([CardKind::Agent(self.turn.other()); CODENAMES_FIRST_TEAM_CARDS - 1]);
+        key.push(CardKind::Assassin);
+        key.resize(CODENAMES_CARDS, CardKind::Bystander);
+        key.shuffle(&mut self.rng);
+        self.key = key;
+
+        self.start_state = GameStartState::Active;
+        self.action.push(CodenamesAction::GameStarted(self.turn));
+
+        Ok(())
+    }
+
+    fn act(&mut self, user_id: u32, action: CodenamesClientAction) -> anyhow::Result<()> {
+        let Some(seat_idx) = self.players.iter().position(|player| player.id == user_id) else {
+            return Err(anyhow!("User not in game"));
+        };
+
+        if let CodenamesClientAction::Pick(team, role) = action {
+            return self.pick(seat_idx, team, role);
+        }
+
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("Received Game message when not active"));
+        }
+
+        let seat = &self.players[seat_idx];
+        if seat.team != self.turn {
+            return Err(anyhow!("Not this user's team's turn"));
+        }
+
+        match (action, seat.role) {
+            (CodenamesClientAction::GiveClue(clue), Role::Spymaster) => {
+                self.give_clue(seat_idx, clue)
+            }
+            (CodenamesClientAction::Guess(idx), Role::Operative) => {
+                self.guess(seat_idx, idx as usize)
+            }
+            (CodenamesClientAction::EndTurn, Role::Operative) => {
+                if self.clue.is_none() || self.guesses_made == 0 {
+                    return Err(anyhow!("At least one guess has to be made first"));
+                }
+
+                self.action
+                    .push(CodenamesAction::UserEndedTurn(seat.name.clone()));
+                self.end_turn();
+                Ok(())
+            }
+            (action, role) => Err(anyhow!("A {role:?} can't {action:?}")),
+        }
+    }
+
+    fn pick(&mut self, seat_idx: usize, team: Team, role: Role) -> anyhow::Result<()> {
+        if self.start_state != GameStartState::Setup {
+            return Err(anyhow!("Teams are fixed once the game starts"));
+        }
+
+        let seat_id = self.players[seat_idx].id;
+        if role == Role::Spymaster
+            && self
+                .spymaster(team)
+                .is_some_and(|spymaster| spymaster.id != seat_id)
+        {
+            return Err(anyhow!("{team:?} already has a spymaster"));
+        }
+
+        let seat = &mut self.players[seat_idx];
+        seat.team = team;
+        seat.role = role;
+        self.action
+            .push(CodenamesAction::UserMoved(seat.name.clone(), team, role));
+
+        Ok(())
+    }
+
+    fn give_clue(&mut self, seat_idx: usize, clue: Clue) -> anyhow::Result<()> {
+        if self.clue.is_some() {
+            return Err(anyhow!("There's already a clue being guessed"));
+        }
+
+        if !clue.is_valid(&self.cards) {
+            return Err(anyhow!("{clue:?} isn't a clue that can be given"));
+        }
+
+        let seat = &self.players[seat_idx];
+        self.action.push(CodenamesAction::ClueGiven(
+            seat.name.clone(),
+            self.turn,
+            clue.clone(),
+        ));
+        *self.moves_made.entry(seat.id).or_insert(0) += 1;
+
+        self.guesses_left = clue.count + 1;
+        self.guesses_made = 0;
+        self.clue = Some(clue);
+
+        Ok(())
+    }
+
+    /// Guessing carries on until a miss, running out of guesses or someone winning
+    fn guess(&mut self, seat_idx: usize, idx: usize) -> anyhow::Result<()> {
+        if self.clue.is_none() {
+            return Err(anyhow!("There's no clue to guess on yet"));
+        }
+
+        let Some(card) = self.cards.get_mut(idx) else {
+            return Err(anyhow!("No card at {idx}"));
+        };
+
+        if card.revealed.is_some() {
+            return Err(anyhow!("{} has already been guessed", card.word));
+        }
+
+        let kind = self.key[idx];
+        card.revealed = Some(kind);
+
+        let seat = &self.players[seat_idx];
+        self.action.push(CodenamesAction::UserGuessed(
+            seat.name.clone(),
+            card.word.clone(),
+            kind,
+        ));
+        *self.moves_made.entry(seat.id).or_insert(0) += 1;
+
+        self.guesses_made += 1;
+        self.guesses_left = self.guesses_left.saturating_sub(1);
+
+        match kind {
+            CardKind::Assassin => self.end_game(self.turn.other()),
+            CardKind::Agent(team) if self.remaining(team) == 0 => self.end_game(team),
+            CardKind::Agent(team) if team == self.turn && self.guesses_left > 0 => {}
+            CardKind::Agent(_) | CardKind::Bystander => self.end_turn(),
+        }
+
+        Ok(())
+    }
+
+    fn remaining(&self, team: Team) -> u8 {
+        self.cards
+            .iter()
+            .zip(self.key.iter())
+            .filter(|(card, kind)| card.revealed.is_none() && **kind == CardKind::Agent(team))
+            .count() as u8
+    }
+
+    fn end_turn(&mut self) {
+        self.turn = self.turn.other();
+        self.clue = None;
+        self.guesses_left = 0;
+        self.guesses_made = 0;
+    }
+
+    fn end_game(&mut self, winner: Team) {
+        self.winner = Some(winner);
+        self.clue = None;
+        self.guesses_left = 0;
+        self.action.push(CodenamesAction::TeamWon(winner));
+        self.action.push(CodenamesAction::GameEnded);
+        self.start_state = GameStartState::Ending;
+    }
+
+    /// A team left without a spymaster or anyone to guess can't carry on, so they lose
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.players.iter().position(|player| player.id == user_id) else {
+            return;
+        };
+
+        let seat = self.players.remove(seat_idx);
+        self.action
+            .push(CodenamesAction::UserLeft(seat.name.clone()));
+
+        if self.start_state != GameStartState::Active {
+            return;
+        }
+
+        self.left_users.push((seat.id, seat.name, seat.team));
+
+        if !self.team_ready(seat.team) {
+            self.end_game(seat.team.other());
+        }
+    }
+}
+
+impl RoomGame for ServerCodenames {
+    const GAME_TYPE: GameType = GameType::Codenames;
+    const MAX_PLAYERS: usize = CODENAMES_MAX_PLAYERS;
+
+    type Action = CodenamesClientAction;
+    type State = CodenamesClientGameState;
+    type Message = ServerCodenamesCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.players.iter().map(|player| player.id).collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.players
+            .iter()
+            .map(|player| LobbyPlayer {
+                name: player.name.clone(),
+                rating: player.rating,
+            })
+            .collect()
+    }
+
+    /// New players even the teams up, and take the spymaster spot if it's free
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        let team = if self.team_size(Team::Blue) < self.team_size(Team::Red) {
+            Team::Blue
+        } else {
+            Team::Red
+        };
+        let role = if self.spymaster(team).is_none() {
+            Role::Spymaster
+        } else {
+            Role::Operative
+        };
+
+        self.action
+            .push(CodenamesAction::UserJoined(user.name.clone()));
+        self.players.push(CodenamesSeat {
+            id: user_id,
+            name: user.name.clone(),
+            team,
+            role,
+            rating: Some(user.rating),
+        });
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: CodenamesClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    /// The whole winning team shares first place
+    fn results(&self) -> Vec<PlayerResult> {
+        let Some(winner) = self.winner else {
+            return vec![];
+        };
+
+        self.players
+            .iter()
+            .map(|player| (player.id, player.team, false))
+            .chain(
+                self.left_users
+                    .iter()
+                    .map(|(id, _, team)| (*id, *team, true)),
+            )
+            .map(|(user_id, team, left)| {
+                let rank = if team == winner { 1 } else { 2 };
+
+                PlayerResult {
+                    user_id,
+                    placement: (!left).then_some(rank),
+                    rank,
+                    won: team == winner,
+                    busted: false,
+                    cards_played: self.moves_made.get(&user_id).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+
+    fn client_state(&mut self, host_user: u32) -> CodenamesClientGameState {
+        CodenamesClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .players
+                .iter()
+                .map(|player| CodenamesPlayer {
+                    id: player.id,
+                    name: player.name.clone(),
+                    team: player.team,
+                    role: player.role,
+                    rating: player.rating,
+                })
+                .collect(),
+            host_user,
+            cards: self.cards.clone(),
+            key: None,
+            turn: self.turn,
+            clue: self.clue.clone(),
+            guesses_left: self.guesses_left,
+            remaining: [self.remaining(Team::Red), self.remaining(Team::Blue)],
+            winner: self.winner,
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut CodenamesClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Spymasters see the key, operatives and spectators only get it once the game is over
+    fn project(
+        &self,
+        mut state: CodenamesClientGameState,
+        viewer: Viewer,
+    ) -> ServerCodenamesCommand {
+        let is_spymaster = match viewer {
+            Viewer::Player(user_id) => self
+                .players
+                .iter()
+                .any(|player| player.id == user_id && player.role == Role::Spymaster),
+            Viewer::Spectator => false,
+        };
+
+        if !self.key.is_empty() && (is_spymaster || self.start_state == GameStartState::Ending) {
+            state.key = Some(self.key.clone());
+        }
+
+        ServerCodenamesCommand::GameState(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rpc::codenames::CODENAMES_MAX_CLUE_LEN;
+
+    use super::*;
+
+    const RED_SPYMASTER: u32 = 0;
+    const RED_OPERATIVE: u32 = 1;
+    const BLUE_SPYMASTER: u32 = 2;
+    const BLUE_OPERATIVE: u32 = 3;
+
+    /// Red to go, with the first nine cards theirs, the next eight blue's, then the assassin.
+    /// The words are all letters, so any of them could be a clue.
+    fn game() -> ServerCodenames {
+        let words: Vec<String> = (b'A'..)
+            .take(CODENAMES_CARDS)
+            .map(|letter| format!("WORD{}", letter as char))
+            .collect();
+        let mut game = ServerCodenames::new(ChaCha8Rng::seed_from_u64(0), &words).unwrap();
+
+        for (id, team, role) in [
+            (RED_SPYMASTER, Team::Red, Role::Spymaster),
+            (RED_OPERATIVE, Team::Red, Role::Operative),
+            (BLUE_SPYMASTER, Team::Blue, Role::Spymaster),
+            (BLUE_OPERATIVE, Team::Blue, Role::Operative),
+        ] {
+            game.players.push(CodenamesSeat {
+                id,
+                name: format!("Player {id}"),
+                team,
+                role,
+                rating: None,
+            });
+        }
+
+        game.start_game().unwrap();
+        game.turn = Team::Red;
+        game.key = (0..CODENAMES_CARDS)
+            .map(|idx| match idx {
+                0..9 => CardKind::Agent(Team::Red),
+                9..17 => CardKind::Agent(Team::Blue),
+                17 => CardKind::Assassin,
+                _ => CardKind::Bystander,
+            })
+            .collect();
+        game
+    }
+
+    fn clue(word: &str, count: u8) -> CodenamesClientAction {
+        CodenamesClientAction::GiveClue(Clue {
+            word: word.to_string(),
+            count,
+        })
+    }
+
+    fn key_seen_by(game: &mut ServerCodenames, viewer: Viewer) -> bool {
+        let state = game.client_state(RED_SPYMASTER);
+        let ServerCodenamesCommand::GameState(state) = game.project(state, viewer);
+        state.key.is_some()
+    }
+
+    #[test]
+    fn clues_are_checked() {
+        let mut game = game();
+        let on_board = game.cards[3].word.to_lowercase();
+
+        for bad in [
+            clue("", 1),
+            clue("two words", 1),
+            clue("R2D2", 1),
+            clue("A".repeat(CODENAMES_MAX_CLUE_LEN + 1).as_str(), 1),
+            clue("fruit", 10),
+            // Words still on the board can't be used, whatever the case
+            clue(&on_board, 1),
+        ] {
+            assert!(game.act(RED_SPYMASTER, bad).is_err());
+        }
+
+        // Only the spymaster whose turn it is
+        assert!(game.act(RED_OPERATIVE, clue("fruit", 2)).is_err());
+        assert!(game.act(BLUE_SPYMASTER, clue("fruit", 2)).is_err());
+
+        game.act(RED_SPYMASTER, clue("fruit", 2)).unwrap();
+        assert_eq!(game.guesses_left, 3);
+        assert!(game.act(RED_SPYMASTER, clue("veg", 1)).is_err());
+
+        // Once a word is guessed it's fair game
+        game.act(RED_OPERATIVE, CodenamesClientAction::Guess(3))
+            .unwrap();
+        game.act(RED_OPERATIVE, CodenamesClientAction::EndTurn)
+            .unwrap();
+        assert!(game.act(BLUE_SPYMASTER, clue(&on_board, 1)).is_ok());
+    }
+
+    #[test]
+    fn guessing_waits_on_a_clue() {
+        let mut game = game();
+
+        assert!(
+            game.act(RED_OPERATIVE, CodenamesClientAction::Guess(0))
+                .is_err()
+        );
+        assert!(
+            game.act(RED_OPERATIVE, CodenamesClientAction::EndTurn)
+                .is_err()
+        );
+
+        game.act(RED_SPYMASTER, clue("fruit", 1)).unwrap();
+        // Nor can the turn be passed without a guess
+        assert!(
+            game.act(RED_OPERATIVE, CodenamesClientAction::EndTurn)
+                .is_err()
+        );
+        assert!(
+            game.act(BLUE_OPERATIVE, CodenamesClientAction::Guess(9))
+                .is_err()
+        );
+        assert!(
+            game.act(RED_SPYMASTER, CodenamesClientAction::Guess(0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn wrong_guess_ends_the_turn() {
+        let mut game = game();
+        game.act(RED_SPYMASTER, clue("fruit", 3)).unwrap();
+
+        game.act(RED_OPERATIVE, CodenamesClientAction::Guess(0))
+            .unwrap();
+        assert_eq!(game.turn, Team::Red);
+
+        game.act(RED_OPERATIVE, CodenamesClientAction::Guess(20))
+            .unwrap();
+        assert_eq!(game.cards[20].revealed, Some(CardKind::Bystander));
+        assert_eq!(game.turn, Team::Blue);
+        assert_eq!(game.clue, None);
+
+        // Finding the other team's agent helps them out
+        game.act(BLUE_SPYMASTER, clue("veg", 2)).unwrap();
+        game.act(BLUE_OPERATIVE, CodenamesClientAction::Guess(1))
+            .unwrap();
+        assert_eq!(game.turn, Team::Red);
+        assert_eq!(game.remaining(Team::Red), 7);
+        assert!(
+            game.act(BLUE_OPERATIVE, CodenamesClientAction::Guess(1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn guesses_run_out() {
+        let mut game = game();
+        game.act(RED_SPYMASTER, clue("fruit", 1)).unwrap();
+
+        game.act(RED_OPERATIVE, CodenamesClientAction::Guess(0))
+            .unwrap();
+        assert_eq!(game.turn, Team::Red);
+        game.act(RED_OPERATIVE, CodenamesClientAction::Guess(1))
+            .unwrap();
+
+        assert_eq!(game.turn, Team::Blue);
+        assert_eq!(game.remaining(Team::Red), 7);
+    }
+
+    #[test]
+    fn assassin_loses() {
+        let mut game = game();
+        game.act(RED_SPYMASTER, clue("fruit", 1)).unwrap();
+        game.act(RED_OPERATIVE, CodenamesClientAction::Guess(17))
+            .unwrap();
+
+        assert_eq!(game.winner, Some(Team::Blue));
+        assert_eq!(game.start_state, GameStartState::Ending);
+
+        let results = game.results();
+        assert!(
+            results
+                .iter()
+                .all(|result| result.won == (result.user_id >= BLUE_SPYMASTER))
+        );
+    }
+
+    #[test]
+    fn finding_every_agent_wins() {
+        let mut game = game();
+        game.act(RED_SPYMASTER, clue("fruit", 9)).unwrap();
+
+        for idx in 0..9 {
+            game.act(RED_OPERATIVE, CodenamesClientAction::Guess(idx))
+                .unwrap();
+        }
+
+        assert_eq!(game.winner, Some(Team::Red));
+        assert_eq!(game.start_state, GameStartState::Ending);
+    }
+
+    #[test]
+    fn only_spymasters_see_the_key() {
+        let mut game = game();
+
+        assert!(key_seen_by(&mut game, Viewer::Player(RED_SPYMASTER)));
+        assert!(key_seen_by(&mut game, Viewer::Player(BLUE_SPYMASTER)));
+        assert!(!key_seen_by(&mut game, Viewer::Player(RED_OPERATIVE)));
+        assert!(!key_seen_by(&mut game, Viewer::Player(BLUE_OPERATIVE)));
+        assert!(!key_seen_by(&mut game, Viewer::Spectator));
+
+        // Everyone gets to see it once it's over
+        game.end_game(Team::Red);
+        assert!(key_seen_by(&mut game, Viewer::Player(RED_OPERATIVE)));
+        assert!(key_seen_by(&mut game, Viewer::Spectator));
+    }
+
+    #[test]
+    fn too_few_words_wont_make_a_grid() {
+        let words: Vec<String> = (1..CODENAMES_CARDS)
+            .map(|idx| format!("WORD{idx}"))
+            .collect();
+
+        assert!(ServerCodenames::new(ChaCha8Rng::seed_from_u64(0), &words).is_err());
+    }
+}
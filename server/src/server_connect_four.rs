@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    comms::LobbyPlayer,
+    connect_four::{
+        ConnectFourAction, ConnectFourBoard, ConnectFourClientAction, ConnectFourClientGameState,
+        ConnectFourPlayer, ConnectFourResult, Disc, ServerConnectFourCommand, board_full, drop_row,
+        empty_board, winning_line,
+    },
+    game_state::{GameStartState, GameType},
+};
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+const CONNECT_FOUR_PLAYERS: usize = 2;
+
+/// Played in a `GameRoom`.
+/// Either player leaving part way hands the game to the other one.
+pub struct ServerConnectFour {
+    seats: Vec<ConnectFourSeat>,
+    board: ConnectFourBoard,
+    turn: Disc,
+    last_drop: Option<(u8, u8)>,
+    winning_line: Vec<(u8, u8)>,
+    result: Option<ConnectFourResult>,
+    start_state: GameStartState,
+    /// Filled in once the game is over, with each player's rank and whether they left
+    standings: Vec<(u32, u32, bool)>,
+    action: Vec<ConnectFourAction>,
+    discs_dropped: HashMap<u32, u32>,
+    /// Seeded by the room, enough to reproduce who went first
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct ConnectFourSeat {
+    id: u32,
+    name: String,
+    disc: Option<Disc>,
+    rating: Option<u32>,
+    /// Left part way, the seat stays so the board still shows whose discs they were
+    left: bool,
+}
+
+impl ConnectFourSeat {
+    fn new(id: u32, name: String, rating: u32) -> ConnectFourSeat {
+        ConnectFourSeat {
+            id,
+            name,
+            disc: None,
+            rating: Some(rating),
+            left: false,
+        }
+    }
+}
+
+impl ServerConnectFour {
+    pub fn new(rng: ChaCha8Rng) -> ServerConnectFour {
+        ServerConnectFour {
+            seats: vec![],
+            board: empty_board(),
+            turn: Disc::Red,
+            last_drop: None,
+            winning_line: vec![],
+            result: None,
+            start_state: GameStartState::Setup,
+            standings: vec![],
+            action: vec![ConnectFourAction::Init],
+            discs_dropped: HashMap::new(),
+            rng,
+        }
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if self.seats.len() != CONNECT_FOUR_PLAYERS {
+            return Err(anyhow!(
+                "Connect Four needs exactly {CONNECT_FOUR_PLAYERS} players"
+            ));
+        }
+
+        // Red goes first, so who gets it is left to chance
+        self.seats.shuffle(&mut self.rng);
+        self.seats[0].disc = Some(Disc::Red);
+        self.seats[1].disc = Some(Disc::Yellow);
+
+        self.start_state = GameStartState::Active;
+        self.action
+            .push(ConnectFourAction::GameStarted(self.seats[0].name.clone()));
+
+        Ok(())
+    }
+
+    fn act(&mut self, user_id: u32, action: ConnectFourClientAction) -> anyhow::Result<()> {
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("The game isn't being played"));
+        }
+
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return Err(anyhow!("User isn't seated"));
+        };
+
+        if self.seats[seat_idx].disc != Some(self.turn) {
+            return Err(anyhow!("It's not this user's turn"));
+        }
+
+        match action {
+            ConnectFourClientAction::Drop(column) => self.drop_disc(seat_idx, column as usize),
+        }
+    }
+
+    fn drop_disc(&mut self, seat_idx: usize, column: usize) -> anyhow::Result<()> {
+        let Some(row) = drop_row(&self.board, column) else {
+            return Err(anyhow!("Column {column} is full or off the board"));
+        };
+
+        self.board[row][column] = Some(self.turn);
+        self.last_drop = Some((row as u8, column as u8));
+        *self
+            .discs_dropped
+            .entry(self.seats[seat_idx].id)
+            .or_default() += 1;
+
+        self.action.push(ConnectFourAction::Dropped(
+            self.seats[seat_idx].name.clone(),
+            column as u8,
+        ));
+
+        if let Some(line) = winning_line(&self.board, row, column) {
+            self.winning_line = line;
+            self.end_game(ConnectFourResult::Won(self.seats[seat_idx].name.clone()));
+            return Ok(());
+        }
+
+        if board_full(&self.board) {
+            self.end_game(ConnectFourResult::Draw);
+            return Ok(());
+        }
+
+        self.turn = self.turn.other();
+
+        Ok(())
+    }
+
+    fn end_game(&mut self, result: ConnectFourResult) {
+        self.standings = self
+            .seats
+            .iter()
+            .map(|seat| {
+                let rank = match &result {
+                    ConnectFourResult::Draw => 1,
+                    ConnectFourResult::Won(name) | ConnectFourResult::Forfeit(name) => {
+                        if *name == seat.name { 1 } else { 2 }
+                    }
+                };
+                (seat.id, rank, seat.left)
+            })
+            .collect();
+
+        self.action
+            .push(ConnectFourAction::GameEnded(result.clone()));
+        self.result = Some(result);
+        self.start_state = GameStartState::Ending;
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return;
+        };
+
+        self.action.push(ConnectFourAction::UserLeft(
+            self.seats[seat_idx].name.clone(),
+        ));
+
+        match self.start_state {
+            GameStartState::Setup => {
+                self.seats.remove(seat_idx);
+            }
+            // The seat is kept so the board still shows whose discs they were
+            GameStartState::Active => {
+                self.seats[seat_idx].left = true;
+                let winner = self.seats[1 - seat_idx].name.clone();
+                self.end_game(ConnectFourResult::Forfeit(winner));
+            }
+            GameStartState::Ending => {
+                self.seats[seat_idx].left = true;
+            }
+        }
+    }
+}
+
+impl RoomGame for ServerConnectFour {
+    const GAME_TYPE: GameType = GameType::ConnectFour;
+    const MAX_PLAYERS: usize = CONNECT_FOUR_PLAYERS;
+
+    type Action = ConnectFourClientAction;
+    type State = ConnectFourClientGameState;
+    type Message = ServerConnectFourCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.seats
+            .iter()
+            .filter(|seat| !seat.left)
+            .map(|seat| seat.id)
+            .collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.seats.push(ConnectFourSeat::new(
+            user_id,
+            user.name.clone(),
+            user.rating,
+        ));
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: ConnectFourClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        let draw = self.result == Some(ConnectFourResult::Draw);
+
+        self.standings
+            .iter()
+            .map(|(user_id, rank, left)| PlayerResult {
+                user_id: *user_id,
+                placement: (!left).then_some(*rank),
+                rank: *rank,
+                won: *rank == 1 && !draw,
+                busted: false,
+                cards_played: self.discs_dropped.get(user_id).copied().unwrap_or(0),
+            })
+            .collect()
+    }
+
+    fn client_state(&mut self, host_user: u32) -> ConnectFourClientGameState {
+        ConnectFourClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| ConnectFourPlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    disc: seat.disc,
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            board: self.board,
+            turn: self.turn,
+            last_drop: self.last_drop,
+            winning_line: self.winning_line.clone(),
+            result: self.result.clone(),
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut ConnectFourClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Nothing is hidden, everyone sees the same board
+    fn project(
+        &self,
+        state: ConnectFourClientGameState,
+        _viewer: Viewer,
+    ) -> ServerConnectFourCommand {
+        ServerConnectFourCommand::GameState(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use rpc::connect_four::CONNECT_FOUR_ROWS;
+
+    /// Started between users 0 and 1, returning the game and the user holding red.
+    fn game() -> (ServerConnectFour, u32) {
+        let mut game = ServerConnectFour::new(ChaCha8Rng::seed_from_u64(0));
+        game.seats = vec![
+            ConnectFourSeat::new(0, "Player 0".into(), 1000),
+            ConnectFourSeat::new(1, "Player 1".into(), 1000),
+        ];
+        game.start_game().unwrap();
+        let red = game.seats[0].id;
+        (game, red)
+    }
+
+    fn drop(game: &mut ServerConnectFour, user_id: u32, column: u8) -> anyhow::Result<()> {
+        game.act(user_id, ConnectFourClientAction::Drop(column))
+    }
+
+    #[test]
+    fn moves_out_of_turn_are_refused() {
+        let (mut game, red) = game();
+        let yellow = 1 - red;
+
+        assert!(drop(&mut game, yellow, 3).is_err());
+        assert_eq!(game.board, empty_board());
+
+        drop(&mut game, red, 3).unwrap();
+        assert!(drop(&mut game, red, 3).is_err());
+        drop(&mut game, yellow, 3).unwrap();
+        assert_eq!(game.turn, Disc::Red);
+    }
+
+    #[test]
+    fn full_columns_are_refused() {
+        let (mut game, red) = game();
+        let yellow = 1 - red;
+
+        for row in 0..3 {
+            drop(&mut game, red, 0).unwrap();
+            drop(&mut game, yellow, 0).unwrap();
+            assert_eq!(game.board[row * 2][0], Some(Disc::Red));
+        }
+
+        assert!(drop(&mut game, red, 0).is_err());
+        assert_eq!(game.turn, Disc::Red);
+        drop(&mut game, red, 1).unwrap();
+    }
+
+    #[test]
+    fn four_in_a_row_wins() {
+        let (mut game, red) = game();
+        let yellow = 1 - red;
+
+        for column in 0..3 {
+            drop(&mut game, red, column).unwrap();
+            drop(&mut game, yellow, column).unwrap();
+        }
+        drop(&mut game, red, 3).unwrap();
+
+        assert_eq!(game.winning_line.len(), 4);
+        assert_eq!(
+            game.result,
+            Some(ConnectFourResult::Won(game.seats[0].name.clone()))
+        );
+        assert_eq!(game.start_state, GameStartState::Ending);
+        assert!(drop(&mut game, yellow, 4).is_err());
+    }
+
+    #[test]
+    fn full_board_is_a_draw() {
+        let (mut game, red) = game();
+
+        // Pairs of colours that shift every row never line up four
+        for (row, cells) in game.board.iter_mut().enumerate() {
+            let pattern = if row % 2 == 0 { "RRYYRRY" } else { "YYRRYYR" };
+            for (cell, disc) in cells.iter_mut().zip(pattern.chars()) {
+                *cell = Some(if disc == 'R' { Disc::Red } else { Disc::Yellow });
+            }
+        }
+        game.board[CONNECT_FOUR_ROWS - 1][6] = None;
+
+        drop(&mut game, red, 6).unwrap();
+
+        assert!(game.winning_line.is_empty());
+        assert_eq!(game.result, Some(ConnectFourResult::Draw));
+        assert_eq!(game.start_state, GameStartState::Ending);
+    }
+}
@@ -0,0 +1,587 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    cards::{PlayingCard, Rank, Suit},
+    comms::LobbyPlayer,
+    crazy_eights::{
+        EIGHTS_MAX_PLAYERS, EightsAction, EightsActiveUser, EightsClientAction,
+        EightsClientGameState, ServerEightsCommand, can_play,
+    },
+    game_state::{GameStartState, GameType},
+};
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+/// Two players get 7 cards each, anything bigger gets 5
+const HEADS_UP_HAND: usize = 7;
+const HAND: usize = 5;
+
+/// Played in a `GameRoom`.
+/// Crazy Eights is kept simple, there are no bots, turn timers or replays.
+pub struct ServerEights {
+    draw_pile: Vec<PlayingCard>,
+    /// Everything played so far, the top card is the last one
+    discard_pile: Vec<PlayingCard>,
+    active_users: Vec<EightsUser>,
+    /// Whoever left part way through, in the order they went
+    left_users: Vec<(u32, String)>,
+    user_turn: usize,
+    /// The suit to follow, an eight can change it from the top card's
+    suit: Suit,
+    start_state: GameStartState,
+    /// Filled in once someone wins, best placed first with their rank
+    standings: Vec<(u32, String, u32)>,
+    action: Vec<EightsAction>,
+    cards_played: HashMap<u32, u32>,
+    /// Seeded by the room, enough to reproduce a deal
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct EightsUser {
+    id: u32,
+    name: String,
+    cards: Vec<PlayingCard>,
+    rating: Option<u32>,
+}
+
+impl ServerEights {
+    pub fn new(rng: ChaCha8Rng) -> ServerEights {
+        ServerEights {
+            draw_pile: vec![],
+            discard_pile: vec![],
+            active_users: vec![],
+            left_users: vec![],
+            user_turn: 0,
+            suit: Suit::Clubs,
+            start_state: GameStartState::Setup,
+            standings: vec![],
+            action: vec![EightsAction::Init],
+            cards_played: HashMap::new(),
+            rng,
+        }
+    }
+
+    /// The deck is shuffled from the seed and everyone is dealt in.
+    /// An eight can't be the first card, it goes back under the pile.
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if self.active_users.len() < 2 {
+            return Err(anyhow!("Need at least 2 people to play"));
+        }
+
+        self.draw_pile = PlayingCard::deck();
+        self.draw_pile.shuffle(&mut self.rng);
+
+        let hand_size = if self.active_users.len() == 2 {
+            HEADS_UP_HAND
+        } else {
+            HAND
+        };
+
+        for user in self.active_users.iter_mut() {
+            let split = self.draw_pile.len() - hand_size;
+            user.cards = self.draw_pile.split_off(split);
+        }
+
+        let top_card = loop {
+            let card = self
+                .draw_pile
+                .pop()
+                .ok_or(anyhow!("Ran out of cards while dealing"))?;
+
+            if card.rank != Rank::Eight {
+                break card;
+            }
+            self.draw_pile.insert(0, card);
+        };
+
+        self.discard_pile = vec![top_card];
+        self.suit = top_card.suit;
+        self.user_turn = 0;
+        self.start_state = GameStartState::Active;
+        self.action.push(EightsAction::InitialCard(top_card));
+
+        Ok(())
+    }
+
+    fn act(&mut self, user_id: u32, action: EightsClientAction) -> anyhow::Result<()> {
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("Received Game message when not active"));
+        }
+
+        let Some(user_idx) = self.active_users.iter().position(|user| user.id == user_id) else {
+            return Err(anyhow!("User not in game"));
+        };
+
+        if user_idx != self.user_turn {
+            return Err(anyhow!("Not this user's turn"));
+        }
+
+        let top_card = *self
+            .discard_pile
+            .last()
+            .ok_or(anyhow!("Nothing has been played"))?;
+
+        match action {
+            EightsClientAction::Play(card, named_suit) => {
+                if !can_play(&card, &top_card, self.suit) {
+                    return Err(anyhow!("{card} can't go on {top_card}"));
+                }
+
+                let new_suit = match (card.rank, named_suit) {
+                    (Rank::Eight, Some(suit)) => suit,
+                    (Rank::Eight, None) => return Err(anyhow!("An eight needs a suit named")),
+                    _ => card.suit,
+                };
+
+                let user = &mut self.active_users[user_idx];
+                let Some(card_idx) = user.cards.iter().position(|held| *held == card) else {
+                    return Err(anyhow!("User doesn't have {card}"));
+                };
+
+                user.cards.remove(card_idx);
+                self.action
+                    .push(EightsAction::UserPlayed(user.name.clone(), card));
+                if card.rank == Rank::Eight {
+                    self.action
+                        .push(EightsAction::UserNamedSuit(user.name.clone(), new_suit));
+                }
+
+                self.discard_pile.push(card);
+                self.suit = new_suit;
+                *self.cards_played.entry(user_id).or_insert(0) += 1;
+
+                if self.active_users[user_idx].cards.is_empty() {
+                    self.user_won(user_idx);
+                } else {
+                    self.user_turn = (self.user_turn + 1) % self.active_users.len();
+                }
+            }
+            EightsClientAction::Draw => {
+                if self.active_users[user_idx]
+                    .cards
+                    .iter()
+                    .any(|card| can_play(card, &top_card, self.suit))
+                {
+                    return Err(anyhow!("Can't draw with a card to play"));
+                }
+
+                let mut drawn = 0;
+                let playable = loop {
+                    if self.draw_pile.is_empty() {
+                        self.reshuffle();
+                    }
+
+                    let Some(card) = self.draw_pile.pop() else {
+                        break false;
+                    };
+
+                    drawn += 1;
+                    self.active_users[user_idx].cards.push(card);
+
+                    if can_play(&card, &top_card, self.suit) {
+                        break true;
+                    }
+                };
+
+                let name = self.active_users[user_idx].name.clone();
+                if drawn > 0 {
+                    self.action
+                        .push(EightsAction::UserDrew(name.clone(), drawn));
+                }
+
+                // With every card in someone's hand there's nothing to do but move on
+                if !playable {
+                    self.action.push(EightsAction::UserPassed(name));
+                    self.user_turn = (self.user_turn + 1) % self.active_users.len();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Everything under the top card goes back into the draw pile
+    fn reshuffle(&mut self) {
+        let Some(top_card) = self.discard_pile.pop() else {
+            return;
+        };
+
+        self.draw_pile.append(&mut self.discard_pile);
+        self.draw_pile.shuffle(&mut self.rng);
+        self.discard_pile.push(top_card);
+    }
+
+    /// The rest are placed by how many cards they were left holding,
+    ///  anyone who left is behind them, whoever left first last
+    fn user_won(&mut self, winner_idx: usize) {
+        let winner = self.active_users.remove(winner_idx);
+
+        self.action.push(EightsAction::UserWon(winner.name.clone()));
+        self.standings = vec![(winner.id, winner.name, 1)];
+
+        let mut rest: Vec<&EightsUser> = self.active_users.iter().collect();
+        rest.sort_by_key(|user| user.cards.len());
+
+        for user in rest.iter() {
+            let fewer = rest
+                .iter()
+                .filter(|other| other.cards.len() < user.cards.len())
+                .count();
+            self.standings
+                .push((user.id, user.name.clone(), fewer as u32 + 2));
+        }
+
+        for (user_id, name) in self.left_users.iter().rev() {
+            let rank = self.standings.len() as u32 + 1;
+            self.standings.push((*user_id, name.clone(), rank));
+        }
+
+        self.action.push(EightsAction::GameEnded);
+        self.start_state = GameStartState::Ending;
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(user_idx) = self.active_users.iter().position(|user| user.id == user_id) else {
+            if let Some((_, name, _)) = self.standings.iter().find(|(id, _, _)| *id == user_id) {
+                self.action.push(EightsAction::UserLeft(name.clone()));
+            }
+            return;
+        };
+
+        let user = self.active_users.remove(user_idx);
+        self.action.push(EightsAction::UserLeft(user.name.clone()));
+
+        if self.start_state != GameStartState::Active {
+            return;
+        }
+
+        // Their cards go to the bottom of the pile, they can still come up later
+        self.draw_pile.splice(0..0, user.cards);
+        self.left_users.push((user.id, user.name));
+
+        if user_idx < self.user_turn {
+            self.user_turn -= 1;
+        }
+        if self.user_turn >= self.active_users.len() {
+            self.user_turn = 0;
+        }
+
+        // Whoever is left on their own wins by default
+        if self.active_users.len() == 1 {
+            self.user_won(0);
+        }
+    }
+}
+
+impl RoomGame for ServerEights {
+    const GAME_TYPE: GameType = GameType::CrazyEights;
+    const MAX_PLAYERS: usize = EIGHTS_MAX_PLAYERS;
+
+    type Action = EightsClientAction;
+    type State = EightsClientGameState;
+    type Message = ServerEightsCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.active_users.iter().map(|user| user.id).collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.active_users
+            .iter()
+            .map(|user| LobbyPlayer {
+                name: user.name.clone(),
+                rating: user.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.action
+            .push(EightsAction::UserJoined(user.name.clone()));
+        self.active_users.push(EightsUser {
+            id: user_id,
+            name: user.name.clone(),
+            cards: vec![],
+            rating: Some(user.rating),
+        });
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: EightsClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        self.standings
+            .iter()
+            .enumerate()
+            .map(|(idx, (user_id, _, rank))| {
+                let left = self.left_users.iter().any(|(id, _)| id == user_id);
+
+                PlayerResult {
+                    user_id: *user_id,
+                    placement: (!left).then_some(idx as u32 + 1),
+                    rank: *rank,
+                    won: *rank == 1,
+                    busted: false,
+                    cards_played: self.cards_played.get(user_id).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+
+    fn client_state(&mut self, host_user: u32) -> EightsClientGameState {
+        EightsClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            active_users: self
+                .active_users
+                .iter()
+                .map(|user| EightsActiveUser {
+                    id: user.id,
+                    name: user.name.clone(),
+                    card_count: user.cards.len() as u32,
+                    rating: user.rating,
+                })
+                .collect(),
+            host_user,
+            user_turn: self.user_turn as u8,
+            top_card: self.discard_pile.last().copied(),
+            suit: self.suit,
+            draw_pile: self.draw_pile.len() as u32,
+            standings: self
+                .standings
+                .iter()
+                .map(|(id, name, _)| (*id, name.clone()))
+                .collect(),
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut EightsClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Everyone only gets their own hand, the rest just see how many cards are held
+    fn project(&self, state: EightsClientGameState, viewer: Viewer) -> ServerEightsCommand {
+        let cards = match viewer {
+            Viewer::Player(user_id) => self
+                .active_users
+                .iter()
+                .find(|user| user.id == user_id)
+                .map(|user| user.cards.clone())
+                .unwrap_or_default(),
+            Viewer::Spectator => vec![],
+        };
+
+        ServerEightsCommand::GameState(cards, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::test_cards::{card, cards};
+
+    /// User `idx` holds `hands[idx]` and it's user 0's turn.
+    /// The draw pile is given in the order it'll be drawn.
+    fn table(hands: &[&str], top: &str, draw: &str) -> ServerEights {
+        let mut game = ServerEights::new(ChaCha8Rng::seed_from_u64(0));
+        for (id, hand) in hands.iter().enumerate() {
+            game.active_users.push(EightsUser {
+                id: id as u32,
+                name: format!("Player {id}"),
+                cards: cards(hand),
+                rating: None,
+            });
+        }
+
+        game.draw_pile = cards(draw);
+        game.draw_pile.reverse();
+        game.discard_pile = vec![card(top)];
+        game.suit = card(top).suit;
+        game.start_state = GameStartState::Active;
+        game
+    }
+
+    fn play(game: &mut ServerEights, user_id: u32, text: &str) -> anyhow::Result<()> {
+        game.act(user_id, EightsClientAction::Play(card(text), None))
+    }
+
+    #[test]
+    fn deal_never_starts_on_an_eight() {
+        for seed in 0..50 {
+            let mut game = ServerEights::new(ChaCha8Rng::seed_from_u64(seed));
+            for id in 0..2 {
+                game.active_users.push(EightsUser {
+                    id,
+                    name: format!("Player {id}"),
+                    cards: vec![],
+                    rating: None,
+                });
+            }
+            game.start_game().unwrap();
+
+            assert_ne!(game.discard_pile[0].rank, Rank::Eight);
+            assert!(
+                game.active_users
+                    .iter()
+                    .all(|user| user.cards.len() == HEADS_UP_HAND)
+            );
+        }
+    }
+
+    #[test]
+    fn eight_is_wild_and_names_the_suit() {
+        let mut game = table(&["8s 2c", "3h 4d 5c"], "Kh", "");
+
+        // Any suit can be named, but one has to be
+        assert!(play(&mut game, 0, "8s").is_err());
+        game.act(
+            0,
+            EightsClientAction::Play(card("8s"), Some(Suit::Diamonds)),
+        )
+        .unwrap();
+
+        assert_eq!(game.suit, Suit::Diamonds);
+        assert!(
+            game.action
+                .iter()
+                .any(|action| matches!(action, EightsAction::UserNamedSuit(_, Suit::Diamonds)))
+        );
+
+        // The named suit is followed, not the eight's own
+        assert!(play(&mut game, 1, "3h").is_err());
+        assert!(play(&mut game, 1, "5c").is_err());
+        play(&mut game, 1, "4d").unwrap();
+        assert_eq!(game.suit, Suit::Diamonds);
+        assert_eq!(game.user_turn, 0);
+    }
+
+    #[test]
+    fn cards_must_match() {
+        let mut game = table(&["2c 3c Kd Qh", "4d 5d"], "Kh", "");
+
+        assert!(play(&mut game, 1, "4d").is_err());
+        assert!(play(&mut game, 0, "2c").is_err());
+        // Nor can a card that isn't held be played
+        assert!(play(&mut game, 0, "Ks").is_err());
+
+        play(&mut game, 0, "Kd").unwrap();
+        assert_eq!(game.suit, Suit::Diamonds);
+        assert_eq!(game.user_turn, 1);
+    }
+
+    #[test]
+    fn draws_until_a_card_can_be_played() {
+        let mut game = table(&["2c 3c", "4d 5d"], "Kh", "4s 5d 9h 6c");
+
+        game.act(0, EightsClientAction::Draw).unwrap();
+
+        assert_eq!(game.active_users[0].cards, cards("2c 3c 4s 5d 9h"));
+        assert_eq!(game.draw_pile, cards("6c"));
+        assert!(
+            game.action
+                .iter()
+                .any(|action| matches!(action, EightsAction::UserDrew(_, 3)))
+        );
+        // Still their turn, to play what they drew
+        assert_eq!(game.user_turn, 0);
+        assert!(game.act(0, EightsClientAction::Draw).is_err());
+        play(&mut game, 0, "9h").unwrap();
+    }
+
+    #[test]
+    fn discards_are_reshuffled_when_the_deck_runs_out() {
+        let mut game = table(&["2c 3c", "4d 5d"], "Kh", "6s");
+        game.discard_pile = cards("2h 3h 4h Kh");
+
+        game.act(0, EightsClientAction::Draw).unwrap();
+
+        // The six didn't play, so the hearts under the king were shuffled back in
+        assert_eq!(game.discard_pile, cards("Kh"));
+        assert_eq!(game.active_users[0].cards.len(), 4);
+        assert_eq!(game.active_users[0].cards[2], card("6s"));
+        assert_eq!(game.draw_pile.len(), 2);
+        assert_eq!(game.user_turn, 0);
+    }
+
+    #[test]
+    fn nothing_left_to_draw_passes() {
+        let mut game = table(&["2c 3c", "4d 5d"], "Kh", "");
+
+        game.act(0, EightsClientAction::Draw).unwrap();
+
+        assert_eq!(game.active_users[0].cards.len(), 2);
+        assert!(
+            game.action
+                .iter()
+                .any(|action| matches!(action, EightsAction::UserPassed(_)))
+        );
+        assert_eq!(game.user_turn, 1);
+    }
+
+    #[test]
+    fn standings_go_by_cards_left() {
+        let mut game = table(&["2c 3c 4c", "5h", "6d 7d 9s", "Jc"], "Kh", "");
+        game.leave_seat(3);
+        game.user_turn = 1;
+
+        play(&mut game, 1, "5h").unwrap();
+
+        assert_eq!(game.start_state, GameStartState::Ending);
+        assert_eq!(
+            game.standings,
+            vec![
+                (1, "Player 1".to_string(), 1),
+                // Level on cards, so they share second
+                (0, "Player 0".to_string(), 2),
+                (2, "Player 2".to_string(), 2),
+                (3, "Player 3".to_string(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn last_one_standing_wins() {
+        let mut game = table(&["2c 3c", "4d 5d", "6s"], "Kh", "");
+        game.leave_seat(0);
+        game.leave_seat(2);
+
+        assert_eq!(game.start_state, GameStartState::Ending);
+        assert_eq!(
+            game.standings,
+            vec![
+                (1, "Player 1".to_string(), 1),
+                (2, "Player 2".to_string(), 2),
+                (0, "Player 0".to_string(), 3),
+            ]
+        );
+    }
+}
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    cards::{PlayingCard, Rank, Suit},
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+    hearts::{
+        HEARTS_END_SCORE, HEARTS_PASS_COUNT, HEARTS_PLAYERS, HEARTS_ROUND_POINTS, HeartsAction,
+        HeartsClientAction, HeartsClientGameState, HeartsPhase, HeartsPlayer, PassDirection,
+        ServerHeartsCommand, can_play, card_points, trick_winner,
+    },
+};
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+/// Played in a `GameRoom`.
+/// Hearts needs all four seats filled, so someone leaving part way ends the game.
+pub struct ServerHearts {
+    seats: Vec<HeartsSeat>,
+    /// Whoever left part way through, in the order they went
+    left_users: Vec<(u32, String)>,
+    round: u32,
+    phase: HeartsPhase,
+    user_turn: Option<usize>,
+    trick: Vec<(u32, PlayingCard)>,
+    last_trick: Vec<(u32, PlayingCard)>,
+    hearts_broken: bool,
+    first_trick: bool,
+    start_state: GameStartState,
+    /// Filled in once the game is over, best placed first with their rank
+    standings: Vec<(u32, String, u32)>,
+    action: Vec<HeartsAction>,
+    cards_played: HashMap<u32, u32>,
+    /// Seeded by the room, enough to reproduce a deal
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct HeartsSeat {
+    id: u32,
+    name: String,
+    cards: Vec<PlayingCard>,
+    /// Picked to pass, handed over once everyone has chosen
+    passing: Vec<PlayingCard>,
+    score: u32,
+    round_points: u32,
+    rating: Option<u32>,
+}
+
+impl HeartsSeat {
+    fn new(id: u32, name: String, rating: u32) -> HeartsSeat {
+        HeartsSeat {
+            id,
+            name,
+            cards: vec![],
+            passing: vec![],
+            score: 0,
+            round_points: 0,
+            rating: Some(rating),
+        }
+    }
+}
+
+impl ServerHearts {
+    pub fn new(rng: ChaCha8Rng) -> ServerHearts {
+        ServerHearts {
+            seats: vec![],
+            left_users: vec![],
+            round: 0,
+            phase: HeartsPhase::Passing,
+            user_turn: None,
+            trick: vec![],
+            last_trick: vec![],
+            hearts_broken: false,
+            first_trick: true,
+            start_state: GameStartState::Setup,
+            standings: vec![],
+            action: vec![HeartsAction::Init],
+            cards_played: HashMap::new(),
+            rng,
+        }
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if self.seats.len() != HEARTS_PLAYERS {
+            return Err(anyhow!("Hearts needs exactly {HEARTS_PLAYERS} players"));
+        }
+
+        self.start_state = GameStartState::Active;
+        self.start_round();
+
+        Ok(())
+    }
+
+    fn start_round(&mut self) {
+        self.round += 1;
+
+        let mut deck = PlayingCard::deck();
+        deck.shuffle(&mut self.rng);
+
+        for seat in self.seats.iter_mut() {
+            let split = deck.len() - 13;
+            seat.cards = deck.split_off(split);
+            seat.passing.clear();
+            seat.round_points = 0;
+        }
+
+        let direction = PassDirection::for_round(self.round);
+        self.action
+            .push(HeartsAction::RoundStarted(self.round, direction));
+
+        self.trick.clear();
+        self.last_trick.clear();
+        self.hearts_broken = false;
+        self.first_trick = true;
+
+        if direction == PassDirection::Hold {
+            self.start_play();
+        } else {
+            self.phase = HeartsPhase::Passing;
+            self.user_turn = None;
+        }
+    }
+
+    /// Whoever holds the two of clubs leads it
+    fn start_play(&mut self) {
+        let two_of_clubs = PlayingCard::new(Rank::Two, Suit::Clubs);
+
+        self.phase = HeartsPhase::Playing;
+        self.user_turn = self
+            .seats
+            .iter()
+            .position(|seat| seat.cards.contains(&two_of_clubs));
+    }
+
+    fn act(&mut self, user_id: u32, action: HeartsClientAction) -> anyhow::Result<()> {
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("Received Game message when not active"));
+        }
+
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return Err(anyhow!("User not in game"));
+        };
+
+        match action {
+            HeartsClientAction::Pass(cards) => self.pass_cards(seat_idx, cards),
+            HeartsClientAction::Play(card) => self.play_card(seat_idx, card),
+        }
+    }
+
+    fn pass_cards(&mut self, seat_idx: usize, mut cards: Vec<PlayingCard>) -> anyhow::Result<()> {
+        if self.phase != HeartsPhase::Passing {
+            return Err(anyhow!("Not passing this round"));
+        }
+
+        cards.sort();
+        cards.dedup();
+
+        let seat = &mut self.seats[seat_idx];
+        if !seat.passing.is_empty() {
+            return Err(anyhow!("Already passed"));
+        }
+
+        if cards.len() != HEARTS_PASS_COUNT || !cards.iter().all(|card| seat.cards.contains(card)) {
+            return Err(anyhow!(
+                "Has to pass {HEARTS_PASS_COUNT} cards from their hand"
+            ));
+        }
+
+        seat.cards.retain(|card| !cards.contains(card));
+        seat.passing = cards;
+        self.action
+            .push(HeartsAction::UserPassed(seat.name.clone()));
+
+        if self.seats.iter().all(|seat| !seat.passing.is_empty()) {
+            let offset = PassDirection::for_round(self.round).offset();
+
+            for from in 0..self.seats.len() {
+                let to = (from + offset) % self.seats.len();
+                let passed = std::mem::take(&mut self.seats[from].passing);
+                self.seats[to].cards.extend(passed);
+            }
+
+            self.action.push(HeartsAction::CardsExchanged);
+            self.start_play();
+        }
+
+        Ok(())
+    }
+
+    fn play_card(&mut self, seat_idx: usize, card: PlayingCard) -> anyhow::Result<()> {
+        if self.phase != HeartsPhase::Playing {
+            return Err(anyhow!("Cards are still being passed"));
+        }
+
+        if self.user_turn != Some(seat_idx) {
+            return Err(anyhow!("Not this user's turn"));
+        }
+
+        let seat = &self.seats[seat_idx];
+        let Some(card_idx) = seat.cards.iter().position(|held| *held == card) else {
+            return Err(anyhow!("User doesn't have {card}"));
+        };
+
+        if !can_play(
+            &card,
+            &seat.cards,
+            &self.trick,
+            self.hearts_broken,
+            self.first_trick,
+        ) {
+            return Err(anyhow!("{card} can't be played on this trick"));
+        }
+
+        let seat = &mut self.seats[seat_idx];
+        seat.cards.remove(card_idx);
+        self.trick.push((seat.id, card));
+        self.action
+            .push(HeartsAction::UserPlayed(seat.name.clone(), card));
+        *self.cards_played.entry(seat.id).or_insert(0) += 1;
+
+        if card.suit == Suit::Hearts && !self.hearts_broken {
+            self.hearts_broken = true;
+            self.action.push(HeartsAction::HeartsBroken);
+        }
+
+        if self.trick.len() < self.seats.len() {
+            self.user_turn = Some((seat_idx + 1) % self.seats.len());
+            return Ok(());
+        }
+
+        self.take_trick();
+        Ok(())
+    }
+
+    /// The winner gets the points and leads the next trick
+    fn take_trick(&mut self) {
+        let Some(winner_id) = trick_winner(&self.trick) else {
+            return;
+        };
+        let Some(winner_idx) = self.seats.iter().position(|seat| seat.id == winner_id) else {
+            return;
+        };
+
+        let points: u32 = self.trick.iter().map(|(_, card)| card_points(card)).sum();
+        let winner = &mut self.seats[winner_idx];
+        winner.round_points += points;
+        self.action
+            .push(HeartsAction::TrickTaken(winner.name.clone(), points));
+
+        self.last_trick = std::mem::take(&mut self.trick);
+        self.first_trick = false;
+        self.user_turn = Some(winner_idx);
+
+        if self.seats.iter().all(|seat| seat.cards.is_empty()) {
+            self.score_round();
+        }
+    }
+
+    fn score_round(&mut self) {
+        let moon = self
+            .seats
+            .iter()
+            .position(|seat| seat.round_points == HEARTS_ROUND_POINTS);
+
+        if let Some(shooter) = moon {
+            self.action
+                .push(HeartsAction::ShotTheMoon(self.seats[shooter].name.clone()));
+
+            for (idx, seat) in self.seats.iter_mut().enumerate() {
+                seat.round_points = if idx == shooter {
+                    0
+                } else {
+                    HEARTS_ROUND_POINTS
+                };
+            }
+        }
+
+        for seat in self.seats.iter_mut() {
+            seat.score += seat.round_points;
+        }
+
+        self.action.push(HeartsAction::RoundScored(
+            self.seats
+                .iter()
+                .map(|seat| (seat.name.clone(), seat.round_points))
+                .collect(),
+        ));
+
+        if self.seats.iter().any(|seat| seat.score >= HEARTS_END_SCORE) {
+            self.end_game();
+        } else {
+            self.start_round();
+        }
+    }
+
+    /// Lowest score first, anyone on the same score shares a place.
+    /// Whoever left is placed behind everyone still playing.
+    fn end_game(&mut self) {
+        let mut players: Vec<&HeartsSeat> = self.seats.iter().collect();
+        players.sort_by_key(|seat| seat.score);
+
+        self.standings = players
+            .iter()
+            .map(|seat| {
+                let lower = players
+                    .iter()
+                    .filter(|other| other.score < seat.score)
+                    .count();
+                (seat.id, seat.name.clone(), lower as u32 + 1)
+            })
+            .collect();
+
+        for (user_id, name) in self.left_users.iter().rev() {
+            let rank = self.standings.len() as u32 + 1;
+            self.standings.push((*user_id, name.clone(), rank));
+        }
+
+        if let Some((_, name, _)) = self.standings.first() {
+            self.action.push(HeartsAction::UserWon(name.clone()));
+        }
+
+        self.action.push(HeartsAction::GameEnded);
+        self.start_state = GameStartState::Ending;
+        self.user_turn = None;
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            if let Some((_, name, _)) = self.standings.iter().find(|(id, _, _)| *id == user_id) {
+                self.action.push(HeartsAction::UserLeft(name.clone()));
+            }
+            return;
+        };
+
+        let seat = self.seats.remove(seat_idx);
+        self.action.push(HeartsAction::UserLeft(seat.name.clone()));
+
+        // Three can't carry on, the game is scored as it stands
+        if self.start_state == GameStartState::Active {
+            self.left_users.push((seat.id, seat.name));
+            self.end_game();
+        }
+    }
+}
+
+impl RoomGame for ServerHearts {
+    const GAME_TYPE: GameType = GameType::Hearts;
+    const MAX_PLAYERS: usize = HEARTS_PLAYERS;
+
+    type Action = HeartsClientAction;
+    type State = HeartsClientGameState;
+    type Message = ServerHeartsCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.seats.iter().map(|seat| seat.id).collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.action
+            .push(HeartsAction::UserJoined(user.name.clone()));
+        self.seats
+            .push(HeartsSeat::new(user_id, user.name.clone(), user.rating));
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: HeartsClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        self.standings
+            .iter()
+            .map(|(user_id, _, rank)| {
+                let left = self.left_users.iter().any(|(id, _)| id == user_id);
+
+                PlayerResult {
+                    user_id: *user_id,
+                    placement: (!left).then_some(*rank),
+                    rank: *rank,
+                    won: *rank == 1,
+                    busted: false,
+                    cards_played: self.cards_played.get(user_id).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+
+    fn client_state(&mut self, host_user: u32) -> HeartsClientGameState {
+        HeartsClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| HeartsPlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    score: seat.score,
+                    round_points: seat.round_points,
+                    card_count: seat.cards.len() as u32,
+                    passed: !seat.passing.is_empty(),
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            round: self.round,
+            phase: self.phase,
+            pass_direction: PassDirection::for_round(self.round),
+            user_turn: self.user_turn.map(|idx| idx as u8),
+            trick: self.trick.clone(),
+            last_trick: self.last_trick.clone(),
+            hearts_broken: self.hearts_broken,
+            first_trick: self.first_trick,
+            standings: self
+                .standings
+                .iter()
+                .map(|(id, name, _)| (*id, name.clone()))
+                .collect(),
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut HeartsClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Everyone only gets their own hand
+    fn project(&self, state: HeartsClientGameState, viewer: Viewer) -> ServerHeartsCommand {
+        let cards = match viewer {
+            Viewer::Player(user_id) => self
+                .seats
+                .iter()
+                .find(|seat| seat.id == user_id)
+                .map(|seat| seat.cards.clone())
+                .unwrap_or_default(),
+            Viewer::Spectator => vec![],
+        };
+
+        ServerHeartsCommand::GameState(cards, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::test_cards::{card, cards};
+
+    fn seated() -> ServerHearts {
+        let mut game = ServerHearts::new(ChaCha8Rng::seed_from_u64(0));
+        for id in 0..HEARTS_PLAYERS as u32 {
+            game.seats
+                .push(HeartsSeat::new(id, format!("Player {id}"), 0));
+        }
+        game
+    }
+
+    /// Partway into a round with no passing, seat `idx` is user id `idx` and holds `hands[idx]`
+    fn table(hands: [&str; HEARTS_PLAYERS]) -> ServerHearts {
+        let mut game = seated();
+        for (seat, hand) in game.seats.iter_mut().zip(hands) {
+            seat.cards = cards(hand);
+        }
+
+        game.start_state = GameStartState::Active;
+        game.round = 4;
+        game.start_play();
+        game
+    }
+
+    fn play(game: &mut ServerHearts, user_id: u32, text: &str) -> anyhow::Result<()> {
+        game.act(user_id, HeartsClientAction::Play(card(text)))
+    }
+
+    #[test]
+    fn pass_direction_rotates() {
+        let directions: Vec<PassDirection> = (1..=8).map(PassDirection::for_round).collect();
+        assert_eq!(
+            directions,
+            vec![
+                PassDirection::Left,
+                PassDirection::Right,
+                PassDirection::Across,
+                PassDirection::Hold,
+                PassDirection::Left,
+                PassDirection::Right,
+                PassDirection::Across,
+                PassDirection::Hold,
+            ]
+        );
+    }
+
+    #[test]
+    fn passed_cards_go_the_right_way() {
+        for (round, offset) in [(1, 1), (2, 3), (3, 2)] {
+            let mut game = seated();
+            game.start_state = GameStartState::Active;
+            game.round = round - 1;
+            game.start_round();
+            assert_eq!(game.phase, HeartsPhase::Passing);
+
+            let passed: Vec<Vec<PlayingCard>> = game
+                .seats
+                .iter()
+                .map(|seat| seat.cards[..HEARTS_PASS_COUNT].to_vec())
+                .collect();
+
+            for (id, cards) in passed.iter().enumerate() {
+                game.act(id as u32, HeartsClientAction::Pass(cards.clone()))
+                    .unwrap();
+            }
+
+            for (from, cards) in passed.iter().enumerate() {
+                let to = &game.seats[(from + offset) % HEARTS_PLAYERS];
+                assert!(cards.iter().all(|card| to.cards.contains(card)));
+            }
+            assert!(game.seats.iter().all(|seat| seat.cards.len() == 13));
+            assert_eq!(game.phase, HeartsPhase::Playing);
+        }
+    }
+
+    #[test]
+    fn every_fourth_round_holds() {
+        let mut game = seated();
+        game.start_state = GameStartState::Active;
+        game.round = 3;
+        game.start_round();
+
+        assert_eq!(game.phase, HeartsPhase::Playing);
+        let hand = game.seats[0].cards[..HEARTS_PASS_COUNT].to_vec();
+        assert!(game.act(0, HeartsClientAction::Pass(hand)).is_err());
+    }
+
+    #[test]
+    fn two_of_clubs_leads() {
+        let mut game = seated();
+        game.start_state = GameStartState::Active;
+        game.round = 3;
+        game.start_round();
+
+        let holder = game.user_turn.unwrap();
+        assert!(game.seats[holder].cards.contains(&card("2c")));
+
+        let other = *game.seats[holder]
+            .cards
+            .iter()
+            .find(|held| **held != card("2c"))
+            .unwrap();
+        assert!(
+            game.act(holder as u32, HeartsClientAction::Play(other))
+                .is_err()
+        );
+        assert!(play(&mut game, holder as u32, "2c").is_ok());
+    }
+
+    #[test]
+    fn no_points_on_the_first_trick() {
+        let mut game = table(["2c 3d", "Qs 4d", "5c 6d", "7c 8d"]);
+
+        play(&mut game, 0, "2c").unwrap();
+        // Void in clubs, but there's something worth nothing to throw instead
+        assert!(play(&mut game, 1, "Qs").is_err());
+        assert!(play(&mut game, 1, "4d").is_ok());
+
+        // Unless there's nothing else
+        assert!(can_play(
+            &card("Qs"),
+            &cards("Qs 5h"),
+            &[(0, card("2c"))],
+            false,
+            true
+        ));
+    }
+
+    #[test]
+    fn hearts_are_broken_by_a_discard() {
+        let mut game = table(["2c 3h 4d", "5c 6h 7d", "8c 9h Td", "Jh Qh Kd"]);
+
+        for (id, text) in [(0, "2c"), (1, "5c"), (2, "8c"), (3, "Kd")] {
+            play(&mut game, id, text).unwrap();
+        }
+        assert_eq!(game.user_turn, Some(2));
+
+        // Nobody has thrown a heart yet
+        assert!(play(&mut game, 2, "9h").is_err());
+        play(&mut game, 2, "Td").unwrap();
+
+        play(&mut game, 3, "Jh").unwrap();
+        assert!(game.hearts_broken);
+        assert!(
+            game.action
+                .iter()
+                .any(|action| matches!(action, HeartsAction::HeartsBroken))
+        );
+
+        play(&mut game, 0, "4d").unwrap();
+        play(&mut game, 1, "7d").unwrap();
+
+        assert_eq!(game.user_turn, Some(2));
+        assert_eq!(game.seats[2].round_points, 1);
+        assert!(play(&mut game, 2, "9h").is_ok());
+    }
+
+    #[test]
+    fn only_hearts_left_can_lead_hearts() {
+        assert!(can_play(&card("5h"), &cards("5h 9h"), &[], false, false));
+        assert!(!can_play(&card("5h"), &cards("5h 9d"), &[], false, false));
+    }
+
+    #[test]
+    fn shooting_the_moon_gives_everyone_else_the_points() {
+        let mut game = table(["", "", "", ""]);
+        game.seats[1].score = 10;
+        game.seats[1].round_points = HEARTS_ROUND_POINTS;
+
+        game.score_round();
+
+        let scores: Vec<u32> = game.seats.iter().map(|seat| seat.score).collect();
+        assert_eq!(scores, vec![26, 10, 26, 26]);
+        assert!(
+            game.action.iter().any(
+                |action| matches!(action, HeartsAction::ShotTheMoon(name) if name == "Player 1")
+            )
+        );
+    }
+
+    #[test]
+    fn points_are_kept_without_a_moon() {
+        let mut game = table(["", "", "", ""]);
+        for (seat, points) in game.seats.iter_mut().zip([13, 5, 8, 0]) {
+            seat.round_points = points;
+        }
+
+        game.score_round();
+
+        let scores: Vec<u32> = game.seats.iter().map(|seat| seat.score).collect();
+        assert_eq!(scores, vec![13, 5, 8, 0]);
+        // Nobody's reached the end, so the next round is dealt
+        assert_eq!(game.round, 5);
+    }
+}
@@ -0,0 +1,858 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::anyhow;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    cards::PlayingCard,
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+    holdem::{
+        BIG_BLIND, HOLDEM_MAX_PLAYERS, HoldemAction, HoldemClientAction, HoldemClientGameState,
+        HoldemPlayer, HoldemStage, SMALL_BLIND, STARTING_CHIPS, ServerHoldemCommand, evaluate,
+    },
+};
+use tokio::time::Instant;
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+/// Long enough to see who won the hand before the next one is dealt
+const NEXT_HAND_DELAY: Duration = Duration::from_secs(6);
+
+/// Played in a `GameRoom`.
+/// Chips carry over from hand to hand, the game is over once one player has them all.
+pub struct ServerHoldem {
+    deck: Vec<PlayingCard>,
+    board: Vec<PlayingCard>,
+    seats: Vec<HoldemSeat>,
+    /// Whoever ran out of chips, in the order they went
+    busted_users: Vec<(u32, String)>,
+    /// Whoever left part way through, in the order they went
+    left_users: Vec<(u32, String)>,
+    dealer: usize,
+    user_turn: Option<usize>,
+    stage: HoldemStage,
+    current_bet: u32,
+    min_raise: u32,
+    hand_number: u32,
+    revealed: Vec<(u32, Vec<PlayingCard>)>,
+    /// Set once a hand is over, the next one is dealt when it runs out
+    next_hand: Option<Instant>,
+    start_state: GameStartState,
+    /// Filled in once the game is over, best placed first with their rank
+    standings: Vec<(u32, String, u32)>,
+    action: Vec<HoldemAction>,
+    /// Seeded by the room, enough to reproduce a deal
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct HoldemSeat {
+    id: u32,
+    name: String,
+    chips: u32,
+    hole: Vec<PlayingCard>,
+    /// Put in this betting round
+    bet: u32,
+    /// Put in during earlier rounds of this hand
+    committed: u32,
+    folded: bool,
+    all_in: bool,
+    /// Acted since the last raise, everyone gets a say before the round closes
+    acted: bool,
+    busted: bool,
+    /// Seats are only given up between hands, so the turn order doesn't shift mid hand
+    left: bool,
+    rating: Option<u32>,
+}
+
+impl HoldemSeat {
+    fn new(id: u32, name: String, rating: u32) -> HoldemSeat {
+        HoldemSeat {
+            id,
+            name,
+            chips: 0,
+            hole: vec![],
+            bet: 0,
+            committed: 0,
+            folded: false,
+            all_in: false,
+            acted: false,
+            busted: false,
+            left: false,
+            rating: Some(rating),
+        }
+    }
+
+    /// Still has a say in how the betting goes
+    fn can_act(&self) -> bool {
+        !self.folded && !self.all_in
+    }
+}
+
+impl ServerHoldem {
+    pub fn new(rng: ChaCha8Rng) -> ServerHoldem {
+        ServerHoldem {
+            deck: vec![],
+            board: vec![],
+            seats: vec![],
+            busted_users: vec![],
+            left_users: vec![],
+            dealer: 0,
+            user_turn: None,
+            stage: HoldemStage::PreFlop,
+            current_bet: 0,
+            min_raise: BIG_BLIND,
+            hand_number: 0,
+            revealed: vec![],
+            next_hand: None,
+            start_state: GameStartState::Setup,
+            standings: vec![],
+            action: vec![HoldemAction::Init],
+            rng,
+        }
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if self.seats.len() < 2 {
+            return Err(anyhow!("Need at least 2 people to play"));
+        }
+
+        for seat in self.seats.iter_mut() {
+            seat.chips = STARTING_CHIPS;
+        }
+
+        // The button moves on before every hand, so the first seat deals first
+        self.dealer = self.seats.len() - 1;
+        self.start_state = GameStartState::Active;
+        self.start_hand();
+
+        Ok(())
+    }
+
+    /// The next seat round the table from `from` that matches
+    fn next_seat(&self, from: usize, matches: impl Fn(&HoldemSeat) -> bool) -> Option<usize> {
+        (1..=self.seats.len())
+            .map(|offset| (from + offset) % self.seats.len())
+            .find(|idx| matches(&self.seats[*idx]))
+    }
+
+    /// Anyone who left during the last hand gives up their seat now
+    fn clear_left_seats(&mut self) {
+        let mut idx = 0;
+        while idx < self.seats.len() {
+            if !self.seats[idx].left {
+                idx += 1;
+                continue;
+            }
+
+            self.seats.remove(idx);
+
+            // The button stays put, the seat after it is still next to deal
+            if idx <= self.dealer {
+                self.dealer = match self.dealer {
+                    0 => self.seats.len().saturating_sub(1),
+                    dealer => dealer - 1,
+                };
+            }
+        }
+    }
+
+    fn start_hand(&mut self) {
+        if self.start_state != GameStartState::Active {
+            return;
+        }
+
+        self.clear_left_seats();
+
+        let playing = self.seats.iter().filter(|seat| !seat.busted).count();
+        if playing < 2 {
+            self.end_game();
+            return;
+        }
+
+        self.hand_number += 1;
+        self.action
+            .push(HoldemAction::HandStarted(self.hand_number));
+        self.stage = HoldemStage::PreFlop;
+        self.board.clear();
+        self.revealed.clear();
+
+        self.deck = PlayingCard::deck();
+        self.deck.shuffle(&mut self.rng);
+
+        for seat in self.seats.iter_mut() {
+            seat.bet = 0;
+            seat.committed = 0;
+            seat.acted = false;
+            seat.all_in = false;
+            seat.folded = seat.busted;
+            seat.hole = if seat.busted {
+                vec![]
+            } else {
+                let split = self.deck.len() - 2;
+                self.deck.split_off(split)
+            };
+        }
+
+        let Some(dealer) = self.next_seat(self.dealer, |seat| !seat.busted) else {
+            return;
+        };
+        self.dealer = dealer;
+
+        // Heads up the dealer takes the small blind, and acts first before the flop
+        let small_blind = if playing == 2 {
+            dealer
+        } else {
+            self.next_seat(dealer, |seat| !seat.busted)
+                .unwrap_or(dealer)
+        };
+        let big_blind = self
+            .next_seat(small_blind, |seat| !seat.busted)
+            .unwrap_or(small_blind);
+
+        self.post_blind(small_blind, SMALL_BLIND);
+        self.post_blind(big_blind, BIG_BLIND);
+        self.current_bet = BIG_BLIND;
+        self.min_raise = BIG_BLIND;
+
+        self.next_turn(big_blind);
+    }
+
+    fn post_blind(&mut self, seat_idx: usize, blind: u32) {
+        let amount = blind.min(self.seats[seat_idx].chips);
+        self.put_in(seat_idx, amount);
+
+        let name = self.seats[seat_idx].name.clone();
+        self.action.push(HoldemAction::PostedBlind(name, amount));
+    }
+
+    fn put_in(&mut self, seat_idx: usize, amount: u32) {
+        let seat = &mut self.seats[seat_idx];
+        seat.chips -= amount;
+        seat.bet += amount;
+        if seat.chips == 0 {
+            seat.all_in = true;
+        }
+    }
+
+    fn act(&mut self, user_id: u32, action: HoldemClientAction) -> anyhow::Result<()> {
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("Received Game message when not active"));
+        }
+
+        let Some(seat_idx) = self.user_turn else {
+            return Err(anyhow!("Nobody is acting right now"));
+        };
+
+        if self.seats[seat_idx].id != user_id {
+            return Err(anyhow!("Not this user's turn"));
+        }
+
+        let seat = &self.seats[seat_idx];
+        let name = seat.name.clone();
+        let to_call = self.current_bet.saturating_sub(seat.bet);
+
+        match action {
+            HoldemClientAction::Fold => {
+                self.seats[seat_idx].folded = true;
+                self.action.push(HoldemAction::Folded(name));
+            }
+            HoldemClientAction::Check => {
+                if to_call > 0 {
+                    return Err(anyhow!("Can't check facing a bet of {to_call}"));
+                }
+                self.action.push(HoldemAction::Checked(name));
+            }
+            HoldemClientAction::Call => {
+                if to_call == 0 {
+                    return Err(anyhow!("Nothing to call"));
+                }
+
+                let amount = to_call.min(seat.chips);
+                self.put_in(seat_idx, amount);
+
+                let seat = &self.seats[seat_idx];
+                self.action.push(if seat.all_in {
+                    HoldemAction::AllIn(name, seat.bet)
+                } else {
+                    HoldemAction::Called(name, amount)
+                });
+            }
+            HoldemClientAction::Raise(total) => {
+                if total < self.current_bet + self.min_raise {
+                    return Err(anyhow!(
+                        "Raise to {total} is under the minimum of {}",
+                        self.current_bet + self.min_raise
+                    ));
+                }
+
+                let amount = total - seat.bet;
+                if amount > seat.chips {
+                    return Err(anyhow!("Only has {} chips", seat.chips));
+                }
+
+                self.put_in(seat_idx, amount);
+                self.min_raise = total - self.current_bet;
+                self.current_bet = total;
+
+                self.action.push(if self.seats[seat_idx].all_in {
+                    HoldemAction::AllIn(name, total)
+                } else {
+                    HoldemAction::Raised(name, total)
+                });
+            }
+            HoldemClientAction::AllIn => {
+                if seat.chips == 0 {
+                    return Err(anyhow!("No chips left to bet"));
+                }
+
+                let total = seat.bet + seat.chips;
+                self.put_in(seat_idx, seat.chips);
+
+                // Going all in for less than a full raise still has to be called
+                if total > self.current_bet {
+                    self.min_raise = self.min_raise.max(total - self.current_bet);
+                    self.current_bet = total;
+                }
+
+                self.action.push(HoldemAction::AllIn(name, total));
+            }
+        }
+
+        self.seats[seat_idx].acted = true;
+        self.next_turn(seat_idx);
+
+        Ok(())
+    }
+
+    /// Play moves on from whoever just acted, or the betting round closes
+    fn next_turn(&mut self, from: usize) {
+        if self.seats.iter().filter(|seat| !seat.folded).count() <= 1 {
+            self.finish_hand();
+            return;
+        }
+
+        let current_bet = self.current_bet;
+        // Betting against nobody is pointless, the last one able to act only has to call
+        let others_can_act = self.seats.iter().filter(|seat| seat.can_act()).count() > 1;
+
+        let next = self.next_seat(from, |seat| {
+            seat.can_act() && (seat.bet < current_bet || (!seat.acted && others_can_act))
+        });
+
+        match next {
+            Some(seat_idx) => self.user_turn = Some(seat_idx),
+            None => self.end_round(),
+        }
+    }
+
+    /// Bets are gathered into the pot and the next street is dealt.
+    /// With nobody left to bet the rest of the board is run out.
+    fn end_round(&mut self) {
+        for seat in self.seats.iter_mut() {
+            seat.committed += seat.bet;
+            seat.bet = 0;
+            seat.acted = false;
+        }
+
+        self.current_bet = 0;
+        self.min_raise = BIG_BLIND;
+        self.user_turn = None;
+
+        let (stage, cards) = match self.stage {
+            HoldemStage::PreFlop => (HoldemStage::Flop, 3),
+            HoldemStage::Flop => (HoldemStage::Turn, 1),
+            HoldemStage::Turn => (HoldemStage::River, 1),
+            HoldemStage::River | HoldemStage::Showdown => {
+                self.finish_hand();
+                return;
+            }
+        };
+
+        // Burn one first, like at a real table
+        self.deck.pop();
+        for _ in 0..cards {
+            if let Some(card) = self.deck.pop() {
+                self.board.push(card);
+            }
+        }
+
+        self.stage = stage;
+        self.action.push(HoldemAction::Dealt(stage));
+
+        if self.seats.iter().filter(|seat| seat.can_act()).count() <= 1 {
+            self.end_round();
+            return;
+        }
+
+        self.user_turn = self.next_seat(self.dealer, |seat| seat.can_act());
+    }
+
+    /// Main pot first, then side pots, each with the seats that can win it
+    fn pots(&self) -> Vec<(u32, Vec<usize>)> {
+        let mut levels: Vec<u32> = self
+            .seats
+            .iter()
+            .map(|seat| seat.committed)
+            .filter(|committed| *committed > 0)
+            .collect();
+        levels.sort();
+        levels.dedup();
+
+        let mut pots: Vec<(u32, Vec<usize>)> = vec![];
+        let mut previous = 0;
+
+        for level in levels {
+            let amount: u32 = self
+                .seats
+                .iter()
+                .map(|seat| seat.committed.min(level) - seat.committed.min(previous))
+                .sum();
+
+            let eligible: Vec<usize> = (0..self.seats.len())
+                .filter(|idx| !self.seats[*idx].folded && self.seats[*idx].committed >= level)
+                .collect();
+
+            match pots.last_mut() {
+                // Folded players putting in odd amounts don't make a new pot,
+                //  and chips nobody still in could match are just dead money
+                Some((pot, pot_eligible)) if *pot_eligible == eligible || eligible.is_empty() => {
+                    *pot += amount
+                }
+                _ => pots.push((amount, eligible)),
+            }
+
+            previous = level;
+        }
+
+        pots
+    }
+
+    fn finish_hand(&mut self) {
+        for seat in self.seats.iter_mut() {
+            seat.committed += seat.bet;
+            seat.bet = 0;
+        }
+
+        self.user_turn = None;
+        self.stage = HoldemStage::Showdown;
+
+        let live: Vec<usize> = (0..self.seats.len())
+            .filter(|idx| !self.seats[*idx].folded)
+            .collect();
+
+        if let [winner] = live[..] {
+            let total: u32 = self.seats.iter().map(|seat| seat.committed).sum();
+            let seat = &mut self.seats[winner];
+            seat.chips += total;
+            self.action
+                .push(HoldemAction::WonPot(seat.name.clone(), total, None));
+        } else {
+            self.showdown(&live);
+        }
+
+        for seat in self.seats.iter_mut() {
+            seat.committed = 0;
+
+            if seat.chips == 0 && !seat.busted && !seat.left {
+                seat.busted = true;
+                self.busted_users.push((seat.id, seat.name.clone()));
+                self.action
+                    .push(HoldemAction::UserBusted(seat.name.clone()));
+            }
+        }
+
+        let remaining = self
+            .seats
+            .iter()
+            .filter(|seat| !seat.busted && !seat.left)
+            .count();
+
+        if remaining <= 1 {
+            self.end_game();
+        } else {
+            self.next_hand = Some(Instant::now() + NEXT_HAND_DELAY);
+        }
+    }
+
+    /// Everyone still in turns their cards over, each pot goes to the best hand that can win it
+    fn showdown(&mut self, live: &[usize]) {
+        let mut values = HashMap::new();
+
+        for seat_idx in live {
+            let seat = &self.seats[*seat_idx];
+            let cards: Vec<PlayingCard> = seat.hole.iter().chain(&self.board).copied().collect();
+
+            if let Some(value) = evaluate(&cards) {
+                values.insert(*seat_idx, value);
+                self.action
+                    .push(HoldemAction::Showed(seat.name.clone(), value.rank));
+            }
+            self.revealed.push((seat.id, seat.hole.clone()));
+        }
+
+        for (amount, eligible) in self.pots() {
+            let Some(best) = eligible
+                .iter()
+                .filter_map(|idx| values.get(idx))
+                .max()
+                .copied()
+            else {
+                continue;
+            };
+
+            // Odd chips go to the first winner after the button
+            let mut winners: Vec<usize> = eligible
+                .into_iter()
+                .filter(|idx| values.get(idx) == Some(&best))
+                .collect();
+            winners
+                .sort_by_key(|idx| (*idx + self.seats.len() - self.dealer - 1) % self.seats.len());
+
+            let share = amount / winners.len() as u32;
+            let odd_chips = amount % winners.len() as u32;
+
+            for (i, seat_idx) in winners.iter().enumerate() {
+                let won = share + u32::from((i as u32) < odd_chips);
+                let seat = &mut self.seats[*seat_idx];
+                seat.chips += won;
+                self.action.push(HoldemAction::WonPot(
+                    seat.name.clone(),
+                    won,
+                    Some(best.rank),
+                ));
+            }
+        }
+    }
+
+    /// Whoever still has chips is placed first, then everyone who busted,
+    ///  the last to bust placed best, and anyone who left behind them
+    fn end_game(&mut self) {
+        let mut remaining: Vec<&HoldemSeat> = self
+            .seats
+            .iter()
+            .filter(|seat| !seat.busted && !seat.left)
+            .collect();
+        remaining.sort_by_key(|seat| std::cmp::Reverse(seat.chips));
+
+        self.standings = remaining
+            .iter()
+            .map(|seat| (seat.id, seat.name.clone()))
+            .chain(self.busted_users.iter().rev().cloned())
+            .chain(self.left_users.iter().rev().cloned())
+            .enumerate()
+            .map(|(idx, (user_id, name))| (user_id, name, idx as u32 + 1))
+            .collect();
+
+        if let Some((_, name, _)) = self.standings.first() {
+            self.action.push(HoldemAction::UserWon(name.clone()));
+        }
+
+        self.action.push(HoldemAction::GameEnded);
+        self.start_state = GameStartState::Ending;
+        self.user_turn = None;
+        self.next_hand = None;
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return;
+        };
+
+        let name = self.seats[seat_idx].name.clone();
+        self.action.push(HoldemAction::UserLeft(name.clone()));
+
+        match self.start_state {
+            GameStartState::Setup => {
+                self.seats.remove(seat_idx);
+            }
+            GameStartState::Active => {
+                let seat = &mut self.seats[seat_idx];
+                let busted = seat.busted;
+                seat.left = true;
+                seat.folded = true;
+
+                // Busted players have already been placed
+                if !busted {
+                    self.left_users.push((user_id, name));
+                }
+
+                if self.stage == HoldemStage::Showdown {
+                    let remaining = self
+                        .seats
+                        .iter()
+                        .filter(|seat| !seat.busted && !seat.left)
+                        .count();
+                    if remaining <= 1 {
+                        self.end_game();
+                    }
+                } else if self.user_turn == Some(seat_idx)
+                    || self.seats.iter().filter(|seat| !seat.folded).count() <= 1
+                {
+                    self.next_turn(seat_idx);
+                }
+            }
+            GameStartState::Ending => {}
+        }
+    }
+}
+
+impl RoomGame for ServerHoldem {
+    const GAME_TYPE: GameType = GameType::Holdem;
+    const MAX_PLAYERS: usize = HOLDEM_MAX_PLAYERS;
+
+    type Action = HoldemClientAction;
+    type State = HoldemClientGameState;
+    type Message = ServerHoldemCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    /// Seats are only given up between hands, but the lobby sees them go straight away
+    fn seated(&self) -> Vec<u32> {
+        self.seats
+            .iter()
+            .filter(|seat| !seat.left)
+            .map(|seat| seat.id)
+            .collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .filter(|seat| !seat.left)
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.action
+            .push(HoldemAction::UserJoined(user.name.clone()));
+        self.seats
+            .push(HoldemSeat::new(user_id, user.name.clone(), user.rating));
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: HoldemClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        self.standings
+            .iter()
+            .map(|(user_id, _, rank)| {
+                let left = self.left_users.iter().any(|(id, _)| id == user_id);
+
+                PlayerResult {
+                    user_id: *user_id,
+                    placement: (!left).then_some(*rank),
+                    rank: *rank,
+                    won: *rank == 1,
+                    busted: self.busted_users.iter().any(|(id, _)| id == user_id),
+                    cards_played: 0,
+                }
+            })
+            .collect()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.next_hand
+    }
+
+    /// The next hand is dealt on a timer, nobody has to ask for it
+    fn timer_fired(&mut self) -> Option<ServerHoldemCommand> {
+        self.next_hand = None;
+        self.start_hand();
+        None
+    }
+
+    fn client_state(&mut self, host_user: u32) -> HoldemClientGameState {
+        HoldemClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| HoldemPlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    chips: seat.chips,
+                    bet: seat.bet,
+                    folded: seat.folded,
+                    all_in: seat.all_in,
+                    busted: seat.busted,
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            hand_number: self.hand_number,
+            stage: self.stage,
+            dealer: self.dealer as u8,
+            user_turn: self.user_turn.map(|idx| idx as u8),
+            board: self.board.clone(),
+            pots: self.pots().into_iter().map(|(amount, _)| amount).collect(),
+            current_bet: self.current_bet,
+            min_raise: self.min_raise,
+            revealed: self.revealed.clone(),
+            standings: self
+                .standings
+                .iter()
+                .map(|(id, name, _)| (*id, name.clone()))
+                .collect(),
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut HoldemClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Hole cards only go to whoever holds them, everyone sees them once they're turned over
+    fn project(&self, state: HoldemClientGameState, viewer: Viewer) -> ServerHoldemCommand {
+        let cards = match viewer {
+            Viewer::Player(user_id) => self
+                .seats
+                .iter()
+                .find(|seat| seat.id == user_id)
+                .map(|seat| seat.hole.clone())
+                .unwrap_or_default(),
+            Viewer::Spectator => vec![],
+        };
+
+        ServerHoldemCommand::GameState(cards, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::test_cards::cards;
+
+    /// A hand at the river, each seat as (hole cards, chips committed, folded)
+    fn river(board: &str, seats: &[(&str, u32, bool)]) -> ServerHoldem {
+        let mut game = ServerHoldem::new(ChaCha8Rng::seed_from_u64(0));
+        game.board = cards(board);
+
+        for (idx, (hole, committed, folded)) in seats.iter().enumerate() {
+            let mut seat = HoldemSeat::new(idx as u32, format!("Player {idx}"), 0);
+            seat.hole = cards(hole);
+            seat.committed = *committed;
+            seat.folded = *folded;
+            game.seats.push(seat);
+        }
+
+        game
+    }
+
+    fn chips(game: &ServerHoldem) -> Vec<u32> {
+        game.seats.iter().map(|seat| seat.chips).collect()
+    }
+
+    #[test]
+    fn side_pots_for_each_all_in() {
+        let game = river(
+            "2c 7d 9h Js Kd",
+            &[
+                ("", 20, false),
+                ("", 60, false),
+                ("", 100, false),
+                ("", 100, false),
+            ],
+        );
+
+        assert_eq!(
+            game.pots(),
+            vec![
+                (80, vec![0, 1, 2, 3]),
+                (120, vec![1, 2, 3]),
+                (80, vec![2, 3])
+            ]
+        );
+    }
+
+    #[test]
+    fn folded_chips_join_the_pot_they_fell_in() {
+        let game = river(
+            "2c 7d 9h Js Kd",
+            &[
+                ("", 50, false),
+                ("", 100, false),
+                ("", 100, false),
+                ("", 30, true),
+            ],
+        );
+
+        assert_eq!(game.pots(), vec![(180, vec![0, 1, 2]), (100, vec![1, 2])]);
+    }
+
+    #[test]
+    fn short_stack_only_wins_the_main_pot() {
+        let mut game = river(
+            "2c 7d 9h Js Kd",
+            &[
+                ("Kc Ks", 50, false),
+                ("Ac Ad", 100, false),
+                ("3c 4h", 100, false),
+            ],
+        );
+
+        game.showdown(&[0, 1, 2]);
+
+        assert_eq!(chips(&game), vec![150, 100, 0]);
+        assert_eq!(game.revealed.len(), 3);
+    }
+
+    #[test]
+    fn split_pot_shares_out_evenly() {
+        let mut game = river(
+            "Ah Kd Qc Js Tc",
+            &[
+                ("2c 3d", 100, false),
+                ("4h 5s", 100, false),
+                ("6c 8d", 100, true),
+            ],
+        );
+
+        game.showdown(&[0, 1]);
+
+        assert_eq!(chips(&game), vec![150, 150, 0]);
+    }
+
+    #[test]
+    fn odd_chip_goes_left_of_the_button() {
+        let seats = [("As 3c", 10, false), ("Ah 3d", 10, false), ("", 1, true)];
+
+        let mut game = river("2c 7d 9h Js Kd", &seats);
+        game.dealer = 0;
+        game.showdown(&[0, 1]);
+        assert_eq!(chips(&game), vec![10, 11, 0]);
+
+        let mut game = river("2c 7d 9h Js Kd", &seats);
+        game.dealer = 1;
+        game.showdown(&[0, 1]);
+        assert_eq!(chips(&game), vec![11, 10, 0]);
+    }
+}
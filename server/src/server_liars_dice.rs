@@ -0,0 +1,523 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+    liars_dice::{
+        Bid, LIARS_DICE_MAX_PLAYERS, LIARS_DICE_START_DICE, LiarsDiceAction, LiarsDiceClientAction,
+        LiarsDiceClientGameState, LiarsDicePlayer, ServerLiarsDiceCommand, count_face,
+    },
+};
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+/// Played in a `GameRoom`.
+/// Knocked out players keep their seat with no dice, so they can watch the rest.
+pub struct ServerLiarsDice {
+    seats: Vec<DiceSeat>,
+    /// Whoever left part way through, in the order they went
+    left_users: Vec<(u32, String)>,
+    /// Everyone out of the game, first out first
+    knocked_out: Vec<(u32, String)>,
+    round: u32,
+    user_turn: Option<usize>,
+    bid: Option<(usize, Bid)>,
+    last_reveal: Vec<(u32, Vec<u8>)>,
+    start_state: GameStartState,
+    /// Filled in once the game is over, best placed first with their rank
+    standings: Vec<(u32, String, u32)>,
+    action: Vec<LiarsDiceAction>,
+    bids_made: HashMap<u32, u32>,
+    /// Seeded by the room, enough to reproduce every roll
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct DiceSeat {
+    id: u32,
+    name: String,
+    dice: Vec<u8>,
+    dice_count: u8,
+    rating: Option<u32>,
+}
+
+impl DiceSeat {
+    fn new(id: u32, name: String, rating: u32) -> DiceSeat {
+        DiceSeat {
+            id,
+            name,
+            dice: vec![],
+            dice_count: LIARS_DICE_START_DICE,
+            rating: Some(rating),
+        }
+    }
+}
+
+impl ServerLiarsDice {
+    pub fn new(rng: ChaCha8Rng) -> ServerLiarsDice {
+        ServerLiarsDice {
+            seats: vec![],
+            left_users: vec![],
+            knocked_out: vec![],
+            round: 0,
+            user_turn: None,
+            bid: None,
+            last_reveal: vec![],
+            start_state: GameStartState::Setup,
+            standings: vec![],
+            action: vec![LiarsDiceAction::Init],
+            bids_made: HashMap::new(),
+            rng,
+        }
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        if self.seats.len() < 2 {
+            return Err(anyhow!("Liar's Dice needs at least two players"));
+        }
+
+        self.start_state = GameStartState::Active;
+        self.start_round(0);
+
+        Ok(())
+    }
+
+    /// Everyone still in rolls again, the given seat bids first
+    fn start_round(&mut self, starter: usize) {
+        self.round += 1;
+
+        for seat in self.seats.iter_mut() {
+            seat.dice = (0..seat.dice_count)
+                .map(|_| self.rng.random_range(1..=6))
+                .collect();
+            seat.dice.sort();
+        }
+
+        self.bid = None;
+        self.user_turn = self.next_in(starter);
+        self.action.push(LiarsDiceAction::RoundStarted(self.round));
+    }
+
+    /// The first seat from this one on that still has dice, including itself
+    fn next_in(&self, from: usize) -> Option<usize> {
+        let count = self.seats.len();
+
+        (0..count)
+            .map(|offset| (from + offset) % count)
+            .find(|idx| self.seats[*idx].dice_count > 0)
+    }
+
+    fn total_dice(&self) -> u32 {
+        self.seats.iter().map(|seat| seat.dice_count as u32).sum()
+    }
+
+    fn act(&mut self, user_id: u32, action: LiarsDiceClientAction) -> anyhow::Result<()> {
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("Received Game message when not active"));
+        }
+
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return Err(anyhow!("User not in game"));
+        };
+
+        if self.user_turn != Some(seat_idx) {
+            return Err(anyhow!("Not this user's turn"));
+        }
+
+        match action {
+            LiarsDiceClientAction::Bid(bid) => self.make_bid(seat_idx, bid),
+            LiarsDiceClientAction::Call => self.call(seat_idx),
+        }
+    }
+
+    fn make_bid(&mut self, seat_idx: usize, bid: Bid) -> anyhow::Result<()> {
+        if !bid.is_valid(self.total_dice()) {
+            return Err(anyhow!("{bid:?} isn't a bid that can be made"));
+        }
+
+        if let Some((_, current)) = &self.bid
+            && !bid.beats(current)
+        {
+            return Err(anyhow!("{bid:?} doesn't beat {current:?}"));
+        }
+
+        let seat = &self.seats[seat_idx];
+        self.action
+            .push(LiarsDiceAction::UserBid(seat.name.clone(), bid));
+        *self.bids_made.entry(seat.id).or_insert(0) += 1;
+
+        self.bid = Some((seat_idx, bid));
+        self.user_turn = self.next_in(seat_idx + 1);
+
+        Ok(())
+    }
+
+    /// Every cup is lifted, whoever was wrong loses a die and starts the next round
+    fn call(&mut self, seat_idx: usize) -> anyhow::Result<()> {
+        let Some((bidder_idx, bid)) = self.bid else {
+            return Err(anyhow!("There's no bid to call"));
+        };
+
+        let actual = count_face(self.seats.iter().flat_map(|seat| &seat.dice), bid.face);
+
+        self.action.push(LiarsDiceAction::UserCalled(
+            self.seats[seat_idx].name.clone(),
+            self.seats[bidder_idx].name.clone(),
+            actual,
+        ));
+
+        self.last_reveal = self
+            .seats
+            .iter()
+            .filter(|seat| !seat.dice.is_empty())
+            .map(|seat| (seat.id, seat.dice.clone()))
+            .collect();
+
+        let loser_idx = if actual >= bid.quantity {
+            seat_idx
+        } else {
+            bidder_idx
+        };
+
+        let loser = &mut self.seats[loser_idx];
+        loser.dice_count -= 1;
+        self.action
+            .push(LiarsDiceAction::UserLostDie(loser.name.clone()));
+
+        if loser.dice_count == 0 {
+            loser.dice.clear();
+            self.action
+                .push(LiarsDiceAction::UserKnockedOut(loser.name.clone()));
+            self.knocked_out.push((loser.id, loser.name.clone()));
+        }
+
+        if self.seats.iter().filter(|seat| seat.dice_count > 0).count() <= 1 {
+            self.end_game();
+        } else {
+            self.start_round(loser_idx);
+        }
+
+        Ok(())
+    }
+
+    /// The last one with dice wins, then everyone else in the reverse of the order they went out
+    fn end_game(&mut self) {
+        self.standings = self
+            .seats
+            .iter()
+            .filter(|seat| seat.dice_count > 0)
+            .map(|seat| (seat.id, seat.name.clone(), 1))
+            .collect();
+
+        for (user_id, name) in self.knocked_out.iter().rev() {
+            let rank = self.standings.len() as u32 + 1;
+            self.standings.push((*user_id, name.clone(), rank));
+        }
+
+        if let Some((_, name, _)) = self.standings.first() {
+            self.action.push(LiarsDiceAction::UserWon(name.clone()));
+        }
+
+        for seat in self.seats.iter_mut() {
+            seat.dice.clear();
+        }
+
+        self.action.push(LiarsDiceAction::GameEnded);
+        self.start_state = GameStartState::Ending;
+        self.user_turn = None;
+        self.bid = None;
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return;
+        };
+
+        let seat = self.seats.remove(seat_idx);
+        self.action
+            .push(LiarsDiceAction::UserLeft(seat.name.clone()));
+
+        if self.start_state != GameStartState::Active {
+            return;
+        }
+
+        self.left_users.push((seat.id, seat.name.clone()));
+
+        // Anyone knocked out already keeps the place they had
+        if seat.dice_count > 0 {
+            self.knocked_out.push((seat.id, seat.name));
+        }
+
+        if self.seats.iter().filter(|seat| seat.dice_count > 0).count() <= 1 {
+            self.end_game();
+            return;
+        }
+
+        // The bid on the table counted their dice, so everyone rolls again
+        let starter = self.user_turn.unwrap_or(0);
+        let starter = if starter > seat_idx {
+            starter - 1
+        } else {
+            starter
+        };
+        self.start_round(starter % self.seats.len());
+    }
+}
+
+impl RoomGame for ServerLiarsDice {
+    const GAME_TYPE: GameType = GameType::LiarsDice;
+    const MAX_PLAYERS: usize = LIARS_DICE_MAX_PLAYERS;
+
+    type Action = LiarsDiceClientAction;
+    type State = LiarsDiceClientGameState;
+    type Message = ServerLiarsDiceCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.seats.iter().map(|seat| seat.id).collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.action
+            .push(LiarsDiceAction::UserJoined(user.name.clone()));
+        self.seats
+            .push(DiceSeat::new(user_id, user.name.clone(), user.rating));
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: LiarsDiceClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        self.standings
+            .iter()
+            .map(|(user_id, _, rank)| {
+                let left = self.left_users.iter().any(|(id, _)| id == user_id);
+
+                PlayerResult {
+                    user_id: *user_id,
+                    placement: (!left).then_some(*rank),
+                    rank: *rank,
+                    won: *rank == 1,
+                    busted: false,
+                    cards_played: self.bids_made.get(user_id).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+
+    fn client_state(&mut self, host_user: u32) -> LiarsDiceClientGameState {
+        LiarsDiceClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| LiarsDicePlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    dice_count: seat.dice_count,
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            round: self.round,
+            user_turn: self.user_turn.map(|idx| idx as u8),
+            bid: self
+                .bid
+                .map(|(seat_idx, bid)| (self.seats[seat_idx].id, bid)),
+            last_reveal: self.last_reveal.clone(),
+            standings: self
+                .standings
+                .iter()
+                .map(|(id, name, _)| (*id, name.clone()))
+                .collect(),
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut LiarsDiceClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Everyone only sees their own dice until they're revealed
+    fn project(&self, state: LiarsDiceClientGameState, viewer: Viewer) -> ServerLiarsDiceCommand {
+        let dice = match viewer {
+            Viewer::Player(user_id) => self
+                .seats
+                .iter()
+                .find(|seat| seat.id == user_id)
+                .map(|seat| seat.dice.clone())
+                .unwrap_or_default(),
+            Viewer::Spectator => vec![],
+        };
+
+        ServerLiarsDiceCommand::GameState(dice, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn bid(quantity: u8, face: u8) -> Bid {
+        Bid { quantity, face }
+    }
+
+    /// A round in progress, seat `idx` is user id `idx` and holds `cups[idx]`
+    fn table(cups: &[&[u8]]) -> ServerLiarsDice {
+        let mut game = ServerLiarsDice::new(ChaCha8Rng::seed_from_u64(0));
+        for (id, cup) in cups.iter().enumerate() {
+            let mut seat = DiceSeat::new(id as u32, format!("Player {id}"), 0);
+            seat.dice = cup.to_vec();
+            seat.dice_count = cup.len() as u8;
+            game.seats.push(seat);
+        }
+
+        game.start_state = GameStartState::Active;
+        game.round = 1;
+        game.user_turn = Some(0);
+        game
+    }
+
+    fn dice_counts(game: &ServerLiarsDice) -> Vec<u8> {
+        game.seats.iter().map(|seat| seat.dice_count).collect()
+    }
+
+    #[test]
+    fn bids_must_go_up() {
+        let mut game = table(&[&[2, 3], &[4, 5], &[6, 6]]);
+
+        // Nothing to call yet
+        assert!(game.act(0, LiarsDiceClientAction::Call).is_err());
+        game.act(0, LiarsDiceClientAction::Bid(bid(2, 4))).unwrap();
+
+        assert!(game.act(0, LiarsDiceClientAction::Bid(bid(3, 4))).is_err());
+        assert!(game.act(1, LiarsDiceClientAction::Bid(bid(2, 4))).is_err());
+        assert!(game.act(1, LiarsDiceClientAction::Bid(bid(2, 3))).is_err());
+        // Only six dice on the table
+        assert!(game.act(1, LiarsDiceClientAction::Bid(bid(7, 2))).is_err());
+
+        game.act(1, LiarsDiceClientAction::Bid(bid(2, 5))).unwrap();
+        assert_eq!(game.bid, Some((1, bid(2, 5))));
+        assert_eq!(game.user_turn, Some(2));
+    }
+
+    #[test]
+    fn wrong_call_costs_the_caller() {
+        // Three fives counting the wild one
+        let mut game = table(&[&[1, 2, 5], &[3, 5, 6]]);
+        game.act(0, LiarsDiceClientAction::Bid(bid(3, 5))).unwrap();
+        game.act(1, LiarsDiceClientAction::Call).unwrap();
+
+        assert_eq!(dice_counts(&game), vec![3, 2]);
+        assert!(game.action.iter().any(|action| matches!(
+            action,
+            LiarsDiceAction::UserCalled(caller, bidder, 3)
+                if caller == "Player 1" && bidder == "Player 0"
+        )));
+        assert_eq!(
+            game.last_reveal,
+            vec![(0, vec![1, 2, 5]), (1, vec![3, 5, 6])]
+        );
+
+        // The loser starts the next round, with fresh dice
+        assert_eq!(game.round, 2);
+        assert_eq!(game.user_turn, Some(1));
+        assert_eq!(game.bid, None);
+        assert_eq!(game.seats[1].dice.len(), 2);
+    }
+
+    #[test]
+    fn caught_lie_costs_the_bidder() {
+        let mut game = table(&[&[1, 2, 5], &[3, 5, 6]]);
+        game.act(0, LiarsDiceClientAction::Bid(bid(4, 5))).unwrap();
+        game.act(1, LiarsDiceClientAction::Call).unwrap();
+
+        assert_eq!(dice_counts(&game), vec![2, 3]);
+        assert_eq!(game.user_turn, Some(0));
+    }
+
+    #[test]
+    fn knocked_out_seats_are_skipped() {
+        let mut game = table(&[&[2], &[3, 3], &[4, 4]]);
+        game.act(0, LiarsDiceClientAction::Bid(bid(3, 3))).unwrap();
+        game.act(1, LiarsDiceClientAction::Bid(bid(3, 4))).unwrap();
+        game.act(2, LiarsDiceClientAction::Call).unwrap();
+
+        // Player 1 bid too high, then player 0 does with their last die
+        assert_eq!(dice_counts(&game), vec![1, 1, 2]);
+        game.seats[0].dice = vec![2];
+        game.seats[1].dice = vec![3];
+        game.seats[2].dice = vec![4, 4];
+        game.act(1, LiarsDiceClientAction::Bid(bid(4, 2))).unwrap();
+        game.act(2, LiarsDiceClientAction::Bid(bid(4, 3))).unwrap();
+        game.act(0, LiarsDiceClientAction::Bid(bid(4, 5))).unwrap();
+        game.act(1, LiarsDiceClientAction::Call).unwrap();
+
+        assert_eq!(dice_counts(&game), vec![0, 1, 2]);
+        assert_eq!(game.knocked_out, vec![(0, "Player 0".to_string())]);
+        // Out of dice, so the round starts with the next one along
+        assert_eq!(game.user_turn, Some(1));
+        assert!(game.seats[0].dice.is_empty());
+    }
+
+    #[test]
+    fn last_one_with_dice_wins() {
+        let mut game = table(&[&[6], &[2], &[3]]);
+        game.knocked_out.push((3, "Player 3".to_string()));
+        game.seats[2].dice_count = 0;
+        game.seats[2].dice.clear();
+        game.knocked_out.push((2, "Player 2".to_string()));
+
+        game.act(0, LiarsDiceClientAction::Bid(bid(2, 6))).unwrap();
+        game.act(1, LiarsDiceClientAction::Call).unwrap();
+
+        assert_eq!(game.start_state, GameStartState::Ending);
+        assert_eq!(
+            game.standings,
+            vec![
+                (1, "Player 1".to_string(), 1),
+                (0, "Player 0".to_string(), 2),
+                (2, "Player 2".to_string(), 3),
+                (3, "Player 3".to_string(), 4),
+            ]
+        );
+        assert_eq!(game.user_turn, None);
+    }
+}
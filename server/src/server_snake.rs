@@ -0,0 +1,700 @@
+use std::{collections::VecDeque, time::Duration};
+
+use anyhow::anyhow;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use rpc::{
+    comms::LobbyPlayer,
+    game_state::{GameStartState, GameType},
+    snake::{
+        Direction, SNAKE_COUNTDOWN_TICKS, SNAKE_HEIGHT, SNAKE_MAX_PLAYERS, SNAKE_TICK_MS,
+        SNAKE_WIDTH, ServerSnakeCommand, SnakeAction, SnakeBody, SnakeClientAction,
+        SnakeClientGameState, SnakeDelta, SnakeMovement, SnakePlayer, SnakeStep, SnakeTick,
+    },
+};
+use tokio::time::Instant;
+
+use crate::{
+    PlayerState,
+    game_room::{RoomGame, Viewer},
+    stats::PlayerResult,
+};
+
+const TICK: Duration = Duration::from_millis(SNAKE_TICK_MS);
+/// Turns pressed faster than the snake moves are saved up, but only this many
+const MAX_QUEUED_TURNS: usize = 2;
+const START_LENGTH: usize = 3;
+
+/// Played in a `GameRoom`, except it doesn't wait for anyone.
+/// Once the game starts the snakes move every tick and everyone is sent where they are,
+///  input only changes which way a snake goes next.
+pub struct ServerSnake {
+    seats: Vec<SnakeSeat>,
+    tick: u32,
+    next_tick: Option<Instant>,
+    food: Vec<(u8, u8)>,
+    /// Snakes that started, so one player on their own plays until they crash
+    starting_snakes: usize,
+    start_state: GameStartState,
+    /// Filled in once the game is over, best placed first with their rank
+    standings: Vec<(u32, String, u32)>,
+    action: Vec<SnakeAction>,
+    /// Seeded by the room, enough to reproduce where food turns up
+    rng: ChaCha8Rng,
+}
+
+#[derive(Debug)]
+struct SnakeSeat {
+    id: u32,
+    name: String,
+    rating: Option<u32>,
+    body: VecDeque<(u8, u8)>,
+    direction: Direction,
+    turns: VecDeque<(Direction, u32)>,
+    last_input: u32,
+    /// The tick they crashed on, later is better
+    died_at: Option<u32>,
+    length: u32,
+    food_eaten: u32,
+    left: bool,
+}
+
+impl SnakeSeat {
+    fn new(id: u32, name: String, rating: u32) -> SnakeSeat {
+        SnakeSeat {
+            id,
+            name,
+            rating: Some(rating),
+            body: VecDeque::new(),
+            direction: Direction::Right,
+            turns: VecDeque::new(),
+            last_input: 0,
+            died_at: None,
+            length: 0,
+            food_eaten: 0,
+            left: false,
+        }
+    }
+
+    fn alive(&self) -> bool {
+        self.died_at.is_none() && !self.body.is_empty()
+    }
+}
+
+impl ServerSnake {
+    pub fn new(rng: ChaCha8Rng) -> ServerSnake {
+        ServerSnake {
+            seats: vec![],
+            tick: 0,
+            next_tick: None,
+            food: vec![],
+            starting_snakes: 0,
+            start_state: GameStartState::Setup,
+            standings: vec![],
+            action: vec![SnakeAction::Init],
+            rng,
+        }
+    }
+
+    fn start_game(&mut self) -> anyhow::Result<()> {
+        // Half start on the left heading right, half on the right heading left,
+        //  spread out down the board
+        let rows_each_side = self.seats.len().div_ceil(2);
+        for (i, seat) in self.seats.iter_mut().enumerate() {
+            let row = ((i / 2 + 1) * SNAKE_HEIGHT as usize / (rows_each_side + 1)) as u8;
+
+            let (head, direction) = if i % 2 == 0 {
+                ((START_LENGTH as u8 + 1, row), Direction::Right)
+            } else {
+                ((SNAKE_WIDTH - START_LENGTH as u8 - 2, row), Direction::Left)
+            };
+
+            seat.direction = direction;
+            seat.body = VecDeque::from([head]);
+            for _ in 1..START_LENGTH {
+                let tail = *seat.body.back().unwrap_or(&head);
+                if let Some(next) = direction.opposite().step(tail) {
+                    seat.body.push_back(next);
+                }
+            }
+            seat.length = seat.body.len() as u32;
+        }
+
+        self.starting_snakes = self.seats.len();
+        for _ in 0..=self.seats.len() {
+            self.spawn_food();
+        }
+
+        self.tick = 0;
+        self.start_state = GameStartState::Active;
+        self.next_tick = Some(Instant::now() + TICK);
+        self.action.push(SnakeAction::GameStarted);
+
+        Ok(())
+    }
+
+    fn act(&mut self, user_id: u32, action: SnakeClientAction) -> anyhow::Result<()> {
+        let Some(seat) = self.seats.iter_mut().find(|seat| seat.id == user_id) else {
+            return Err(anyhow!("User isn't playing"));
+        };
+
+        match action {
+            SnakeClientAction::Turn(direction, input) => {
+                if !seat.alive() {
+                    return Err(anyhow!("Snake has already crashed"));
+                }
+
+                // Compared with the last turn saved up, so quick double turns both count
+                let heading = seat
+                    .turns
+                    .back()
+                    .map(|(direction, _)| *direction)
+                    .unwrap_or(seat.direction);
+
+                if direction == heading
+                    || direction == heading.opposite()
+                    || seat.turns.len() >= MAX_QUEUED_TURNS
+                {
+                    seat.last_input = seat.last_input.max(input);
+                    return Ok(());
+                }
+
+                seat.turns.push_back((direction, input));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves every snake one cell, then works out who crashed into what.
+    /// Gives back what changed, for everyone to catch up with.
+    fn advance(&mut self) -> SnakeDelta {
+        self.tick += 1;
+        self.next_tick = Some(self.next_tick.unwrap_or_else(Instant::now) + TICK);
+
+        if self.tick <= SNAKE_COUNTDOWN_TICKS {
+            let movements = vec![SnakeMovement::Still; self.seats.len()];
+            return self.delta(&movements, None);
+        }
+
+        let food_before = self.food.clone();
+        let mut movements = vec![SnakeMovement::Still; self.seats.len()];
+        let mut heads: Vec<Option<(u8, u8)>> = vec![None; self.seats.len()];
+
+        for (i, seat) in self.seats.iter_mut().enumerate() {
+            if !seat.alive() {
+                continue;
+            }
+
+            if let Some((direction, input)) = seat.turns.pop_front() {
+                seat.direction = direction;
+                seat.last_input = seat.last_input.max(input);
+            }
+
+            let head = seat.body.front().copied();
+            heads[i] = head.and_then(|head| seat.direction.step(head));
+
+            // Off the edge of the board
+            if heads[i].is_none() {
+                seat.died_at = Some(self.tick);
+            }
+        }
+
+        // Tails move out of the way first, so following right behind a tail is fine
+        for (i, seat) in self.seats.iter_mut().enumerate() {
+            let Some(head) = heads[i] else {
+                continue;
+            };
+
+            let ate = match self.food.iter().position(|food| *food == head) {
+                Some(idx) => {
+                    self.food.remove(idx);
+                    seat.food_eaten += 1;
+                    true
+                }
+                None => {
+                    seat.body.pop_back();
+                    false
+                }
+            };
+            movements[i] = SnakeMovement::Moved(head, ate);
+        }
+
+        // Everyone's checked against the board as it is before anyone is taken off it
+        let crashed: Vec<bool> = heads
+            .iter()
+            .enumerate()
+            .map(|(i, head)| {
+                let Some(head) = head else {
+                    return false;
+                };
+
+                let hit_body = self.seats.iter().any(|seat| seat.body.contains(head));
+                let hit_head = heads
+                    .iter()
+                    .enumerate()
+                    .any(|(j, other)| j != i && other.as_ref() == Some(head));
+
+                hit_body || hit_head
+            })
+            .collect();
+
+        for (seat, crashed) in self.seats.iter_mut().zip(crashed) {
+            if crashed {
+                seat.died_at = Some(self.tick);
+            }
+        }
+
+        for (i, seat) in self.seats.iter_mut().enumerate() {
+            if let Some(head) = heads[i]
+                && seat.died_at.is_none()
+            {
+                seat.body.push_front(head);
+                seat.length = seat.body.len() as u32;
+            }
+
+            if seat.died_at == Some(self.tick) {
+                self.action
+                    .push(SnakeAction::UserDied(seat.name.clone(), seat.length));
+                seat.body.clear();
+                seat.turns.clear();
+                movements[i] = SnakeMovement::Crashed;
+            }
+        }
+
+        while self.food.len() < self.starting_snakes + 1 {
+            if !self.spawn_food() {
+                break;
+            }
+        }
+
+        let alive = self.seats.iter().filter(|seat| seat.alive()).count();
+        if alive == 0 || (self.starting_snakes > 1 && alive == 1) {
+            self.end_game();
+        }
+
+        let food = (self.food != food_before).then(|| self.food.clone());
+        self.delta(&movements, food)
+    }
+
+    /// Snakes that were already gone before this tick have nothing to say
+    fn delta(&self, movements: &[SnakeMovement], food: Option<Vec<(u8, u8)>>) -> SnakeDelta {
+        SnakeDelta {
+            tick: self.tick,
+            countdown: SNAKE_COUNTDOWN_TICKS.saturating_sub(self.tick),
+            snakes: self
+                .seats
+                .iter()
+                .zip(movements)
+                .filter(|(seat, movement)| seat.alive() || **movement == SnakeMovement::Crashed)
+                .map(|(seat, movement)| SnakeStep {
+                    id: seat.id,
+                    movement: *movement,
+                    direction: seat.direction,
+                    last_input: seat.last_input,
+                })
+                .collect(),
+            food,
+        }
+    }
+
+    /// Food goes on a random empty cell, there's only no room once the board is packed
+    fn spawn_food(&mut self) -> bool {
+        for _ in 0..100 {
+            let cell = (
+                self.rng.random_range(0..SNAKE_WIDTH),
+                self.rng.random_range(0..SNAKE_HEIGHT),
+            );
+
+            let taken = self.food.contains(&cell)
+                || self.seats.iter().any(|seat| seat.body.contains(&cell));
+
+            if !taken {
+                self.food.push(cell);
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn end_game(&mut self) {
+        self.next_tick = None;
+        self.start_state = GameStartState::Ending;
+
+        // Whoever lasted longest comes first, crashing on the same tick is a tie
+        let mut order: Vec<&SnakeSeat> = self.seats.iter().collect();
+        order.sort_by_key(|seat| {
+            (
+                std::cmp::Reverse(seat.died_at.unwrap_or(u32::MAX)),
+                std::cmp::Reverse(seat.length),
+            )
+        });
+
+        let mut standings = vec![];
+        for (i, seat) in order.iter().enumerate() {
+            let rank = match i {
+                0 => 1,
+                _ if order[i - 1].died_at == seat.died_at => {
+                    standings.last().map(|(_, _, rank)| *rank).unwrap_or(1)
+                }
+                _ => i as u32 + 1,
+            };
+            standings.push((seat.id, seat.name.clone(), rank));
+        }
+
+        if let Some(winner) = order.first()
+            && winner.died_at.is_none()
+        {
+            self.action.push(SnakeAction::UserWon(winner.name.clone()));
+        }
+
+        self.standings = standings;
+        self.action.push(SnakeAction::GameEnded);
+    }
+
+    fn leave_seat(&mut self, user_id: u32) {
+        let Some(seat_idx) = self.seats.iter().position(|seat| seat.id == user_id) else {
+            return;
+        };
+
+        self.action
+            .push(SnakeAction::UserLeft(self.seats[seat_idx].name.clone()));
+
+        match self.start_state {
+            GameStartState::Setup => {
+                self.seats.remove(seat_idx);
+            }
+            // Their snake is gone, but they're still placed for the results
+            GameStartState::Active => {
+                let seat = &mut self.seats[seat_idx];
+                seat.left = true;
+                if seat.alive() {
+                    seat.died_at = Some(self.tick);
+                    seat.body.clear();
+                }
+
+                let alive = self.seats.iter().filter(|seat| seat.alive()).count();
+                if alive == 0 || (self.starting_snakes > 1 && alive == 1) {
+                    self.end_game();
+                }
+            }
+            GameStartState::Ending => {
+                self.seats[seat_idx].left = true;
+            }
+        }
+    }
+
+    fn snapshot(&self) -> SnakeTick {
+        SnakeTick {
+            tick: self.tick,
+            countdown: SNAKE_COUNTDOWN_TICKS.saturating_sub(self.tick),
+            snakes: self
+                .seats
+                .iter()
+                .map(|seat| SnakeBody {
+                    id: seat.id,
+                    body: seat.body.iter().copied().collect(),
+                    direction: seat.direction,
+                    alive: seat.alive(),
+                    last_input: seat.last_input,
+                })
+                .collect(),
+            food: self.food.clone(),
+        }
+    }
+}
+
+impl RoomGame for ServerSnake {
+    const GAME_TYPE: GameType = GameType::Snake;
+    const MAX_PLAYERS: usize = SNAKE_MAX_PLAYERS;
+    /// Turns show up in the next tick, nothing needs sending straight away
+    const QUIET_ACTIONS: bool = true;
+
+    type Action = SnakeClientAction;
+    type State = SnakeClientGameState;
+    type Message = ServerSnakeCommand;
+
+    fn start_state(&self) -> GameStartState {
+        self.start_state
+    }
+
+    fn seated(&self) -> Vec<u32> {
+        self.seats
+            .iter()
+            .filter(|seat| !seat.left)
+            .map(|seat| seat.id)
+            .collect()
+    }
+
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.seats
+            .iter()
+            .map(|seat| LobbyPlayer {
+                name: seat.name.clone(),
+                rating: seat.rating,
+            })
+            .collect()
+    }
+
+    fn seat(&mut self, user_id: u32, user: &PlayerState) {
+        self.action.push(SnakeAction::UserJoined(user.name.clone()));
+        self.seats
+            .push(SnakeSeat::new(user_id, user.name.clone(), user.rating));
+    }
+
+    fn start(&mut self) -> anyhow::Result<()> {
+        self.start_game()
+    }
+
+    fn handle_action(
+        &mut self,
+        user_id: u32,
+        _is_host: bool,
+        action: SnakeClientAction,
+    ) -> anyhow::Result<()> {
+        self.act(user_id, action)
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        self.leave_seat(user_id);
+    }
+
+    fn results(&self) -> Vec<PlayerResult> {
+        self.standings
+            .iter()
+            .filter_map(|(user_id, _, rank)| {
+                let seat = self.seats.iter().find(|seat| seat.id == *user_id)?;
+
+                Some(PlayerResult {
+                    user_id: *user_id,
+                    placement: (!seat.left).then_some(*rank),
+                    rank: *rank,
+                    won: *rank == 1 && seat.died_at.is_none(),
+                    busted: false,
+                    cards_played: seat.food_eaten,
+                })
+            })
+            .collect()
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.next_tick
+    }
+
+    /// The game moves on every tick, everyone sees the same thing so it's only what moved that goes out
+    fn timer_fired(&mut self) -> Option<ServerSnakeCommand> {
+        let delta = self.advance();
+
+        (self.start_state != GameStartState::Ending).then_some(ServerSnakeCommand::Tick(delta))
+    }
+
+    fn client_state(&mut self, host_user: u32) -> SnakeClientGameState {
+        SnakeClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            players: self
+                .seats
+                .iter()
+                .map(|seat| SnakePlayer {
+                    id: seat.id,
+                    name: seat.name.clone(),
+                    rating: seat.rating,
+                })
+                .collect(),
+            host_user,
+            standings: self.standings.clone(),
+            tick: (self.start_state != GameStartState::Setup).then(|| self.snapshot()),
+            invite_code: None,
+        }
+    }
+
+    fn set_invite_code(state: &mut SnakeClientGameState, invite_code: String) {
+        state.invite_code = Some(invite_code);
+    }
+
+    /// Nothing is hidden, everyone sees the same board
+    fn project(&self, state: SnakeClientGameState, _viewer: Viewer) -> ServerSnakeCommand {
+        ServerSnakeCommand::GameState(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// A game past the countdown with each snake given as its body, head first, and heading.
+    /// Food is kept along the bottom row, out of the way.
+    fn game(snakes: &[(&[(u8, u8)], Direction)]) -> ServerSnake {
+        let mut game = ServerSnake::new(ChaCha8Rng::seed_from_u64(0));
+
+        for (i, (body, direction)) in snakes.iter().enumerate() {
+            let mut seat = SnakeSeat::new(i as u32, format!("Player {i}"), 0);
+            seat.body = body.iter().copied().collect();
+            seat.direction = *direction;
+            seat.length = body.len() as u32;
+            game.seats.push(seat);
+        }
+
+        game.starting_snakes = snakes.len();
+        game.food = (0..=snakes.len() as u8)
+            .map(|column| (column, SNAKE_HEIGHT - 1))
+            .collect();
+        game.start_state = GameStartState::Active;
+        game.tick = SNAKE_COUNTDOWN_TICKS;
+        game
+    }
+
+    fn alive(game: &ServerSnake) -> Vec<bool> {
+        game.seats.iter().map(|seat| seat.alive()).collect()
+    }
+
+    #[test]
+    fn snakes_move_forward() {
+        let mut game = game(&[(&[(5, 5), (4, 5), (3, 5)], Direction::Right)]);
+
+        game.advance();
+
+        let body: Vec<(u8, u8)> = game.seats[0].body.iter().copied().collect();
+        assert_eq!(body, vec![(6, 5), (5, 5), (4, 5)]);
+    }
+
+    #[test]
+    fn wall_kills() {
+        let mut game = game(&[
+            (
+                &[(SNAKE_WIDTH - 1, 5), (SNAKE_WIDTH - 2, 5)],
+                Direction::Right,
+            ),
+            (&[(5, 0), (5, 1)], Direction::Up),
+            (&[(10, 8), (9, 8)], Direction::Right),
+        ]);
+
+        game.advance();
+
+        assert_eq!(alive(&game), vec![false, false, true]);
+        assert_eq!(game.seats[0].died_at, Some(game.tick));
+        assert!(game.seats[0].body.is_empty());
+    }
+
+    #[test]
+    fn running_into_yourself_kills() {
+        // Turning down into the side of itself
+        let mut game = game(&[
+            (&[(5, 5), (4, 5), (4, 6), (5, 6), (6, 6)], Direction::Down),
+            (&[(20, 5), (19, 5), (19, 6), (20, 6)], Direction::Down),
+        ]);
+
+        game.advance();
+
+        // The second only follows its own tail, which moves out of the way first
+        assert_eq!(alive(&game), vec![false, true]);
+    }
+
+    #[test]
+    fn running_into_another_snake_kills() {
+        let mut game = game(&[
+            (&[(5, 4), (4, 4)], Direction::Right),
+            (&[(6, 3), (6, 4), (6, 5), (6, 6)], Direction::Up),
+        ]);
+
+        game.advance();
+
+        assert_eq!(alive(&game), vec![false, true]);
+        assert_eq!(game.start_state, GameStartState::Ending);
+        assert_eq!(game.standings[0], (1, "Player 1".to_string(), 1));
+        assert_eq!(game.standings[1], (0, "Player 0".to_string(), 2));
+    }
+
+    #[test]
+    fn head_on_kills_both() {
+        // Heads next to each other swap places, both run into the other's neck
+        let mut game = game(&[
+            (&[(10, 5), (9, 5)], Direction::Right),
+            (&[(11, 5), (12, 5)], Direction::Left),
+            (&[(10, 9), (9, 9)], Direction::Right),
+        ]);
+
+        game.advance();
+
+        assert_eq!(alive(&game), vec![false, false, true]);
+    }
+
+    #[test]
+    fn same_cell_on_one_tick_kills_both() {
+        let mut game = game(&[
+            (&[(10, 5), (9, 5)], Direction::Right),
+            (&[(11, 4), (11, 3)], Direction::Down),
+        ]);
+
+        game.advance();
+
+        // Neither outlasted the other, so it's a tie and nobody won
+        assert_eq!(alive(&game), vec![false, false]);
+        assert_eq!(game.standings[0].2, 1);
+        assert_eq!(game.standings[1].2, 1);
+        assert!(game.results().iter().all(|result| !result.won));
+    }
+
+    #[test]
+    fn eating_grows_the_snake() {
+        let mut game = game(&[(&[(5, 5), (4, 5), (3, 5)], Direction::Right)]);
+        game.food[0] = (6, 5);
+
+        game.advance();
+
+        assert_eq!(game.seats[0].length, 4);
+        assert_eq!(game.seats[0].food_eaten, 1);
+        assert_eq!(game.food.len(), 2);
+        assert!(!game.food.contains(&(6, 5)));
+    }
+
+    #[test]
+    fn deltas_keep_the_board_in_step() {
+        let mut game = game(&[
+            (&[(5, 5), (4, 5), (3, 5)], Direction::Right),
+            (&[(20, 0), (20, 1)], Direction::Up),
+            (&[(10, 8), (9, 8)], Direction::Right),
+        ]);
+        game.food[0] = (6, 5);
+
+        let mut board = game.snapshot();
+        for _ in 0..5 {
+            let delta = game.advance();
+
+            // Nothing more than the ends of snakes still going, and the food when it changes
+            assert!(delta.snakes.len() <= 3);
+            if game.tick > SNAKE_COUNTDOWN_TICKS + 1 {
+                assert!(delta.food.is_none());
+            }
+
+            board.apply(delta);
+            assert_eq!(board, game.snapshot());
+        }
+
+        // The one off the top only shows up on the tick it crashed
+        assert!(!game.seats[1].alive());
+    }
+
+    #[test]
+    fn countdown_deltas_move_nothing() {
+        let mut game = game(&[(&[(5, 5), (4, 5)], Direction::Right)]);
+        game.tick = 0;
+
+        let mut board = game.snapshot();
+        let delta = game.advance();
+
+        assert_eq!(delta.countdown, SNAKE_COUNTDOWN_TICKS - 1);
+        assert!(
+            delta
+                .snakes
+                .iter()
+                .all(|step| step.movement == SnakeMovement::Still)
+        );
+
+        board.apply(delta);
+        assert_eq!(board, game.snapshot());
+    }
+}
@@ -1,21 +1,32 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use anyhow::anyhow;
-use bincode::config::Configuration;
-use rand::Rng;
+use bincode::{Decode, Encode, config::Configuration};
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
 use rpc::{
-    comms::{ClientGameCommand, ServerMessage},
-    game_state::{GameStartState, GameType},
+    chat::Reaction,
+    comms::{ClientGameCommand, LobbyPlayer, RoomVisibility, ServerMessage},
+    game_state::{GameStartState, GameType, GameUserState},
     uno::{
-        ServerUnoCommand, UnoAction, UnoActiveUser, UnoCard, UnoCardColour, UnoCardPower,
-        UnoClientAction, UnoClientGameState,
+        ServerUnoCommand, UNO_MAX_PLAYERS, UnoAction, UnoActiveUser, UnoBotDifficulty, UnoCard,
+        UnoCardColour, UnoCardPower, UnoClientAction, UnoClientGameState, UnoEffect,
+        UnoPartnerView, UnoReplayFrame, UnoRules, UnoSettings, UnoTeamStanding,
     },
 };
-use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    time::{Instant, sleep_until},
+};
 
 use crate::{
     GameServerMessage, GameServerState, GameServerStateUpdate, PlayerState, ServerGameCommand,
     ServerIntraMessage,
+    game_room::NewRoom,
+    matchmaking::TableSize,
+    replays,
+    stats::{AccountId, GameResults, PlayerResult},
+    uno_bot::{UnoBot, UnoBotView},
 };
 
 /// My idea for game implementations is that they are stored
@@ -35,12 +46,51 @@ pub struct ServerUno {
     finished_users: Vec<(u32, String)>,
     bust_users: Vec<(u32, String)>,
     user_senders: HashMap<u32, UnboundedSender<ServerMessage>>,
+    /// Spectators get the same state as players, just without a hand
+    spectator_senders: HashMap<u32, UnboundedSender<ServerMessage>>,
     last_card: UnoCard,
     host_user: u32,
     user_turn: u8,
     is_ord: bool,
     pub start_state: GameStartState,
+    settings: UnoSettings,
+    /// Plain Uno, or one of the variants loaded by the server
+    game_type: GameType,
+    rules: UnoRules,
+    /// In team mode, the team of every user dealt in, in seat order
+    user_teams: Vec<(u32, u8)>,
+    winning_team: Option<u8>,
+    /// Whose turn the timer is running for, and when it runs out
+    turn_timer: Option<(u32, Instant)>,
+    /// Timeouts in a row for each user, reset when they play themselves
+    turn_timeouts: HashMap<u32, u8>,
+    /// Seats played by the server, bots have no sender
+    bots: HashMap<u32, UnoBot>,
+    /// The bot whose turn it is, and when they'll make their move
+    bot_move: Option<(u32, Instant)>,
     action: Vec<UnoAction>,
+    /// Everything needed to play the game again from the seed, recorded from the start
+    replay: UnoReplay,
+    started_at: Option<Instant>,
+    cards_played: HashMap<u32, u32>,
+    /// Only the users who went bust, `bust_users` has everyone who left too
+    busted: Vec<u32>,
+    /// Everyone who ever took a seat, results still count for those who left
+    accounts: HashMap<u32, AccountId>,
+    results_reported: bool,
+    /// Replays and tests don't have one
+    invite_code: Option<String>,
+    /// Nobody new can join while the room is locked
+    locked: bool,
+    visibility: RoomVisibility,
+    /// Accounts rather than ids, a reconnect gets a new id but keeps its key
+    banned: Vec<AccountId>,
+    /// When each user last reacted
+    last_reactions: HashMap<u32, Instant>,
+    /// Every random choice in a game comes from this rng.
+    /// Given the seed and the ordered actions, a game can be replayed exactly.
+    seed: u64,
+    rng: ChaCha8Rng,
 }
 
 #[derive(Debug)]
@@ -48,6 +98,8 @@ struct UnoUser {
     id: u32,
     name: String,
     cards: Vec<UnoCard>,
+    /// Bots don't have one
+    rating: Option<u32>,
 }
 
 // struct WaitingUser {
@@ -56,69 +108,339 @@ struct UnoUser {
 //     sender: UnboundedSender<ServerMessage>,
 // }
 
-const DECK_SIZE: u8 = 108;
+/// A finished game is saved as the seed, the rules, who sat where and
+///  every move in order.
+/// Playing the moves back from the seed gets the exact same game, so
+///  nothing else needs storing.
+#[derive(Debug, Encode, Decode, Default)]
+struct UnoReplay {
+    seed: u64,
+    host_user: u32,
+    settings: UnoSettings,
+    rules: UnoRules,
+    seats: Vec<UnoReplaySeat>,
+    events: Vec<UnoReplayEvent>,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct UnoReplaySeat {
+    id: u32,
+    name: String,
+    bot: Option<UnoBotDifficulty>,
+}
+
+#[derive(Debug, Encode, Decode)]
+enum UnoReplayEvent {
+    Action(u32, UnoClientAction),
+    Left(u32),
+    BotTookOver(u32),
+    TimedOut(u32),
+}
+
+/// Bots wait a moment before playing so people can follow what happened
+const BOT_THINK_TIME: Duration = Duration::from_millis(800);
+
+/// How long someone has to wait between reactions, so they can't flood the events
+const REACTION_COOLDOWN: Duration = Duration::from_secs(3);
 
 impl ServerUno {
     /// The idea here is to create the game on it's own thread
     ///  we can then return a channel to the game thread for
     ///  the main loop to send messages to.
-    /// We also need a channel to the main thread here.
+    /// The channel to the main thread comes in with the rest of the `NewRoom`.
+    /// Uno keeps its own loop rather than a `GameRoom`, for the bots, kicks, replays and reactions.
+    ///
+    /// Variants are the same game with different rules, they only differ in the game type.
     pub fn create(
+        room: NewRoom,
+        game_type: GameType,
+        rules: UnoRules,
+    ) -> anyhow::Result<GameServerState> {
+        let NewRoom {
+            game_id,
+            host_id,
+            host,
+            lobby_name,
+            invite_code,
+            visibility,
+            service_sender,
+        } = room;
+        let seed: u64 = rand::random();
+
+        println!(
+            "Creating Uno game {game_id} with seed {seed} and the {} rules",
+            rules.name
+        );
+
+        let mut server = ServerUno::new(game_id, lobby_name.clone(), host_id, seed)?;
+        server.invite_code = Some(invite_code);
+        server.visibility = visibility;
+        server.game_type = game_type;
+        server.rules = rules;
+
+        server.active_users.push(UnoUser {
+            id: host_id,
+            name: host.name.clone(),
+            cards: vec![],
+            rating: Some(host.rating),
+        });
+        server.user_senders.insert(host_id, host.sender.clone());
+        server.accounts.insert(host_id, host.account);
+        server.action.push(UnoAction::Init);
+
+        let _x = host.sender.send(ServerMessage::JoinedGame(
+            lobby_name.clone(),
+            server.game_type,
+            GameUserState::Active,
+        ));
+
+        Ok(server.launch(service_sender))
+    }
+
+    /// Quick play tables skip the setup, everyone is sat down with the rules
+    ///  they queued for and the game starts straight away.
+    /// The first player is the host, not that there's anything left to host.
+    /// Quick play is only ever plain Uno, `uno_rules` is whatever that is on this server.
+    pub fn create_matched(
         game_id: u32,
-        host_id: u32,
-        host: &PlayerState,
-        lobby_name: String,
+        players: &[(u32, &PlayerState)],
+        rules: &[u8],
+        uno_rules: UnoRules,
+        bot_count: usize,
+        invite_code: String,
         service_sender: UnboundedSender<ServerIntraMessage>,
     ) -> anyhow::Result<GameServerState> {
-        let (send_channel, receive_channel) = mpsc::unbounded_channel::<GameServerMessage>();
+        let settings = Self::decode_rules(rules)?;
+        let host_id = players
+            .first()
+            .map(|(id, _)| *id)
+            .ok_or(anyhow!("A match needs players"))?;
 
-        let mut deck = UnoDeck::new();
-        let mut rng = rand::rng();
+        let seed: u64 = rand::random();
+        let lobby_name = format!("Quick Play {game_id}");
 
-        let last_card = deck.pickup();
+        println!("Creating matched Uno game {game_id} with seed {seed}");
 
-        let host_player = UnoUser {
-            id: host_id,
-            name: host.name.clone(),
-            cards: deck.get_new_hand(&mut rng)?,
-        };
+        let mut server = ServerUno::new(game_id, lobby_name.clone(), host_id, seed)?;
+        server.settings = settings;
+        server.rules = uno_rules;
+        server.invite_code = Some(invite_code);
+        server.action.push(UnoAction::Init);
+
+        for (user_id, user) in players {
+            let (uno_user, sender) = UnoUser::new_joiner(*user_id, (*user).clone());
 
-        let mut user_senders = HashMap::new();
-        user_senders.insert(host_id, host.sender.clone());
+            server.active_users.push(uno_user);
+            server.user_senders.insert(*user_id, sender);
+            server.accounts.insert(*user_id, user.account);
+        }
+
+        for _ in 0..bot_count {
+            server.push_bot(UnoBotDifficulty::Greedy);
+        }
+
+        server.can_start()?;
+        server.start_game();
+
+        for sender in server.user_senders.values() {
+            let _x = sender.send(ServerMessage::JoinedGame(
+                lobby_name.clone(),
+                server.game_type,
+                GameUserState::Active,
+            ));
+        }
+
+        Ok(server.launch(service_sender))
+    }
+
+    /// How many players a quick play table needs for the rules asked for
+    pub fn quick_play_size(rules: &[u8]) -> anyhow::Result<TableSize> {
+        let settings = Self::decode_rules(rules)?;
+
+        Ok(if settings.team_mode {
+            TableSize { min: 4, ideal: 4 }
+        } else {
+            TableSize { min: 2, ideal: 4 }
+        })
+    }
+
+    fn decode_rules(rules: &[u8]) -> anyhow::Result<UnoSettings> {
+        let (settings, _) = bincode::decode_from_slice::<UnoSettings, Configuration>(
+            rules,
+            bincode::config::standard(),
+        )?;
+
+        if !settings.validate() {
+            return Err(anyhow!("Invalid settings {settings:?}"));
+        }
+
+        Ok(settings)
+    }
+
+    /// Hands the game off to its own task
+    fn launch(self, service_sender: UnboundedSender<ServerIntraMessage>) -> GameServerState {
+        let (send_channel, receive_channel) = mpsc::unbounded_channel::<GameServerMessage>();
 
         let state = GameServerState {
-            name: lobby_name.clone(),
-            player_count: 1,
-            game_type: GameType::Uno,
+            name: self.lobby_name.clone(),
+            player_count: self.active_users.len() as u32,
+            game_type: self.game_type,
             channel: send_channel,
-            start_state: GameStartState::Setup,
+            start_state: self.start_state,
+            max_players: UNO_MAX_PLAYERS as u32,
+            spectator_count: 0,
+            players: self.lobby_players(),
+            visibility: self.visibility.clone(),
+            invite_code: self.invite_code.clone().unwrap_or_default(),
+            locked: self.locked,
+            seed: self.seed,
         };
 
-        let _x = host
-            .sender
-            .send(ServerMessage::JoinedGame(lobby_name.clone(), GameType::Uno));
-
         tokio::spawn(async move {
-            let server = ServerUno {
-                id: game_id,
-                lobby_name,
-                deck,
-                active_users: vec![host_player],
-                finished_users: vec![],
-                bust_users: vec![],
-                last_card,
-                host_user: host_id,
-                user_turn: 0,
-                is_ord: true,
-                start_state: GameStartState::Setup,
-                action: vec![UnoAction::Init],
-                user_senders,
-            };
-
-            server.start_server(receive_channel, service_sender).await;
+            self.start_server(receive_channel, service_sender).await;
         });
 
-        Ok(state)
+        state
+    }
+
+    /// An empty room, everything random comes from the seed from here on
+    fn new(id: u32, lobby_name: String, host_user: u32, seed: u64) -> anyhow::Result<ServerUno> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let settings = UnoSettings::default();
+        let rules = UnoRules::standard();
+
+        // Cards are only dealt once the game starts, this is just a placeholder
+        //  until the host has decided how many decks to play with
+        let mut deck = UnoDeck::new(&rules, settings.deck_count, &mut rng);
+        let last_card = deck
+            .pickup(&mut rng)
+            .ok_or(anyhow!("Created an empty deck"))?;
+
+        Ok(ServerUno {
+            id,
+            lobby_name,
+            deck,
+            active_users: vec![],
+            finished_users: vec![],
+            bust_users: vec![],
+            user_senders: HashMap::new(),
+            spectator_senders: HashMap::new(),
+            last_card,
+            host_user,
+            user_turn: 0,
+            is_ord: true,
+            start_state: GameStartState::Setup,
+            settings,
+            game_type: GameType::Uno,
+            rules,
+            user_teams: vec![],
+            winning_team: None,
+            turn_timer: None,
+            turn_timeouts: HashMap::new(),
+            bots: HashMap::new(),
+            bot_move: None,
+            action: vec![],
+            replay: UnoReplay::default(),
+            started_at: None,
+            cards_played: HashMap::new(),
+            busted: vec![],
+            accounts: HashMap::new(),
+            results_reported: false,
+            invite_code: None,
+            locked: false,
+            visibility: RoomVisibility::Public,
+            banned: vec![],
+            last_reactions: HashMap::new(),
+            seed,
+            rng,
+        })
+    }
+
+    /// Plays a saved game back from its seed, keeping the state after every move
+    pub fn replay_frames(data: &[u8]) -> anyhow::Result<Vec<UnoReplayFrame>> {
+        let (replay, _) = bincode::decode_from_slice::<UnoReplay, Configuration>(
+            data,
+            bincode::config::standard(),
+        )?;
+
+        let mut game = ServerUno::new(0, String::new(), replay.host_user, replay.seed)?;
+        game.settings = replay.settings;
+        game.rules = replay.rules;
+
+        for seat in replay.seats.iter() {
+            game.active_users.push(UnoUser {
+                id: seat.id,
+                name: seat.name.clone(),
+                cards: vec![],
+                rating: None,
+            });
+
+            // Bots only need to be known about for showing, their moves are in the events
+            if let Some(difficulty) = seat.bot {
+                game.bots.insert(seat.id, UnoBot::new(difficulty, 0));
+            }
+        }
+
+        game.start_game();
+
+        let mut frames = vec![game.replay_frame()];
+
+        for event in replay.events {
+            match event {
+                UnoReplayEvent::Action(user_id, action) => game.handle_action(user_id, action)?,
+                UnoReplayEvent::Left(user_id) => game.user_left(user_id),
+                UnoReplayEvent::BotTookOver(user_id) => {
+                    game.bot_takes_over(user_id);
+                }
+                UnoReplayEvent::TimedOut(user_id) => {
+                    if let Some(user) = game.active_users.iter().find(|user| user.id == user_id) {
+                        game.action.push(UnoAction::UserTimedOut(user.name.clone()));
+                    }
+                    // The pickup that follows is its own event
+                    continue;
+                }
+            }
+
+            frames.push(game.replay_frame());
+        }
+
+        Ok(frames)
+    }
+
+    fn replay_frame(&mut self) -> UnoReplayFrame {
+        UnoReplayFrame {
+            hands: self
+                .active_users
+                .iter()
+                .map(|user| (user.id, user.cards.clone()))
+                .collect(),
+            state: self.client_state(),
+        }
+    }
+
+    /// Only started games are worth keeping.
+    /// Anyone can watch a replay, so games in private or hidden rooms aren't kept at all.
+    fn keeps_replay(&self) -> bool {
+        self.start_state != GameStartState::Setup && self.visibility == RoomVisibility::Public
+    }
+
+    async fn save_replay(&self) {
+        if !self.keeps_replay() {
+            return;
+        }
+
+        let players = self
+            .replay
+            .seats
+            .iter()
+            .map(|seat| seat.name.clone())
+            .collect();
+
+        let _ = replays::save(&self.lobby_name, self.game_type, players, &self.replay)
+            .await
+            .inspect_err(|err| {
+                println!("Failed to save replay for uno game {} : {err:?}", self.id)
+            });
     }
 
     async fn start_server(
@@ -128,20 +450,70 @@ impl ServerUno {
     ) {
         self.update_user_state();
 
-        while let Some(msg) = receiver_channel.recv().await {
+        loop {
+            if let Some(results) = self.take_results() {
+                let _x = service_sender.send(ServerIntraMessage::GameResults(results));
+            }
+
+            // The turn timer runs alongside incoming messages, a player walking away
+            //  shouldn't hold up the rest of the table
+            let msg = tokio::select! {
+                msg = receiver_channel.recv() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    msg
+                }
+                _ = Self::wait_for_timer(self.turn_timer) => {
+                    if let Some(user_id) = self.turn_timed_out() {
+                        self.kick_user(user_id, "Timed out too many times");
+                        self.migrate_host();
+
+                        let _x =
+                            service_sender.send(ServerIntraMessage::UserLeftGame(user_id, self.id));
+                        let _x = service_sender.send(self.service_update_state());
+                    }
+
+                    if self.close_if_deck_broken(&service_sender) {
+                        break;
+                    }
+                    self.update_user_state();
+
+                    if self.user_senders.is_empty() {
+                        break;
+                    }
+                    continue;
+                }
+                _ = Self::wait_for_timer(self.bot_move) => {
+                    if let Some((bot_id, _)) = self.bot_move.take() {
+                        self.bot_turn(bot_id);
+                    }
+
+                    if self.close_if_deck_broken(&service_sender) {
+                        break;
+                    }
+                    self.update_user_state();
+                    continue;
+                }
+            };
+
             let cmd = match msg.command {
                 ServerGameCommand::UserJoin(user) => {
-                    if self.active_users.len() >= 4 || self.start_state != GameStartState::Setup {
+                    if let Some(reason) = self.join_refusal(&user) {
                         println!("Not allowed in");
+                        let _x = user
+                            .sender
+                            .send(ServerMessage::JoinFailed(reason.to_string()));
                         continue;
                     }
 
-                    let (user, user_sender) =
-                        UnoUser::new_joiner(&mut self.deck, msg.user_id, user);
+                    self.accounts.insert(msg.user_id, user.account);
+                    let (user, user_sender) = UnoUser::new_joiner(msg.user_id, user);
 
                     let _x = user_sender.send(ServerMessage::JoinedGame(
                         self.lobby_name.clone(),
-                        GameType::Uno,
+                        self.game_type,
+                        GameUserState::Active,
                     ));
 
                     self.user_senders.insert(user.id, user_sender);
@@ -157,35 +529,48 @@ impl ServerUno {
 
                     continue;
                 }
+                ServerGameCommand::UserSpectate(user) => {
+                    if !self.add_spectator(msg.user_id, user) {
+                        continue;
+                    }
+
+                    let _x = service_sender
+                        .send(ServerIntraMessage::UserJoinedGame(msg.user_id, self.id));
+                    let _x = service_sender.send(self.service_update_state());
+
+                    self.update_user_state();
+                    continue;
+                }
                 ServerGameCommand::Cmd(cmd) => cmd,
             };
 
+            // Spectators can only watch, and leave
+            if self.spectator_senders.contains_key(&msg.user_id) {
+                if let ClientGameCommand::Leave = cmd {
+                    self.spectator_senders.remove(&msg.user_id);
+
+                    let _x =
+                        service_sender.send(ServerIntraMessage::UserLeftGame(msg.user_id, self.id));
+                    let _x = service_sender.send(self.service_update_state());
+                }
+                continue;
+            }
+
             match cmd {
                 ClientGameCommand::Start => {
                     if msg.user_id != self.host_user || self.start_state != GameStartState::Setup {
                         println!("Pointless start message from {}", msg.user_id);
                         continue;
                     }
-                    if self.active_users.len() < 2 {
-                        println!("Tried to start a game with less than 2 people");
+                    if let Err(err) = self.can_start() {
+                        println!("Tried to start a game that can't be started {err:?}");
                         continue;
                     }
-                    self.start_state = GameStartState::Active;
+                    self.start_game();
                     let _x = service_sender.send(self.service_update_state());
                     self.update_user_state();
                 }
                 ClientGameCommand::Raw(raw_data) => {
-                    let Some(user_idx) = self
-                        .active_users
-                        .iter()
-                        .position(|user| user.id == msg.user_id)
-                    else {
-                        println!("Received message for user not in game {}", msg.user_id);
-                        continue;
-                    };
-
-                    let user_idx = user_idx as u8;
-
                     let Ok((action, _)) =
                         bincode::decode_from_slice::<UnoClientAction, Configuration>(
                             &raw_data,
@@ -201,88 +586,57 @@ impl ServerUno {
                         continue;
                     };
 
-                    if self.start_state != GameStartState::Active {
-                        println!("Received Game message when not active");
+                    let seats_changed = matches!(
+                        action,
+                        UnoClientAction::AddBot(_)
+                            | UnoClientAction::RemoveBot(_)
+                            | UnoClientAction::SetLocked(_)
+                    );
+                    let removed_user = match action {
+                        UnoClientAction::KickUser(target) | UnoClientAction::BanUser(target) => {
+                            Some(target)
+                        }
+                        _ => None,
+                    };
+
+                    let played = matches!(
+                        action,
+                        UnoClientAction::PickupCard | UnoClientAction::PlayCard(_)
+                    );
+
+                    if let Err(err) = self.handle_action(msg.user_id, action) {
+                        println!("Rejected uno action from user {} : {err:?}", msg.user_id);
                         continue;
                     }
 
-                    match action {
-                        UnoClientAction::PickupCard => {
-                            if self.user_turn != user_idx {
-                                println!(
-                                    "Received message from user when not turn {} : {action:?}",
-                                    msg.user_id
-                                );
-                                continue;
-                            }
-
-                            let card = self.deck.pickup();
-                            let user = self
-                                .active_users
-                                .get_mut(user_idx as usize)
-                                .expect("User should be valid here from position get");
-
-                            self.action
-                                .push(UnoAction::UserPickup(user.name.clone(), 1));
-
-                            user.cards.push(card);
-                            self.push_turn();
-
-                            self.update_user_state();
-                        }
-                        UnoClientAction::PlayCard(uno_card) => {
-                            let cards_left = match self.submit_card(msg.user_id, uno_card) {
-                                Ok(cards_left) => cards_left,
-                                Err(err) => {
-                                    println!(
-                                        "Not allowed to submit such card {} -> {uno_card:?} : {err:?}",
-                                        msg.user_id
-                                    );
-                                    continue;
-                                }
-                            };
-                            self.commit_card(uno_card);
-
-                            if cards_left == 0 {
-                                self.user_finished(msg.user_id);
-                            }
-                            self.check_user_bust();
-
-                            self.update_user_state();
-                        }
+                    // Playing resets the timer, even if it's somehow still their turn
+                    if played {
+                        self.turn_timeouts.remove(&msg.user_id);
+                        self.turn_timer = None;
                     }
-                }
-                ClientGameCommand::Leave => {
-                    if let Some(user_idx) = self
-                        .active_users
-                        .iter()
-                        .position(|user| user.id == msg.user_id)
-                    {
-                        let user = self.active_users.remove(user_idx);
 
-                        self.action.push(UnoAction::UserLeft(user.name.clone()));
+                    // The kicked user was already told, the main server still needs to know
+                    if let Some(target) = removed_user {
+                        let _x =
+                            service_sender.send(ServerIntraMessage::UserLeftGame(target, self.id));
+                    }
 
-                        if self.start_state != GameStartState::Setup {
-                            self.bust_users.push((user.id, user.name));
-                        }
+                    if seats_changed || removed_user.is_some() {
+                        let _x = service_sender.send(self.service_update_state());
+                    }
 
-                        self.turn_from_leaver(user_idx);
-                    } else if let Some((_, user)) = self
-                        .finished_users
-                        .iter()
-                        .find(|(u_id, _)| msg.user_id.eq(u_id))
-                    {
-                        self.action.push(UnoAction::UserLeft(user.clone()));
-                    } else if let Some((_, user)) = self
-                        .bust_users
-                        .iter()
-                        .find(|(u_id, _)| msg.user_id.eq(u_id))
-                    {
-                        self.action.push(UnoAction::UserLeft(user.clone()));
-                    };
+                    if self.close_if_deck_broken(&service_sender) {
+                        break;
+                    }
+                    self.update_user_state();
+                }
+                ClientGameCommand::Leave => {
+                    if !self.bot_takes_over(msg.user_id) {
+                        self.user_left(msg.user_id);
+                    }
 
                     let _ = self.user_senders.remove(&msg.user_id);
-                    self.check_over();
+                    self.migrate_host();
 
                     let _x =
                         service_sender.send(ServerIntraMessage::UserLeftGame(msg.user_id, self.id));
@@ -298,48 +652,725 @@ impl ServerUno {
             }
         }
 
+        // Nobody is left to watch, spectators are sent back to the lobby
+        for (user_id, sender) in self.spectator_senders.drain() {
+            let _ = sender.send(ServerMessage::RemovedFromGame(
+                "Everyone has left the game".to_string(),
+            ));
+            let _ = service_sender.send(ServerIntraMessage::UserLeftGame(user_id, self.id));
+        }
+
+        if let Some(results) = self.take_results() {
+            let _x = service_sender.send(ServerIntraMessage::GameResults(results));
+        }
+
+        self.save_replay().await;
+
         let _ = service_sender.send(ServerIntraMessage::GameFinished(self.id));
     }
 
+    /// Once the game is over, how everyone got on is handed back once for the stats.
+    /// Bots aren't counted, a seat a bot took over counts as a loss for whoever left.
+    fn take_results(&mut self) -> Option<GameResults> {
+        if self.start_state != GameStartState::Ending || self.results_reported {
+            return None;
+        }
+        self.results_reported = true;
+
+        let players = self
+            .replay
+            .seats
+            .iter()
+            .filter(|seat| seat.bot.is_none())
+            .filter_map(|seat| {
+                let finish_idx = self
+                    .finished_users
+                    .iter()
+                    .position(|(id, _)| *id == seat.id);
+                let bust_idx = self.bust_users.iter().position(|(id, _)| *id == seat.id);
+
+                // Teams win or lose together, there's no order within them
+                let (placement, won, rank) = match self.team_of(seat.id) {
+                    _ if self.bots.contains_key(&seat.id) => (None, false, u32::MAX),
+                    Some(team) => {
+                        let won = self.winning_team == Some(team);
+                        (won.then_some(1), won, if won { 1 } else { 2 })
+                    }
+                    None => {
+                        let placement = finish_idx.map(|idx| idx as u32 + 1);
+
+                        // Whoever went bust first did the worst
+                        let rank = placement.unwrap_or_else(|| {
+                            let busts_after = bust_idx
+                                .map(|idx| self.bust_users.len() - idx)
+                                .unwrap_or(self.bust_users.len() + 1);
+                            (self.finished_users.len() + busts_after) as u32
+                        });
+
+                        (placement, placement == Some(1), rank)
+                    }
+                };
+
+                let result = PlayerResult {
+                    user_id: seat.id,
+                    placement,
+                    rank,
+                    won,
+                    busted: self.busted.contains(&seat.id),
+                    cards_played: self.cards_played.get(&seat.id).copied().unwrap_or(0),
+                };
+
+                Some((*self.accounts.get(&seat.id)?, result))
+            })
+            .collect();
+
+        Some(GameResults {
+            game_type: self.game_type,
+            game_secs: self
+                .started_at
+                .map(|started| started.elapsed().as_secs())
+                .unwrap_or(0),
+            players,
+        })
+    }
+
+    async fn wait_for_timer(turn_timer: Option<(u32, Instant)>) {
+        match turn_timer {
+            Some((_, deadline)) => sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Restarts the timer whenever the turn moves on to someone new
+    fn sync_turn_timer(&mut self) {
+        let turn_user = self
+            .active_users
+            .get(self.user_turn as usize)
+            .filter(|_| self.start_state == GameStartState::Active)
+            .map(|user| user.id);
+
+        // Bots don't need a timer, they'll move on their own
+        if let Some(bot_id) = turn_user.filter(|id| self.bots.contains_key(id)) {
+            self.turn_timer = None;
+            if self.bot_move.is_none_or(|(move_id, _)| move_id != bot_id) {
+                self.bot_move = Some((bot_id, Instant::now() + BOT_THINK_TIME));
+            }
+            return;
+        }
+        self.bot_move = None;
+
+        let (Some(user_id), Some(secs)) = (turn_user, self.settings.turn_timer_secs) else {
+            self.turn_timer = None;
+            return;
+        };
+
+        if self
+            .turn_timer
+            .is_none_or(|(timer_user, _)| timer_user != user_id)
+        {
+            self.turn_timer = Some((user_id, Instant::now() + Duration::from_secs(secs as u64)));
+        }
+    }
+
+    /// The player who ran out of time draws a card and play moves on.
+    /// Gives back the user if they should now be kicked.
+    fn turn_timed_out(&mut self) -> Option<u32> {
+        let (user_id, _) = self.turn_timer.take()?;
+
+        let name = self
+            .active_users
+            .iter()
+            .find(|user| user.id == user_id)?
+            .name
+            .clone();
+
+        self.action.push(UnoAction::UserTimedOut(name));
+        self.replay.events.push(UnoReplayEvent::TimedOut(user_id));
+
+        if let Err(err) = self.handle_action(user_id, UnoClientAction::PickupCard) {
+            println!("Failed to pickup for timed out user {user_id} : {err:?}");
+        }
+
+        let timeouts = self.turn_timeouts.entry(user_id).or_insert(0);
+        *timeouts += 1;
+
+        let still_playing = self.active_users.iter().any(|user| user.id == user_id);
+
+        self.settings
+            .kick_after_timeouts
+            .is_some_and(|limit| still_playing && *timeouts >= limit)
+            .then_some(user_id)
+    }
+
+    /// The user is told why they were removed, they then leave as normal
+    ///  which tidies up their place in the main server
+    fn kick_user(&mut self, user_id: u32, reason: &str) {
+        let Some(user) = self.active_users.iter().find(|user| user.id == user_id) else {
+            return;
+        };
+
+        self.action.push(UnoAction::UserKicked(user.name.clone()));
+        self.user_left(user_id);
+
+        if let Some(sender) = self.user_senders.remove(&user_id) {
+            let _ = sender.send(ServerMessage::RemovedFromGame(reason.to_string()));
+        }
+    }
+
+    /// Why someone can't take a seat, if there's a reason
+    fn join_refusal(&self, user: &PlayerState) -> Option<&'static str> {
+        if self.banned.contains(&user.account) {
+            Some("You've been banned from this room")
+        } else if self.locked {
+            Some("The host has locked the room")
+        } else if self.active_users.len() >= UNO_MAX_PLAYERS
+            || self.start_state != GameStartState::Setup
+        {
+            Some("The game is full or has already started")
+        } else {
+            None
+        }
+    }
+
+    /// The host can only kick people who are still waiting for the game to start.
+    /// Banning also stops them from coming back in.
+    /// Watchers only ever get the table, never a hand
+    fn add_spectator(&mut self, user_id: u32, user: PlayerState) -> bool {
+        if self.banned.contains(&user.account) {
+            let _x = user.sender.send(ServerMessage::JoinFailed(
+                "You've been banned from this room".to_string(),
+            ));
+            return false;
+        }
+
+        let _x = user.sender.send(ServerMessage::JoinedGame(
+            self.lobby_name.clone(),
+            self.game_type,
+            GameUserState::Spectator,
+        ));
+
+        self.spectator_senders.insert(user_id, user.sender);
+        true
+    }
+
+    fn remove_user(&mut self, user_id: u32, target: u32, ban: bool) -> anyhow::Result<()> {
+        if user_id != self.host_user {
+            return Err(anyhow!("Only the host can kick players"));
+        }
+
+        if self.start_state != GameStartState::Setup {
+            return Err(anyhow!("Players can only be kicked before the game starts"));
+        }
+
+        if target == self.host_user || self.bots.contains_key(&target) {
+            return Err(anyhow!("User {target} can't be kicked"));
+        }
+
+        let Some(user) = self.active_users.iter().find(|user| user.id == target) else {
+            return Err(anyhow!("User {target} is not in the game"));
+        };
+        let name = user.name.clone();
+
+        if ban {
+            let Some(account) = self.accounts.get(&target).copied() else {
+                return Err(anyhow!("User {target} has no account to ban"));
+            };
+
+            self.kick_user(target, "You've been banned from the room");
+            self.action.push(UnoAction::UserBanned(name));
+            self.banned.push(account);
+        } else {
+            self.kick_user(target, "The host kicked you from the room");
+        }
+
+        Ok(())
+    }
+
+    fn set_locked(&mut self, user_id: u32, locked: bool) -> anyhow::Result<()> {
+        if user_id != self.host_user {
+            return Err(anyhow!("Only the host can lock the room"));
+        }
+
+        if self.start_state != GameStartState::Setup {
+            return Err(anyhow!("Nobody can join once the game has started anyway"));
+        }
+
+        if self.locked != locked {
+            self.locked = locked;
+            self.action.push(UnoAction::RoomLocked(locked));
+        }
+
+        Ok(())
+    }
+
+    /// Only someone actually at the table can be made host, bots can't host
+    fn transfer_host(&mut self, user_id: u32, target: u32) -> anyhow::Result<()> {
+        if user_id != self.host_user {
+            return Err(anyhow!("Only the host can hand over the room"));
+        }
+
+        if self.start_state != GameStartState::Setup {
+            return Err(anyhow!("There's nothing left to host once the game starts"));
+        }
+
+        if !self.user_senders.contains_key(&target) {
+            return Err(anyhow!("User {target} is not a player in the game"));
+        }
+
+        self.set_host(target);
+
+        Ok(())
+    }
+
+    /// When the host goes, the room is handed to the next person still in it,
+    ///  going round the table first so it's fairly predictable who gets it
+    fn migrate_host(&mut self) {
+        if self.user_senders.contains_key(&self.host_user) {
+            return;
+        }
+
+        let next_host = self
+            .active_users
+            .iter()
+            .map(|user| user.id)
+            .find(|id| self.user_senders.contains_key(id))
+            .or_else(|| self.user_senders.keys().min().copied());
+
+        if let Some(next_host) = next_host {
+            self.set_host(next_host);
+        }
+    }
+
+    fn set_host(&mut self, user_id: u32) {
+        self.host_user = user_id;
+
+        if let Some(name) = self.name_of(user_id) {
+            self.action.push(UnoAction::HostChanged(name));
+        }
+    }
+
+    /// Whether they're still playing, finished or bust
+    fn name_of(&self, user_id: u32) -> Option<String> {
+        self.active_users
+            .iter()
+            .map(|user| (user.id, &user.name))
+            .chain(self.finished_users.iter().map(|(id, name)| (*id, name)))
+            .chain(self.bust_users.iter().map(|(id, name)| (*id, name)))
+            .find(|(id, _)| *id == user_id)
+            .map(|(_, name)| name.clone())
+    }
+
+    /// Reactions go out with the rest of the events, they aren't part of the game itself
+    fn react(&mut self, user_id: u32, reaction: Reaction) -> anyhow::Result<()> {
+        if !self.user_senders.contains_key(&user_id) {
+            return Err(anyhow!("User {user_id} is not at the table"));
+        }
+
+        let now = Instant::now();
+        if self
+            .last_reactions
+            .get(&user_id)
+            .is_some_and(|last| now.saturating_duration_since(*last) < REACTION_COOLDOWN)
+        {
+            return Err(anyhow!("User {user_id} reacted too recently"));
+        }
+
+        let Some(name) = self.name_of(user_id) else {
+            return Err(anyhow!("User {user_id} has no name"));
+        };
+
+        self.last_reactions.insert(user_id, now);
+        self.action.push(UnoAction::Reacted(name, reaction));
+
+        Ok(())
+    }
+
+    /// The bot is asked for its move, it then goes through the same checks as anyone else.
+    /// If it somehow gets it wrong, it picks up instead so the game can't get stuck.
+    fn bot_turn(&mut self, bot_id: u32) {
+        let Some(user) = self.active_users.get(self.user_turn as usize) else {
+            return;
+        };
+
+        if user.id != bot_id {
+            return;
+        }
+
+        let next_user = &self.active_users[self.next_turn()];
+        let next_is_partner = next_user.id != bot_id
+            && self.team_of(bot_id).is_some()
+            && self.team_of(bot_id) == self.team_of(next_user.id);
+
+        let view = UnoBotView {
+            hand: &user.cards,
+            last_card: self.last_card,
+            next_card_count: next_user.cards.len(),
+            next_is_partner,
+            rules: &self.rules,
+        };
+
+        let Some(bot) = self.bots.get_mut(&bot_id) else {
+            return;
+        };
+
+        let action = bot.choose(&view);
+
+        if let Err(err) = self.handle_action(bot_id, action) {
+            println!("Bot {bot_id} tried a bad move : {err:?}");
+
+            let _ = self
+                .handle_action(bot_id, UnoClientAction::PickupCard)
+                .inspect_err(|err| println!("Bot {bot_id} could not pick up : {err:?}"));
+        }
+    }
+
+    fn add_bot(&mut self, user_id: u32, difficulty: UnoBotDifficulty) -> anyhow::Result<()> {
+        if user_id != self.host_user {
+            return Err(anyhow!("Only the host can add bots"));
+        }
+
+        if self.start_state != GameStartState::Setup {
+            return Err(anyhow!("Bots can only be added before the game starts"));
+        }
+
+        if self.active_users.len() >= UNO_MAX_PLAYERS {
+            return Err(anyhow!("No seats left for a bot"));
+        }
+
+        self.push_bot(difficulty);
+
+        Ok(())
+    }
+
+    fn push_bot(&mut self, difficulty: UnoBotDifficulty) {
+        // Real user ids count up from 1, bots count down from the top so they never meet
+        let bot_id = (0..)
+            .map(|n| u32::MAX - n)
+            .find(|id| !self.bots.contains_key(id))
+            .expect("There are far fewer bots than ids");
+
+        let name = format!("{difficulty:?} Bot {}", self.bots.len() + 1);
+
+        self.action.push(UnoAction::UserJoined(name.clone()));
+        self.active_users.push(UnoUser {
+            id: bot_id,
+            name,
+            cards: vec![],
+            rating: None,
+        });
+        self.bots
+            .insert(bot_id, UnoBot::new(difficulty, self.seed ^ bot_id as u64));
+    }
+
+    fn remove_bot(&mut self, user_id: u32, bot_id: u32) -> anyhow::Result<()> {
+        if user_id != self.host_user {
+            return Err(anyhow!("Only the host can remove bots"));
+        }
+
+        if self.start_state != GameStartState::Setup {
+            return Err(anyhow!("Bots can only be removed before the game starts"));
+        }
+
+        if self.bots.remove(&bot_id).is_none() {
+            return Err(anyhow!("User {bot_id} is not a bot"));
+        }
+
+        self.user_left(bot_id);
+
+        Ok(())
+    }
+
+    /// When the room allows it, a player leaving mid game hands their seat and cards to a bot.
+    /// Gives back whether a bot took over.
+    fn bot_takes_over(&mut self, user_id: u32) -> bool {
+        let Some(difficulty) = self.settings.replace_leavers else {
+            return false;
+        };
+
+        if self.start_state != GameStartState::Active {
+            return false;
+        }
+
+        let Some(user) = self.active_users.iter_mut().find(|user| user.id == user_id) else {
+            return false;
+        };
+
+        self.action.push(UnoAction::UserLeft(user.name.clone()));
+        user.name = format!("{} (Bot)", user.name);
+        user.rating = None;
+        self.action.push(UnoAction::UserJoined(user.name.clone()));
+
+        self.bots
+            .insert(user_id, UnoBot::new(difficulty, self.seed ^ user_id as u64));
+        self.turn_timeouts.remove(&user_id);
+        self.replay
+            .events
+            .push(UnoReplayEvent::BotTookOver(user_id));
+
+        // The bot picks up from the current turn if it was theirs
+        self.turn_timer = None;
+
+        true
+    }
+
+    fn can_start(&self) -> anyhow::Result<()> {
+        let user_count = self.active_users.len();
+
+        if user_count < 2 {
+            return Err(anyhow!("Need at least 2 people to play"));
+        }
+
+        if self.settings.team_mode && (user_count < 4 || !user_count.is_multiple_of(2)) {
+            return Err(anyhow!(
+                "Team games need an even number of at least 4 people"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// A fresh deck is made from the settings and everyone is dealt their hand.
+    /// For team games, seating alternates between the 2 teams.
+    fn start_game(&mut self) {
+        self.deck = UnoDeck::new(&self.rules, self.settings.deck_count, &mut self.rng);
+        self.last_card = self
+            .deck
+            .pickup(&mut self.rng)
+            .expect("A new deck should have cards");
+
+        for user in self.active_users.iter_mut() {
+            user.cards = self
+                .deck
+                .pickup_many(self.rules.hand_size as usize, &mut self.rng);
+        }
+
+        self.user_teams = if self.settings.team_mode {
+            self.active_users
+                .iter()
+                .enumerate()
+                .map(|(seat, user)| (user.id, (seat % 2) as u8))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        self.replay = UnoReplay {
+            seed: self.seed,
+            host_user: self.host_user,
+            settings: self.settings,
+            rules: self.rules.clone(),
+            seats: self
+                .active_users
+                .iter()
+                .map(|user| UnoReplaySeat {
+                    id: user.id,
+                    name: user.name.clone(),
+                    bot: self.bots.get(&user.id).map(|bot| bot.difficulty),
+                })
+                .collect(),
+            events: vec![],
+        };
+
+        self.action.push(UnoAction::InitialCard(self.last_card));
+        self.start_state = GameStartState::Active;
+        self.started_at = Some(Instant::now());
+    }
+
+    fn update_settings(&mut self, user_id: u32, settings: UnoSettings) -> anyhow::Result<()> {
+        if user_id != self.host_user {
+            return Err(anyhow!("Only the host can change settings"));
+        }
+
+        if self.start_state != GameStartState::Setup {
+            return Err(anyhow!(
+                "Settings can only be changed before the game starts"
+            ));
+        }
+
+        if !settings.validate() {
+            return Err(anyhow!("Invalid settings {settings:?}"));
+        }
+
+        self.settings = settings;
+
+        Ok(())
+    }
+
+    /// Every in game action goes through here, regardless of where it came from.
+    /// Nothing is changed when an action is rejected.
+    fn handle_action(&mut self, user_id: u32, action: UnoClientAction) -> anyhow::Result<()> {
+        match action {
+            UnoClientAction::UpdateSettings(settings) => {
+                return self.update_settings(user_id, settings);
+            }
+            UnoClientAction::AddBot(difficulty) => return self.add_bot(user_id, difficulty),
+            UnoClientAction::RemoveBot(bot_id) => return self.remove_bot(user_id, bot_id),
+            UnoClientAction::KickUser(target) => return self.remove_user(user_id, target, false),
+            UnoClientAction::BanUser(target) => return self.remove_user(user_id, target, true),
+            UnoClientAction::SetLocked(locked) => return self.set_locked(user_id, locked),
+            UnoClientAction::TransferHost(target) => return self.transfer_host(user_id, target),
+            UnoClientAction::React(reaction) => return self.react(user_id, reaction),
+            UnoClientAction::PickupCard | UnoClientAction::PlayCard(_) => {}
+        }
+
+        if self.start_state != GameStartState::Active {
+            return Err(anyhow!("Received Game message when not active"));
+        }
+
+        let Some(user_idx) = self.active_users.iter().position(|user| user.id == user_id) else {
+            return Err(anyhow!("User not in game"));
+        };
+
+        match action {
+            UnoClientAction::PickupCard => {
+                if self.user_turn as usize != user_idx {
+                    return Err(anyhow!("Not this user's turn"));
+                }
+
+                self.replay
+                    .events
+                    .push(UnoReplayEvent::Action(user_id, action.clone()));
+
+                let cards = self.deck.pickup_many(1, &mut self.rng);
+                let user = &mut self.active_users[user_idx];
+
+                self.action
+                    .push(UnoAction::UserPickup(user.name.clone(), cards.len() as u8));
+
+                user.cards.extend(cards);
+                self.push_turn();
+                self.check_user_bust();
+            }
+            UnoClientAction::PlayCard(uno_card) => {
+                let cards_left = self.submit_card(user_id, uno_card)?;
+
+                self.replay
+                    .events
+                    .push(UnoReplayEvent::Action(user_id, action.clone()));
+
+                self.commit_card(uno_card);
+                *self.cards_played.entry(user_id).or_insert(0) += 1;
+
+                if cards_left == 0 {
+                    self.user_finished(user_id);
+                }
+                self.check_user_bust();
+            }
+            UnoClientAction::UpdateSettings(_)
+            | UnoClientAction::AddBot(_)
+            | UnoClientAction::RemoveBot(_)
+            | UnoClientAction::KickUser(_)
+            | UnoClientAction::BanUser(_)
+            | UnoClientAction::SetLocked(_)
+            | UnoClientAction::TransferHost(_)
+            | UnoClientAction::React(_) => {
+                unreachable!("Room changes are handled before the game is checked")
+            }
+        }
+
+        Ok(())
+    }
+
+    fn user_left(&mut self, user_id: u32) {
+        if self.start_state == GameStartState::Active {
+            self.replay.events.push(UnoReplayEvent::Left(user_id));
+        }
+
+        if let Some(user_idx) = self.active_users.iter().position(|user| user.id == user_id) {
+            let user = self.active_users.remove(user_idx);
+
+            self.action.push(UnoAction::UserLeft(user.name.clone()));
+            self.deck.discard_all(&user.cards);
+
+            if self.start_state != GameStartState::Setup {
+                self.bust_users.push((user.id, user.name));
+            }
+
+            self.turn_from_leaver(user_idx);
+        } else if let Some((_, user)) = self
+            .finished_users
+            .iter()
+            .find(|(u_id, _)| user_id.eq(u_id))
+        {
+            self.action.push(UnoAction::UserLeft(user.clone()));
+        } else if let Some((_, user)) = self.bust_users.iter().find(|(u_id, _)| user_id.eq(u_id)) {
+            self.action.push(UnoAction::UserLeft(user.clone()));
+        };
+
+        self.check_over();
+    }
+
+    /// Every card should be in exactly one place, a hand, a pile or on top of the stack.
+    /// This should never fail, if it does the game can't go on, so everyone is sent back.
+    fn close_if_deck_broken(
+        &mut self,
+        service_sender: &UnboundedSender<ServerIntraMessage>,
+    ) -> bool {
+        let Err(err) = self.verify_deck() else {
+            return false;
+        };
+
+        println!(
+            "CRITICAL ERROR, uno game {} deck is broken, closing it {err:?}",
+            self.id
+        );
+
+        for (user_id, sender) in self
+            .user_senders
+            .drain()
+            .chain(self.spectator_senders.drain())
+        {
+            let _ = sender.send(ServerMessage::RemovedFromGame(
+                "Something went wrong with the deck, the game had to be closed".to_string(),
+            ));
+            let _ = service_sender.send(ServerIntraMessage::UserLeftGame(user_id, self.id));
+        }
+
+        true
+    }
+
+    fn verify_deck(&self) -> anyhow::Result<()> {
+        let mut in_play: Vec<UnoCard> = self
+            .active_users
+            .iter()
+            .flat_map(|user| user.cards.iter().copied())
+            .collect();
+        in_play.push(self.last_card);
+
+        self.deck.verify(&in_play)
+    }
+
     fn service_update_state(&self) -> ServerIntraMessage {
         ServerIntraMessage::UpdateGameServer(
             self.id,
             GameServerStateUpdate {
                 name: self.lobby_name.clone(),
                 player_count: self.active_users.len() as u32,
-                game_type: GameType::Uno,
+                spectator_count: self.spectator_senders.len() as u32,
+                players: self.lobby_players(),
+                game_type: self.game_type,
                 start_state: self.start_state,
+                locked: self.locked,
             },
         )
     }
 
+    fn lobby_players(&self) -> Vec<LobbyPlayer> {
+        self.active_users
+            .iter()
+            .map(|user| LobbyPlayer {
+                name: user.name.clone(),
+                rating: user.rating,
+            })
+            .collect()
+    }
+
     fn update_user_state(&mut self) {
-        let state = UnoClientGameState {
-            game_state: self.start_state,
-            action: self.action.drain(..).collect(),
-            active_users: self
-                .active_users
-                .iter()
-                .map(|user| UnoActiveUser {
-                    id: user.id,
-                    name: user.name.clone(),
-                    card_count: user.cards.len() as u32,
-                })
-                .collect(),
-            host_user: self.host_user,
-            user_turn: self.user_turn,
-            is_ord: self.is_ord,
-            last_card: match self.start_state {
-                GameStartState::Setup | GameStartState::Ending => {
-                    UnoCard::encode(false, UnoCardColour::Red, 0)
-                }
-                GameStartState::Active => self.last_card,
-            },
-            finished_users: self.finished_users.clone(),
-            bust_users: self.bust_users.clone(),
-        };
+        self.sync_turn_timer();
+
+        let state = self.client_state();
 
-        for (user_id, sender) in self.user_senders.iter() {
+        for (user_id, sender) in self.user_senders.iter().chain(&self.spectator_senders) {
             let user_cards = if self.start_state == GameStartState::Active {
                 self.active_users
                     .iter()
@@ -350,7 +1381,13 @@ impl ServerUno {
                 vec![]
             };
 
-            let msg = ServerUnoCommand::GameState(user_cards, state.clone());
+            let mut state = state.clone();
+            state.partner_hands = self.partner_hands(*user_id);
+            if self.user_senders.contains_key(user_id) {
+                state.invite_code = self.invite_code.clone();
+            }
+
+            let msg = ServerUnoCommand::GameState(user_cards, state);
 
             let Ok(encoded) = bincode::encode_to_vec(&msg, bincode::config::standard())
                 .inspect_err(|err| println!("Failed to encode state for user {user_id} : {err:?}"))
@@ -366,6 +1403,53 @@ impl ServerUno {
         }
     }
 
+    /// What everyone at the table can see, taking the actions since the last update
+    fn client_state(&mut self) -> UnoClientGameState {
+        UnoClientGameState {
+            game_state: self.start_state,
+            action: self.action.drain(..).collect(),
+            active_users: self
+                .active_users
+                .iter()
+                .map(|user| UnoActiveUser {
+                    id: user.id,
+                    name: user.name.clone(),
+                    card_count: user.cards.len() as u32,
+                    team: self.team_of(user.id),
+                    bot: self.bots.get(&user.id).map(|bot| bot.difficulty),
+                    rating: user.rating,
+                })
+                .collect(),
+            host_user: self.host_user,
+            user_turn: self.user_turn,
+            is_ord: self.is_ord,
+            last_card: match self.start_state {
+                GameStartState::Setup | GameStartState::Ending => {
+                    UnoCard::encode(false, UnoCardColour::Red, 0)
+                }
+                GameStartState::Active => self.last_card,
+            },
+            finished_users: self.finished_users.clone(),
+            bust_users: self.bust_users.clone(),
+            // Handing out the seed mid game would let clients predict the draw pile
+            seed: match self.start_state {
+                GameStartState::Ending => Some(self.seed),
+                GameStartState::Setup | GameStartState::Active => None,
+            },
+            settings: self.settings,
+            team_standings: self.team_standings(),
+            partner_hands: vec![],
+            turn_time_left_ms: self.turn_timer.map(|(_, deadline)| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_millis() as u32
+            }),
+            invite_code: None,
+            locked: self.locked,
+            rules: self.rules.clone(),
+        }
+    }
+
     /// Steps when a user plays a card:
     ///
     /// 1. Check is player's turn
@@ -377,7 +1461,7 @@ impl ServerUno {
             return Err(anyhow!("Invalid Card"));
         }
 
-        let (is_power, mut colour, value) = card.decode();
+        let (is_power, _, value) = card.decode();
 
         // When we receive a power card, black colour changers are stored as red
         //  but the colour submitted determines the colour to change to
@@ -385,9 +1469,7 @@ impl ServerUno {
             match UnoCardPower::from(value) {
                 UnoCardPower::PlusTwo | UnoCardPower::Skip | UnoCardPower::Reverse => {}
                 UnoCardPower::ClrChange | UnoCardPower::PlusFour => {
-                    println!("CHANGE COLOUR FOR BLACK CARD");
                     card = UnoCard::encode(is_power, UnoCardColour::Red, value);
-                    colour = UnoCardColour::Red;
                 }
             }
         }
@@ -398,10 +1480,7 @@ impl ServerUno {
             return Err(anyhow!("Not this user's turn"));
         }
 
-        let (_, curr_colour, curr_value) = self.last_card.decode();
-
-        // Black cards can be played on anything, just need to check regular cards
-        if !card.is_black() && curr_colour != colour && curr_value != value {
+        if !self.rules.allows(card, self.last_card) {
             return Err(anyhow!("Card not allowed"));
         }
 
@@ -419,60 +1498,52 @@ impl ServerUno {
             return Err(anyhow!("User does not have card"));
         }
 
-        self.deck.discard(card);
-
         self.action
             .push(UnoAction::UserPlaceCard(curr_user.name.clone(), card));
         Ok(curr_user.cards.len())
     }
 
+    /// The card played goes on top of the stack, the card it covers
+    ///  can now be discarded.
+    ///
+    /// Whatever the rules say the card does happens before play moves on,
+    ///  so a draw always lands on whoever is next at that point.
     fn commit_card(&mut self, card: UnoCard) {
-        self.last_card = card;
+        let covered = std::mem::replace(&mut self.last_card, card);
+        self.deck.discard(covered);
 
-        if card.is_power() {
-            match UnoCardPower::from(card.get_value()) {
-                UnoCardPower::PlusTwo => {
-                    self.push_turn();
-                    let curr_user = &mut self.active_users[self.user_turn as usize];
+        for bot in self.bots.values_mut() {
+            bot.observe(card);
+        }
 
-                    curr_user.cards.push(self.deck.pickup());
-                    curr_user.cards.push(self.deck.pickup());
+        let mut skip = false;
 
-                    self.action
-                        .push(UnoAction::UserPickup(curr_user.name.clone(), 2));
+        for effect in self.rules.effects_of(card).to_vec() {
+            match effect {
+                UnoEffect::Draw(count) => {
+                    let cards = self.deck.pickup_many(count as usize, &mut self.rng);
+                    let next_idx = self.next_turn();
+                    let next_user = &mut self.active_users[next_idx];
 
-                    self.push_turn();
-                }
-                UnoCardPower::Skip => {
-                    self.push_turn();
-                    self.push_turn();
+                    self.action.push(UnoAction::UserPickup(
+                        next_user.name.clone(),
+                        cards.len() as u8,
+                    ));
+                    next_user.cards.extend(cards);
                 }
-                UnoCardPower::Reverse => {
+                UnoEffect::Skip => skip = true,
+                UnoEffect::Reverse => {
                     self.is_ord = !self.is_ord;
+                    // With two players, reversing hands the turn straight back
                     if self.active_users.len() == 2 {
-                        self.push_turn();
+                        skip = true;
                     }
-                    self.push_turn();
-                }
-                UnoCardPower::PlusFour => {
-                    self.push_turn();
-                    let curr_user = &mut self.active_users[self.user_turn as usize];
-
-                    curr_user.cards.push(self.deck.pickup());
-                    curr_user.cards.push(self.deck.pickup());
-                    curr_user.cards.push(self.deck.pickup());
-                    curr_user.cards.push(self.deck.pickup());
-
-                    self.action
-                        .push(UnoAction::UserPickup(curr_user.name.clone(), 4));
-
-                    self.push_turn();
-                }
-                UnoCardPower::ClrChange => {
-                    self.push_turn();
                 }
             }
-        } else {
+        }
+
+        self.push_turn();
+        if skip {
             self.push_turn();
         }
     }
@@ -480,16 +1551,22 @@ impl ServerUno {
     /// I may consider changing the type of user_turn
     ///  the amount of conversions is not ideal
     fn push_turn(&mut self) {
+        self.user_turn = self.next_turn() as u8;
+    }
+
+    fn next_turn(&self) -> usize {
+        let curr_idx = self.user_turn as usize;
+
         if self.is_ord {
-            if (self.user_turn as usize) == self.active_users.len() - 1 {
-                self.user_turn = 0;
+            if curr_idx == self.active_users.len() - 1 {
+                0
             } else {
-                self.user_turn += 1
+                curr_idx + 1
             }
-        } else if self.user_turn == 0 {
-            self.user_turn = (self.active_users.len() - 1) as u8;
+        } else if curr_idx == 0 {
+            self.active_users.len() - 1
         } else {
-            self.user_turn -= 1;
+            curr_idx - 1
         }
     }
 
@@ -505,7 +1582,85 @@ impl ServerUno {
         self.finished_users.push((user_id, rm_user.name.clone()));
         self.action.push(UnoAction::UserFinished(rm_user.name));
 
+        let team = self.team_of(user_id);
+        if let Some(team) = team {
+            self.winning_team.get_or_insert(team);
+        }
+
         self.turn_from_leaver(user_idx);
+
+        // The whole team is finished as soon as one of them is
+        let Some(team) = team else {
+            return;
+        };
+
+        while let Some(partner_idx) = self
+            .active_users
+            .iter()
+            .position(|user| self.team_of(user.id) == Some(team))
+        {
+            let partner = self.active_users.remove(partner_idx);
+
+            self.deck.discard_all(&partner.cards);
+            self.finished_users.push((partner.id, partner.name.clone()));
+            self.action.push(UnoAction::UserFinished(partner.name));
+
+            self.turn_from_leaver(partner_idx);
+        }
+    }
+
+    fn partner_hands(&self, user_id: u32) -> Vec<(u32, Vec<UnoCard>)> {
+        if self.start_state != GameStartState::Active
+            || self.settings.partner_view != UnoPartnerView::Hand
+        {
+            return vec![];
+        }
+
+        let Some(team) = self.team_of(user_id) else {
+            return vec![];
+        };
+
+        self.active_users
+            .iter()
+            .filter(|user| user.id != user_id && self.team_of(user.id) == Some(team))
+            .map(|user| (user.id, user.cards.clone()))
+            .collect()
+    }
+
+    fn team_of(&self, user_id: u32) -> Option<u8> {
+        self.user_teams
+            .iter()
+            .find(|(id, _)| *id == user_id)
+            .map(|(_, team)| *team)
+    }
+
+    /// The winning team comes first, everyone else is ordered by the cards they have left
+    fn team_standings(&self) -> Vec<UnoTeamStanding> {
+        if self.user_teams.is_empty() {
+            return vec![];
+        }
+
+        let mut standings: Vec<UnoTeamStanding> = (0..2)
+            .map(|team| UnoTeamStanding {
+                team,
+                user_ids: self
+                    .user_teams
+                    .iter()
+                    .filter(|(_, user_team)| *user_team == team)
+                    .map(|(id, _)| *id)
+                    .collect(),
+                card_count: self
+                    .active_users
+                    .iter()
+                    .filter(|user| self.team_of(user.id) == Some(team))
+                    .map(|user| user.cards.len() as u32)
+                    .sum(),
+                has_won: self.winning_team == Some(team),
+            })
+            .collect();
+
+        standings.sort_by_key(|standing| (!standing.has_won, standing.card_count));
+        standings
     }
 
     /// In reality, on 1 user ever goes bust at one time.
@@ -518,7 +1673,7 @@ impl ServerUno {
             .active_users
             .iter()
             .enumerate()
-            .filter(|(_, user)| user.cards.len() > 20)
+            .filter(|(_, user)| user.cards.len() > self.rules.bust_over as usize)
             .map(|(idx, _)| idx)
             .collect();
 
@@ -530,13 +1685,11 @@ impl ServerUno {
         for &idx in busted_indices.iter().rev() {
             let user = self.active_users.remove(idx);
 
-            println!("USER BUST {}", user.name);
             self.action.push(UnoAction::UserBust(user.name.clone()));
             self.bust_users.push((user.id, user.name.clone()));
+            self.busted.push(user.id);
 
-            for card in user.cards.iter() {
-                self.deck.discard(*card);
-            }
+            self.deck.discard_all(&user.cards);
 
             if idx < curr_idx {
                 removed_before_turn += 1;
@@ -557,7 +1710,7 @@ impl ServerUno {
     fn turn_from_leaver(&mut self, user_idx: usize) {
         let curr_idx = self.user_turn as usize;
 
-        if curr_idx == self.active_users.len() {
+        if curr_idx == self.active_users.len() && curr_idx > 0 {
             self.user_turn = curr_idx as u8 - 1;
         } else if curr_idx > user_idx {
             self.user_turn -= 1;
@@ -566,9 +1719,34 @@ impl ServerUno {
         self.check_over();
     }
 
+    /// Teams play until only one team has anyone left
     fn check_over(&mut self) {
-        if self.active_users.len() <= 1 {
+        // People coming and going before the start doesn't end anything
+        if self.start_state != GameStartState::Active {
+            return;
+        }
+
+        let first_team = self
+            .active_users
+            .first()
+            .and_then(|user| self.team_of(user.id));
+
+        let is_over = if self.user_teams.is_empty() {
+            self.active_users.len() <= 1
+        } else {
+            self.active_users
+                .iter()
+                .all(|user| self.team_of(user.id) == first_team)
+        };
+
+        if is_over {
+            // Everyone else has gone bust or left, the last team standing wins
+            if self.winning_team.is_none() {
+                self.winning_team = first_team;
+            }
+
             for user in self.active_users.drain(..) {
+                self.deck.discard_all(&user.cards);
                 self.finished_users.push((user.id, user.name));
             }
             self.action.push(UnoAction::GameEnded);
@@ -577,233 +1755,783 @@ impl ServerUno {
     }
 }
 
+/// The draw pile is a real shuffled stack of cards, we only ever take from the top.
+/// When it runs dry, the discard pile is shuffled back in to become the new draw pile.
+///
+/// The discard pile is stored as flags, each bit being a slot in the deck's layout,
+///  one slot for every copy of every card in the rules. With multiple decks, each deck
+///  gets its own run of slots so the flags just grow with the deck count.
 pub struct UnoDeck {
-    main_deck: (u64, u64),
-    discard_deck: (u64, u64),
+    deck_count: usize,
+    /// A single deck, laid out in the order of the rules
+    layout: Vec<UnoCard>,
+    /// Where each card sits in `layout`, by the card's byte
+    positions: Vec<Vec<usize>>,
+    draw_pile: Vec<UnoCard>,
+    discard_deck: Vec<u64>,
 }
 
 impl UnoDeck {
-    /// We are using the version of an uno deck with 108 cards ( no blanks )
-    /// To do this, we fill the first u64 completely and assign 44 flags in the 2nd
-    fn new() -> Self {
-        UnoDeck {
-            main_deck: (u64::MAX, (1u64 << 44) - 1),
-            discard_deck: (0, 0),
+    /// Shuffles together as many decks of the rules' cards as the game is set to use
+    fn new(rules: &UnoRules, deck_count: u8, rng: &mut impl Rng) -> Self {
+        let layout: Vec<UnoCard> = rules
+            .deck
+            .iter()
+            .flat_map(|(card, copies)| std::iter::repeat_n(*card, *copies as usize))
+            .collect();
+
+        let mut positions = vec![vec![]; u8::MAX as usize + 1];
+        for (pos, card) in layout.iter().enumerate() {
+            positions[card.0 as usize].push(pos);
         }
+
+        let mut deck = UnoDeck {
+            deck_count: deck_count as usize,
+            layout,
+            positions,
+            draw_pile: vec![],
+            discard_deck: vec![],
+        };
+
+        deck.draw_pile = (0..deck.size())
+            .map(|slot| deck.slot_to_card(slot))
+            .collect();
+        deck.draw_pile.shuffle(rng);
+        deck.discard_deck = vec![0; deck.size().div_ceil(64)];
+
+        deck
     }
 
-    fn is_empty(&self) -> bool {
-        self.main_deck.0 == 0 && self.main_deck.1 == 0
+    fn size(&self) -> usize {
+        self.deck_count * self.layout.len()
     }
 
-    // We generate the pos outside of
-    fn get_card(&mut self, mut pos: u8) -> UnoCard {
-        assert!(
-            !self.is_empty(),
-            "Should be at least one card in deck when trying to pick up"
-        );
+    /// With enough players holding enough cards, both piles can be empty.
+    /// In that case there is just nothing to pick up.
+    fn pickup(&mut self, rng: &mut impl Rng) -> Option<UnoCard> {
+        if self.draw_pile.is_empty() {
+            self.recycle_discard(rng);
+        }
 
-        pos %= DECK_SIZE;
+        self.draw_pile.pop()
+    }
 
-        // This is a terrible implementation
-        // I'm going to switch it with a trailing zeros check instead
-        // of this loop
-        loop {
-            if pos >= DECK_SIZE {
-                pos -= DECK_SIZE;
+    fn pickup_many(&mut self, count: usize, rng: &mut impl Rng) -> Vec<UnoCard> {
+        (0..count).filter_map(|_| self.pickup(rng)).collect()
+    }
+
+    /// Move every discarded card back into the draw pile and shuffle it
+    fn recycle_discard(&mut self, rng: &mut impl Rng) {
+        for slot in 0..self.size() {
+            if self.is_discarded(slot) {
+                self.draw_pile.push(self.slot_to_card(slot));
             }
+        }
 
-            let right_check = pos > 63;
+        self.discard_deck.fill(0);
+        self.draw_pile.shuffle(rng);
+    }
 
-            let check_cursor: u64 = 1 << (if right_check { pos - 64 } else { pos });
+    fn discard(&mut self, card: UnoCard) {
+        let _ = self.try_discard(card).inspect_err(|err| {
+            println!(
+                "Tried to discard but no empty slot {:?} : {err:?}",
+                card.decode()
+            );
+        });
+    }
 
-            let card_exist = if right_check {
-                self.main_deck.1 & check_cursor != 0
-            } else {
-                self.main_deck.0 & check_cursor != 0
-            };
+    fn discard_all(&mut self, cards: &[UnoCard]) {
+        for card in cards.iter() {
+            self.discard(*card);
+        }
+    }
 
-            if !card_exist {
-                pos += 1;
-                continue;
-            }
+    /// A card has a slot for each copy of it in the deck, we take the first free one.
+    /// Having no free slot means the card has been discarded more times than it exists.
+    fn try_discard(&mut self, card: UnoCard) -> anyhow::Result<()> {
+        let Some(slot) = self.card_slots(card).find(|slot| !self.is_discarded(*slot)) else {
+            return Err(anyhow!("Every copy of card already discarded"));
+        };
 
-            // Desire
-            // A   B   Q
-            // 0   0   0
-            // 0   1   0
-            // 1   0   1
-            // 1   1   0
-            //
-            //  A and not B
-            if right_check {
-                self.main_deck.1 &= !check_cursor;
-            } else {
-                self.main_deck.0 &= !check_cursor;
+        self.discard_deck[slot / 64] |= 1u64 << (slot % 64);
+
+        Ok(())
+    }
+
+    fn is_discarded(&self, slot: usize) -> bool {
+        self.discard_deck[slot / 64] & (1u64 << (slot % 64)) != 0
+    }
+
+    /// Checks that the deck is whole.
+    ///
+    /// Every card of the deck must be in exactly one of the draw pile, the discard pile
+    ///  or the cards in play ( player hands and the top of the stack ).
+    pub fn verify(&self, in_play: &[UnoCard]) -> anyhow::Result<()> {
+        let mut slots: Vec<bool> = (0..self.size())
+            .map(|slot| self.is_discarded(slot))
+            .collect();
+
+        for card in self.draw_pile.iter().chain(in_play.iter()) {
+            let Some(slot) = self.card_slots(*card).find(|slot| !slots[*slot]) else {
+                return Err(anyhow!("Card duplicated {:?}", card.decode()));
             };
 
-            return Self::pos_to_card(pos);
+            slots[slot] = true;
+        }
+
+        if let Some(slot) = slots.iter().position(|taken| !taken) {
+            return Err(anyhow!("Card lost {:?}", self.slot_to_card(slot).decode()));
         }
+
+        Ok(())
+    }
+
+    /// Every slot across all decks a card can take.
+    /// Black cards keep the colour they were played as, the layout has them all as red.
+    fn card_slots(&self, card: UnoCard) -> impl Iterator<Item = usize> {
+        let card = if card.is_black() {
+            UnoCard::encode(true, UnoCardColour::Red, card.get_value())
+        } else {
+            card
+        };
+        let deck_size = self.layout.len();
+        let positions = &self.positions[card.0 as usize];
+
+        (0..self.deck_count)
+            .flat_map(move |deck| positions.iter().map(move |pos| deck * deck_size + pos))
+    }
+
+    fn slot_to_card(&self, slot: usize) -> UnoCard {
+        self.layout[slot % self.layout.len()]
+    }
+}
+
+impl UnoUser {
+    fn new_joiner(user_id: u32, user: PlayerState) -> (UnoUser, UnboundedSender<ServerMessage>) {
+        (
+            UnoUser {
+                id: user_id,
+                name: user.name,
+                cards: vec![],
+                rating: Some(user.rating),
+            },
+            user.sender.clone(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::{ProptestConfig, TestCaseError, any, prop_assert, proptest};
+
+    use rpc::uno::UNO_MAX_DECKS;
+
+    use super::*;
+
+    fn test_game(seed: u64, players: u32, deck_count: u8, team_mode: bool) -> ServerUno {
+        let mut game = ServerUno::new(0, "Test".to_string(), 0, seed).unwrap();
+
+        game.active_users = (0..players)
+            .map(|id| UnoUser {
+                id,
+                name: format!("Player {id}"),
+                cards: vec![],
+                rating: None,
+            })
+            .collect();
+        game.settings = UnoSettings {
+            deck_count,
+            team_mode,
+            ..Default::default()
+        };
+
+        game.start_game();
+        game
     }
 
-    fn get_new_hand(&mut self, rng: &mut impl Rng) -> anyhow::Result<Vec<UnoCard>> {
-        let mut hand = vec![];
+    /// The current player tries their cards in a random order and plays the first
+    ///  one the server accepts, picking up when none are allowed.
+    fn play_random_turn(game: &mut ServerUno, rng: &mut impl Rng) {
+        let user = &game.active_users[game.user_turn as usize];
+        let user_id = user.id;
+
+        let mut hand = user.cards.clone();
+        hand.shuffle(rng);
 
-        for _ in 0..10 {
-            let pos = rng.random_range(0..DECK_SIZE);
+        for mut card in hand {
+            if card.is_black() {
+                let colour = UnoCardColour::from(rng.random_range(0..4));
+                card = UnoCard::encode(true, colour, card.get_value());
+            }
 
-            hand.push(self.get_card(pos));
+            if game
+                .handle_action(user_id, UnoClientAction::PlayCard(card))
+                .is_ok()
+            {
+                return;
+            }
         }
 
-        Ok(hand)
+        game.handle_action(user_id, UnoClientAction::PickupCard)
+            .expect("Should always be able to pick up on your turn");
     }
 
-    fn pickup(&mut self) -> UnoCard {
-        let pos = rand::random_range(0..DECK_SIZE) as u8;
-        let card = self.get_card(pos);
+    #[test]
+    fn card_positions_agree() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let deck = UnoDeck::new(&UnoRules::standard(), UNO_MAX_DECKS, &mut rng);
+
+        for slot in 0..deck.size() {
+            let card = deck.slot_to_card(slot);
 
-        if self.is_empty() {
-            self.main_deck = self.discard_deck;
-            self.discard_deck = (0, 0);
+            assert!(
+                deck.card_slots(card).any(|other| other == slot),
+                "Card {:?} from slot {slot} does not map back",
+                card.decode()
+            );
         }
+    }
+
+    #[test]
+    fn black_cards_are_discarded() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut deck = UnoDeck::new(&UnoRules::standard(), 1, &mut rng);
+        let mut hand: Vec<UnoCard> = deck.draw_pile.drain(..).collect();
 
-        card
+        let wild_idx = hand.iter().position(|card| card.is_black()).unwrap();
+        let wild = hand.remove(wild_idx);
+
+        assert!(deck.verify(&hand).is_err());
+
+        deck.try_discard(wild).unwrap();
+        deck.verify(&hand).unwrap();
     }
 
-    fn discard(&mut self, card: UnoCard) {
-        // Black cards can have 4 of them, possibly need to check all 4
-        if card.is_black() {
-            // All black cards are in the 2nd part of the deck, no need to scan
-            let mut scan_flag = match UnoCardPower::from(card.and(0b00000111)) {
-                UnoCardPower::PlusFour => 1u64 << 36,
-                UnoCardPower::ClrChange => 1u64 << 40,
-                _ => {
-                    panic!("Colour validated as black but is not");
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn random_games_conserve_cards(
+            seed in any::<u64>(),
+            choice_seed in any::<u64>(),
+            players in 2u32..=UNO_MAX_PLAYERS as u32,
+            deck_count in 1u8..=UNO_MAX_DECKS,
+            team_mode in any::<bool>(),
+        ) {
+            let team_mode = team_mode && players >= 4 && players.is_multiple_of(2);
+            let mut game = test_game(seed, players, deck_count, team_mode);
+            let mut rng = ChaCha8Rng::seed_from_u64(choice_seed);
+
+            prop_assert!(game.verify_deck().is_ok());
+
+            for _ in 0..5000 {
+                if game.start_state != GameStartState::Active {
+                    break;
                 }
-            };
 
-            for _ in 0..4 {
-                if self.discard_deck.1 & scan_flag != 0 {
-                    self.discard_deck.1 |= scan_flag;
-                    return;
+                if rng.random_ratio(1, 200) {
+                    let leaver = game.active_users[rng.random_range(0..game.active_users.len())].id;
+                    game.user_left(leaver);
+                } else {
+                    play_random_turn(&mut game, &mut rng);
+                }
+
+                if let Err(err) = game.verify_deck() {
+                    return Err(TestCaseError::fail(format!("{err:?}")));
+                }
+
+                if game.start_state == GameStartState::Active {
+                    prop_assert!((game.user_turn as usize) < game.active_users.len());
                 }
-                scan_flag <<= 1;
             }
-            return;
         }
 
-        let (pos, diff) = Self::card_to_pos(card);
-        {
-            let right_check = pos > 63;
-
-            let check_cursor: u64 = 1u64 << (if right_check { pos - 64 } else { pos });
+        #[test]
+        fn bot_games_finish(
+            seed in any::<u64>(),
+            players in 2u32..=UNO_MAX_PLAYERS as u32,
+            deck_count in 1u8..=UNO_MAX_DECKS,
+            team_mode in any::<bool>(),
+        ) {
+            let team_mode = team_mode && players >= 4 && players.is_multiple_of(2);
+            let mut game = test_game(seed, players, deck_count, team_mode);
+
+            let difficulties = [
+                UnoBotDifficulty::Random,
+                UnoBotDifficulty::Greedy,
+                UnoBotDifficulty::Heuristic,
+            ];
+            for (i, user) in game.active_users.iter().enumerate() {
+                game.bots.insert(
+                    user.id,
+                    UnoBot::new(difficulties[i % difficulties.len()], seed ^ user.id as u64),
+                );
+            }
 
-            if right_check {
-                if self.discard_deck.1 & check_cursor == 0 {
-                    self.discard_deck.1 |= check_cursor;
-                    return;
+            for _ in 0..5000 {
+                if game.start_state != GameStartState::Active {
+                    break;
                 }
-            } else if self.discard_deck.0 & check_cursor == 0 {
-                self.discard_deck.0 |= check_cursor;
-                return;
-            };
-        }
 
-        if diff != 0 {
-            let pos = pos + diff;
-            let right_check = pos > 63;
+                let bot_id = game.active_users[game.user_turn as usize].id;
+                let events = game.replay.events.len();
+
+                game.bot_turn(bot_id);
 
-            let check_cursor: u64 = 1u64 << (if right_check { pos - 64 } else { pos });
+                // Only a legal play or a pickup gets into the replay, a pickup with every
+                //  card already in someone's hand just passes the turn
+                prop_assert!(
+                    game.replay.events.len() > events,
+                    "Bot {bot_id} didn't do anything"
+                );
 
-            if right_check {
-                if self.discard_deck.1 & check_cursor == 0 {
-                    self.discard_deck.1 |= check_cursor;
-                    return;
+                if let Err(err) = game.verify_deck() {
+                    return Err(TestCaseError::fail(format!("{err:?}")));
                 }
-            } else if self.discard_deck.0 & check_cursor == 0 {
-                self.discard_deck.0 |= check_cursor;
-                return;
-            };
-        }
+            }
 
-        println!("Tried to discard but no empty slot {:?}", card.decode());
+            prop_assert!(game.start_state == GameStartState::Ending);
+        }
     }
 
-    /// This function may be a bit ass, should most likely switch it out
-    ///  with a better match statement
-    fn pos_to_card(pos: u8) -> UnoCard {
-        // println!("Get from pos {pos} -> {}", pos % 4);
-        assert!(pos < DECK_SIZE, "Malformed pos, too high");
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(200))]
+
+        #[test]
+        fn replays_reproduce_games(
+            seed in any::<u64>(),
+            choice_seed in any::<u64>(),
+            players in 2u32..=UNO_MAX_PLAYERS as u32,
+            deck_count in 1u8..=UNO_MAX_DECKS,
+        ) {
+            let mut game = test_game(seed, players, deck_count, false);
+            let mut rng = ChaCha8Rng::seed_from_u64(choice_seed);
+
+            for _ in 0..5000 {
+                if game.start_state != GameStartState::Active {
+                    break;
+                }
+
+                if rng.random_ratio(1, 200) {
+                    let leaver = game.active_users[rng.random_range(0..game.active_users.len())].id;
+                    game.user_left(leaver);
+                } else {
+                    play_random_turn(&mut game, &mut rng);
+                }
+            }
+
+            let data = bincode::encode_to_vec(&game.replay, bincode::config::standard()).unwrap();
+            let frames = ServerUno::replay_frames(&data)
+                .map_err(|err| TestCaseError::fail(format!("{err:?}")))?;
 
-        // Handling the case here for the 2 sets of base 1-9 cards
-        if pos < 72 {
-            let value = (pos / 8) + 1;
-            let colour = UnoCardColour::from(pos % 4);
+            let last = frames.last().unwrap();
+            let hands: Vec<(u32, Vec<UnoCard>)> = game
+                .active_users
+                .iter()
+                .map(|user| (user.id, user.cards.clone()))
+                .collect();
 
-            return UnoCard::encode(false, colour, value);
+            prop_assert!(last.hands == hands);
+            prop_assert!(last.state.finished_users == game.finished_users);
+            prop_assert!(last.state.bust_users == game.bust_users);
+            prop_assert!(last.state.game_state == game.start_state);
         }
+    }
 
-        // Handle the value 0 cards
-        if pos < 76 {
-            let colour = UnoCardColour::from(pos - 72);
+    #[test]
+    fn broken_deck_closes_the_room() {
+        let mut game = test_game(0, 2, 1, false);
+        let (service_sender, mut service_receiver) = mpsc::unbounded_channel();
 
-            return UnoCard::encode(false, colour, 0);
+        let mut receivers = vec![];
+        for user_id in [0, 1] {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            game.user_senders.insert(user_id, sender);
+            receivers.push(receiver);
         }
+        let (sender, receiver) = mpsc::unbounded_channel();
+        game.spectator_senders.insert(2, sender);
+        receivers.push(receiver);
+
+        assert!(!game.close_if_deck_broken(&service_sender));
 
-        // Coloured Power Cards
-        if pos < 100 {
-            let power = UnoCardPower::from((pos - 76) / 8);
-            let colour = UnoCardColour::from((pos - 76) % 4);
+        // The same card in two places
+        let card = game.active_users[0].cards[0];
+        game.active_users[1].cards.push(card);
 
-            return UnoCard::encode(true, colour, power as u8);
+        assert!(game.close_if_deck_broken(&service_sender));
+        assert!(game.user_senders.is_empty());
+        assert!(game.spectator_senders.is_empty());
+
+        for receiver in receivers.iter_mut() {
+            assert!(matches!(
+                receiver.try_recv(),
+                Ok(ServerMessage::RemovedFromGame(_))
+            ));
         }
 
-        let power = if pos < 104 {
-            UnoCardPower::PlusFour
-        } else {
-            UnoCardPower::ClrChange
-        };
+        let mut left = 0;
+        while let Ok(msg) = service_receiver.try_recv() {
+            assert!(matches!(msg, ServerIntraMessage::UserLeftGame(_, 0)));
+            left += 1;
+        }
+        assert_eq!(left, 3);
+    }
 
-        UnoCard::encode(true, UnoCardColour::Red, power as u8)
+    fn turn_user(game: &ServerUno) -> u32 {
+        game.active_users[game.user_turn as usize].id
     }
 
-    fn card_to_pos(card: UnoCard) -> (u8, u8) {
+    #[test]
+    fn running_out_of_time_picks_up() {
+        let mut game = test_game(0, 2, 1, false);
+        game.settings.turn_timer_secs = Some(30);
+
+        let user_id = turn_user(&game);
+        let hand = game.active_users[game.user_turn as usize].cards.len();
+
+        game.sync_turn_timer();
+        let (timer_user, deadline) = game.turn_timer.unwrap();
+        assert_eq!(timer_user, user_id);
+        assert!(deadline > Instant::now());
+
+        // Syncing again for the same turn doesn't restart the clock
+        game.sync_turn_timer();
+        assert_eq!(game.turn_timer, Some((timer_user, deadline)));
+
+        assert_eq!(game.turn_timed_out(), None);
+
+        let user = game.active_users.iter().find(|user| user.id == user_id);
+        assert_eq!(user.unwrap().cards.len(), hand + 1);
+        assert_ne!(turn_user(&game), user_id);
         assert!(
-            !card.is_black(),
-            "Only coloured cards can be checked here {card:?}"
+            game.action
+                .iter()
+                .any(|action| matches!(action, UnoAction::UserTimedOut(_)))
         );
+        assert!(matches!(
+            game.replay.events.last(),
+            Some(UnoReplayEvent::Action(id, UnoClientAction::PickupCard)) if *id == user_id
+        ));
+        game.verify_deck().unwrap();
+
+        // The next player gets a fresh timer
+        game.sync_turn_timer();
+        assert_eq!(game.turn_timer.map(|(id, _)| id), Some(turn_user(&game)));
+    }
 
-        let (is_power, colour, value) = card.decode();
+    #[test]
+    fn no_timer_without_the_setting() {
+        let mut game = test_game(0, 2, 1, false);
 
-        if value == 0 {
-            return (76 + colour as u8, 0);
-        }
+        game.sync_turn_timer();
+        assert_eq!(game.turn_timer, None);
+        assert_eq!(game.turn_timed_out(), None);
+    }
+
+    #[test]
+    fn repeated_timeouts_kick() {
+        let mut game = test_game(0, 2, 1, false);
+        game.settings.turn_timer_secs = Some(30);
+        game.settings.kick_after_timeouts = Some(2);
+
+        let slow_user = turn_user(&game);
+        game.sync_turn_timer();
+        assert_eq!(game.turn_timed_out(), None);
+
+        let other = turn_user(&game);
+        game.handle_action(other, UnoClientAction::PickupCard)
+            .unwrap();
+
+        game.sync_turn_timer();
+        assert_eq!(game.turn_timed_out(), Some(slow_user));
+    }
+
+    fn account(n: u8) -> AccountId {
+        AccountId([n; 32])
+    }
 
-        if !is_power {
-            let pos = ((value - 1) * 8) + colour as u8;
+    /// Someone new wanting to sit down, under the given name and account
+    fn joiner(name: &str, account: AccountId) -> PlayerState {
+        let (sender, _receiver) = mpsc::unbounded_channel();
+
+        PlayerState {
+            name: name.to_string(),
+            account,
+            addr: "127.0.0.1:0".parse().unwrap(),
+            sender,
+            game_id: None,
+            rating: 0,
+            chips: 0,
+        }
+    }
 
-            return (pos, colour as u8);
+    /// Still waiting to start, user `0` is host and everyone has a connection
+    fn setup_room(players: u32) -> (ServerUno, Vec<UnboundedReceiver<ServerMessage>>) {
+        let mut game = ServerUno::new(0, "Test".to_string(), 0, 0).unwrap();
+        let mut receivers = vec![];
+
+        for id in 0..players {
+            game.active_users.push(UnoUser {
+                id,
+                name: format!("Player {id}"),
+                cards: vec![],
+                rating: None,
+            });
+
+            let (sender, receiver) = mpsc::unbounded_channel();
+            game.user_senders.insert(id, sender);
+            game.accounts.insert(id, account(id as u8));
+            receivers.push(receiver);
         }
 
-        (76 + value + colour as u8, colour as u8)
+        (game, receivers)
     }
-}
 
-impl UnoUser {
-    fn new_joiner(
-        deck: &mut UnoDeck,
-        user_id: u32,
-        user: PlayerState,
-    ) -> (UnoUser, UnboundedSender<ServerMessage>) {
-        (
-            UnoUser {
-                id: user_id,
-                name: user.name,
-                cards: deck
-                    .get_new_hand(&mut rand::rng())
-                    .expect("Should be able to fmt deck here"),
-            },
-            user.sender.clone(),
+    #[test]
+    fn host_kicks_before_the_start() {
+        let (mut game, mut receivers) = setup_room(3);
+
+        assert!(game.remove_user(1, 2, false).is_err());
+        assert!(game.remove_user(0, 0, false).is_err());
+        assert!(game.remove_user(0, 7, false).is_err());
+
+        game.remove_user(0, 1, false).unwrap();
+        assert!(!game.active_users.iter().any(|user| user.id == 1));
+        assert!(!game.user_senders.contains_key(&1));
+        assert!(matches!(
+            receivers[1].try_recv(),
+            Ok(ServerMessage::RemovedFromGame(_))
+        ));
+        // Kicked, not banned
+        assert!(game.banned.is_empty());
+        assert!(receivers[0].try_recv().is_err());
+    }
+
+    #[test]
+    fn host_bans_by_account() {
+        let (mut game, mut receivers) = setup_room(3);
+
+        game.remove_user(0, 2, true).unwrap();
+        assert_eq!(game.banned, vec![account(2)]);
+        assert!(matches!(
+            receivers[2].try_recv(),
+            Ok(ServerMessage::RemovedFromGame(_))
+        ));
+
+        // Coming back under another name doesn't help
+        assert!(
+            game.join_refusal(&joiner("Someone Else", account(2)))
+                .is_some()
+        );
+        // Someone else who happens to share the name is let in
+        assert_eq!(game.join_refusal(&joiner("Player 2", account(9))), None);
+    }
+
+    #[test]
+    fn no_kicks_once_started() {
+        let (mut game, _receivers) = setup_room(3);
+        game.start_game();
+
+        assert!(game.remove_user(0, 1, false).is_err());
+        assert!(game.transfer_host(0, 1).is_err());
+        assert_eq!(game.active_users.len(), 3);
+    }
+
+    #[test]
+    fn host_can_be_handed_over() {
+        let (mut game, _receivers) = setup_room(3);
+
+        assert!(game.transfer_host(1, 2).is_err());
+        assert!(game.transfer_host(0, 7).is_err());
+
+        game.transfer_host(0, 2).unwrap();
+        assert_eq!(game.host_user, 2);
+        assert!(
+            game.action
+                .iter()
+                .any(|action| matches!(action, UnoAction::HostChanged(name) if name == "Player 2"))
+        );
+        assert!(game.remove_user(0, 1, false).is_err());
+    }
+
+    #[test]
+    fn host_moves_on_when_they_leave() {
+        let (mut game, _receivers) = setup_room(3);
+
+        // Still here, nothing changes
+        game.migrate_host();
+        assert_eq!(game.host_user, 0);
+
+        game.user_left(0);
+        game.user_senders.remove(&0);
+        game.migrate_host();
+        assert_eq!(game.host_user, 1);
+
+        // Someone who finished but is still watching can take over
+        game.start_game();
+        game.finished_users.push((2, "Player 2".to_string()));
+        game.active_users.retain(|user| user.id != 2);
+        game.user_left(1);
+        game.user_senders.remove(&1);
+        game.migrate_host();
+        assert_eq!(game.host_user, 2);
+    }
+
+    #[test]
+    fn reactions_have_a_cooldown() {
+        let (mut game, _receivers) = setup_room(2);
+
+        game.react(0, Reaction::Nice).unwrap();
+        assert!(game.react(0, Reaction::Wow).is_err());
+        // Everyone has their own
+        assert!(game.react(1, Reaction::Wow).is_ok());
+
+        let reacted = game
+            .action
+            .iter()
+            .filter(|action| matches!(action, UnoAction::Reacted(..)))
+            .count();
+        assert_eq!(reacted, 2);
+
+        // Once it's passed they can go again
+        let last = game.last_reactions.get_mut(&0).unwrap();
+        *last -= REACTION_COOLDOWN;
+        assert!(game.react(0, Reaction::Wow).is_ok());
+    }
+
+    #[test]
+    fn only_the_table_reacts() {
+        let (mut game, _receivers) = setup_room(2);
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        game.spectator_senders.insert(5, sender);
+
+        assert!(game.react(5, Reaction::Nice).is_err());
+        assert!(game.react(7, Reaction::Nice).is_err());
+    }
+
+    #[test]
+    fn only_public_games_are_recorded() {
+        let (mut game, _receivers) = setup_room(2);
+        assert!(!game.keeps_replay());
+
+        game.start_game();
+        assert!(game.keeps_replay());
+
+        for visibility in [
+            RoomVisibility::Private("secret".to_string()),
+            RoomVisibility::Hidden,
+        ] {
+            game.visibility = visibility;
+            assert!(!game.keeps_replay());
+        }
+    }
+
+    /// The last state sent down a connection, with the hand that came with it
+    fn sent_state(
+        receiver: &mut UnboundedReceiver<ServerMessage>,
+    ) -> (Vec<UnoCard>, UnoClientGameState) {
+        let mut last = None;
+        while let Ok(message) = receiver.try_recv() {
+            if let ServerMessage::GameState(encoded) = message {
+                last = Some(encoded);
+            }
+        }
+
+        let (ServerUnoCommand::GameState(cards, state), _) = bincode::decode_from_slice(
+            &last.expect("No state was sent"),
+            bincode::config::standard(),
         )
+        .unwrap();
+        (cards, state)
+    }
+
+    #[test]
+    fn either_partner_finishing_wins_for_the_team() {
+        // Seats 0 and 2 are one team, 1 and 3 the other
+        for (finisher, partner) in [(0, 2), (2, 0)] {
+            let mut game = test_game(0, 4, 1, true);
+            game.user_turn = finisher as u8;
+
+            let wild = UnoCard::encode(true, UnoCardColour::Red, UnoCardPower::ClrChange as u8);
+            game.active_users[finisher].cards = vec![wild];
+            let finisher_id = game.active_users[finisher].id;
+            let partner_id = game.active_users[partner].id;
+
+            let played = UnoCard::encode(true, UnoCardColour::Blue, UnoCardPower::ClrChange as u8);
+            game.handle_action(finisher_id, UnoClientAction::PlayCard(played))
+                .unwrap();
+
+            // Their partner still had cards but is finished along with them
+            assert_eq!(game.winning_team, game.team_of(finisher_id));
+            assert!(game.active_users.iter().all(|user| user.id != partner_id));
+            for id in [finisher_id, partner_id] {
+                assert!(
+                    game.finished_users
+                        .iter()
+                        .any(|(user_id, _)| *user_id == id)
+                );
+            }
+
+            let standings = game.team_standings();
+            assert!(standings[0].has_won);
+            assert!(standings[0].user_ids.contains(&finisher_id));
+            assert!(standings[0].user_ids.contains(&partner_id));
+            assert!(!standings[1].has_won);
+        }
+    }
+
+    #[test]
+    fn partner_view_decides_if_partners_see_hands() {
+        for (partner_view, shown) in [
+            (UnoPartnerView::Hand, true),
+            (UnoPartnerView::CardCount, false),
+        ] {
+            let (mut game, mut receivers) = setup_room(4);
+            game.settings.team_mode = true;
+            game.settings.partner_view = partner_view;
+            game.start_game();
+            game.update_user_state();
+
+            for (user_id, receiver) in receivers.iter_mut().enumerate() {
+                let (_, state) = sent_state(receiver);
+                let partner = (user_id + 2) % 4;
+
+                let expected = if shown {
+                    vec![(partner as u32, game.active_users[partner].cards.clone())]
+                } else {
+                    vec![]
+                };
+                assert_eq!(state.partner_hands, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn spectators_see_no_hand_and_are_counted() {
+        let (mut game, _receivers) = setup_room(2);
+        game.start_game();
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let mut watcher = joiner("Watcher", account(9));
+        watcher.sender = sender;
+        assert!(game.add_spectator(9, watcher));
+
+        let Ok(ServerMessage::JoinedGame(_, _, GameUserState::Spectator)) = receiver.try_recv()
+        else {
+            panic!("Spectator wasn't told they're watching");
+        };
+
+        game.update_user_state();
+        let (cards, state) = sent_state(&mut receiver);
+        assert!(cards.is_empty());
+        assert!(state.partner_hands.is_empty());
+
+        let ServerIntraMessage::UpdateGameServer(_, update) = game.service_update_state() else {
+            panic!("Expected a lobby update");
+        };
+        assert_eq!(update.spectator_count, 1);
+        assert_eq!(update.player_count, 2);
     }
 }
@@ -0,0 +1,650 @@
+use std::{collections::HashMap, time::Duration};
+
+use bincode::{Decode, Encode, config::Configuration};
+use rpc::{
+    blackjack::BLACKJACK_STARTING_CHIPS,
+    game_state::GameType,
+    stats::{DEFAULT_RATING, LeaderboardEntry, PlayerProfile, PlayerStats, RatingChange},
+};
+use tokio::{fs, signal, sync::watch, time::sleep};
+
+/// Kept next to wherever the server is run from, like the replays
+const STATS_FILE: &str = "stats.bin";
+
+const LEADERBOARD_SIZE: usize = 20;
+
+/// Games finishing close together are saved in one write
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// The most a rating can move in one game, split across everyone played against
+const RATING_K: f64 = 32.0;
+
+/// Only the recent history is worth keeping around
+const RATING_HISTORY_SIZE: usize = 50;
+
+/// The public half of the static key the client connects with.
+/// The handshake proves the client holds the private half, so nobody else can use it.
+#[derive(Debug, Encode, Decode, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AccountId(pub [u8; 32]);
+
+impl AccountId {
+    pub fn from_key(key: &[u8]) -> Option<AccountId> {
+        key.try_into().ok().map(AccountId)
+    }
+}
+
+/// How a single player got on in a finished game
+#[derive(Debug)]
+pub struct PlayerResult {
+    pub user_id: u32,
+    /// Only players who ran out of cards, or were on the winning team, get a place
+    pub placement: Option<u32>,
+    /// Finishing order for ratings, lower is better.
+    /// Players on the same rank drew with each other.
+    pub rank: u32,
+    pub won: bool,
+    pub busted: bool,
+    pub cards_played: u32,
+}
+
+/// Sent by a game to the main loop once it's over, the room fills in whose account each result is
+#[derive(Debug)]
+pub struct GameResults {
+    pub game_type: GameType,
+    pub game_secs: u64,
+    pub players: Vec<(AccountId, PlayerResult)>,
+}
+
+/// Kept against the key a player connects with, so two players can share a name.
+/// The whole thing is small enough to just be rewritten after every game.
+#[derive(Debug, Encode, Decode, Default)]
+pub struct StatsStore {
+    players: HashMap<AccountId, Vec<(GameType, PlayerStats)>>,
+    ratings: HashMap<AccountId, PlayerRating>,
+    /// Blackjack chips, the only game where what's won carries over
+    chips: HashMap<AccountId, u64>,
+    /// Whatever each account last connected as, profiles are still looked up by name
+    names: HashMap<AccountId, String>,
+}
+
+/// One rating across every game, kept unrounded so small changes aren't lost
+#[derive(Debug, Encode, Decode)]
+struct PlayerRating {
+    rating: f64,
+    history: Vec<RatingChange>,
+}
+
+impl StatsStore {
+    /// A missing or broken file just means starting again from nothing
+    pub async fn load() -> StatsStore {
+        let data = match fs::read(STATS_FILE).await {
+            Ok(data) => data,
+            Err(err) => {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    println!("Failed to read player stats {err:?}");
+                }
+                return StatsStore::default();
+            }
+        };
+
+        match bincode::decode_from_slice::<StatsStore, Configuration>(
+            &data,
+            bincode::config::standard(),
+        ) {
+            Ok((store, _)) => store,
+            Err(err) => {
+                println!("Failed to decode player stats {err:?}");
+                StatsStore::default()
+            }
+        }
+    }
+
+    /// Hands a copy to the saving task, the main loop never waits on the disk
+    pub fn save(&self, saver: &watch::Sender<Vec<u8>>) {
+        match bincode::encode_to_vec(self, bincode::config::standard()) {
+            Ok(data) => {
+                saver.send_replace(data);
+            }
+            Err(err) => println!("Failed to encode player stats {err:?}"),
+        }
+    }
+
+    pub fn register(&mut self, account: AccountId, name: &str) {
+        self.names.insert(account, name.to_string());
+    }
+
+    pub fn record(&mut self, results: &GameResults) {
+        for (account, result) in results.players.iter() {
+            let games = self.players.entry(*account).or_default();
+
+            let stats = match games
+                .iter()
+                .position(|(game_type, _)| *game_type == results.game_type)
+            {
+                Some(idx) => &mut games[idx].1,
+                None => {
+                    games.push((results.game_type, PlayerStats::default()));
+                    &mut games.last_mut().expect("Just pushed").1
+                }
+            };
+
+            stats.games_played += 1;
+            stats.cards_played += result.cards_played as u64;
+            stats.total_game_secs += results.game_secs;
+
+            if result.won {
+                stats.wins += 1;
+            }
+
+            if result.busted {
+                stats.busts += 1;
+            }
+
+            if let Some(placement) = result.placement.filter(|place| *place > 0) {
+                let idx = placement as usize - 1;
+                if stats.finishes.len() <= idx {
+                    stats.finishes.resize(idx + 1, 0);
+                }
+                stats.finishes[idx] += 1;
+            }
+        }
+
+        self.update_ratings(results);
+    }
+
+    /// Elo stretched over more than two players.
+    /// Every pair in the game is scored as a head to head from where they finished,
+    ///  each player's change is the average of their head to heads.
+    ///
+    /// Bots aren't in the results, so a game against only bots isn't rated.
+    fn update_ratings(&mut self, results: &GameResults) {
+        if results.players.len() < 2 {
+            return;
+        }
+
+        let before: Vec<f64> = results
+            .players
+            .iter()
+            .map(|(account, _)| self.rating_of(account))
+            .collect();
+
+        let opponents = (results.players.len() - 1) as f64;
+
+        for (idx, (account, player)) in results.players.iter().enumerate() {
+            let mut score = 0.0;
+
+            for (other_idx, (_, other)) in results.players.iter().enumerate() {
+                if idx == other_idx {
+                    continue;
+                }
+
+                let actual = match player.rank.cmp(&other.rank) {
+                    std::cmp::Ordering::Less => 1.0,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Greater => 0.0,
+                };
+                let expected = 1.0 / (1.0 + 10f64.powf((before[other_idx] - before[idx]) / 400.0));
+
+                score += actual - expected;
+            }
+
+            let rating = before[idx] + RATING_K * score / opponents;
+
+            let entry = self
+                .ratings
+                .entry(*account)
+                .or_insert_with(|| PlayerRating {
+                    rating: DEFAULT_RATING as f64,
+                    history: vec![],
+                });
+
+            entry.history.push(RatingChange {
+                game_type: results.game_type,
+                rating: rating.round() as u32,
+                change: (rating.round() - before[idx].round()) as i32,
+            });
+            if entry.history.len() > RATING_HISTORY_SIZE {
+                entry.history.remove(0);
+            }
+            entry.rating = rating;
+        }
+    }
+
+    fn rating_of(&self, account: &AccountId) -> f64 {
+        self.ratings
+            .get(account)
+            .map(|rating| rating.rating)
+            .unwrap_or(DEFAULT_RATING as f64)
+    }
+
+    /// Rounded for showing, anyone who hasn't played a rated game gets the default
+    pub fn rating(&self, account: &AccountId) -> u32 {
+        self.rating_of(account).round() as u32
+    }
+
+    /// Anyone who hasn't played yet starts with the same stack
+    pub fn chips(&self, account: &AccountId) -> u64 {
+        self.chips
+            .get(account)
+            .copied()
+            .unwrap_or(BLACKJACK_STARTING_CHIPS)
+    }
+
+    /// Changes rather than balances, so two tables can't overwrite each other
+    pub fn settle_chips(&mut self, changes: &[(AccountId, i64)]) {
+        for (account, change) in changes.iter() {
+            let chips = self
+                .chips
+                .entry(*account)
+                .or_insert(BLACKJACK_STARTING_CHIPS);
+
+            *chips = chips.saturating_add_signed(*change);
+        }
+    }
+
+    /// Names aren't unique, it's whoever by that name has played the most
+    pub fn profile(&self, name: &str) -> Option<PlayerProfile> {
+        let (account, games) = self
+            .players
+            .iter()
+            .filter(|(account, _)| self.name_of(account) == name)
+            .max_by_key(|(_, games)| {
+                games
+                    .iter()
+                    .map(|(_, stats)| stats.games_played)
+                    .sum::<u32>()
+            })?;
+
+        let mut overall = PlayerStats::default();
+        for (_, stats) in games.iter() {
+            overall.add(stats);
+        }
+
+        Some(PlayerProfile {
+            name: name.to_string(),
+            rating: self.rating(account),
+            rating_history: self
+                .ratings
+                .get(account)
+                .map(|rating| rating.history.clone())
+                .unwrap_or_default(),
+            overall,
+            per_game: games.clone(),
+        })
+    }
+
+    /// Most wins first, ties go to whoever wins more of the games they play
+    pub fn leaderboard(&self, game_type: Option<GameType>) -> Vec<LeaderboardEntry> {
+        let mut entries: Vec<LeaderboardEntry> = self
+            .players
+            .iter()
+            .filter_map(|(account, games)| {
+                let mut stats = PlayerStats::default();
+
+                for (_, game_stats) in games
+                    .iter()
+                    .filter(|(game, _)| game_type.is_none_or(|game_type| *game == game_type))
+                {
+                    stats.add(game_stats);
+                }
+
+                (stats.games_played > 0).then(|| LeaderboardEntry {
+                    name: self.name_of(account).to_string(),
+                    rating: self.rating(account),
+                    stats,
+                })
+            })
+            .collect();
+
+        entries.sort_by(|a, b| {
+            let win_rate = |stats: &PlayerStats| stats.wins as f64 / stats.games_played as f64;
+
+            b.stats
+                .wins
+                .cmp(&a.stats.wins)
+                .then(win_rate(&b.stats).total_cmp(&win_rate(&a.stats)))
+                .then(a.name.cmp(&b.name))
+        });
+        entries.truncate(LEADERBOARD_SIZE);
+
+        entries
+    }
+
+    fn name_of(&self, account: &AccountId) -> &str {
+        self.names.get(account).map(String::as_str).unwrap_or("")
+    }
+}
+
+/// Saves whatever it's handed on its own task, only the latest copy is ever written.
+/// Stopping the server writes anything still waiting out the delay before exiting.
+pub fn spawn_saver() -> watch::Sender<Vec<u8>> {
+    let (sender, mut receiver) = watch::channel(vec![]);
+
+    tokio::spawn(async move {
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+        let mut pending = false;
+
+        loop {
+            tokio::select! {
+                changed = receiver.changed(), if !pending => {
+                    if changed.is_err() {
+                        break;
+                    }
+                    pending = true;
+                }
+                _ = sleep(SAVE_DELAY), if pending => {
+                    save_latest(&mut receiver).await;
+                    pending = false;
+                }
+                _ = &mut shutdown => {
+                    if pending {
+                        save_latest(&mut receiver).await;
+                    }
+                    std::process::exit(0);
+                }
+            }
+        }
+    });
+
+    sender
+}
+
+async fn save_latest(receiver: &mut watch::Receiver<Vec<u8>>) {
+    let data = receiver.borrow_and_update().clone();
+    if let Err(err) = write(&data).await {
+        println!("Failed to save player stats {err:?}");
+    }
+}
+
+/// Ctrl-C, or being asked to stop by the system
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let Ok(mut terminate) = signal::unix::signal(signal::unix::SignalKind::terminate()) else {
+            println!("Couldn't listen for the server being stopped");
+            return std::future::pending().await;
+        };
+
+        tokio::select! {
+            _ = signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    if signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Written to the side first so a crash mid write can't lose everything
+async fn write(data: &[u8]) -> anyhow::Result<()> {
+    let tmp_file = format!("{STATS_FILE}.tmp");
+
+    fs::write(&tmp_file, data).await?;
+    fs::rename(tmp_file, STATS_FILE).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(n: u8) -> AccountId {
+        AccountId([n; 32])
+    }
+
+    /// A finished game, each player as (account, rank), the best rank won
+    fn game(game_type: GameType, players: &[(u8, u32)]) -> GameResults {
+        GameResults {
+            game_type,
+            game_secs: 60,
+            players: players
+                .iter()
+                .map(|(n, rank)| {
+                    (
+                        account(*n),
+                        PlayerResult {
+                            user_id: *n as u32,
+                            placement: Some(*rank),
+                            rank: *rank,
+                            won: *rank == 1,
+                            busted: false,
+                            cards_played: 10,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn store(names: &[(u8, &str)]) -> StatsStore {
+        let mut stats = StatsStore::default();
+        for (n, name) in names {
+            stats.register(account(*n), name);
+        }
+        stats
+    }
+
+    #[test]
+    fn record_adds_up_per_game_type() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob")]);
+
+        stats.record(&game(GameType::Uno, &[(1, 1), (2, 2)]));
+        stats.record(&game(GameType::Uno, &[(1, 2), (2, 1)]));
+        stats.record(&game(GameType::Chess, &[(1, 1), (2, 2)]));
+
+        let profile = stats.profile("Alice").unwrap();
+        assert_eq!(profile.overall.games_played, 3);
+        assert_eq!(profile.overall.wins, 2);
+        assert_eq!(profile.overall.cards_played, 30);
+        assert_eq!(profile.overall.total_game_secs, 180);
+        assert_eq!(profile.rating_history.len(), 3);
+
+        let (_, uno) = profile
+            .per_game
+            .iter()
+            .find(|(game_type, _)| *game_type == GameType::Uno)
+            .unwrap();
+        assert_eq!(uno.games_played, 2);
+        assert_eq!(uno.finishes, vec![1, 1]);
+
+        assert!(stats.profile("Carol").is_none());
+    }
+
+    #[test]
+    fn same_name_is_kept_apart() {
+        let mut stats = store(&[(1, "Alice"), (2, "Alice"), (3, "Bob")]);
+
+        stats.record(&game(GameType::Uno, &[(1, 1), (3, 2)]));
+        stats.record(&game(GameType::Uno, &[(2, 2), (3, 1)]));
+        stats.record(&game(GameType::Uno, &[(2, 2), (3, 1)]));
+
+        assert!(stats.rating(&account(1)) > DEFAULT_RATING);
+        assert!(stats.rating(&account(2)) < DEFAULT_RATING);
+
+        // The profile is whichever Alice has played more
+        let profile = stats.profile("Alice").unwrap();
+        assert_eq!(profile.overall.games_played, 2);
+        assert_eq!(profile.overall.wins, 0);
+
+        stats.settle_chips(&[(account(1), 200)]);
+        assert_eq!(stats.chips(&account(1)), BLACKJACK_STARTING_CHIPS + 200);
+        assert_eq!(stats.chips(&account(2)), BLACKJACK_STARTING_CHIPS);
+    }
+
+    #[test]
+    fn renaming_keeps_the_stats() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob")]);
+        stats.record(&game(GameType::Uno, &[(1, 1), (2, 2)]));
+
+        stats.register(account(1), "Alicia");
+
+        assert!(stats.profile("Alice").is_none());
+        assert_eq!(stats.profile("Alicia").unwrap().overall.wins, 1);
+    }
+
+    fn exact(stats: &StatsStore, n: u8) -> f64 {
+        stats.rating_of(&account(n))
+    }
+
+    #[test]
+    fn new_players_start_at_the_default() {
+        let stats = store(&[(1, "Alice")]);
+
+        assert_eq!(stats.rating(&account(1)), DEFAULT_RATING);
+        assert_eq!(stats.rating(&account(2)), DEFAULT_RATING);
+    }
+
+    #[test]
+    fn two_player_game_is_zero_sum() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob")]);
+
+        stats.record(&game(GameType::Uno, &[(1, 1), (2, 2)]));
+        stats.record(&game(GameType::Uno, &[(1, 1), (2, 2)]));
+        stats.record(&game(GameType::Uno, &[(1, 2), (2, 1)]));
+
+        let total = exact(&stats, 1) + exact(&stats, 2);
+        assert!((total - 2.0 * DEFAULT_RATING as f64).abs() < 1e-9);
+
+        // Between equals the winner takes half of K
+        let mut stats = store(&[(1, "Alice"), (2, "Bob")]);
+        stats.record(&game(GameType::Uno, &[(1, 1), (2, 2)]));
+        assert!((exact(&stats, 1) - (DEFAULT_RATING as f64 + RATING_K / 2.0)).abs() < 1e-9);
+        assert_eq!(stats.profile("Bob").unwrap().rating_history[0].change, -16);
+    }
+
+    #[test]
+    fn results_are_symmetric() {
+        let mut first = store(&[(1, "Alice"), (2, "Bob")]);
+        first.record(&game(GameType::Uno, &[(1, 1), (2, 2)]));
+
+        // The same game the other way round, listed in the other order
+        let mut second = store(&[(1, "Alice"), (2, "Bob")]);
+        second.record(&game(GameType::Uno, &[(2, 1), (1, 2)]));
+
+        assert_eq!(exact(&first, 1), exact(&second, 2));
+        assert_eq!(exact(&first, 2), exact(&second, 1));
+    }
+
+    #[test]
+    fn draws_between_equals_change_nothing() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob")]);
+
+        stats.record(&game(GameType::Chess, &[(1, 1), (2, 1)]));
+
+        assert_eq!(exact(&stats, 1), DEFAULT_RATING as f64);
+        assert_eq!(exact(&stats, 2), DEFAULT_RATING as f64);
+    }
+
+    #[test]
+    fn draws_move_toward_each_other() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob"), (3, "Carol")]);
+
+        // Alice gets ahead of Bob, then they draw
+        stats.record(&game(GameType::Chess, &[(1, 1), (3, 2)]));
+        let (alice, bob) = (exact(&stats, 1), exact(&stats, 2));
+        stats.record(&game(GameType::Chess, &[(1, 1), (2, 1)]));
+
+        assert!(exact(&stats, 1) < alice);
+        assert!(exact(&stats, 2) > bob);
+    }
+
+    #[test]
+    fn multi_player_follows_finishing_order() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob"), (3, "Carol"), (4, "Dave")]);
+
+        stats.record(&game(GameType::Uno, &[(3, 3), (1, 1), (4, 4), (2, 2)]));
+
+        let ratings: Vec<f64> = (1..=4).map(|n| exact(&stats, n)).collect();
+        assert!(ratings.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(ratings[1] > DEFAULT_RATING as f64);
+        assert!(ratings[2] < DEFAULT_RATING as f64);
+
+        // Starting level it's still zero sum, and the first beat everyone so gains the full K
+        let total: f64 = ratings.iter().sum();
+        assert!((total - 4.0 * DEFAULT_RATING as f64).abs() < 1e-9);
+        assert!((ratings[0] - (DEFAULT_RATING as f64 + RATING_K / 2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shared_ranks_in_a_bigger_game() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob"), (3, "Carol"), (4, "Dave")]);
+
+        // Two teams, winners share a rank as do the losers
+        stats.record(&game(
+            GameType::Codenames,
+            &[(1, 1), (2, 1), (3, 2), (4, 2)],
+        ));
+
+        assert_eq!(exact(&stats, 1), exact(&stats, 2));
+        assert_eq!(exact(&stats, 3), exact(&stats, 4));
+        assert!(exact(&stats, 1) > exact(&stats, 3));
+    }
+
+    #[test]
+    fn one_player_games_are_not_rated() {
+        let mut stats = store(&[(1, "Alice")]);
+
+        stats.record(&game(GameType::Uno, &[(1, 1)]));
+
+        assert_eq!(exact(&stats, 1), DEFAULT_RATING as f64);
+        assert!(stats.profile("Alice").unwrap().rating_history.is_empty());
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob")]);
+
+        for _ in 0..RATING_HISTORY_SIZE + 5 {
+            stats.record(&game(GameType::Uno, &[(1, 1), (2, 2)]));
+        }
+
+        let history = stats.profile("Alice").unwrap().rating_history;
+        assert_eq!(history.len(), RATING_HISTORY_SIZE);
+        assert_eq!(history.last().unwrap().rating, stats.rating(&account(1)));
+    }
+
+    #[test]
+    fn leaderboard_orders_by_wins_then_win_rate() {
+        let mut stats = store(&[(1, "Alice"), (2, "Bob"), (3, "Carol"), (4, "Dave")]);
+
+        // Alice wins twice in three, Bob twice in two, Carol once, Dave never
+        stats.record(&game(GameType::Uno, &[(1, 1), (4, 2)]));
+        stats.record(&game(GameType::Uno, &[(1, 1), (4, 2)]));
+        stats.record(&game(GameType::Uno, &[(1, 2), (3, 1)]));
+        stats.record(&game(GameType::Chess, &[(2, 1), (4, 2)]));
+        stats.record(&game(GameType::Chess, &[(2, 1), (4, 2)]));
+
+        let names = |board: Vec<LeaderboardEntry>| -> Vec<String> {
+            board.into_iter().map(|entry| entry.name).collect()
+        };
+
+        assert_eq!(
+            names(stats.leaderboard(None)),
+            vec!["Bob", "Alice", "Carol", "Dave"]
+        );
+        // Only the games of that type count, and nobody who hasn't played it
+        assert_eq!(
+            names(stats.leaderboard(Some(GameType::Uno))),
+            vec!["Alice", "Carol", "Dave"]
+        );
+        assert_eq!(
+            names(stats.leaderboard(Some(GameType::Chess))),
+            vec!["Bob", "Dave"]
+        );
+    }
+
+    #[test]
+    fn leaderboard_ties_go_by_name() {
+        let mut stats = store(&[(1, "Bob"), (2, "Alice")]);
+
+        stats.record(&game(GameType::Uno, &[(1, 1), (2, 1)]));
+
+        let board = stats.leaderboard(None);
+        assert_eq!(board[0].name, "Alice");
+        assert_eq!(board[1].name, "Bob");
+    }
+}
@@ -0,0 +1,29 @@
+use rpc::cards::{PlayingCard, Rank, Suit};
+
+/// Cards written like "As Td 2c", rank then suit
+pub fn cards(text: &str) -> Vec<PlayingCard> {
+    text.split_whitespace()
+        .map(|card| {
+            let (rank, suit) = card.split_at(1);
+            let rank = match rank {
+                "T" => Rank::Ten,
+                "J" => Rank::Jack,
+                "Q" => Rank::Queen,
+                "K" => Rank::King,
+                "A" => Rank::Ace,
+                digit => Rank::ALL[digit.parse::<usize>().unwrap() - 2],
+            };
+            let suit = match suit {
+                "c" => Suit::Clubs,
+                "d" => Suit::Diamonds,
+                "h" => Suit::Hearts,
+                _ => Suit::Spades,
+            };
+            PlayingCard::new(rank, suit)
+        })
+        .collect()
+}
+
+pub fn card(text: &str) -> PlayingCard {
+    cards(text)[0]
+}
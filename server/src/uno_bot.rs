@@ -0,0 +1,148 @@
+use rand::{Rng, SeedableRng, seq::IndexedRandom};
+use rand_chacha::ChaCha8Rng;
+use rpc::uno::{UnoBotDifficulty, UnoCard, UnoCardColour, UnoClientAction, UnoEffect, UnoRules};
+
+/// Bots sit in a seat like anyone else, the game asks them for an action
+///  on their turn and then runs it through the same path as a human's.
+///
+/// They only get to see what a player at the table would see.
+pub struct UnoBot {
+    pub difficulty: UnoBotDifficulty,
+    /// Cards played by everyone, by colour.
+    /// Lots of a colour gone means the others are less likely to be holding it.
+    played_colours: [u32; 4],
+    /// Bots have their own rng so their choices don't change the game's draw pile,
+    ///  a replay only needs the moves they made
+    rng: ChaCha8Rng,
+}
+
+/// What the bot can see when it's their turn
+pub struct UnoBotView<'a> {
+    pub hand: &'a [UnoCard],
+    pub last_card: UnoCard,
+    /// Cards held by whoever goes next
+    pub next_card_count: usize,
+    /// Whether whoever goes next is on the same team
+    pub next_is_partner: bool,
+    /// The variant being played, so the bot knows what a card does
+    pub rules: &'a UnoRules,
+}
+
+impl UnoBot {
+    pub fn new(difficulty: UnoBotDifficulty, seed: u64) -> UnoBot {
+        UnoBot {
+            difficulty,
+            played_colours: [0; 4],
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Called for every card that goes on the stack
+    pub fn observe(&mut self, card: UnoCard) {
+        if !card.is_black() {
+            let (_, colour, _) = card.decode();
+            self.played_colours[colour as usize] += 1;
+        }
+    }
+
+    pub fn choose(&mut self, view: &UnoBotView) -> UnoClientAction {
+        let playable: Vec<UnoCard> = view
+            .hand
+            .iter()
+            .copied()
+            .filter(|&card| view.rules.allows(card, view.last_card))
+            .collect();
+
+        let card = match self.difficulty {
+            UnoBotDifficulty::Random => playable.choose(&mut self.rng).copied(),
+            UnoBotDifficulty::Greedy => playable.iter().copied().max_by_key(|&card| points(card)),
+            UnoBotDifficulty::Heuristic => playable
+                .iter()
+                .copied()
+                .max_by_key(|&card| self.score(card, view)),
+        };
+
+        let Some(card) = card else {
+            return UnoClientAction::PickupCard;
+        };
+
+        if !card.is_black() {
+            return UnoClientAction::PlayCard(card);
+        }
+
+        let colour = match self.difficulty {
+            UnoBotDifficulty::Random => UnoCardColour::from(self.rng.random_range(0..4)),
+            UnoBotDifficulty::Greedy | UnoBotDifficulty::Heuristic => self.pick_colour(view.hand),
+        };
+
+        UnoClientAction::PlayCard(UnoCard::encode(true, colour, card.get_value()))
+    }
+
+    /// The heuristic bot wants to:
+    ///  Hold on to black cards until they're needed
+    ///  Stay in the colour it has most of
+    ///  Hit the next player hard when they're close to finishing, unless they're a partner
+    fn score(&self, card: UnoCard, view: &UnoBotView) -> i32 {
+        let mut score = 0;
+
+        if card.is_black() {
+            if view.hand.len() > 2 {
+                score -= 10;
+            }
+        } else {
+            let (_, colour, _) = card.decode();
+            score += 2 * colour_count(view.hand, colour) as i32;
+            score += self.played_colours[colour as usize] as i32 / 4;
+        }
+
+        if is_attack(card, view.rules) {
+            if view.next_is_partner {
+                score -= 20;
+            } else if view.next_card_count <= 2 {
+                score += 20;
+            }
+        }
+
+        score
+    }
+
+    /// Colour with the most cards in hand, ties go to the colour the others have seen the most of
+    fn pick_colour(&self, hand: &[UnoCard]) -> UnoCardColour {
+        (0..4)
+            .map(UnoCardColour::from)
+            .max_by_key(|&colour| {
+                let in_hand = colour_count(hand, colour);
+
+                match self.difficulty {
+                    UnoBotDifficulty::Heuristic => (in_hand, self.played_colours[colour as usize]),
+                    UnoBotDifficulty::Random | UnoBotDifficulty::Greedy => (in_hand, 0),
+                }
+            })
+            .unwrap_or(UnoCardColour::Red)
+    }
+}
+
+/// The usual Uno scoring, power cards being the worst to be left holding
+fn points(card: UnoCard) -> u8 {
+    if card.is_black() {
+        50
+    } else if card.is_power() {
+        20
+    } else {
+        card.get_value()
+    }
+}
+
+/// Anything that makes the next player draw or lose their turn
+fn is_attack(card: UnoCard, rules: &UnoRules) -> bool {
+    rules
+        .effects_of(card)
+        .iter()
+        .any(|effect| matches!(effect, UnoEffect::Draw(_) | UnoEffect::Skip))
+}
+
+fn colour_count(hand: &[UnoCard], colour: UnoCardColour) -> usize {
+    hand.iter()
+        .filter(|card| !card.is_black() && card.decode().1 == colour)
+        .count()
+}
@@ -0,0 +1,421 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use rpc::{
+    game_state::GameType,
+    uno::{UNO_MAX_PLAYERS, UnoCard, UnoCardColour, UnoCardPower, UnoEffect, UnoRules},
+};
+use serde::Deserialize;
+use tokio::fs;
+
+/// Every `.toml` file in here is offered as its own game in the lobby,
+///  apart from `uno.toml` which is the rules for plain Uno
+const VARIANTS_DIR: &str = "variants";
+const STANDARD_FILE: &str = "uno.toml";
+
+/// What a variant file looks like, `variants/uno.toml` is the standard rules written out.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VariantFile {
+    name: String,
+    #[serde(default)]
+    description: String,
+    hand_size: u8,
+    bust_over: u8,
+    matching: MatchingFile,
+    cards: Vec<CardsFile>,
+    /// By power card, `plus_two = [{ draw = 2 }, "skip"]`
+    #[serde(default)]
+    effects: BTreeMap<String, Vec<UnoEffectFile>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MatchingFile {
+    colour: bool,
+    value: bool,
+}
+
+/// A run of faces with this many copies of each in every colour.
+/// Black cards don't have a colour, for them it's just how many there are.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CardsFile {
+    /// "0" to "9", or one of the power cards by name
+    faces: Vec<String>,
+    copies: u8,
+    /// All four unless it says otherwise
+    colours: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+enum UnoEffectFile {
+    Draw(u8),
+    Skip,
+    Reverse,
+}
+
+/// The rules plain Uno is played with, along with every variant that loaded
+pub struct UnoVariants {
+    standard: UnoRules,
+    variants: Vec<UnoRules>,
+}
+
+impl UnoVariants {
+    pub fn standard(&self) -> &UnoRules {
+        &self.standard
+    }
+
+    /// Nothing for games that aren't Uno, or a variant that isn't there
+    pub fn rules(&self, game_type: GameType) -> Option<&UnoRules> {
+        match game_type {
+            GameType::Uno => Some(&self.standard),
+            GameType::UnoVariant(idx) => self.variants.get(idx as usize),
+            _ => None,
+        }
+    }
+
+    /// Variants are told apart by name in the lobby, so a second one with the same name is refused
+    fn add(&mut self, rules: UnoRules) -> anyhow::Result<()> {
+        let name = rules.name.trim();
+        if self
+            .variants
+            .iter()
+            .chain([&self.standard])
+            .any(|other| other.name.trim().eq_ignore_ascii_case(name))
+        {
+            return Err(anyhow!("There's already a variant called {name}"));
+        }
+
+        if self.variants.len() > u8::MAX as usize {
+            return Err(anyhow!("Too many uno variants"));
+        }
+
+        self.variants.push(rules);
+
+        Ok(())
+    }
+
+    /// In the same order as `GameType::UnoVariant`
+    pub fn names(&self) -> Vec<String> {
+        self.variants
+            .iter()
+            .map(|rules| rules.name.clone())
+            .collect()
+    }
+}
+
+/// Read once when the server starts, in file name order so the variants keep their place.
+/// A file that doesn't make sense is skipped, without `uno.toml` the built in rules are used.
+pub async fn load() -> UnoVariants {
+    let mut loaded = UnoVariants {
+        standard: UnoRules::standard(),
+        variants: vec![],
+    };
+
+    let mut entries = match fs::read_dir(VARIANTS_DIR).await {
+        Ok(entries) => entries,
+        Err(err) => {
+            println!("Failed to read uno variants from {VARIANTS_DIR} {err:?}");
+            return loaded;
+        }
+    };
+
+    let mut paths = vec![];
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            paths.push(path);
+        }
+    }
+    // The standard rules go first, so no variant can take their name
+    paths.sort_by_key(|path| {
+        (
+            path.file_name().is_none_or(|name| name != STANDARD_FILE),
+            path.clone(),
+        )
+    });
+
+    for path in paths {
+        let rules = match fs::read_to_string(&path).await {
+            Ok(text) => parse(&text),
+            Err(err) => Err(anyhow!(err)),
+        };
+
+        let rules = match rules {
+            Ok(rules) => rules,
+            Err(err) => {
+                println!("Skipping uno variant {} : {err:?}", path.display());
+                continue;
+            }
+        };
+
+        if path.file_name().is_some_and(|name| name == STANDARD_FILE) {
+            loaded.standard = rules;
+        } else if let Err(err) = loaded.add(rules) {
+            println!("Skipping uno variant {} : {err:?}", path.display());
+        }
+    }
+
+    println!(
+        "Loaded {} uno variants {:?}",
+        loaded.variants.len(),
+        loaded.names()
+    );
+
+    loaded
+}
+
+/// Everything is checked here so a game never has to deal with rules that can't be played
+pub fn parse(text: &str) -> anyhow::Result<UnoRules> {
+    let file: VariantFile = toml::from_str(text)?;
+
+    let mut deck: Vec<(UnoCard, u8)> = vec![];
+    for cards in &file.cards {
+        if cards.copies == 0 {
+            return Err(anyhow!("Cards {:?} need at least one copy", cards.faces));
+        }
+
+        let colours = match &cards.colours {
+            Some(colours) => colours
+                .iter()
+                .map(|colour| parse_colour(colour))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            None => (0..4).map(UnoCardColour::from).collect(),
+        };
+
+        for face in &cards.faces {
+            let faced: Vec<UnoCard> = match parse_face(face)? {
+                (true, value) if UnoCardPower::from(value).is_black() => {
+                    if cards.colours.is_some() {
+                        return Err(anyhow!("{face} is wild, it can't be given colours"));
+                    }
+                    vec![UnoCard::encode(true, UnoCardColour::Red, value)]
+                }
+                (power, value) => colours
+                    .iter()
+                    .map(|colour| UnoCard::encode(power, *colour, value))
+                    .collect(),
+            };
+
+            for card in faced {
+                if deck.iter().any(|(other, _)| *other == card) {
+                    return Err(anyhow!("{face} is listed more than once"));
+                }
+                deck.push((card, cards.copies));
+            }
+        }
+    }
+
+    // The same deck always lays out the same, whatever order the file lists it in
+    deck.sort_by_key(|(card, _)| *card);
+
+    let mut effects = vec![];
+    for (name, file_effects) in &file.effects {
+        let power = parse_power(name).ok_or(anyhow!("{name} isn't a power card"))?;
+
+        let power_effects = file_effects
+            .iter()
+            .map(|effect| match effect {
+                UnoEffectFile::Draw(0) => Err(anyhow!("{name} has to draw at least one")),
+                UnoEffectFile::Draw(count) => Ok(UnoEffect::Draw(*count)),
+                UnoEffectFile::Skip => Ok(UnoEffect::Skip),
+                UnoEffectFile::Reverse => Ok(UnoEffect::Reverse),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if !power_effects.is_empty() {
+            effects.push((power, power_effects));
+        }
+    }
+    // Keeps the order the same as the built in rules, whatever order the file had them in
+    effects.sort_by_key(|(power, _)| *power as u8);
+
+    let rules = UnoRules {
+        name: file.name,
+        description: file.description,
+        deck,
+        hand_size: file.hand_size,
+        bust_over: file.bust_over,
+        match_colour: file.matching.colour,
+        match_value: file.matching.value,
+        effects,
+    };
+
+    if rules.name.trim().is_empty() {
+        return Err(anyhow!("Variants need a name"));
+    }
+
+    if rules.hand_size == 0 || rules.bust_over < rules.hand_size {
+        return Err(anyhow!(
+            "A hand of {} has to be at least one card and no more than the bust limit of {}",
+            rules.hand_size,
+            rules.bust_over
+        ));
+    }
+
+    // A full table dealt from a single deck still needs a card left to start the stack
+    if rules.deck_size() <= rules.hand_size as usize * UNO_MAX_PLAYERS {
+        return Err(anyhow!(
+            "A deck of {} is too small to deal {} players {} cards",
+            rules.deck_size(),
+            UNO_MAX_PLAYERS,
+            rules.hand_size
+        ));
+    }
+
+    if !rules.match_colour && !rules.match_value {
+        return Err(anyhow!("Cards have to match on colour, value or both"));
+    }
+
+    Ok(rules)
+}
+
+/// Numbers come back as they are, power cards as their power
+fn parse_face(face: &str) -> anyhow::Result<(bool, u8)> {
+    if let Ok(value) = face.parse::<u8>()
+        && value <= 9
+    {
+        return Ok((false, value));
+    }
+
+    parse_power(face)
+        .map(|power| (true, power as u8))
+        .ok_or(anyhow!("{face} isn't a card"))
+}
+
+fn parse_power(name: &str) -> Option<UnoCardPower> {
+    match name {
+        "plus_two" => Some(UnoCardPower::PlusTwo),
+        "skip" => Some(UnoCardPower::Skip),
+        "reverse" => Some(UnoCardPower::Reverse),
+        "plus_four" => Some(UnoCardPower::PlusFour),
+        "colour_change" => Some(UnoCardPower::ClrChange),
+        _ => None,
+    }
+}
+
+fn parse_colour(name: &str) -> anyhow::Result<UnoCardColour> {
+    match name {
+        "red" => Ok(UnoCardColour::Red),
+        "blue" => Ok(UnoCardColour::Blue),
+        "green" => Ok(UnoCardColour::Green),
+        "yellow" => Ok(UnoCardColour::Yellow),
+        _ => Err(anyhow!("{name} isn't a colour")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_file_is_the_built_in_rules() {
+        let text = include_str!("../../variants/uno.toml");
+
+        assert_eq!(parse(text).unwrap(), UnoRules::standard());
+    }
+
+    #[test]
+    fn shipped_variants_parse() {
+        let text = include_str!("../../variants/uno_mayhem.toml");
+
+        parse(text).unwrap();
+    }
+
+    /// The standard rules with one line swapped out
+    fn standard_with(from: &str, to: &str) -> String {
+        let text = include_str!("../../variants/uno.toml");
+        assert!(text.contains(from), "{from} isn't in the standard rules");
+
+        text.replacen(from, to, 1)
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert!(parse("name = ").is_err());
+        assert!(parse("this isn't toml").is_err());
+        assert!(parse(&standard_with("hand_size = 10", "")).is_err());
+        assert!(parse(&standard_with("hand_size = 10", "hand_size = \"ten\"")).is_err());
+        // Anything not in the format is a mistake, rather than being ignored
+        assert!(
+            parse(&standard_with(
+                "hand_size = 10",
+                "hand_size = 10\nhands = 2"
+            ))
+            .is_err()
+        );
+        assert!(parse(&standard_with("\"skip\"]", "\"jump\"]")).is_err());
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        for (from, to) in [
+            ("hand_size = 10", "hand_size = 0"),
+            ("hand_size = 10", "hand_size = 300"),
+            ("hand_size = 10", "hand_size = 21"),
+            ("bust_over = 20", "bust_over = -1"),
+            ("copies = 1", "copies = 0"),
+            ("faces = [\"0\"]", "faces = [\"10\"]"),
+            ("faces = [\"0\"]", "faces = [\"0\", \"1\"]"),
+            ("{ draw = 2 }", "{ draw = 0 }"),
+            ("colour_change = []", "wild = []"),
+            ("name = \"Uno\"", "name = \"  \""),
+        ] {
+            assert!(
+                parse(&standard_with(from, to)).is_err(),
+                "{to} should be rejected"
+            );
+        }
+
+        // Cards have to match on something
+        let text = standard_with("colour = true", "colour = false");
+        assert!(parse(&text.replacen("value = true", "value = false", 1)).is_err());
+    }
+
+    #[test]
+    fn colours_are_checked() {
+        let coloured = standard_with("copies = 1", "copies = 1\ncolours = [\"red\", \"blue\"]");
+        assert!(parse(&coloured).is_ok());
+
+        let unknown = standard_with("copies = 1", "copies = 1\ncolours = [\"purple\"]");
+        assert!(parse(&unknown).is_err());
+
+        let wild = standard_with("copies = 4", "copies = 4\ncolours = [\"red\"]");
+        assert!(parse(&wild).is_err());
+    }
+
+    #[test]
+    fn small_decks_are_rejected() {
+        // One of each number in each colour and two wilds is 42 cards, too few for a full table
+        let text = standard_with("\"plus_two\", \"skip\", \"reverse\"]", "]");
+        let text = text.replacen("copies = 2", "copies = 1", 1);
+        let text = text.replacen("copies = 4", "copies = 1", 1);
+
+        assert!(parse(&text).is_err());
+    }
+
+    #[test]
+    fn duplicate_names_are_rejected() {
+        let mut variants = UnoVariants {
+            standard: UnoRules::standard(),
+            variants: vec![],
+        };
+
+        let mayhem = parse(include_str!("../../variants/uno_mayhem.toml")).unwrap();
+        variants.add(mayhem.clone()).unwrap();
+        assert!(variants.add(mayhem.clone()).is_err());
+
+        let mut shouted = mayhem;
+        shouted.name = shouted.name.to_uppercase();
+        assert!(variants.add(shouted).is_err());
+
+        // Nor can a variant pass itself off as the standard rules
+        assert!(variants.add(UnoRules::standard()).is_err());
+
+        assert_eq!(variants.variants.len(), 1);
+        assert!(variants.rules(GameType::UnoVariant(0)).is_some());
+        assert!(variants.rules(GameType::UnoVariant(1)).is_none());
+    }
+}